@@ -33,6 +33,13 @@ pub struct KeyDerivationMetadata {
     pub hash_fn: HashFunction,
     pub iterations: u64,
     pub salt: ::std::vec::Vec<u8>,
+    pub memory_cost: u32,
+    pub time_cost: u32,
+    pub parallelism: u32,
+    pub log_n: u32,
+    pub block_size: u32,
+    pub ephemeral_public_key: ::std::vec::Vec<u8>,
+    pub info: ::std::vec::Vec<u8>,
     // special fields
     pub unknown_fields: ::protobuf::UnknownFields,
     pub cached_size: ::protobuf::CachedSize,
@@ -119,6 +126,133 @@ impl KeyDerivationMetadata {
     pub fn take_salt(&mut self) -> ::std::vec::Vec<u8> {
         ::std::mem::replace(&mut self.salt, ::std::vec::Vec::new())
     }
+
+    // uint32 memory_cost = 5;
+
+
+    pub fn get_memory_cost(&self) -> u32 {
+        self.memory_cost
+    }
+    pub fn clear_memory_cost(&mut self) {
+        self.memory_cost = 0;
+    }
+
+    // Param is passed by value, moved
+    pub fn set_memory_cost(&mut self, v: u32) {
+        self.memory_cost = v;
+    }
+
+    // uint32 time_cost = 6;
+
+
+    pub fn get_time_cost(&self) -> u32 {
+        self.time_cost
+    }
+    pub fn clear_time_cost(&mut self) {
+        self.time_cost = 0;
+    }
+
+    // Param is passed by value, moved
+    pub fn set_time_cost(&mut self, v: u32) {
+        self.time_cost = v;
+    }
+
+    // uint32 parallelism = 7;
+
+
+    pub fn get_parallelism(&self) -> u32 {
+        self.parallelism
+    }
+    pub fn clear_parallelism(&mut self) {
+        self.parallelism = 0;
+    }
+
+    // Param is passed by value, moved
+    pub fn set_parallelism(&mut self, v: u32) {
+        self.parallelism = v;
+    }
+
+    // uint32 log_n = 8;
+
+
+    pub fn get_log_n(&self) -> u32 {
+        self.log_n
+    }
+    pub fn clear_log_n(&mut self) {
+        self.log_n = 0;
+    }
+
+    // Param is passed by value, moved
+    pub fn set_log_n(&mut self, v: u32) {
+        self.log_n = v;
+    }
+
+    // uint32 block_size = 9;
+
+
+    pub fn get_block_size(&self) -> u32 {
+        self.block_size
+    }
+    pub fn clear_block_size(&mut self) {
+        self.block_size = 0;
+    }
+
+    // Param is passed by value, moved
+    pub fn set_block_size(&mut self, v: u32) {
+        self.block_size = v;
+    }
+
+    // bytes ephemeral_public_key = 10;
+
+
+    pub fn get_ephemeral_public_key(&self) -> &[u8] {
+        &self.ephemeral_public_key
+    }
+    pub fn clear_ephemeral_public_key(&mut self) {
+        self.ephemeral_public_key.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_ephemeral_public_key(&mut self, v: ::std::vec::Vec<u8>) {
+        self.ephemeral_public_key = v;
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_ephemeral_public_key(&mut self) -> &mut ::std::vec::Vec<u8> {
+        &mut self.ephemeral_public_key
+    }
+
+    // Take field
+    pub fn take_ephemeral_public_key(&mut self) -> ::std::vec::Vec<u8> {
+        ::std::mem::replace(&mut self.ephemeral_public_key, ::std::vec::Vec::new())
+    }
+
+    // bytes info = 11;
+
+
+    pub fn get_info(&self) -> &[u8] {
+        &self.info
+    }
+    pub fn clear_info(&mut self) {
+        self.info.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_info(&mut self, v: ::std::vec::Vec<u8>) {
+        self.info = v;
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_info(&mut self) -> &mut ::std::vec::Vec<u8> {
+        &mut self.info
+    }
+
+    // Take field
+    pub fn take_info(&mut self) -> ::std::vec::Vec<u8> {
+        ::std::mem::replace(&mut self.info, ::std::vec::Vec::new())
+    }
 }
 
 impl ::protobuf::Message for KeyDerivationMetadata {
@@ -146,6 +280,47 @@ impl ::protobuf::Message for KeyDerivationMetadata {
                 4 => {
                     ::protobuf::rt::read_singular_proto3_bytes_into(wire_type, is, &mut self.salt)?;
                 },
+                5 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeVarint {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    let tmp = is.read_uint32()?;
+                    self.memory_cost = tmp;
+                },
+                6 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeVarint {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    let tmp = is.read_uint32()?;
+                    self.time_cost = tmp;
+                },
+                7 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeVarint {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    let tmp = is.read_uint32()?;
+                    self.parallelism = tmp;
+                },
+                8 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeVarint {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    let tmp = is.read_uint32()?;
+                    self.log_n = tmp;
+                },
+                9 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeVarint {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    let tmp = is.read_uint32()?;
+                    self.block_size = tmp;
+                },
+                10 => {
+                    ::protobuf::rt::read_singular_proto3_bytes_into(wire_type, is, &mut self.ephemeral_public_key)?;
+                },
+                11 => {
+                    ::protobuf::rt::read_singular_proto3_bytes_into(wire_type, is, &mut self.info)?;
+                },
                 _ => {
                     ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
                 },
@@ -170,6 +345,27 @@ impl ::protobuf::Message for KeyDerivationMetadata {
         if !self.salt.is_empty() {
             my_size += ::protobuf::rt::bytes_size(4, &self.salt);
         }
+        if self.memory_cost != 0 {
+            my_size += ::protobuf::rt::value_size(5, self.memory_cost, ::protobuf::wire_format::WireTypeVarint);
+        }
+        if self.time_cost != 0 {
+            my_size += ::protobuf::rt::value_size(6, self.time_cost, ::protobuf::wire_format::WireTypeVarint);
+        }
+        if self.parallelism != 0 {
+            my_size += ::protobuf::rt::value_size(7, self.parallelism, ::protobuf::wire_format::WireTypeVarint);
+        }
+        if self.log_n != 0 {
+            my_size += ::protobuf::rt::value_size(8, self.log_n, ::protobuf::wire_format::WireTypeVarint);
+        }
+        if self.block_size != 0 {
+            my_size += ::protobuf::rt::value_size(9, self.block_size, ::protobuf::wire_format::WireTypeVarint);
+        }
+        if !self.ephemeral_public_key.is_empty() {
+            my_size += ::protobuf::rt::bytes_size(10, &self.ephemeral_public_key);
+        }
+        if !self.info.is_empty() {
+            my_size += ::protobuf::rt::bytes_size(11, &self.info);
+        }
         my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
         self.cached_size.set(my_size);
         my_size
@@ -188,6 +384,27 @@ impl ::protobuf::Message for KeyDerivationMetadata {
         if !self.salt.is_empty() {
             os.write_bytes(4, &self.salt)?;
         }
+        if self.memory_cost != 0 {
+            os.write_uint32(5, self.memory_cost)?;
+        }
+        if self.time_cost != 0 {
+            os.write_uint32(6, self.time_cost)?;
+        }
+        if self.parallelism != 0 {
+            os.write_uint32(7, self.parallelism)?;
+        }
+        if self.log_n != 0 {
+            os.write_uint32(8, self.log_n)?;
+        }
+        if self.block_size != 0 {
+            os.write_uint32(9, self.block_size)?;
+        }
+        if !self.ephemeral_public_key.is_empty() {
+            os.write_bytes(10, &self.ephemeral_public_key)?;
+        }
+        if !self.info.is_empty() {
+            os.write_bytes(11, &self.info)?;
+        }
         os.write_unknown_fields(self.get_unknown_fields())?;
         ::std::result::Result::Ok(())
     }
@@ -250,6 +467,41 @@ impl ::protobuf::Message for KeyDerivationMetadata {
                     |m: &KeyDerivationMetadata| { &m.salt },
                     |m: &mut KeyDerivationMetadata| { &mut m.salt },
                 ));
+                fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeUint32>(
+                    "memory_cost",
+                    |m: &KeyDerivationMetadata| { &m.memory_cost },
+                    |m: &mut KeyDerivationMetadata| { &mut m.memory_cost },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeUint32>(
+                    "time_cost",
+                    |m: &KeyDerivationMetadata| { &m.time_cost },
+                    |m: &mut KeyDerivationMetadata| { &mut m.time_cost },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeUint32>(
+                    "parallelism",
+                    |m: &KeyDerivationMetadata| { &m.parallelism },
+                    |m: &mut KeyDerivationMetadata| { &mut m.parallelism },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeUint32>(
+                    "log_n",
+                    |m: &KeyDerivationMetadata| { &m.log_n },
+                    |m: &mut KeyDerivationMetadata| { &mut m.log_n },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeUint32>(
+                    "block_size",
+                    |m: &KeyDerivationMetadata| { &m.block_size },
+                    |m: &mut KeyDerivationMetadata| { &mut m.block_size },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeBytes>(
+                    "ephemeral_public_key",
+                    |m: &KeyDerivationMetadata| { &m.ephemeral_public_key },
+                    |m: &mut KeyDerivationMetadata| { &mut m.ephemeral_public_key },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeBytes>(
+                    "info",
+                    |m: &KeyDerivationMetadata| { &m.info },
+                    |m: &mut KeyDerivationMetadata| { &mut m.info },
+                ));
                 ::protobuf::reflect::MessageDescriptor::new::<KeyDerivationMetadata>(
                     "KeyDerivationMetadata",
                     fields,
@@ -276,6 +528,13 @@ impl ::protobuf::Clear for KeyDerivationMetadata {
         self.hash_fn = HashFunction::HASH_FUNCTION_INVALID;
         self.iterations = 0;
         self.salt.clear();
+        self.memory_cost = 0;
+        self.time_cost = 0;
+        self.parallelism = 0;
+        self.log_n = 0;
+        self.block_size = 0;
+        self.ephemeral_public_key.clear();
+        self.info.clear();
         self.unknown_fields.clear();
     }
 }
@@ -493,24 +752,25 @@ impl ::protobuf::reflect::ProtobufValue for EncryptionMetadata {
 }
 
 #[derive(PartialEq,Clone,Default)]
-pub struct Metadata {
+pub struct KeySlot {
     // message fields
     pub key_deriv_meta: ::protobuf::SingularPtrField<KeyDerivationMetadata>,
     pub enc_meta: ::protobuf::SingularPtrField<EncryptionMetadata>,
-    pub ciphertext_size: u64,
+    pub wrapped_key: ::std::vec::Vec<u8>,
+    pub kek_id: ::std::string::String,
     // special fields
     pub unknown_fields: ::protobuf::UnknownFields,
     pub cached_size: ::protobuf::CachedSize,
 }
 
-impl<'a> ::std::default::Default for &'a Metadata {
-    fn default() -> &'a Metadata {
-        <Metadata as ::protobuf::Message>::default_instance()
+impl<'a> ::std::default::Default for &'a KeySlot {
+    fn default() -> &'a KeySlot {
+        <KeySlot as ::protobuf::Message>::default_instance()
     }
 }
 
-impl Metadata {
-    pub fn new() -> Metadata {
+impl KeySlot {
+    pub fn new() -> KeySlot {
         ::std::default::Default::default()
     }
 
@@ -580,23 +840,60 @@ impl Metadata {
         self.enc_meta.take().unwrap_or_else(|| EncryptionMetadata::new())
     }
 
-    // uint64 ciphertext_size = 3;
+    // bytes wrapped_key = 3;
 
 
-    pub fn get_ciphertext_size(&self) -> u64 {
-        self.ciphertext_size
+    pub fn get_wrapped_key(&self) -> &[u8] {
+        &self.wrapped_key
     }
-    pub fn clear_ciphertext_size(&mut self) {
-        self.ciphertext_size = 0;
+    pub fn clear_wrapped_key(&mut self) {
+        self.wrapped_key.clear();
     }
 
     // Param is passed by value, moved
-    pub fn set_ciphertext_size(&mut self, v: u64) {
-        self.ciphertext_size = v;
+    pub fn set_wrapped_key(&mut self, v: ::std::vec::Vec<u8>) {
+        self.wrapped_key = v;
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_wrapped_key(&mut self) -> &mut ::std::vec::Vec<u8> {
+        &mut self.wrapped_key
+    }
+
+    // Take field
+    pub fn take_wrapped_key(&mut self) -> ::std::vec::Vec<u8> {
+        ::std::mem::replace(&mut self.wrapped_key, ::std::vec::Vec::new())
+    }
+
+    // string kek_id = 4;
+
+
+    pub fn get_kek_id(&self) -> &str {
+        &self.kek_id
+    }
+    pub fn clear_kek_id(&mut self) {
+        self.kek_id.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_kek_id(&mut self, v: ::std::string::String) {
+        self.kek_id = v;
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_kek_id(&mut self) -> &mut ::std::string::String {
+        &mut self.kek_id
+    }
+
+    // Take field
+    pub fn take_kek_id(&mut self) -> ::std::string::String {
+        ::std::mem::replace(&mut self.kek_id, ::std::string::String::new())
     }
 }
 
-impl ::protobuf::Message for Metadata {
+impl ::protobuf::Message for KeySlot {
     fn is_initialized(&self) -> bool {
         for v in &self.key_deriv_meta {
             if !v.is_initialized() {
@@ -622,11 +919,10 @@ impl ::protobuf::Message for Metadata {
                     ::protobuf::rt::read_singular_message_into(wire_type, is, &mut self.enc_meta)?;
                 },
                 3 => {
-                    if wire_type != ::protobuf::wire_format::WireTypeVarint {
-                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
-                    }
-                    let tmp = is.read_uint64()?;
-                    self.ciphertext_size = tmp;
+                    ::protobuf::rt::read_singular_proto3_bytes_into(wire_type, is, &mut self.wrapped_key)?;
+                },
+                4 => {
+                    ::protobuf::rt::read_singular_proto3_string_into(wire_type, is, &mut self.kek_id)?;
                 },
                 _ => {
                     ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
@@ -648,8 +944,11 @@ impl ::protobuf::Message for Metadata {
             let len = v.compute_size();
             my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
         }
-        if self.ciphertext_size != 0 {
-            my_size += ::protobuf::rt::value_size(3, self.ciphertext_size, ::protobuf::wire_format::WireTypeVarint);
+        if !self.wrapped_key.is_empty() {
+            my_size += ::protobuf::rt::bytes_size(3, &self.wrapped_key);
+        }
+        if !self.kek_id.is_empty() {
+            my_size += ::protobuf::rt::string_size(4, &self.kek_id);
         }
         my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
         self.cached_size.set(my_size);
@@ -667,8 +966,11 @@ impl ::protobuf::Message for Metadata {
             os.write_raw_varint32(v.get_cached_size())?;
             v.write_to_with_cached_sizes(os)?;
         }
-        if self.ciphertext_size != 0 {
-            os.write_uint64(3, self.ciphertext_size)?;
+        if !self.wrapped_key.is_empty() {
+            os.write_bytes(3, &self.wrapped_key)?;
+        }
+        if !self.kek_id.is_empty() {
+            os.write_string(4, &self.kek_id)?;
         }
         os.write_unknown_fields(self.get_unknown_fields())?;
         ::std::result::Result::Ok(())
@@ -700,8 +1002,8 @@ impl ::protobuf::Message for Metadata {
         Self::descriptor_static()
     }
 
-    fn new() -> Metadata {
-        Metadata::new()
+    fn new() -> KeySlot {
+        KeySlot::new()
     }
 
     fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
@@ -714,18 +1016,840 @@ impl ::protobuf::Message for Metadata {
                 let mut fields = ::std::vec::Vec::new();
                 fields.push(::protobuf::reflect::accessor::make_singular_ptr_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<KeyDerivationMetadata>>(
                     "key_deriv_meta",
-                    |m: &Metadata| { &m.key_deriv_meta },
-                    |m: &mut Metadata| { &mut m.key_deriv_meta },
+                    |m: &KeySlot| { &m.key_deriv_meta },
+                    |m: &mut KeySlot| { &mut m.key_deriv_meta },
                 ));
                 fields.push(::protobuf::reflect::accessor::make_singular_ptr_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<EncryptionMetadata>>(
                     "enc_meta",
-                    |m: &Metadata| { &m.enc_meta },
-                    |m: &mut Metadata| { &mut m.enc_meta },
+                    |m: &KeySlot| { &m.enc_meta },
+                    |m: &mut KeySlot| { &mut m.enc_meta },
                 ));
-                fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeUint64>(
-                    "ciphertext_size",
-                    |m: &Metadata| { &m.ciphertext_size },
-                    |m: &mut Metadata| { &mut m.ciphertext_size },
+                fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeBytes>(
+                    "wrapped_key",
+                    |m: &KeySlot| { &m.wrapped_key },
+                    |m: &mut KeySlot| { &mut m.wrapped_key },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeString>(
+                    "kek_id",
+                    |m: &KeySlot| { &m.kek_id },
+                    |m: &mut KeySlot| { &mut m.kek_id },
+                ));
+                ::protobuf::reflect::MessageDescriptor::new::<KeySlot>(
+                    "KeySlot",
+                    fields,
+                    file_descriptor_proto()
+                )
+            })
+        }
+    }
+
+    fn default_instance() -> &'static KeySlot {
+        static mut instance: ::protobuf::lazy::Lazy<KeySlot> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const KeySlot,
+        };
+        unsafe {
+            instance.get(KeySlot::new)
+        }
+    }
+}
+
+impl ::protobuf::Clear for KeySlot {
+    fn clear(&mut self) {
+        self.key_deriv_meta.clear();
+        self.enc_meta.clear();
+        self.wrapped_key.clear();
+        self.kek_id.clear();
+        self.unknown_fields.clear();
+    }
+}
+
+impl ::std::fmt::Debug for KeySlot {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for KeySlot {
+    fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef {
+        ::protobuf::reflect::ProtobufValueRef::Message(self)
+    }
+}
+
+#[derive(PartialEq,Clone,Default)]
+pub struct Signature {
+    // message fields
+    pub public_key: ::std::vec::Vec<u8>,
+    pub signature: ::std::vec::Vec<u8>,
+    // special fields
+    pub unknown_fields: ::protobuf::UnknownFields,
+    pub cached_size: ::protobuf::CachedSize,
+}
+
+impl<'a> ::std::default::Default for &'a Signature {
+    fn default() -> &'a Signature {
+        <Signature as ::protobuf::Message>::default_instance()
+    }
+}
+
+impl Signature {
+    pub fn new() -> Signature {
+        ::std::default::Default::default()
+    }
+
+    // bytes public_key = 1;
+
+
+    pub fn get_public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+    pub fn clear_public_key(&mut self) {
+        self.public_key.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_public_key(&mut self, v: ::std::vec::Vec<u8>) {
+        self.public_key = v;
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_public_key(&mut self) -> &mut ::std::vec::Vec<u8> {
+        &mut self.public_key
+    }
+
+    // Take field
+    pub fn take_public_key(&mut self) -> ::std::vec::Vec<u8> {
+        ::std::mem::replace(&mut self.public_key, ::std::vec::Vec::new())
+    }
+
+    // bytes signature = 2;
+
+
+    pub fn get_signature(&self) -> &[u8] {
+        &self.signature
+    }
+    pub fn clear_signature(&mut self) {
+        self.signature.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_signature(&mut self, v: ::std::vec::Vec<u8>) {
+        self.signature = v;
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_signature(&mut self) -> &mut ::std::vec::Vec<u8> {
+        &mut self.signature
+    }
+
+    // Take field
+    pub fn take_signature(&mut self) -> ::std::vec::Vec<u8> {
+        ::std::mem::replace(&mut self.signature, ::std::vec::Vec::new())
+    }
+}
+
+impl ::protobuf::Message for Signature {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        while !is.eof()? {
+            let (field_number, wire_type) = is.read_tag_unpack()?;
+            match field_number {
+                1 => {
+                    ::protobuf::rt::read_singular_proto3_bytes_into(wire_type, is, &mut self.public_key)?;
+                },
+                2 => {
+                    ::protobuf::rt::read_singular_proto3_bytes_into(wire_type, is, &mut self.signature)?;
+                },
+                _ => {
+                    ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u32 {
+        let mut my_size = 0;
+        if !self.public_key.is_empty() {
+            my_size += ::protobuf::rt::bytes_size(1, &self.public_key);
+        }
+        if !self.signature.is_empty() {
+            my_size += ::protobuf::rt::bytes_size(2, &self.signature);
+        }
+        my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
+        self.cached_size.set(my_size);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        if !self.public_key.is_empty() {
+            os.write_bytes(1, &self.public_key)?;
+        }
+        if !self.signature.is_empty() {
+            os.write_bytes(2, &self.signature)?;
+        }
+        os.write_unknown_fields(self.get_unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn get_cached_size(&self) -> u32 {
+        self.cached_size.get()
+    }
+
+    fn get_unknown_fields(&self) -> &::protobuf::UnknownFields {
+        &self.unknown_fields
+    }
+
+    fn mut_unknown_fields(&mut self) -> &mut ::protobuf::UnknownFields {
+        &mut self.unknown_fields
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self as &dyn (::std::any::Any)
+    }
+    fn as_any_mut(&mut self) -> &mut dyn (::std::any::Any) {
+        self as &mut dyn (::std::any::Any)
+    }
+    fn into_any(self: Box<Self>) -> ::std::boxed::Box<dyn (::std::any::Any)> {
+        self
+    }
+
+    fn descriptor(&self) -> &'static ::protobuf::reflect::MessageDescriptor {
+        Self::descriptor_static()
+    }
+
+    fn new() -> Signature {
+        Signature::new()
+    }
+
+    fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
+        static mut descriptor: ::protobuf::lazy::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const ::protobuf::reflect::MessageDescriptor,
+        };
+        unsafe {
+            descriptor.get(|| {
+                let mut fields = ::std::vec::Vec::new();
+                fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeBytes>(
+                    "public_key",
+                    |m: &Signature| { &m.public_key },
+                    |m: &mut Signature| { &mut m.public_key },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeBytes>(
+                    "signature",
+                    |m: &Signature| { &m.signature },
+                    |m: &mut Signature| { &mut m.signature },
+                ));
+                ::protobuf::reflect::MessageDescriptor::new::<Signature>(
+                    "Signature",
+                    fields,
+                    file_descriptor_proto()
+                )
+            })
+        }
+    }
+
+    fn default_instance() -> &'static Signature {
+        static mut instance: ::protobuf::lazy::Lazy<Signature> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const Signature,
+        };
+        unsafe {
+            instance.get(Signature::new)
+        }
+    }
+}
+
+impl ::protobuf::Clear for Signature {
+    fn clear(&mut self) {
+        self.public_key.clear();
+        self.signature.clear();
+        self.unknown_fields.clear();
+    }
+}
+
+impl ::std::fmt::Debug for Signature {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for Signature {
+    fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef {
+        ::protobuf::reflect::ProtobufValueRef::Message(self)
+    }
+}
+
+#[derive(PartialEq,Clone,Default)]
+pub struct Metadata {
+    // message fields
+    pub key_deriv_meta: ::protobuf::SingularPtrField<KeyDerivationMetadata>,
+    pub enc_meta: ::protobuf::SingularPtrField<EncryptionMetadata>,
+    pub ciphertext_size: u64,
+    pub authenticated_header: bool,
+    pub chunk_size: u64,
+    pub deterministic: bool,
+    pub key_slots: ::protobuf::RepeatedField<KeySlot>,
+    pub key_id: ::std::string::String,
+    pub signature: ::protobuf::SingularPtrField<Signature>,
+    pub key_commitment: ::std::vec::Vec<u8>,
+    pub key_check: ::std::vec::Vec<u8>,
+    // special fields
+    pub unknown_fields: ::protobuf::UnknownFields,
+    pub cached_size: ::protobuf::CachedSize,
+}
+
+impl<'a> ::std::default::Default for &'a Metadata {
+    fn default() -> &'a Metadata {
+        <Metadata as ::protobuf::Message>::default_instance()
+    }
+}
+
+impl Metadata {
+    pub fn new() -> Metadata {
+        ::std::default::Default::default()
+    }
+
+    // .metadata.KeyDerivationMetadata key_deriv_meta = 1;
+
+
+    pub fn get_key_deriv_meta(&self) -> &KeyDerivationMetadata {
+        self.key_deriv_meta.as_ref().unwrap_or_else(|| KeyDerivationMetadata::default_instance())
+    }
+    pub fn clear_key_deriv_meta(&mut self) {
+        self.key_deriv_meta.clear();
+    }
+
+    pub fn has_key_deriv_meta(&self) -> bool {
+        self.key_deriv_meta.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_key_deriv_meta(&mut self, v: KeyDerivationMetadata) {
+        self.key_deriv_meta = ::protobuf::SingularPtrField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_key_deriv_meta(&mut self) -> &mut KeyDerivationMetadata {
+        if self.key_deriv_meta.is_none() {
+            self.key_deriv_meta.set_default();
+        }
+        self.key_deriv_meta.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_key_deriv_meta(&mut self) -> KeyDerivationMetadata {
+        self.key_deriv_meta.take().unwrap_or_else(|| KeyDerivationMetadata::new())
+    }
+
+    // .metadata.EncryptionMetadata enc_meta = 2;
+
+
+    pub fn get_enc_meta(&self) -> &EncryptionMetadata {
+        self.enc_meta.as_ref().unwrap_or_else(|| EncryptionMetadata::default_instance())
+    }
+    pub fn clear_enc_meta(&mut self) {
+        self.enc_meta.clear();
+    }
+
+    pub fn has_enc_meta(&self) -> bool {
+        self.enc_meta.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_enc_meta(&mut self, v: EncryptionMetadata) {
+        self.enc_meta = ::protobuf::SingularPtrField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_enc_meta(&mut self) -> &mut EncryptionMetadata {
+        if self.enc_meta.is_none() {
+            self.enc_meta.set_default();
+        }
+        self.enc_meta.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_enc_meta(&mut self) -> EncryptionMetadata {
+        self.enc_meta.take().unwrap_or_else(|| EncryptionMetadata::new())
+    }
+
+    // uint64 ciphertext_size = 3;
+
+
+    pub fn get_ciphertext_size(&self) -> u64 {
+        self.ciphertext_size
+    }
+    pub fn clear_ciphertext_size(&mut self) {
+        self.ciphertext_size = 0;
+    }
+
+    // Param is passed by value, moved
+    pub fn set_ciphertext_size(&mut self, v: u64) {
+        self.ciphertext_size = v;
+    }
+
+    // bool authenticated_header = 4;
+
+
+    pub fn get_authenticated_header(&self) -> bool {
+        self.authenticated_header
+    }
+    pub fn clear_authenticated_header(&mut self) {
+        self.authenticated_header = false;
+    }
+
+    // Param is passed by value, moved
+    pub fn set_authenticated_header(&mut self, v: bool) {
+        self.authenticated_header = v;
+    }
+
+    // uint64 chunk_size = 5;
+
+
+    pub fn get_chunk_size(&self) -> u64 {
+        self.chunk_size
+    }
+    pub fn clear_chunk_size(&mut self) {
+        self.chunk_size = 0;
+    }
+
+    // Param is passed by value, moved
+    pub fn set_chunk_size(&mut self, v: u64) {
+        self.chunk_size = v;
+    }
+
+    // bool deterministic = 6;
+
+
+    pub fn get_deterministic(&self) -> bool {
+        self.deterministic
+    }
+    pub fn clear_deterministic(&mut self) {
+        self.deterministic = false;
+    }
+
+    // Param is passed by value, moved
+    pub fn set_deterministic(&mut self, v: bool) {
+        self.deterministic = v;
+    }
+
+    // repeated .metadata.KeySlot key_slots = 7;
+
+
+    pub fn get_key_slots(&self) -> &[KeySlot] {
+        &self.key_slots
+    }
+    pub fn clear_key_slots(&mut self) {
+        self.key_slots.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_key_slots(&mut self, v: ::protobuf::RepeatedField<KeySlot>) {
+        self.key_slots = v;
+    }
+
+    // Mutable pointer to the field.
+    pub fn mut_key_slots(&mut self) -> &mut ::protobuf::RepeatedField<KeySlot> {
+        &mut self.key_slots
+    }
+
+    // Take field
+    pub fn take_key_slots(&mut self) -> ::protobuf::RepeatedField<KeySlot> {
+        ::std::mem::replace(&mut self.key_slots, ::protobuf::RepeatedField::new())
+    }
+
+    // string key_id = 8;
+
+
+    pub fn get_key_id(&self) -> &str {
+        &self.key_id
+    }
+    pub fn clear_key_id(&mut self) {
+        self.key_id.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_key_id(&mut self, v: ::std::string::String) {
+        self.key_id = v;
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_key_id(&mut self) -> &mut ::std::string::String {
+        &mut self.key_id
+    }
+
+    // Take field
+    pub fn take_key_id(&mut self) -> ::std::string::String {
+        ::std::mem::replace(&mut self.key_id, ::std::string::String::new())
+    }
+
+    // .metadata.Signature signature = 9;
+
+
+    pub fn get_signature(&self) -> &Signature {
+        self.signature.as_ref().unwrap_or_else(|| Signature::default_instance())
+    }
+    pub fn clear_signature(&mut self) {
+        self.signature.clear();
+    }
+
+    pub fn has_signature(&self) -> bool {
+        self.signature.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_signature(&mut self, v: Signature) {
+        self.signature = ::protobuf::SingularPtrField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_signature(&mut self) -> &mut Signature {
+        if self.signature.is_none() {
+            self.signature.set_default();
+        }
+        self.signature.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_signature(&mut self) -> Signature {
+        self.signature.take().unwrap_or_else(|| Signature::new())
+    }
+
+    // bytes key_commitment = 10;
+
+
+    pub fn get_key_commitment(&self) -> &[u8] {
+        &self.key_commitment
+    }
+    pub fn clear_key_commitment(&mut self) {
+        self.key_commitment.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_key_commitment(&mut self, v: ::std::vec::Vec<u8>) {
+        self.key_commitment = v;
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_key_commitment(&mut self) -> &mut ::std::vec::Vec<u8> {
+        &mut self.key_commitment
+    }
+
+    // Take field
+    pub fn take_key_commitment(&mut self) -> ::std::vec::Vec<u8> {
+        ::std::mem::replace(&mut self.key_commitment, ::std::vec::Vec::new())
+    }
+
+    // bytes key_check = 11;
+
+
+    pub fn get_key_check(&self) -> &[u8] {
+        &self.key_check
+    }
+    pub fn clear_key_check(&mut self) {
+        self.key_check.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_key_check(&mut self, v: ::std::vec::Vec<u8>) {
+        self.key_check = v;
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_key_check(&mut self) -> &mut ::std::vec::Vec<u8> {
+        &mut self.key_check
+    }
+
+    // Take field
+    pub fn take_key_check(&mut self) -> ::std::vec::Vec<u8> {
+        ::std::mem::replace(&mut self.key_check, ::std::vec::Vec::new())
+    }
+}
+
+impl ::protobuf::Message for Metadata {
+    fn is_initialized(&self) -> bool {
+        for v in &self.key_deriv_meta {
+            if !v.is_initialized() {
+                return false;
+            }
+        };
+        for v in &self.enc_meta {
+            if !v.is_initialized() {
+                return false;
+            }
+        };
+        for v in &self.key_slots {
+            if !v.is_initialized() {
+                return false;
+            }
+        };
+        for v in &self.signature {
+            if !v.is_initialized() {
+                return false;
+            }
+        };
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        while !is.eof()? {
+            let (field_number, wire_type) = is.read_tag_unpack()?;
+            match field_number {
+                1 => {
+                    ::protobuf::rt::read_singular_message_into(wire_type, is, &mut self.key_deriv_meta)?;
+                },
+                2 => {
+                    ::protobuf::rt::read_singular_message_into(wire_type, is, &mut self.enc_meta)?;
+                },
+                3 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeVarint {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    let tmp = is.read_uint64()?;
+                    self.ciphertext_size = tmp;
+                },
+                4 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeVarint {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    let tmp = is.read_bool()?;
+                    self.authenticated_header = tmp;
+                },
+                5 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeVarint {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    let tmp = is.read_uint64()?;
+                    self.chunk_size = tmp;
+                },
+                6 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeVarint {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    let tmp = is.read_bool()?;
+                    self.deterministic = tmp;
+                },
+                7 => {
+                    ::protobuf::rt::read_repeated_message_into(wire_type, is, &mut self.key_slots)?;
+                },
+                8 => {
+                    ::protobuf::rt::read_singular_proto3_string_into(wire_type, is, &mut self.key_id)?;
+                },
+                9 => {
+                    ::protobuf::rt::read_singular_message_into(wire_type, is, &mut self.signature)?;
+                },
+                10 => {
+                    ::protobuf::rt::read_singular_proto3_bytes_into(wire_type, is, &mut self.key_commitment)?;
+                },
+                11 => {
+                    ::protobuf::rt::read_singular_proto3_bytes_into(wire_type, is, &mut self.key_check)?;
+                },
+                _ => {
+                    ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u32 {
+        let mut my_size = 0;
+        if let Some(ref v) = self.key_deriv_meta.as_ref() {
+            let len = v.compute_size();
+            my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+        }
+        if let Some(ref v) = self.enc_meta.as_ref() {
+            let len = v.compute_size();
+            my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+        }
+        if self.ciphertext_size != 0 {
+            my_size += ::protobuf::rt::value_size(3, self.ciphertext_size, ::protobuf::wire_format::WireTypeVarint);
+        }
+        if self.authenticated_header != false {
+            my_size += 2;
+        }
+        if self.chunk_size != 0 {
+            my_size += ::protobuf::rt::value_size(5, self.chunk_size, ::protobuf::wire_format::WireTypeVarint);
+        }
+        if self.deterministic != false {
+            my_size += 2;
+        }
+        for value in &self.key_slots {
+            let len = value.compute_size();
+            my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+        };
+        if !self.key_id.is_empty() {
+            my_size += ::protobuf::rt::string_size(8, &self.key_id);
+        }
+        if let Some(ref v) = self.signature.as_ref() {
+            let len = v.compute_size();
+            my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+        }
+        if !self.key_commitment.is_empty() {
+            my_size += ::protobuf::rt::bytes_size(10, &self.key_commitment);
+        }
+        if !self.key_check.is_empty() {
+            my_size += ::protobuf::rt::bytes_size(11, &self.key_check);
+        }
+        my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
+        self.cached_size.set(my_size);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        if let Some(ref v) = self.key_deriv_meta.as_ref() {
+            os.write_tag(1, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+            os.write_raw_varint32(v.get_cached_size())?;
+            v.write_to_with_cached_sizes(os)?;
+        }
+        if let Some(ref v) = self.enc_meta.as_ref() {
+            os.write_tag(2, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+            os.write_raw_varint32(v.get_cached_size())?;
+            v.write_to_with_cached_sizes(os)?;
+        }
+        if self.ciphertext_size != 0 {
+            os.write_uint64(3, self.ciphertext_size)?;
+        }
+        if self.authenticated_header != false {
+            os.write_bool(4, self.authenticated_header)?;
+        }
+        if self.chunk_size != 0 {
+            os.write_uint64(5, self.chunk_size)?;
+        }
+        if self.deterministic != false {
+            os.write_bool(6, self.deterministic)?;
+        }
+        for v in &self.key_slots {
+            os.write_tag(7, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+            os.write_raw_varint32(v.get_cached_size())?;
+            v.write_to_with_cached_sizes(os)?;
+        };
+        if !self.key_id.is_empty() {
+            os.write_string(8, &self.key_id)?;
+        }
+        if let Some(ref v) = self.signature.as_ref() {
+            os.write_tag(9, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+            os.write_raw_varint32(v.get_cached_size())?;
+            v.write_to_with_cached_sizes(os)?;
+        }
+        if !self.key_commitment.is_empty() {
+            os.write_bytes(10, &self.key_commitment)?;
+        }
+        if !self.key_check.is_empty() {
+            os.write_bytes(11, &self.key_check)?;
+        }
+        os.write_unknown_fields(self.get_unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn get_cached_size(&self) -> u32 {
+        self.cached_size.get()
+    }
+
+    fn get_unknown_fields(&self) -> &::protobuf::UnknownFields {
+        &self.unknown_fields
+    }
+
+    fn mut_unknown_fields(&mut self) -> &mut ::protobuf::UnknownFields {
+        &mut self.unknown_fields
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self as &dyn (::std::any::Any)
+    }
+    fn as_any_mut(&mut self) -> &mut dyn (::std::any::Any) {
+        self as &mut dyn (::std::any::Any)
+    }
+    fn into_any(self: Box<Self>) -> ::std::boxed::Box<dyn (::std::any::Any)> {
+        self
+    }
+
+    fn descriptor(&self) -> &'static ::protobuf::reflect::MessageDescriptor {
+        Self::descriptor_static()
+    }
+
+    fn new() -> Metadata {
+        Metadata::new()
+    }
+
+    fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
+        static mut descriptor: ::protobuf::lazy::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const ::protobuf::reflect::MessageDescriptor,
+        };
+        unsafe {
+            descriptor.get(|| {
+                let mut fields = ::std::vec::Vec::new();
+                fields.push(::protobuf::reflect::accessor::make_singular_ptr_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<KeyDerivationMetadata>>(
+                    "key_deriv_meta",
+                    |m: &Metadata| { &m.key_deriv_meta },
+                    |m: &mut Metadata| { &mut m.key_deriv_meta },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_singular_ptr_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<EncryptionMetadata>>(
+                    "enc_meta",
+                    |m: &Metadata| { &m.enc_meta },
+                    |m: &mut Metadata| { &mut m.enc_meta },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeUint64>(
+                    "ciphertext_size",
+                    |m: &Metadata| { &m.ciphertext_size },
+                    |m: &mut Metadata| { &mut m.ciphertext_size },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeBool>(
+                    "authenticated_header",
+                    |m: &Metadata| { &m.authenticated_header },
+                    |m: &mut Metadata| { &mut m.authenticated_header },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeUint64>(
+                    "chunk_size",
+                    |m: &Metadata| { &m.chunk_size },
+                    |m: &mut Metadata| { &mut m.chunk_size },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeBool>(
+                    "deterministic",
+                    |m: &Metadata| { &m.deterministic },
+                    |m: &mut Metadata| { &mut m.deterministic },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_repeated_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<KeySlot>>(
+                    "key_slots",
+                    |m: &Metadata| { &m.key_slots },
+                    |m: &mut Metadata| { &mut m.key_slots },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeString>(
+                    "key_id",
+                    |m: &Metadata| { &m.key_id },
+                    |m: &mut Metadata| { &mut m.key_id },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_singular_ptr_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<Signature>>(
+                    "signature",
+                    |m: &Metadata| { &m.signature },
+                    |m: &mut Metadata| { &mut m.signature },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeBytes>(
+                    "key_commitment",
+                    |m: &Metadata| { &m.key_commitment },
+                    |m: &mut Metadata| { &mut m.key_commitment },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeBytes>(
+                    "key_check",
+                    |m: &Metadata| { &m.key_check },
+                    |m: &mut Metadata| { &mut m.key_check },
                 ));
                 ::protobuf::reflect::MessageDescriptor::new::<Metadata>(
                     "Metadata",
@@ -752,6 +1876,14 @@ impl ::protobuf::Clear for Metadata {
         self.key_deriv_meta.clear();
         self.enc_meta.clear();
         self.ciphertext_size = 0;
+        self.authenticated_header = false;
+        self.chunk_size = 0;
+        self.deterministic = false;
+        self.key_slots.clear();
+        self.key_id.clear();
+        self.signature.clear();
+        self.key_commitment.clear();
+        self.key_check.clear();
         self.unknown_fields.clear();
     }
 }
@@ -834,6 +1966,10 @@ pub enum KeyDerivationAlgorithm {
     KEY_DERIVATION_ALGORITHM_INVALID = 0,
     KEY_DERIVATION_ALGORITHM_NONE = 1,
     KEY_DERIVATION_ALGORITHM_PBKDF2 = 2,
+    KEY_DERIVATION_ALGORITHM_ARGON2ID = 3,
+    KEY_DERIVATION_ALGORITHM_SCRYPT = 4,
+    KEY_DERIVATION_ALGORITHM_X25519 = 5,
+    KEY_DERIVATION_ALGORITHM_HKDF = 6,
 }
 
 impl ::protobuf::ProtobufEnum for KeyDerivationAlgorithm {
@@ -846,6 +1982,10 @@ impl ::protobuf::ProtobufEnum for KeyDerivationAlgorithm {
             0 => ::std::option::Option::Some(KeyDerivationAlgorithm::KEY_DERIVATION_ALGORITHM_INVALID),
             1 => ::std::option::Option::Some(KeyDerivationAlgorithm::KEY_DERIVATION_ALGORITHM_NONE),
             2 => ::std::option::Option::Some(KeyDerivationAlgorithm::KEY_DERIVATION_ALGORITHM_PBKDF2),
+            3 => ::std::option::Option::Some(KeyDerivationAlgorithm::KEY_DERIVATION_ALGORITHM_ARGON2ID),
+            4 => ::std::option::Option::Some(KeyDerivationAlgorithm::KEY_DERIVATION_ALGORITHM_SCRYPT),
+            5 => ::std::option::Option::Some(KeyDerivationAlgorithm::KEY_DERIVATION_ALGORITHM_X25519),
+            6 => ::std::option::Option::Some(KeyDerivationAlgorithm::KEY_DERIVATION_ALGORITHM_HKDF),
             _ => ::std::option::Option::None
         }
     }
@@ -855,6 +1995,10 @@ impl ::protobuf::ProtobufEnum for KeyDerivationAlgorithm {
             KeyDerivationAlgorithm::KEY_DERIVATION_ALGORITHM_INVALID,
             KeyDerivationAlgorithm::KEY_DERIVATION_ALGORITHM_NONE,
             KeyDerivationAlgorithm::KEY_DERIVATION_ALGORITHM_PBKDF2,
+            KeyDerivationAlgorithm::KEY_DERIVATION_ALGORITHM_ARGON2ID,
+            KeyDerivationAlgorithm::KEY_DERIVATION_ALGORITHM_SCRYPT,
+            KeyDerivationAlgorithm::KEY_DERIVATION_ALGORITHM_X25519,
+            KeyDerivationAlgorithm::KEY_DERIVATION_ALGORITHM_HKDF,
         ];
         values
     }
@@ -892,6 +2036,8 @@ pub enum EncryptionAlgorithm {
     ENCRYPTION_ALGORITHM_INVALID = 0,
     ENCRYPTION_ALGORITHM_AES256GCM = 1,
     ENCRYPTION_ALGORITHM_CHACHA20_POLY1305 = 2,
+    ENCRYPTION_ALGORITHM_XCHACHA20_POLY1305 = 3,
+    ENCRYPTION_ALGORITHM_AES256GCMSIV = 4,
 }
 
 impl ::protobuf::ProtobufEnum for EncryptionAlgorithm {
@@ -904,6 +2050,8 @@ impl ::protobuf::ProtobufEnum for EncryptionAlgorithm {
             0 => ::std::option::Option::Some(EncryptionAlgorithm::ENCRYPTION_ALGORITHM_INVALID),
             1 => ::std::option::Option::Some(EncryptionAlgorithm::ENCRYPTION_ALGORITHM_AES256GCM),
             2 => ::std::option::Option::Some(EncryptionAlgorithm::ENCRYPTION_ALGORITHM_CHACHA20_POLY1305),
+            3 => ::std::option::Option::Some(EncryptionAlgorithm::ENCRYPTION_ALGORITHM_XCHACHA20_POLY1305),
+            4 => ::std::option::Option::Some(EncryptionAlgorithm::ENCRYPTION_ALGORITHM_AES256GCMSIV),
             _ => ::std::option::Option::None
         }
     }
@@ -913,6 +2061,8 @@ impl ::protobuf::ProtobufEnum for EncryptionAlgorithm {
             EncryptionAlgorithm::ENCRYPTION_ALGORITHM_INVALID,
             EncryptionAlgorithm::ENCRYPTION_ALGORITHM_AES256GCM,
             EncryptionAlgorithm::ENCRYPTION_ALGORITHM_CHACHA20_POLY1305,
+            EncryptionAlgorithm::ENCRYPTION_ALGORITHM_XCHACHA20_POLY1305,
+            EncryptionAlgorithm::ENCRYPTION_ALGORITHM_AES256GCMSIV,
         ];
         values
     }
@@ -946,26 +2096,49 @@ impl ::protobuf::reflect::ProtobufValue for EncryptionAlgorithm {
 }
 
 static file_descriptor_proto_data: &'static [u8] = b"\
-    \n\x14proto/metadata.proto\x12\x08metadata\"\xb2\x01\n\x15KeyDerivationM\
+    \n\x14proto/metadata.proto\x12\x08metadata\"\x8c\x03\n\x15KeyDerivationM\
     etadata\x124\n\x04algo\x18\x01\x20\x01(\x0e2\x20.metadata.KeyDerivationA\
     lgorithmR\x04algo\x12/\n\x07hash_fn\x18\x02\x20\x01(\x0e2\x16.metadata.H\
     ashFunctionR\x06hashFn\x12\x1e\n\niterations\x18\x03\x20\x01(\x04R\niter\
-    ations\x12\x12\n\x04salt\x18\x04\x20\x01(\x0cR\x04salt\"]\n\x12Encryptio\
-    nMetadata\x121\n\x04algo\x18\x01\x20\x01(\x0e2\x1d.metadata.EncryptionAl\
-    gorithmR\x04algo\x12\x14\n\x05nonce\x18\x02\x20\x01(\x0cR\x05nonce\"\xb3\
-    \x01\n\x08Metadata\x12E\n\x0ekey_deriv_meta\x18\x01\x20\x01(\x0b2\x1f.me\
-    tadata.KeyDerivationMetadataR\x0ckeyDerivMeta\x127\n\x08enc_meta\x18\x02\
-    \x20\x01(\x0b2\x1c.metadata.EncryptionMetadataR\x07encMeta\x12'\n\x0fcip\
-    hertext_size\x18\x03\x20\x01(\x04R\x0eciphertextSize*w\n\x0cHashFunction\
-    \x12\x19\n\x15HASH_FUNCTION_INVALID\x10\0\x12\x18\n\x14HASH_FUNCTION_SHA\
-    256\x10\x01\x12\x18\n\x14HASH_FUNCTION_SHA384\x10\x02\x12\x18\n\x14HASH_\
-    FUNCTION_SHA512\x10\x03*\x86\x01\n\x16KeyDerivationAlgorithm\x12$\n\x20K\
-    EY_DERIVATION_ALGORITHM_INVALID\x10\0\x12!\n\x1dKEY_DERIVATION_ALGORITHM\
-    _NONE\x10\x01\x12#\n\x1fKEY_DERIVATION_ALGORITHM_PBKDF2\x10\x02*\x87\x01\
-    \n\x13EncryptionAlgorithm\x12\x20\n\x1cENCRYPTION_ALGORITHM_INVALID\x10\
-    \0\x12\"\n\x1eENCRYPTION_ALGORITHM_AES256GCM\x10\x01\x12*\n&ENCRYPTION_A\
-    LGORITHM_CHACHA20_POLY1305\x10\x02B+\n\x0ccom.metadataB\rMetadataProtoP\
-    \x01Z\nmetadatapbb\x06proto3\
+    ations\x12\x12\n\x04salt\x18\x04\x20\x01(\x0cR\x04salt\x12\x1f\n\x0bmemo\
+    ry_cost\x18\x05\x20\x01(\rR\nmemoryCost\x12\x1b\n\ttime_cost\x18\x06\x20\
+    \x01(\rR\x08timeCost\x12\x20\n\x0bparallelism\x18\x07\x20\x01(\rR\x0bpar\
+    allelism\x12\x13\n\x05log_n\x18\x08\x20\x01(\rR\x04logN\x12\x1d\n\nblock\
+    _size\x18\t\x20\x01(\rR\tblockSize\x120\n\x14ephemeral_public_key\x18\n\
+    \x20\x01(\x0cR\x12ephemeralPublicKey\x12\x12\n\x04info\x18\x0b\x20\x01(\
+    \x0cR\x04info\"]\n\x12EncryptionMetadata\x121\n\x04algo\x18\x01\x20\x01(\
+    \x0e2\x1d.metadata.EncryptionAlgorithmR\x04algo\x12\x14\n\x05nonce\x18\
+    \x02\x20\x01(\x0cR\x05nonce\"\xc1\x01\n\x07KeySlot\x12E\n\x0ekey_deriv_m\
+    eta\x18\x01\x20\x01(\x0b2\x1f.metadata.KeyDerivationMetadataR\x0ckeyDeri\
+    vMeta\x127\n\x08enc_meta\x18\x02\x20\x01(\x0b2\x1c.metadata.EncryptionMe\
+    tadataR\x07encMeta\x12\x1f\n\x0bwrapped_key\x18\x03\x20\x01(\x0cR\nwrapp\
+    edKey\x12\x15\n\x06kek_id\x18\x04\x20\x01(\tR\x05kekId\"H\n\tSignature\
+    \x12\x1d\n\npublic_key\x18\x01\x20\x01(\x0cR\tpublicKey\x12\x1c\n\tsigna\
+    ture\x18\x02\x20\x01(\x0cR\tsignature\"\xe9\x03\n\x08Metadata\x12E\n\x0e\
+    key_deriv_meta\x18\x01\x20\x01(\x0b2\x1f.metadata.KeyDerivationMetadataR\
+    \x0ckeyDerivMeta\x127\n\x08enc_meta\x18\x02\x20\x01(\x0b2\x1c.metadata.E\
+    ncryptionMetadataR\x07encMeta\x12'\n\x0fciphertext_size\x18\x03\x20\x01(\
+    \x04R\x0eciphertextSize\x121\n\x14authenticated_header\x18\x04\x20\x01(\
+    \x08R\x13authenticatedHeader\x12\x1d\n\nchunk_size\x18\x05\x20\x01(\x04R\
+    \tchunkSize\x12$\n\rdeterministic\x18\x06\x20\x01(\x08R\rdeterministic\
+    \x12.\n\tkey_slots\x18\x07\x20\x03(\x0b2\x11.metadata.KeySlotR\x08keySlo\
+    ts\x12\x15\n\x06key_id\x18\x08\x20\x01(\tR\x05keyId\x121\n\tsignature\
+    \x18\t\x20\x01(\x0b2\x13.metadata.SignatureR\tsignature\x12%\n\x0ekey_co\
+    mmitment\x18\n\x20\x01(\x0cR\rkeyCommitment\x12\x1b\n\tkey_check\x18\x0b\
+    \x20\x01(\x0cR\x08keyCheck*w\n\x0cHashFunction\x12\x19\n\x15HASH_FUNCTIO\
+    N_INVALID\x10\0\x12\x18\n\x14HASH_FUNCTION_SHA256\x10\x01\x12\x18\n\x14H\
+    ASH_FUNCTION_SHA384\x10\x02\x12\x18\n\x14HASH_FUNCTION_SHA512\x10\x03*\
+    \x9a\x02\n\x16KeyDerivationAlgorithm\x12$\n\x20KEY_DERIVATION_ALGORITHM_\
+    INVALID\x10\0\x12!\n\x1dKEY_DERIVATION_ALGORITHM_NONE\x10\x01\x12#\n\x1f\
+    KEY_DERIVATION_ALGORITHM_PBKDF2\x10\x02\x12%\n!KEY_DERIVATION_ALGORITHM_\
+    ARGON2ID\x10\x03\x12#\n\x1fKEY_DERIVATION_ALGORITHM_SCRYPT\x10\x04\x12#\
+    \n\x1fKEY_DERIVATION_ALGORITHM_X25519\x10\x05\x12!\n\x1dKEY_DERIVATION_A\
+    LGORITHM_HKDF\x10\x06*\xdb\x01\n\x13EncryptionAlgorithm\x12\x20\n\x1cENC\
+    RYPTION_ALGORITHM_INVALID\x10\0\x12\"\n\x1eENCRYPTION_ALGORITHM_AES256GC\
+    M\x10\x01\x12*\n&ENCRYPTION_ALGORITHM_CHACHA20_POLY1305\x10\x02\x12+\n'E\
+    NCRYPTION_ALGORITHM_XCHACHA20_POLY1305\x10\x03\x12%\n!ENCRYPTION_ALGORIT\
+    HM_AES256GCMSIV\x10\x04B+\n\x0ccom.metadataB\rMetadataProtoP\x01Z\nmetad\
+    atapbb\x06proto3\
 ";
 
 static mut file_descriptor_proto_lazy: ::protobuf::lazy::Lazy<::protobuf::descriptor::FileDescriptorProto> = ::protobuf::lazy::Lazy {
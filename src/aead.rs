@@ -41,6 +41,11 @@
 //!
 //! ```
 //!
+//! The above functions expand the key on every call. If a key is used for a
+//! lot of messages, it's faster to expand it once, and use the
+//! `seal_in_place_with_key()` and `open_in_place_with_key()` functions
+//! instead.
+//!
 //! [`cryptors`]: ../cryptors/index.html
 
 use crate::errors;
@@ -108,13 +113,29 @@ pub fn seal_in_place(
     in_out: &mut [u8],
 ) -> Result<usize, errors::Error> {
     _check_key(algo, key)?;
+    let unbound_key = aead::UnboundKey::new(algo, key).unwrap();
+    let key = aead::LessSafeKey::new(unbound_key);
+    seal_in_place_with_key(&key, nonce, aad, in_out)
+}
+
+/// Seal the contents of a data buffer in place, with an expanded key.
+///
+/// This function is the same as `seal_in_place()`, with the exception that
+/// it accepts a `ring` key, which has been created beforehand for a specific
+/// AEAD algorithm. This way, the key is expanded only once, and not on every
+/// call.
+pub fn seal_in_place_with_key(
+    key: &aead::LessSafeKey,
+    nonce: [u8; NONCE_SIZE],
+    aad: &[u8],
+    in_out: &mut [u8],
+) -> Result<usize, errors::Error> {
+    let algo = key.algorithm();
     _check_in_out(algo, in_out)?;
 
     let tag_size = algo.tag_len();
     let plaintext_size: usize = in_out.len() - tag_size;
     let plaintext = &mut in_out[..plaintext_size];
-    let unbound_key = aead::UnboundKey::new(algo, key).unwrap();
-    let key = aead::LessSafeKey::new(unbound_key);
     let nonce = aead::Nonce::assume_unique_for_key(nonce);
     let aad = aead::Aad::from(aad);
     let res = key.seal_in_place_separate_tag(nonce, aad, plaintext);
@@ -157,10 +178,25 @@ pub fn open_in_place(
     in_out: &mut [u8],
 ) -> Result<usize, errors::Error> {
     _check_key(algo, key)?;
-    _check_in_out(algo, in_out)?;
-
     let unbound_key = aead::UnboundKey::new(algo, key).unwrap();
     let key = aead::LessSafeKey::new(unbound_key);
+    open_in_place_with_key(&key, nonce, aad, in_out)
+}
+
+/// Open the contents of a sealed data buffer in place, with an expanded key.
+///
+/// This function is the same as `open_in_place()`, with the exception that
+/// it accepts a `ring` key, which has been created beforehand for a specific
+/// AEAD algorithm. This way, the key is expanded only once, and not on every
+/// call.
+pub fn open_in_place_with_key(
+    key: &aead::LessSafeKey,
+    nonce: [u8; NONCE_SIZE],
+    aad: &[u8],
+    in_out: &mut [u8],
+) -> Result<usize, errors::Error> {
+    _check_in_out(key.algorithm(), in_out)?;
+
     let nonce = aead::Nonce::assume_unique_for_key(nonce);
     let aad = aead::Aad::from(aad);
     let res = key.open_in_place(nonce, aad, in_out);
@@ -297,6 +333,36 @@ mod tests {
         res = open_in_place(algo, nonce.clone(), &aad, &key, &mut in_out);
         assert_eq!(res, exp_res);
         assert_eq!(in_out[..res.unwrap()], vec![4u8; res.unwrap()][..]);
+
+        // Check that an expanded key produces the same results.
+        let unbound_key = aead::UnboundKey::new(algo, &key).unwrap();
+        let expanded_key = aead::LessSafeKey::new(unbound_key);
+        let mut key_in_out = [4; BUF_SIZE];
+        res = seal_in_place_with_key(
+            &expanded_key,
+            nonce,
+            &aad,
+            &mut key_in_out,
+        );
+        assert_eq!(res, exp_res);
+        assert_eq!(key_in_out, seal());
+        res = open_in_place_with_key(
+            &expanded_key,
+            nonce,
+            &aad,
+            &mut key_in_out,
+        );
+        assert_eq!(res, exp_res);
+        assert_eq!(key_in_out[..plaintext_size], in_out[..plaintext_size]);
+        res = seal_in_place_with_key(&expanded_key, nonce, &aad, &mut []);
+        assert_eq!(res, buf_err);
+        res = open_in_place_with_key(
+            &expanded_key,
+            bad_nonce,
+            &aad,
+            &mut seal(),
+        );
+        assert_eq!(res, dec_err);
     }
 
     #[test]
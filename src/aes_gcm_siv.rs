@@ -0,0 +1,270 @@
+//! # AES-256-GCM-SIV helpers
+//!
+//! This module contains some wrappers over the AES-256-GCM-SIV AEAD of the
+//! [`aes-gcm-siv`] crate, since the `ring` library does not support it. They
+//! have the same semantics as the wrappers in the [`aead`] module. You are
+//! advised to not use these low-level functions directly, and instead use the
+//! functions provided by the [`cryptors`] module.
+//!
+//! AES-256-GCM-SIV ([RFC 8452]) is a nonce-misuse-resistant AEAD. If a nonce
+//! is accidentally reused for the same key, an attacker can only learn whether
+//! two messages (and their associated data) are identical, instead of
+//! recovering the plaintexts or forging messages, as is the case with
+//! AES-256-GCM.
+//!
+//! This module also provides the [`synthetic_nonce`] function, which derives
+//! a nonce from the key, the associated data and the plaintext. Using it
+//! turns AES-256-GCM-SIV into a deterministic AEAD, where equal messages are
+//! encrypted to equal ciphertexts. This function is available only if the
+//! `ring` feature is enabled.
+//!
+//! ## Examples
+//!
+//! ```
+//! use tindercrypt::rand::fill_buf;
+//! use tindercrypt::aes_gcm_siv::{seal_in_place, open_in_place};
+//! use tindercrypt::aes_gcm_siv::{KEY_SIZE, NONCE_SIZE, TAG_SIZE};
+//!
+//! let mut nonce = [0u8; NONCE_SIZE];
+//! let aad = "My encryption context".as_bytes();
+//! let mut key = [0u8; KEY_SIZE];
+//! let data = "The cake is a lie".as_bytes();
+//!
+//! // Create a unique nonce and key.
+//! fill_buf(&mut nonce);
+//! fill_buf(&mut key);
+//!
+//! // Create a buffer large enough to hold the ciphertext and its tag.
+//! let mut buf = vec![0; data.len() + TAG_SIZE];
+//! buf[..data.len()].copy_from_slice(&data);
+//!
+//! // Encrypt (seal) and decrypt (open) the data buffer in place.
+//! seal_in_place(nonce, &aad, &key, &mut buf)?;
+//! open_in_place(nonce, &aad, &key, &mut buf)?;
+//! assert_eq!(data, &buf[..data.len()]);
+//!
+//! # use tindercrypt::errors;
+//! # Ok::<(), errors::Error>(())
+//! ```
+//!
+//! [`aes-gcm-siv`]: https://github.com/RustCrypto/AEADs
+//! [RFC 8452]: https://tools.ietf.org/html/rfc8452
+//! [`aead`]: ../aead/index.html
+//! [`cryptors`]: ../cryptors/index.html
+//! [`synthetic_nonce`]: fn.synthetic_nonce.html
+
+use crate::errors;
+use aes_gcm_siv::aead::{AeadInPlace, KeyInit};
+use aes_gcm_siv::{Aes256GcmSiv, Nonce, Tag};
+#[cfg(feature = "ring")]
+use ring::hmac;
+
+/// The size of the nonces for AES-256-GCM-SIV.
+pub const NONCE_SIZE: usize = 12;
+
+/// The size of the keys for AES-256-GCM-SIV.
+pub const KEY_SIZE: usize = 32;
+
+/// The size of the tags for AES-256-GCM-SIV.
+pub const TAG_SIZE: usize = 16;
+
+/// The label that is used to derive the key for the synthetic nonces, so
+/// that it is different from the encryption key.
+#[cfg(feature = "ring")]
+const SYNTHETIC_NONCE_LABEL: &[u8] = b"tindercrypt synthetic nonce";
+
+/// Derive a synthetic nonce from the key, the associated data and the
+/// plaintext.
+///
+/// The nonce is the truncated HMAC-SHA256 of the associated data and the
+/// plaintext, using a subkey of the encryption key. Therefore, it is the same
+/// only for equal messages, which makes the encryption deterministic, but
+/// does not reveal anything else about the plaintext.
+#[cfg(feature = "ring")]
+pub fn synthetic_nonce(
+    key: &[u8],
+    aad: &[u8],
+    plaintext: &[u8],
+) -> [u8; NONCE_SIZE] {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, key);
+    let subkey = hmac::sign(&key, SYNTHETIC_NONCE_LABEL);
+    let subkey = hmac::Key::new(hmac::HMAC_SHA256, subkey.as_ref());
+
+    // Prefix the associated data with their length, so that they cannot be
+    // confused with the plaintext.
+    let mut ctx = hmac::Context::with_key(&subkey);
+    ctx.update(&(aad.len() as u64).to_be_bytes());
+    ctx.update(aad);
+    ctx.update(plaintext);
+
+    let mut nonce = [0u8; NONCE_SIZE];
+    nonce.copy_from_slice(&ctx.sign().as_ref()[..NONCE_SIZE]);
+    nonce
+}
+
+/// Create a cipher instance, after checking the key size.
+fn _new_cipher(key: &[u8]) -> Result<Aes256GcmSiv, errors::Error> {
+    if key.len() != KEY_SIZE {
+        return Err(errors::Error::KeySizeMismatch);
+    }
+    // NOTE: It's safe to unwrap the result, since we have checked the key
+    // size above.
+    Ok(Aes256GcmSiv::new_from_slice(key).unwrap())
+}
+
+/// Seal the contents of a data buffer in place.
+///
+/// The data buffer must start with the plaintext, and have enough space at the
+/// end for the tag. Note that the tag of AES-256-GCM-SIV also serves as the
+/// synthetic IV of the encryption. This function returns an error if the
+/// key/buffer sizes are not the expected ones. If the encryption succeeds, it
+/// returns the length of the plaintext.
+pub fn seal_in_place(
+    nonce: [u8; NONCE_SIZE],
+    aad: &[u8],
+    key: &[u8],
+    in_out: &mut [u8],
+) -> Result<usize, errors::Error> {
+    let cipher = _new_cipher(key)?;
+    if in_out.len() < TAG_SIZE {
+        return Err(errors::Error::BufferTooSmall);
+    }
+
+    let plaintext_size = in_out.len() - TAG_SIZE;
+    let (plaintext, tag) = in_out.split_at_mut(plaintext_size);
+    let nonce = Nonce::from_slice(&nonce);
+    let res = cipher.encrypt_in_place_detached(nonce, aad, plaintext);
+
+    match res {
+        Ok(t) => {
+            tag.copy_from_slice(&t);
+            Ok(plaintext_size)
+        }
+        Err(error) => panic!("Error during sealing: {:?}", error),
+    }
+}
+
+/// Open the contents of a sealed data buffer in place.
+///
+/// The data buffer must contain the ciphertext and its tag. This function
+/// returns an error if the key/buffer sizes are not the expected ones, or if
+/// the decryption process fails, e.g., due to a wrong key, nonce, etc. If the
+/// decryption succeeds, it returns the length of the plaintext.
+pub fn open_in_place(
+    nonce: [u8; NONCE_SIZE],
+    aad: &[u8],
+    key: &[u8],
+    in_out: &mut [u8],
+) -> Result<usize, errors::Error> {
+    let cipher = _new_cipher(key)?;
+    if in_out.len() < TAG_SIZE {
+        return Err(errors::Error::BufferTooSmall);
+    }
+
+    let ciphertext_size = in_out.len() - TAG_SIZE;
+    let (ciphertext, tag) = in_out.split_at_mut(ciphertext_size);
+    let nonce = Nonce::from_slice(&nonce);
+    let tag = Tag::from_slice(tag);
+    let res = cipher.decrypt_in_place_detached(nonce, aad, ciphertext, tag);
+
+    match res {
+        Ok(_) => Ok(ciphertext_size),
+        Err(_) => Err(errors::Error::DecryptionError),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUF_SIZE: usize = 36; // 36 bytes can contain the tag and data.
+
+    #[test]
+    fn test_seal_open() {
+        let nonce = [1; NONCE_SIZE];
+        let aad = [2; 9];
+        let key = [3; KEY_SIZE];
+        let mut in_out: [u8; BUF_SIZE];
+        let mut res: Result<usize, errors::Error>;
+        let exp_res = Ok(BUF_SIZE - TAG_SIZE);
+        let dec_err = Err(errors::Error::DecryptionError);
+        let buf_err = Err(errors::Error::BufferTooSmall);
+        let key_err = Err(errors::Error::KeySizeMismatch);
+
+        let seal = || {
+            let mut _in_out = [4; BUF_SIZE];
+            let r = seal_in_place(nonce, &aad, &key, &mut _in_out);
+            assert_eq!(r, exp_res);
+            _in_out
+        };
+
+        // Check that any type of data corruption makes decryption fail.
+        //
+        // Corrupted nonce.
+        in_out = seal();
+        let mut bad_nonce = nonce;
+        bad_nonce[NONCE_SIZE - 1] = 9;
+        res = open_in_place(bad_nonce, &aad, &key, &mut in_out);
+        assert_eq!(res, dec_err);
+
+        // Corrupted additional authenticated data.
+        in_out = seal();
+        res = open_in_place(nonce, &[9; 9], &key, &mut in_out);
+        assert_eq!(res, dec_err);
+
+        // Corrupted key.
+        in_out = seal();
+        res = open_in_place(nonce, &aad, &[9; KEY_SIZE], &mut in_out);
+        assert_eq!(res, dec_err);
+
+        // Corrupted tag.
+        in_out = seal();
+        in_out[BUF_SIZE - 1] ^= 1;
+        res = open_in_place(nonce, &aad, &key, &mut in_out);
+        assert_eq!(res, dec_err);
+
+        // Incomplete data buffer.
+        res = seal_in_place(nonce, &aad, &key, &mut [0; TAG_SIZE - 1]);
+        assert_eq!(res, buf_err);
+        res = open_in_place(nonce, &aad, &key, &mut []);
+        assert_eq!(res, buf_err);
+
+        // Incomplete key.
+        in_out = seal();
+        res = seal_in_place(nonce, &aad, &[], &mut in_out);
+        assert_eq!(res, key_err);
+        res = open_in_place(nonce, &aad, &key[1..], &mut in_out);
+        assert_eq!(res, key_err);
+
+        // Check that reusing a nonce leaks only the equality of the messages.
+        let mut in_out2 = [5; BUF_SIZE];
+        let _ = seal_in_place(nonce, &aad, &key, &mut in_out2);
+        in_out = seal();
+        assert_ne!(in_out[..], in_out2[..]);
+        assert_eq!(in_out[..], seal()[..]);
+
+        // Correct decryption.
+        in_out = seal();
+        res = open_in_place(nonce, &aad, &key, &mut in_out);
+        assert_eq!(res, exp_res);
+        assert_eq!(in_out[..res.unwrap()], [4u8; BUF_SIZE - TAG_SIZE][..]);
+    }
+
+    #[test]
+    #[cfg(feature = "ring")]
+    fn test_synthetic_nonce() {
+        let key = [1; KEY_SIZE];
+        let nonce = synthetic_nonce(&key, b"aad", b"plaintext");
+
+        // Check that the nonce depends on every input, and that the boundary
+        // between the associated data and the plaintext matters.
+        assert_eq!(nonce, synthetic_nonce(&key, b"aad", b"plaintext"));
+        assert_ne!(
+            nonce,
+            synthetic_nonce(&[2; KEY_SIZE], b"aad", b"plaintext")
+        );
+        assert_ne!(nonce, synthetic_nonce(&key, b"add", b"plaintext"));
+        assert_ne!(nonce, synthetic_nonce(&key, b"aad", b"plaintexT"));
+        assert_ne!(nonce, synthetic_nonce(&key, b"aadp", b"laintext"));
+    }
+}
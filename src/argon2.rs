@@ -0,0 +1,119 @@
+//! # Argon2 helpers
+//!
+//! This module contains helpers for the Argon2id algorithm.
+
+use crate::errors;
+use argon2::{Algorithm, Argon2, Params, Version};
+
+/// Cryptographically create a symmetric key from a secret value.
+///
+/// Create a symmetric key from a secret value, based on various Argon2id
+/// parameters; a memory cost (in KiB), a time cost (number of passes), a
+/// degree of parallelism and a salt.
+///
+/// This method returns an error if the parameters are too weak or not
+/// accepted by the Argon2 specification, e.g., a zero time cost, a memory
+/// cost lower than 8 KiB per lane, or a salt shorter than 8 bytes. Also, it
+/// returns an error if the user has not provided a buffer for the key or a
+/// secret value.
+///
+/// ## Examples
+///
+/// A safe method to derive a key with Argon2id is the following:
+///
+/// ```
+/// use tindercrypt::argon2::derive_key;
+/// use tindercrypt::rand::fill_buf;
+///
+/// let memory_cost = 65536; // 64 MiB
+/// let time_cost = 3;
+/// let parallelism = 4;
+/// let mut salt = [0u8; 32];
+/// let secret = "My secret password".as_bytes();
+/// let mut key = [0u8; 32];
+///
+/// fill_buf(&mut salt);
+/// # // Lower the memory cost for the tests.
+/// # let memory_cost = 32;
+/// derive_key(memory_cost, time_cost, parallelism, &salt, &secret, &mut key);
+/// ```
+pub fn derive_key(
+    memory_cost: u32,
+    time_cost: u32,
+    parallelism: u32,
+    salt: &[u8],
+    secret: &[u8],
+    key: &mut [u8],
+) -> Result<(), errors::Error> {
+    if secret.is_empty() || key.is_empty() {
+        return Err(errors::Error::PassphraseTooSmall);
+    }
+
+    let params = match Params::new(
+        memory_cost,
+        time_cost,
+        parallelism,
+        Some(key.len()),
+    ) {
+        Ok(params) => params,
+        Err(_) => return Err(errors::Error::CryptoParamsWeak),
+    };
+
+    let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+    match argon2.hash_password_into(secret, salt, key) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(errors::Error::CryptoParamsWeak),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_argon2_derive_key() {
+        let mut salt = [9; 10];
+        let mut secret = [99; 10];
+        let mut key1 = [0u8; 32];
+        let mut key2 = [0u8; 32];
+        let mut key3 = [0u8; 32];
+        let mut key4 = [0u8; 32];
+        let mut res: Result<(), errors::Error>;
+        let params_err = Err(errors::Error::CryptoParamsWeak);
+        let size_err = Err(errors::Error::PassphraseTooSmall);
+
+        // Check that weak parameters and empty buffers are reported as errors.
+        res = derive_key(8, 0, 1, &salt, &secret, &mut key1);
+        assert_eq!(res, params_err);
+        res = derive_key(8, 1, 0, &salt, &secret, &mut key1);
+        assert_eq!(res, params_err);
+        res = derive_key(8, 1, 2, &salt, &secret, &mut key1);
+        assert_eq!(res, params_err);
+        res = derive_key(8, 1, 1, &[], &secret, &mut key1);
+        assert_eq!(res, params_err);
+        res = derive_key(8, 1, 1, &salt, &[], &mut key1);
+        assert_eq!(res, size_err);
+        res = derive_key(8, 1, 1, &salt, &secret, &mut []);
+        assert_eq!(res, size_err);
+
+        // Check that key derivation works, and that changes in the salt and
+        // secret produce different keys.
+        res = derive_key(8, 1, 1, &salt, &secret, &mut key1);
+        assert!(res.is_ok());
+        salt[0] = 0;
+        res = derive_key(8, 1, 1, &salt, &secret, &mut key2);
+        assert!(res.is_ok());
+        salt[0] = 9;
+        secret[0] = 0;
+        res = derive_key(8, 1, 1, &salt, &secret, &mut key3);
+        assert!(res.is_ok());
+        secret[0] = 99;
+        res = derive_key(8, 1, 1, &salt, &secret, &mut key4);
+        assert!(res.is_ok());
+
+        assert_ne!(key1, key2);
+        assert_ne!(key1, key3);
+        assert_ne!(key2, key3);
+        assert_eq!(key1, key4);
+    }
+}
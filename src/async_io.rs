@@ -0,0 +1,676 @@
+//! # Async I/O adapters for encryption/decryption
+//!
+//! This module provides the [`AsyncEncryptWriter`] and [`AsyncDecryptReader`]
+//! structs, which implement the `AsyncWrite` and `AsyncRead` traits of the
+//! [Tokio] runtime respectively. They are the async counterparts of the
+//! adapters in the [`io`] module, and produce/consume the exact same wire
+//! format.
+//!
+//! The key derivation, which can take a considerable amount of time, is
+//! offloaded to a blocking thread via `tokio::task::spawn_blocking()`, so that
+//! it does not stall the executor. The encryption/decryption of each chunk is
+//! fast enough to be performed in place.
+//!
+//! This module is available only if the `tokio` feature is enabled.
+//!
+//! ## Examples
+//!
+//! ```
+//! use tokio::io::{AsyncReadExt, AsyncWriteExt};
+//! use tindercrypt::async_io::{AsyncDecryptReader, AsyncEncryptWriter};
+//! use tindercrypt::cryptors::RingCryptor;
+//! use tindercrypt::metadata;
+//!
+//! # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+//! let plaintext = "The cake is a lie".as_bytes();
+//! let key = [9u8; 32];
+//! let cryptor = RingCryptor::new();
+//!
+//! // Encrypt the plaintext and write it to a buffer. Note that the writer
+//! // must be shut down, so that the last chunk is written.
+//! let mut writer = AsyncEncryptWriter::with_key(&cryptor, &key, Vec::new()).await?;
+//! writer.write_all(plaintext).await?;
+//! writer.shutdown().await?;
+//! let ciphertext = writer.into_inner();
+//!
+//! // Decrypt the ciphertext from the buffer.
+//! let mut reader = AsyncDecryptReader::new(&cryptor, &key, &ciphertext[..]).await?;
+//! let mut plaintext2 = Vec::new();
+//! reader.read_to_end(&mut plaintext2).await?;
+//! assert_eq!(plaintext2, plaintext);
+//!
+//! // The ciphertext can also be decrypted with the sync API.
+//! assert_eq!(cryptor.open(&key, &ciphertext).unwrap(), plaintext);
+//! # Ok::<(), std::io::Error>(())
+//! # }).unwrap();
+//! ```
+//!
+//! [`AsyncEncryptWriter`]: struct.AsyncEncryptWriter.html
+//! [`AsyncDecryptReader`]: struct.AsyncDecryptReader.html
+//! [`io`]: ../io/index.html
+//! [Tokio]: https://tokio.rs
+
+use crate::cache::DerivedKeyCache;
+use crate::cryptors::RingCryptor;
+use crate::errors;
+use crate::io::{_parse_header_size, _to_io_error};
+use crate::metadata;
+use crate::stream::{StreamOpener, StreamSealer};
+use std::cmp;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, ReadBuf};
+use zeroize::{Zeroize, Zeroizing};
+
+/// Derive a symmetric key from a secret value, in a blocking thread.
+///
+/// The key derivation algorithms are CPU-intensive by design, so we run them
+/// via `spawn_blocking()`, in order to not stall the executor. The metadata
+/// are checked against the decryption policy of the cryptor beforehand, since
+/// the policy cannot be moved to the blocking thread. For the same reason, the
+/// key cache of the cryptor, if any, is used outside the blocking thread.
+async fn _derive_key(
+    cryptor: &RingCryptor<'_>,
+    meta: &metadata::Metadata,
+    secret: &[u8],
+) -> io::Result<Zeroizing<Vec<u8>>> {
+    if let Err(err) = cryptor._check_policy(meta) {
+        return Err(_to_io_error(err));
+    }
+    let key_size = cryptor._get_key_size(meta);
+
+    // If the key has been cached, there's no need for a blocking thread.
+    let key_cache = match cryptor._get_key_cache() {
+        Some(key_cache)
+            if meta.key_slots.is_empty()
+                && DerivedKeyCache::_is_cacheable(&meta.key_deriv_algo) =>
+        {
+            Some(key_cache)
+        }
+        _ => None,
+    };
+    if let Some(key_cache) = key_cache {
+        let mut key = Zeroizing::new(vec![0u8; key_size]);
+        if key_cache._get(&meta.key_deriv_algo, secret, &mut key) {
+            return match cryptor._get_key(meta, secret, &mut key) {
+                Ok(_) => Ok(key),
+                Err(err) => Err(_to_io_error(err)),
+            };
+        }
+    }
+
+    let blocking_meta = meta.clone();
+    let blocking_secret = Zeroizing::new(secret.to_vec());
+    let res = tokio::task::spawn_blocking(move || {
+        let mut key = Zeroizing::new(vec![0u8; key_size]);
+        RingCryptor::new()
+            ._get_key(&blocking_meta, &blocking_secret, &mut key)
+            .map(|_| key)
+    })
+    .await;
+
+    match res {
+        Ok(Ok(key)) => {
+            if let Some(key_cache) = key_cache {
+                key_cache._insert(&meta.key_deriv_algo, secret, &key);
+            }
+            Ok(key)
+        }
+        Ok(Err(err)) => Err(_to_io_error(err)),
+        Err(err) => Err(io::Error::other(err)),
+    }
+}
+
+/// Read a serialized metadata header from an async reader.
+///
+/// This is the async counterpart of the header parsing logic of the
+/// `DecryptReader`. It does not read more bytes than the header has, so that
+/// the reader is left at the start of the ciphertext.
+async fn _read_header<R: AsyncRead + Unpin>(
+    inner: &mut R,
+) -> io::Result<Vec<u8>> {
+    let mut header = Vec::new();
+    let prefix_size = metadata::HEADER_PREFIX_SIZE as u64;
+    let _ = inner.take(prefix_size).read_to_end(&mut header).await?;
+    if header.len() < metadata::HEADER_PREFIX_SIZE {
+        return Ok(header);
+    }
+
+    // Read the varint, byte by byte.
+    let header_size = loop {
+        match _parse_header_size(&header) {
+            Ok(Some(size)) => break size,
+            Ok(None) => (),
+            Err(err) => return Err(_to_io_error(err)),
+        }
+        let mut byte = [0u8; 1];
+        if inner.read(&mut byte).await? == 0 {
+            return Ok(header);
+        }
+        header.push(byte[0]);
+    };
+
+    // NOTE: The bytes that we have read may exceed the header size only for
+    // legacy headers that are too small to be valid, in which case the
+    // decryption will fail anyway.
+    if header_size <= header.len() {
+        header.truncate(header_size);
+        return Ok(header);
+    }
+
+    let msg_size = (header_size - header.len()) as u64;
+    let _ = inner.take(msg_size).read_to_end(&mut header).await?;
+    Ok(header)
+}
+
+/// An async writer that encrypts the data that are written to it.
+///
+/// This is the async counterpart of the `EncryptWriter`. The encrypted data,
+/// along with their metadata header, are written to an inner writer.
+///
+/// The user must shut down the writer (e.g., via `AsyncWriteExt::shutdown()`)
+/// once all the data have been written, so that the last chunk is encrypted
+/// and written to the inner writer. Else, the encrypted stream will be
+/// incomplete, and its decryption will fail.
+#[derive(Debug)]
+pub struct AsyncEncryptWriter<'a, W: AsyncWrite + Unpin> {
+    inner: W,
+    sealer: Option<StreamSealer<'a>>,
+    chunk_size: usize,
+    buf: Zeroizing<Vec<u8>>,
+    out: Vec<u8>,
+    out_pos: usize,
+}
+
+impl<'a, W: AsyncWrite + Unpin> AsyncEncryptWriter<'a, W> {
+    /// Create a new encrypting writer, using the provided metadata.
+    ///
+    /// This method accepts a cryptor, the metadata for a stream (see
+    /// `Metadata::new_stream()`), a secret value (either a key or a
+    /// passphrase) and the inner writer, where the encrypted data will be
+    /// written to. It returns an error if the metadata do not describe a
+    /// stream, or if the key derivation fails.
+    pub async fn new(
+        cryptor: &RingCryptor<'a>,
+        meta: &metadata::Metadata,
+        secret: &[u8],
+        inner: W,
+    ) -> io::Result<Self> {
+        if meta.chunk_size.is_none() {
+            return Err(_to_io_error(errors::Error::MetadataInvalid));
+        }
+
+        let key = _derive_key(cryptor, meta, secret).await?;
+        let sealer = match StreamSealer::_from_key(cryptor, meta, &key) {
+            Ok(sealer) => sealer,
+            Err(err) => return Err(_to_io_error(err)),
+        };
+        Ok(Self {
+            inner,
+            chunk_size: sealer.chunk_size(),
+            buf: Zeroizing::new(Vec::with_capacity(sealer.chunk_size())),
+            sealer: Some(sealer),
+            out: meta.to_header(),
+            out_pos: 0,
+        })
+    }
+
+    /// Create a new encrypting writer, using a symmetric key.
+    ///
+    /// It generates the metadata for a stream, with no key derivation, the
+    /// encryption algorithm of the cryptor and the default chunk size.
+    pub async fn with_key(
+        cryptor: &RingCryptor<'a>,
+        key: &[u8],
+        inner: W,
+    ) -> io::Result<Self> {
+        let meta = metadata::Metadata::new_stream(
+            metadata::KeyDerivationAlgorithm::None,
+            cryptor.generate_enc_algo(),
+            metadata::STREAM_DEFAULT_CHUNK_SIZE,
+        );
+        Self::new(cryptor, &meta, key, inner).await
+    }
+
+    /// Create a new encrypting writer, using a passphrase.
+    ///
+    /// It generates the metadata for a stream, with the key derivation and
+    /// encryption algorithms of the cryptor and the default chunk size.
+    pub async fn with_passphrase(
+        cryptor: &RingCryptor<'a>,
+        pass: &[u8],
+        inner: W,
+    ) -> io::Result<Self> {
+        let meta = metadata::Metadata::new_stream(
+            cryptor.generate_key_deriv_algo(),
+            cryptor.generate_enc_algo(),
+            metadata::STREAM_DEFAULT_CHUNK_SIZE,
+        );
+        Self::new(cryptor, &meta, pass, inner).await
+    }
+
+    /// Get a reference to the inner writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Consume the writer and return the inner writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Write any pending encrypted data to the inner writer.
+    fn _poll_write_out(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        while self.out_pos < self.out.len() {
+            let data = &self.out[self.out_pos..];
+            match Pin::new(&mut self.inner).poll_write(cx, data) {
+                Poll::Ready(Ok(0)) => {
+                    return Poll::Ready(Err(io::ErrorKind::WriteZero.into()))
+                }
+                Poll::Ready(Ok(size)) => self.out_pos += size,
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        self.out.clear();
+        self.out_pos = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<'a, W: AsyncWrite + Unpin> AsyncWrite for AsyncEncryptWriter<'a, W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        data: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if let Poll::Ready(Err(err)) = this._poll_write_out(cx) {
+            return Poll::Ready(Err(err));
+        }
+        if !this.out.is_empty() {
+            return Poll::Pending;
+        }
+
+        let sealer = match &mut this.sealer {
+            Some(sealer) => sealer,
+            None => return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into())),
+        };
+
+        // NOTE: We can't encrypt a full chunk as soon as it's buffered, since
+        // it may be the last one. So, we wait until more data arrive.
+        if this.buf.len() == this.chunk_size && !data.is_empty() {
+            this.out = match sealer.seal_chunk(&this.buf) {
+                Ok(chunk) => chunk,
+                Err(err) => return Poll::Ready(Err(_to_io_error(err))),
+            };
+            this.buf.zeroize();
+            if let Poll::Ready(Err(err)) = this._poll_write_out(cx) {
+                return Poll::Ready(Err(err));
+            }
+        }
+
+        let size = cmp::min(data.len(), this.chunk_size - this.buf.len());
+        this.buf.extend_from_slice(&data[..size]);
+        Poll::Ready(Ok(size))
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        match this._poll_write_out(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.inner).poll_flush(cx),
+            res => res,
+        }
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        match this._poll_write_out(cx) {
+            Poll::Ready(Ok(())) => (),
+            res => return res,
+        }
+
+        // Encrypt the last chunk, and write it to the inner writer.
+        if let Some(sealer) = this.sealer.take() {
+            this.out = match sealer.seal_last_chunk(&this.buf) {
+                Ok(chunk) => chunk,
+                Err(err) => return Poll::Ready(Err(_to_io_error(err))),
+            };
+            this.buf.zeroize();
+            match this._poll_write_out(cx) {
+                Poll::Ready(Ok(())) => (),
+                res => return res,
+            }
+        }
+
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+/// An async reader that decrypts the data that are read from an inner
+/// reader.
+///
+/// This is the async counterpart of the `DecryptReader`. It expects a
+/// metadata header, followed by the ciphertext. If the data have been
+/// encrypted as a stream, they are decrypted chunk by chunk. Else, the
+/// ciphertext is read and decrypted in one go when the reader is created.
+#[derive(Debug)]
+pub struct AsyncDecryptReader<'a, R: AsyncRead + Unpin> {
+    inner: R,
+    meta: metadata::Metadata,
+    opener: Option<StreamOpener<'a>>,
+    ct_buf: Vec<u8>,
+    eof: bool,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl<'a, R: AsyncRead + Unpin> AsyncDecryptReader<'a, R> {
+    /// Create a new decrypting reader.
+    ///
+    /// This method accepts a cryptor, a secret value (either a key or a
+    /// passphrase) and the inner reader, where the encrypted data will be read
+    /// from. It reads the metadata header from the inner reader and derives
+    /// the key, so it returns an error if the header is missing or invalid,
+    /// or if the key derivation fails. If the data have not been encrypted as
+    /// a stream, it also decrypts them, and returns any decryption error.
+    pub async fn new(
+        cryptor: &RingCryptor<'a>,
+        secret: &[u8],
+        mut inner: R,
+    ) -> io::Result<Self> {
+        let header = _read_header(&mut inner).await?;
+        let meta = match metadata::Metadata::from_buf(&header) {
+            Ok((meta, _)) => meta,
+            Err(err) => return Err(_to_io_error(err)),
+        };
+
+        let key = _derive_key(cryptor, &meta, secret).await?;
+        let mut reader = Self {
+            inner,
+            meta: meta.clone(),
+            opener: None,
+            ct_buf: Vec::new(),
+            eof: false,
+            buf: Vec::new(),
+            pos: 0,
+        };
+
+        if meta.chunk_size.is_some() {
+            match StreamOpener::_from_key(cryptor, &meta, &key) {
+                Ok(opener) => reader.opener = Some(opener),
+                Err(err) => return Err(_to_io_error(err)),
+            }
+            return Ok(reader);
+        }
+
+        // NOTE: We don't allocate the buffer beforehand, since the ciphertext
+        // size comes from an untrusted header.
+        let ciphertext_size = meta.ciphertext_size;
+        let mut buf = Vec::new();
+        let size = (&mut reader.inner)
+            .take(ciphertext_size as u64)
+            .read_to_end(&mut buf)
+            .await?;
+        if size != ciphertext_size {
+            return Err(_to_io_error(errors::Error::BufferTooSmall));
+        }
+
+        let aad = cryptor._get_aad(&meta);
+        match cryptor._open_in_place(&meta.enc_algo, &aad, &key, &mut buf) {
+            Ok(size) => buf.truncate(size),
+            Err(err) => return Err(_to_io_error(err)),
+        }
+        reader.buf = buf;
+        Ok(reader)
+    }
+
+    /// Get the metadata of the encrypted data.
+    pub fn metadata(&self) -> &metadata::Metadata {
+        &self.meta
+    }
+
+    /// Get a reference to the inner reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Read the next encrypted chunk of a stream.
+    ///
+    /// In order to know if a chunk is the last one, we read one byte more
+    /// than the size of an encrypted chunk, and carry it over to the next
+    /// chunk.
+    fn _poll_read_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+        ct_chunk_size: usize,
+    ) -> Poll<io::Result<()>> {
+        while !self.eof && self.ct_buf.len() < ct_chunk_size + 1 {
+            let filled = self.ct_buf.len();
+            self.ct_buf.resize(ct_chunk_size + 1, 0u8);
+            let mut read_buf = ReadBuf::new(&mut self.ct_buf[filled..]);
+            let res = Pin::new(&mut self.inner).poll_read(cx, &mut read_buf);
+            let size = read_buf.filled().len();
+            self.ct_buf.truncate(filled + size);
+
+            match res {
+                Poll::Ready(Ok(())) => self.eof = size == 0,
+                res => return res,
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<'a, R: AsyncRead + Unpin> AsyncRead for AsyncDecryptReader<'a, R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        out: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        while this.pos == this.buf.len() {
+            let ct_chunk_size = match &this.opener {
+                Some(opener) => opener.encrypted_chunk_size(),
+                None => return Poll::Ready(Ok(())),
+            };
+
+            match this._poll_read_chunk(cx, ct_chunk_size) {
+                Poll::Ready(Ok(())) => (),
+                res => return res,
+            }
+
+            let res = if this.ct_buf.len() > ct_chunk_size {
+                // NOTE: It's safe to unwrap the opener, since we have checked
+                // it above.
+                let opener = this.opener.as_mut().unwrap();
+                let res = opener.open_chunk(&this.ct_buf[..ct_chunk_size]);
+                let _ = this.ct_buf.drain(..ct_chunk_size);
+                res
+            } else {
+                let opener = this.opener.take().unwrap();
+                let res = opener.open_last_chunk(&this.ct_buf);
+                this.ct_buf.clear();
+                res
+            };
+
+            match res {
+                Ok(buf) => {
+                    this.buf.zeroize();
+                    this.buf = buf;
+                    this.pos = 0;
+                }
+                Err(err) => {
+                    this.opener = None;
+                    return Poll::Ready(Err(_to_io_error(err)));
+                }
+            }
+        }
+
+        let size = cmp::min(out.remaining(), this.buf.len() - this.pos);
+        out.put_slice(&this.buf[this.pos..this.pos + size]);
+        this.pos += size;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<'a, R: AsyncRead + Unpin> Drop for AsyncDecryptReader<'a, R> {
+    fn drop(&mut self) {
+        self.buf.zeroize();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::{DecryptReader, EncryptWriter};
+    use std::io::{Read, Write};
+    use tokio::io::AsyncWriteExt;
+
+    /// Generate metadata for a stream with the provided chunk size.
+    fn generate_meta(chunk_size: usize) -> metadata::Metadata {
+        let mut key_deriv_meta = metadata::KeyDerivationMetadata::generate();
+        key_deriv_meta.iterations = 1;
+        let key_algo =
+            metadata::KeyDerivationAlgorithm::PBKDF2(key_deriv_meta);
+        let enc_meta = metadata::EncryptionMetadata::generate();
+        let enc_algo =
+            metadata::EncryptionAlgorithm::ChaCha20Poly1305(enc_meta);
+        metadata::Metadata::new_stream(key_algo, enc_algo, chunk_size)
+    }
+
+    /// Get the Tindercrypt error that an I/O error wraps.
+    fn tc_error(err: io::Error) -> errors::Error {
+        let inner = err.get_ref().unwrap();
+        *inner.downcast_ref::<errors::Error>().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_async_encrypt_decrypt() {
+        let pass = "My passphrase".as_bytes();
+        let cryptor = RingCryptor::new();
+        let meta = generate_meta(4);
+
+        for size in 0..13 {
+            let plaintext: Vec<u8> = (0..size as u8).collect();
+
+            // Check that the async writer produces the same format as the
+            // sync one.
+            let mut writer =
+                AsyncEncryptWriter::new(&cryptor, &meta, pass, Vec::new())
+                    .await
+                    .unwrap();
+            for data in plaintext.chunks(3) {
+                writer.write_all(data).await.unwrap();
+            }
+            writer.shutdown().await.unwrap();
+            let ciphertext = writer.into_inner();
+
+            let mut reader =
+                DecryptReader::new(&cryptor, pass, &ciphertext[..]).unwrap();
+            let mut res = Vec::new();
+            let _ = reader.read_to_end(&mut res).unwrap();
+            assert_eq!(res, plaintext);
+
+            // Check that the async reader can decrypt the format of the sync
+            // writer.
+            let mut writer =
+                EncryptWriter::new(&cryptor, &meta, pass, Vec::new()).unwrap();
+            writer.write_all(&plaintext).unwrap();
+            let ciphertext = writer.finish().unwrap();
+
+            let mut reader =
+                AsyncDecryptReader::new(&cryptor, pass, &ciphertext[..])
+                    .await
+                    .unwrap();
+            let mut res = Vec::new();
+            let _ = reader.read_to_end(&mut res).await.unwrap();
+            assert_eq!(res, plaintext);
+        }
+
+        // Check that data which have not been encrypted as a stream can be
+        // decrypted as well.
+        let plaintext = "The cake is a lie".as_bytes();
+        let ciphertext = cryptor.seal_with_key(&[9u8; 32], plaintext).unwrap();
+        let mut reader =
+            AsyncDecryptReader::new(&cryptor, &[9u8; 32], &ciphertext[..])
+                .await
+                .unwrap();
+        let mut res = Vec::new();
+        let _ = reader.read_to_end(&mut res).await.unwrap();
+        assert_eq!(res, plaintext);
+    }
+
+    #[tokio::test]
+    async fn test_async_decrypt_errors() {
+        let pass = "My passphrase".as_bytes();
+        let wrong_pass = "Wrong passphrase".as_bytes();
+        let cryptor = RingCryptor::new();
+        let meta = generate_meta(4);
+
+        let mut writer =
+            AsyncEncryptWriter::new(&cryptor, &meta, pass, Vec::new())
+                .await
+                .unwrap();
+        writer.write_all(&[1u8; 10]).await.unwrap();
+        writer.shutdown().await.unwrap();
+        let ciphertext = writer.into_inner();
+
+        // Check that a missing header is detected.
+        let err = AsyncDecryptReader::new(&cryptor, pass, &[][..])
+            .await
+            .unwrap_err();
+        assert_eq!(Err(tc_error(err)), cryptor.open(pass, &[]));
+
+        // Check that a wrong passphrase is detected.
+        let mut reader =
+            AsyncDecryptReader::new(&cryptor, wrong_pass, &ciphertext[..])
+                .await
+                .unwrap();
+        let err = reader.read_to_end(&mut Vec::new()).await.unwrap_err();
+        assert_eq!(tc_error(err), errors::Error::DecryptionError);
+
+        // Check that a cached key is used only for the same passphrase.
+        let key_cache = DerivedKeyCache::default();
+        let cache_cryptor = RingCryptor::new().with_key_cache(&key_cache);
+        for &secret in &[pass, pass, wrong_pass] {
+            let mut reader = AsyncDecryptReader::new(
+                &cache_cryptor,
+                secret,
+                &ciphertext[..],
+            )
+            .await
+            .unwrap();
+            let mut res = Vec::new();
+            match reader.read_to_end(&mut res).await {
+                Ok(_) => assert_eq!(res, [1u8; 10]),
+                Err(err) => {
+                    assert_eq!(secret, wrong_pass);
+                    assert_eq!(tc_error(err), errors::Error::DecryptionError);
+                }
+            }
+        }
+        assert_eq!(key_cache.len(), 2);
+
+        // Check that a truncated stream is detected.
+        let truncated = &ciphertext[..ciphertext.len() - 1];
+        let mut reader = AsyncDecryptReader::new(&cryptor, pass, truncated)
+            .await
+            .unwrap();
+        let mut res = Vec::new();
+        let err = reader.read_to_end(&mut res).await.unwrap_err();
+        assert_eq!(tc_error(err), errors::Error::DecryptionError);
+        assert_eq!(res, vec![1u8; 8]);
+    }
+}
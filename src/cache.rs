@@ -0,0 +1,304 @@
+//! # Derived key cache
+//!
+//! The key derivation algorithms for passphrases (PBKDF2, Argon2id and
+//! scrypt) are slow by design. If a lot of data have been encrypted with the
+//! same passphrase and key derivation metadata, e.g., a batch of small
+//! messages that share a salt, deriving the key for each one of them wastes a
+//! lot of CPU time.
+//!
+//! This module provides the `DerivedKeyCache` struct, which keeps the keys
+//! that have been derived from a passphrase in memory, for a limited amount
+//! of time. A cryptor with a cache, via the `RingCryptor::with_key_cache()`
+//! method, looks up the key in the cache before it derives it, and stores it
+//! in the cache afterwards.
+//!
+//! The cache entries are keyed on the key derivation metadata (i.e., the cost
+//! parameters and the salt) and on an HMAC of the passphrase, with a random
+//! key per cache. This way, a cached key is never returned for a different
+//! passphrase, and the passphrases are not kept in memory. The keys are
+//! zeroed when they are evicted from the cache, or when the cache is
+//! dropped.
+//!
+//! ## Examples
+//!
+//! ```
+//! use std::time::Duration;
+//! use tindercrypt::cache::DerivedKeyCache;
+//! use tindercrypt::cryptors::RingCryptor;
+//!
+//! let plaintexts = ["The cake".as_bytes(), "is a lie".as_bytes()];
+//! let pass = "My secret passphrase".as_bytes();
+//!
+//! // Encrypt a batch of messages, with a single key derivation.
+//! let cache = DerivedKeyCache::new(16, Duration::from_secs(60));
+//! let cryptor = RingCryptor::new().with_key_cache(&cache);
+//! let ciphertexts = cryptor.seal_batch_with_passphrase(pass, &plaintexts)?;
+//!
+//! // Decrypt the messages. The key has been cached during the encryption, so
+//! // it's not derived again.
+//! assert_eq!(cache.len(), 1);
+//! for (ciphertext, plaintext) in ciphertexts.iter().zip(&plaintexts) {
+//!     assert_eq!(&cryptor.open(pass, ciphertext)?, plaintext);
+//! }
+//!
+//! # use tindercrypt::errors;
+//! # Ok::<(), errors::Error>(())
+//! ```
+
+use crate::metadata;
+use crate::rand;
+use ring::{constant_time, hmac};
+use std::fmt;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use zeroize::Zeroizing;
+
+/// The default maximum number of keys that a cache holds.
+pub const DEFAULT_MAX_SIZE: usize = 64;
+
+/// The default amount of time that a key is kept in the cache.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// A derived key, along with the values that identify it.
+struct CacheEntry {
+    key_deriv_algo: metadata::KeyDerivationAlgorithm,
+    secret_tag: hmac::Tag,
+    key: Zeroizing<Vec<u8>>,
+    created_at: Instant,
+}
+
+/// A cache for the keys that have been derived from passphrases.
+///
+/// The cache holds up to `max_size` keys, and each key expires `ttl` after
+/// it has been stored. If the cache is full, the oldest key is evicted to
+/// make room for a new one. The cache can be shared between threads, and
+/// between cryptor instances.
+///
+/// Two caches are considered equal only if they are the same instance.
+pub struct DerivedKeyCache {
+    max_size: usize,
+    ttl: Duration,
+    secret_key: hmac::Key,
+    entries: Mutex<Vec<CacheEntry>>,
+}
+
+impl fmt::Debug for DerivedKeyCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // NOTE: Do not print the cached keys.
+        f.debug_struct("DerivedKeyCache")
+            .field("max_size", &self.max_size)
+            .field("ttl", &self.ttl)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl PartialEq for DerivedKeyCache {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Default for DerivedKeyCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_SIZE, DEFAULT_TTL)
+    }
+}
+
+impl DerivedKeyCache {
+    /// Create a cache that holds up to `max_size` keys, for `ttl` each.
+    pub fn new(max_size: usize, ttl: Duration) -> Self {
+        let mut secret_key = Zeroizing::new([0u8; 32]);
+        rand::fill_buf(&mut secret_key[..]);
+        Self {
+            max_size,
+            ttl,
+            secret_key: hmac::Key::new(hmac::HMAC_SHA256, &secret_key[..]),
+            entries: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Get the number of keys in the cache, including the expired ones that
+    /// have not been evicted yet.
+    pub fn len(&self) -> usize {
+        self._lock().len()
+    }
+
+    /// Check if the cache holds no keys.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Evict all the keys from the cache.
+    pub fn clear(&self) {
+        self._lock().clear();
+    }
+
+    /// Lock the cache entries.
+    ///
+    /// A panic while the lock is held cannot leave the entries in an
+    /// inconsistent state, so we can safely ignore lock poisoning.
+    fn _lock(&self) -> MutexGuard<'_, Vec<CacheEntry>> {
+        match self.entries.lock() {
+            Ok(entries) => entries,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// Evict the expired keys from the cache.
+    fn _evict_expired(&self, entries: &mut Vec<CacheEntry>) {
+        let ttl = self.ttl;
+        entries.retain(|entry| entry.created_at.elapsed() < ttl);
+    }
+
+    /// Check if the keys of a key derivation algorithm can be cached.
+    ///
+    /// Only the keys that are derived from passphrases are cached, since the
+    /// rest of the key derivation algorithms are cheap.
+    pub(crate) fn _is_cacheable(
+        key_deriv_algo: &metadata::KeyDerivationAlgorithm,
+    ) -> bool {
+        matches!(
+            key_deriv_algo,
+            metadata::KeyDerivationAlgorithm::PBKDF2(_)
+                | metadata::KeyDerivationAlgorithm::Argon2id(_)
+                | metadata::KeyDerivationAlgorithm::Scrypt(_)
+        )
+    }
+
+    /// Look up the key for a key derivation algorithm and a secret value.
+    ///
+    /// If the key is found, it's copied to the provided buffer, and `true` is
+    /// returned.
+    pub(crate) fn _get(
+        &self,
+        key_deriv_algo: &metadata::KeyDerivationAlgorithm,
+        secret: &[u8],
+        key: &mut [u8],
+    ) -> bool {
+        let secret_tag = hmac::sign(&self.secret_key, secret);
+        let mut entries = self._lock();
+        self._evict_expired(&mut entries);
+
+        let found = entries.iter().find(|entry| {
+            entry.key.len() == key.len()
+                && entry.key_deriv_algo == *key_deriv_algo
+                && constant_time::verify_slices_are_equal(
+                    entry.secret_tag.as_ref(),
+                    secret_tag.as_ref(),
+                )
+                .is_ok()
+        });
+        match found {
+            Some(entry) => {
+                key.copy_from_slice(&entry.key);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Store the key for a key derivation algorithm and a secret value.
+    ///
+    /// If the cache is full, the oldest key is evicted.
+    pub(crate) fn _insert(
+        &self,
+        key_deriv_algo: &metadata::KeyDerivationAlgorithm,
+        secret: &[u8],
+        key: &[u8],
+    ) {
+        if self.max_size == 0 {
+            return;
+        }
+
+        let secret_tag = hmac::sign(&self.secret_key, secret);
+        let mut entries = self._lock();
+        self._evict_expired(&mut entries);
+
+        // The entries are sorted by their creation time, so the oldest one is
+        // the first one.
+        if entries.len() >= self.max_size {
+            let _ = entries.remove(0);
+        }
+        entries.push(CacheEntry {
+            key_deriv_algo: key_deriv_algo.clone(),
+            secret_tag,
+            key: Zeroizing::new(key.to_vec()),
+            created_at: Instant::now(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Generate a PBKDF2 key derivation algorithm with a random salt.
+    fn generate_algo() -> metadata::KeyDerivationAlgorithm {
+        let key_deriv_meta = metadata::KeyDerivationMetadata::generate();
+        metadata::KeyDerivationAlgorithm::PBKDF2(key_deriv_meta)
+    }
+
+    #[test]
+    fn test_cache() {
+        let cache = DerivedKeyCache::new(2, Duration::from_secs(60));
+        let algo1 = generate_algo();
+        let algo2 = generate_algo();
+        let algo3 = generate_algo();
+        let mut key = [0u8; 32];
+
+        // Check that a key is found only for the same algorithm, secret and
+        // key size.
+        assert!(cache.is_empty());
+        cache._insert(&algo1, b"pass", &[1; 32]);
+        assert_eq!(cache.len(), 1);
+        assert!(cache._get(&algo1, b"pass", &mut key));
+        assert_eq!(key, [1; 32]);
+        assert!(!cache._get(&algo1, b"pass2", &mut key));
+        assert!(!cache._get(&algo2, b"pass", &mut key));
+        assert!(!cache._get(&algo1, b"pass", &mut key[..16]));
+
+        // Check that the oldest key is evicted when the cache is full.
+        cache._insert(&algo2, b"pass", &[2; 32]);
+        cache._insert(&algo3, b"pass", &[3; 32]);
+        assert_eq!(cache.len(), 2);
+        assert!(!cache._get(&algo1, b"pass", &mut key));
+        assert!(cache._get(&algo2, b"pass", &mut key));
+        assert_eq!(key, [2; 32]);
+        assert!(cache._get(&algo3, b"pass", &mut key));
+        assert_eq!(key, [3; 32]);
+
+        // Check that the cache can be cleared.
+        cache.clear();
+        assert!(cache.is_empty());
+        assert!(!cache._get(&algo2, b"pass", &mut key));
+
+        // Check that expired keys are evicted.
+        let cache = DerivedKeyCache::new(2, Duration::from_secs(0));
+        cache._insert(&algo1, b"pass", &[1; 32]);
+        assert!(!cache._get(&algo1, b"pass", &mut key));
+        assert!(cache.is_empty());
+
+        // Check that a cache with no size does not store any keys.
+        let cache = DerivedKeyCache::new(0, Duration::from_secs(60));
+        cache._insert(&algo1, b"pass", &[1; 32]);
+        assert!(cache.is_empty());
+
+        // Check that only the keys that are derived from passphrases can be
+        // cached.
+        assert!(DerivedKeyCache::_is_cacheable(&algo1));
+        let algo = metadata::KeyDerivationAlgorithm::None;
+        assert!(!DerivedKeyCache::_is_cacheable(&algo));
+
+        // Check that caches are equal only to themselves, and that the keys
+        // are not printed.
+        let cache1 = DerivedKeyCache::default();
+        let cache2 = DerivedKeyCache::default();
+        assert_eq!(cache1, cache1);
+        assert_ne!(cache1, cache2);
+        cache1._insert(&algo1, b"pass", &[0xab; 32]);
+        let debug = format!("{:?}", cache1);
+        assert!(debug.contains("len: 1"));
+        assert!(!debug.contains("171"));
+    }
+}
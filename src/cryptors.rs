@@ -1,22 +1,171 @@
 //! # Cryptor structs for encryption/decryption
 //!
 //! A cryptor is a struct with methods that can encrypt (seal) a plaintext or
-//! decrypt (open) a ciphertext. This module provides [`RingCryptor`], a
+//! decrypt (open) a ciphertext. This module provides the [`Cryptor`] trait,
+//! which is the common interface of the cryptors, and [`RingCryptor`], a
 //! cryptor struct that provides the above functionality using various `ring`
-//! cryptographic primitives.
+//! cryptographic primitives, and is available only if the `ring` feature is
+//! enabled. If the `rustcrypto` feature is enabled, the [`rustcrypto`] module
+//! provides a cryptor that uses the RustCrypto crates instead.
 //!
+//! [`Cryptor`]: trait.Cryptor.html
 //! [`RingCryptor`]: struct.RingCryptor.html
+//! [`rustcrypto`]: ../rustcrypto/index.html
 
 #![allow(missing_docs)]
-use crate::aead;
 use crate::errors;
+#[cfg(feature = "ring")]
+use crate::keys::{self, KeyProvider};
 use crate::metadata;
-use crate::pbkdf2;
-use ring;
+#[cfg(feature = "ring")]
+use crate::{
+    aead, aes_gcm_siv, argon2, cache, ed25519, header, hkdf, pbkdf2, policy,
+    rand, scrypt, stream, x25519, xchacha20,
+};
+#[cfg(feature = "ring")]
+use zeroize::Zeroizing;
 
 /// The maximum key size that the `ring` library supports for encryption
 /// purposes.
-const MAX_KEY_SIZE: usize = 32;
+#[cfg(feature = "ring")]
+pub(crate) const MAX_KEY_SIZE: usize = 32;
+
+/// The common interface of the cryptors.
+///
+/// A cryptor encrypts (seals) a plaintext or decrypts (opens) a ciphertext,
+/// according to the [metadata] of the data. The cryptors of this crate use
+/// different cryptographic backends, but they produce the same wire format,
+/// so data that have been encrypted by one cryptor can be decrypted by any
+/// other.
+///
+/// Implementors need to provide only the in-place methods, since the rest of
+/// the methods are built on top of them. See the respective methods of
+/// `RingCryptor` for more info on each method.
+///
+/// ## Examples
+///
+/// ```
+/// use tindercrypt::cryptors::Cryptor;
+/// # #[cfg(feature = "ring")]
+/// use tindercrypt::cryptors::RingCryptor;
+///
+/// fn roundtrip(cryptor: &dyn Cryptor, pass: &[u8], plaintext: &[u8]) {
+///     let ciphertext = cryptor.seal_with_passphrase(pass, plaintext).unwrap();
+///     assert_eq!(cryptor.open(pass, &ciphertext).unwrap(), plaintext);
+/// }
+///
+/// # #[cfg(feature = "ring")]
+/// roundtrip(&RingCryptor::new(), b"My secret passphrase", b"The cake");
+/// ```
+///
+/// [metadata]: ../metadata/index.html
+pub trait Cryptor {
+    /// Encrypt (seal) the data buffer in place.
+    ///
+    /// The data buffer must contain the plaintext and enough space for the
+    /// tag. On success, the size of the plaintext is returned.
+    fn seal_in_place(
+        &self,
+        meta: &metadata::Metadata,
+        secret: &[u8],
+        buf: &mut [u8],
+    ) -> Result<usize, errors::Error>;
+
+    /// Decrypt (open) the data buffer in place.
+    ///
+    /// The data buffer must contain the ciphertext and its tag. On success,
+    /// the size of the plaintext is returned.
+    fn open_in_place(
+        &self,
+        meta: &metadata::Metadata,
+        secret: &[u8],
+        buf: &mut [u8],
+    ) -> Result<usize, errors::Error>;
+
+    /// Encrypt (seal) the data buffer using the provided metadata.
+    ///
+    /// The returned buffer contains the serialized metadata, followed by the
+    /// ciphertext.
+    fn seal_with_meta(
+        &self,
+        meta: &metadata::Metadata,
+        secret: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        let (mut buf, meta_size) = meta.to_buf();
+
+        // The ciphertext size of a stream is not stored in its metadata, so
+        // we need to make room for the tag of each chunk.
+        if let Some(chunk_size) = meta.chunk_size {
+            let size = metadata::Metadata::calculate_stream_ciphertext_size(
+                plaintext.len(),
+                &meta.enc_algo,
+                chunk_size,
+            );
+            buf.resize(meta_size + size, 0u8);
+        }
+        let ciphertext = &mut buf[meta_size..];
+
+        ciphertext[..plaintext.len()].copy_from_slice(plaintext);
+        let _ = self.seal_in_place(meta, secret, ciphertext)?;
+        Ok(buf)
+    }
+
+    /// Encrypt (seal) the data buffer using a symmetric key.
+    ///
+    /// By default, the data are encrypted with AES-256-GCM.
+    fn seal_with_key(
+        &self,
+        key: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        let meta = metadata::Metadata::generate_for_key(plaintext.len());
+        self.seal_with_meta(&meta, key, plaintext)
+    }
+
+    /// Encrypt (seal) the data buffer using a passphrase.
+    ///
+    /// By default, the key is derived with PBKDF2, and the data are encrypted
+    /// with AES-256-GCM.
+    fn seal_with_passphrase(
+        &self,
+        pass: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        let meta =
+            metadata::Metadata::generate_for_passphrase(plaintext.len());
+        self.seal_with_meta(&meta, pass, plaintext)
+    }
+
+    /// Decrypt (open) the data buffer using the provided metadata.
+    ///
+    /// The data buffer must contain only the ciphertext.
+    fn open_with_meta(
+        &self,
+        meta: &metadata::Metadata,
+        secret: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        let mut buf = ciphertext.to_vec();
+        let size = self.open_in_place(meta, secret, &mut buf)?;
+        let _ = buf.drain(size..);
+        Ok(buf)
+    }
+
+    /// Decrypt (open) the data buffer.
+    ///
+    /// The data buffer must contain the serialized metadata, followed by the
+    /// ciphertext.
+    fn open(
+        &self,
+        secret: &[u8],
+        buf: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        let (meta, meta_size) = metadata::Metadata::from_buf(buf)?;
+        let ciphertext = &buf[meta_size..];
+        self.open_with_meta(&meta, secret, ciphertext)
+    }
+}
 
 /// A cryptor that uses cryptographic primitives from the `ring` crate.
 ///
@@ -29,10 +178,11 @@ const MAX_KEY_SIZE: usize = 32;
 /// * Serialize the [metadata] into a buffer large enough to hold the
 ///   ciphertext and its tag.
 /// * Copy the plaintext in a specific position within the buffer.
-/// * Derive a symmetric key from a passphrase, if the PBKDF2 key derivation
-///   algorithm is used.
-/// * Encrypt the data in place. The original plaintext is not affected since
-///   it's a copy.
+/// * Derive a symmetric key from a passphrase, if a key derivation algorithm
+///   (PBKDF2, Argon2id or scrypt) is used.
+/// * Encrypt the data in place, using the serialized metadata and any
+///   user-provided data as associated data. The original plaintext is not
+///   affected since it's a copy.
 /// * Return the buffer with the serialized metadata, ciphertext and tag.
 ///
 /// If a user wants to decrypt a plaintext, they can use one of the `.open_*`
@@ -42,9 +192,10 @@ const MAX_KEY_SIZE: usize = 32;
 /// * Deserialize the [metadata] from the buffer header. Return an error if
 ///   they are corrupted or don't exist.
 /// * Copy the ciphertext into a new buffer.
-/// * Derive a symmetric key from a passphrase, if the PBKDF2 key derivation
-///   algorithm is used.
-/// * Decrypt the data in place, or return a decryption error. The original
+/// * Derive a symmetric key from a passphrase, if a key derivation algorithm
+///   (PBKDF2, Argon2id or scrypt) is used.
+/// * Decrypt the data in place, or return a decryption error, e.g., if the
+///   ciphertext or its metadata have been tampered with. The original
 ///   ciphertext is not affected, since it's a copy.
 /// * Return the plaintext.
 ///
@@ -87,7 +238,7 @@ const MAX_KEY_SIZE: usize = 32;
 /// let mut buf = &mut _buf[meta_size..];
 /// buf[..plaintext.len()].copy_from_slice(plaintext);
 ///
-/// // These methods will not perform any allocation, and will encrypt/decrypt
+/// // These methods will not copy the data buffer, and will encrypt/decrypt
 /// // the data in place.
 /// cryptor.seal_in_place(&meta, pass, buf);
 /// cryptor.open_in_place(&meta, pass, buf);
@@ -131,16 +282,192 @@ const MAX_KEY_SIZE: usize = 32;
 /// # Ok::<(), errors::Error>(())
 /// ```
 ///
+/// Data can also be encrypted for a recipient, who is identified by an X25519
+/// public key. Only the holder of the respective private key can decrypt
+/// them:
+///
+/// ```
+/// use tindercrypt::cryptors::RingCryptor;
+/// use tindercrypt::x25519;
+///
+/// let plaintext = "The cake is a lie".as_bytes();
+/// let private_key = x25519::generate_private_key();
+/// let public_key = x25519::public_key(&private_key)?;
+/// let cryptor = RingCryptor::new();
+///
+/// let ciphertext = cryptor.seal_for_recipient(&public_key, plaintext)?;
+/// assert_eq!(cryptor.open(&private_key, &ciphertext)?, plaintext);
+///
+/// # use tindercrypt::errors;
+/// # Ok::<(), errors::Error>(())
+/// ```
+///
+/// The encrypted data can also be signed with an Ed25519 key, so that the
+/// recipients can verify who has encrypted them:
+///
+/// ```
+/// use tindercrypt::cryptors::RingCryptor;
+/// use tindercrypt::ed25519;
+/// use tindercrypt::metadata::Metadata;
+///
+/// let plaintext = "The cake is a lie".as_bytes();
+/// let key = [1u8; 32];
+/// let signing_key = ed25519::generate_private_key();
+/// let cryptor = RingCryptor::new();
+///
+/// let meta = Metadata::generate_for_key(plaintext.len());
+/// let ciphertext = cryptor.seal_signed(&meta, &key, &signing_key, plaintext)?;
+/// let (plaintext2, public_key) = cryptor.open_verified(&key, &ciphertext)?;
+/// assert_eq!(plaintext2, plaintext);
+/// assert_eq!(public_key, ed25519::public_key(&signing_key)?);
+///
+/// # use tindercrypt::errors;
+/// # Ok::<(), errors::Error>(())
+/// ```
+///
+/// The algorithms that the cryptor uses when it generates the metadata, e.g.,
+/// in the `.seal_with_key()` and `.seal_with_passphrase()` methods, can be
+/// changed with a builder:
+///
+/// ```
+/// use tindercrypt::cryptors::RingCryptor;
+/// use tindercrypt::metadata::HashFunction;
+/// use tindercrypt::policy::EncryptionKind;
+///
+/// let plaintext = "The cake is a lie".as_bytes();
+/// let pass = "My secret passphrase".as_bytes();
+/// let cryptor = RingCryptor::builder()
+///     .enc_algo(EncryptionKind::ChaCha20Poly1305)
+///     .hash_fn(HashFunction::SHA512)
+///     .iterations(200000)
+///     .build()?;
+///
+/// let ciphertext = cryptor.seal_with_passphrase(pass, plaintext)?;
+/// assert_eq!(cryptor.open(pass, &ciphertext)?, plaintext);
+///
+/// # use tindercrypt::errors;
+/// # Ok::<(), errors::Error>(())
+/// ```
+///
 /// [metadata]: ../metadata/index.html
+#[cfg(feature = "ring")]
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct RingCryptor<'a> {
     aad: &'a [u8],
+    policy: Option<&'a policy::DecryptionPolicy>,
+    key_cache: Option<&'a cache::DerivedKeyCache>,
+    enc_algo: policy::EncryptionKind,
+    key_deriv_algo: policy::KeyDerivationKind,
+    hash_fn: metadata::HashFunction,
+    iterations: usize,
+    salt_size: Option<usize>,
 }
 
+#[cfg(feature = "ring")]
 impl<'a> RingCryptor<'a> {
     /// Create a new cryptor instance.
+    ///
+    /// The cryptor uses the AES-256-GCM encryption algorithm, and the PBKDF2
+    /// key derivation algorithm with the default hash function, number of
+    /// iterations and salt size, when it generates the metadata. Use
+    /// `RingCryptor::builder()` to change them.
     pub fn new() -> Self {
-        Self { aad: &[] }
+        Self {
+            aad: &[],
+            policy: None,
+            key_cache: None,
+            enc_algo: policy::EncryptionKind::AES256GCM,
+            key_deriv_algo: policy::KeyDerivationKind::PBKDF2,
+            hash_fn: metadata::PBKDF2_DEFAULT_HASH_FN,
+            iterations: metadata::PBKDF2_DEFAULT_ITERATIONS,
+            salt_size: None,
+        }
+    }
+
+    /// Create a builder for a cryptor instance.
+    ///
+    /// The builder starts with the defaults of `RingCryptor::new()`.
+    pub fn builder() -> RingCryptorBuilder<'a> {
+        RingCryptorBuilder {
+            cryptor: Self::new(),
+        }
+    }
+
+    /// Generate the encryption algorithm of the cryptor, with a random nonce.
+    pub fn generate_enc_algo(&self) -> metadata::EncryptionAlgorithm {
+        Self::_generate_enc_algo(self.enc_algo)
+    }
+
+    /// Generate an encryption algorithm of the provided kind, with a random
+    /// nonce.
+    pub(crate) fn _generate_enc_algo(
+        enc_algo: policy::EncryptionKind,
+    ) -> metadata::EncryptionAlgorithm {
+        match enc_algo {
+            policy::EncryptionKind::AES256GCM => {
+                let enc_meta = metadata::EncryptionMetadata::generate();
+                metadata::EncryptionAlgorithm::AES256GCM(enc_meta)
+            }
+            policy::EncryptionKind::ChaCha20Poly1305 => {
+                let enc_meta = metadata::EncryptionMetadata::generate();
+                metadata::EncryptionAlgorithm::ChaCha20Poly1305(enc_meta)
+            }
+            policy::EncryptionKind::XChaCha20Poly1305 => {
+                let enc_meta = metadata::XChaCha20Poly1305Metadata::generate();
+                metadata::EncryptionAlgorithm::XChaCha20Poly1305(enc_meta)
+            }
+            policy::EncryptionKind::AES256GCMSIV => {
+                let enc_meta = metadata::EncryptionMetadata::generate();
+                metadata::EncryptionAlgorithm::AES256GCMSIV(enc_meta)
+            }
+        }
+    }
+
+    /// Generate the key derivation algorithm of the cryptor for a
+    /// passphrase, with a random salt.
+    pub fn generate_key_deriv_algo(&self) -> metadata::KeyDerivationAlgorithm {
+        // If the salt size has been specified, replace the default salt with
+        // a random salt of that size.
+        let gen_salt = |salt: &mut Vec<u8>| {
+            if let Some(salt_size) = self.salt_size {
+                salt.resize(salt_size, 0);
+                rand::fill_buf(salt);
+            }
+        };
+
+        match self.key_deriv_algo {
+            policy::KeyDerivationKind::Argon2id => {
+                let mut argon2_meta = metadata::Argon2idMetadata::generate();
+                gen_salt(&mut argon2_meta.salt);
+                metadata::KeyDerivationAlgorithm::Argon2id(argon2_meta)
+            }
+            policy::KeyDerivationKind::Scrypt => {
+                let mut scrypt_meta = metadata::ScryptMetadata::generate();
+                gen_salt(&mut scrypt_meta.salt);
+                metadata::KeyDerivationAlgorithm::Scrypt(scrypt_meta)
+            }
+            // NOTE: The builder accepts only the key derivation algorithms for
+            // passphrases, so every other kind is PBKDF2.
+            _ => {
+                let mut key_deriv_meta =
+                    metadata::KeyDerivationMetadata::generate();
+                key_deriv_meta.hash_fn = self.hash_fn;
+                key_deriv_meta.iterations = self.iterations;
+                gen_salt(&mut key_deriv_meta.salt);
+                metadata::KeyDerivationAlgorithm::PBKDF2(key_deriv_meta)
+            }
+        }
+    }
+
+    /// Generate the metadata for encrypting data with a symmetric key, using
+    /// the encryption algorithm of the cryptor.
+    fn _generate_meta_for_key(
+        &self,
+        plaintext_size: usize,
+    ) -> metadata::Metadata {
+        let key_deriv_algo = metadata::KeyDerivationAlgorithm::None;
+        let enc_algo = self.generate_enc_algo();
+        metadata::Metadata::new(key_deriv_algo, enc_algo, plaintext_size)
     }
 
     /// Specify the additional associated data (AAD) to be used.
@@ -156,84 +483,447 @@ impl<'a> RingCryptor<'a> {
     /// "cut-and-paste" a valid ciphertext into a different context are
     /// detected and rejected.
     pub fn with_aad(self, aad: &'a [u8]) -> Self {
-        Self { aad }
+        Self { aad, ..self }
+    }
+
+    /// Specify the decryption policy to be used.
+    ///
+    /// By default, the cryptor trusts the metadata header of the encrypted
+    /// data, and derives the key with the cost parameters that the header
+    /// dictates. If the encrypted data come from an untrusted source, this
+    /// can be abused to stall the decryption.
+    ///
+    /// By specifying a decryption policy, the cryptor checks the metadata
+    /// against it before it performs any work, and returns an
+    /// `Error::PolicyViolation` error if they violate it. The policy is also
+    /// checked for the metadata that are used for encryption, so that the
+    /// cryptor never creates data that it would refuse to decrypt. See the
+    /// [`policy`] module for more info.
+    ///
+    /// [`policy`]: ../policy/index.html
+    pub fn with_policy(self, policy: &'a policy::DecryptionPolicy) -> Self {
+        Self {
+            policy: Some(policy),
+            ..self
+        }
+    }
+
+    /// Specify the cache for the keys that are derived from passphrases.
+    ///
+    /// By default, the cryptor derives the key anew for every
+    /// encryption/decryption. If a lot of data share the same passphrase and
+    /// key derivation metadata, this is wasteful, since the key derivation
+    /// algorithms for passphrases are slow by design.
+    ///
+    /// By specifying a cache, the cryptor looks up the key in the cache
+    /// before it derives it, and stores it in the cache afterwards. See the
+    /// [`cache`] module for more info.
+    ///
+    /// [`cache`]: ../cache/index.html
+    pub fn with_key_cache(
+        self,
+        key_cache: &'a cache::DerivedKeyCache,
+    ) -> Self {
+        Self {
+            key_cache: Some(key_cache),
+            ..self
+        }
+    }
+
+    /// Get the cache for the keys that are derived from passphrases, if any.
+    pub(crate) fn _get_key_cache(&self) -> Option<&'a cache::DerivedKeyCache> {
+        self.key_cache
+    }
+
+    /// Check the metadata against the decryption policy, if any.
+    pub(crate) fn _check_policy(
+        &self,
+        meta: &metadata::Metadata,
+    ) -> Result<(), errors::Error> {
+        match self.policy {
+            Some(policy) => policy.check(meta),
+            None => Ok(()),
+        }
     }
 
     /// Get the proper key size from the metadata.
-    fn _get_key_size(&self, meta: &metadata::Metadata) -> usize {
-        match meta.enc_algo {
+    pub(crate) fn _get_key_size(&self, meta: &metadata::Metadata) -> usize {
+        Self::_get_enc_key_size(&meta.enc_algo)
+    }
+
+    /// Get the proper key size for an encryption algorithm.
+    fn _get_enc_key_size(enc_algo: &metadata::EncryptionAlgorithm) -> usize {
+        match enc_algo {
             metadata::EncryptionAlgorithm::AES256GCM(_) => {
                 ring::aead::AES_256_GCM.key_len()
             }
             metadata::EncryptionAlgorithm::ChaCha20Poly1305(_) => {
                 ring::aead::CHACHA20_POLY1305.key_len()
             }
+            metadata::EncryptionAlgorithm::XChaCha20Poly1305(_) => {
+                xchacha20::KEY_SIZE
+            }
+            metadata::EncryptionAlgorithm::AES256GCMSIV(_) => {
+                aes_gcm_siv::KEY_SIZE
+            }
         }
     }
 
-    /// Encrypt (seal) the data buffer in place.
+    /// Get the associated data for the encryption/decryption.
     ///
-    /// This method gets the metadata necessary from the `EncryptionAlgorithm`
-    /// enum and calls the respective AEAD wrapper.
-    fn _seal_in_place(
+    /// See `header::get_aad()` for more info.
+    pub(crate) fn _get_aad(&self, meta: &metadata::Metadata) -> Vec<u8> {
+        header::get_aad(meta, self.aad)
+    }
+
+    /// Get the data that the signature of the metadata covers.
+    ///
+    /// These are the serialized metadata, without their signature, followed
+    /// by the ciphertext.
+    fn _get_signed_data(
+        meta: &metadata::Metadata,
+        ciphertext: &[u8],
+    ) -> Vec<u8> {
+        let mut meta = meta.clone();
+        meta.signature = None;
+        let mut data = meta.to_header();
+        data.extend_from_slice(ciphertext);
+        data
+    }
+
+    /// Wrap a data key in a new key slot.
+    ///
+    /// The data key is encrypted with AES-256-GCM, using a key that is
+    /// derived from the secret with the provided key derivation algorithm.
+    fn _wrap_data_key(
         &self,
-        enc_algo: &metadata::EncryptionAlgorithm,
-        key: &[u8],
-        buf: &mut [u8],
+        meta: &metadata::Metadata,
+        key_deriv_algo: metadata::KeyDerivationAlgorithm,
+        data_key: &[u8],
+        secret: &[u8],
+    ) -> Result<metadata::KeySlot, errors::Error> {
+        if data_key.len() != metadata::DATA_KEY_SIZE {
+            return Err(errors::Error::KeySizeMismatch);
+        }
+
+        let enc_meta = metadata::EncryptionMetadata::generate();
+        let enc_algo = metadata::EncryptionAlgorithm::AES256GCM(enc_meta);
+        let mut key = Zeroizing::new([0u8; MAX_KEY_SIZE]);
+        let key = &mut key[..Self::_get_enc_key_size(&enc_algo)];
+        self._derive_key(&key_deriv_algo, secret, key)?;
+
+        let mut wrapped_key = data_key.to_vec();
+        wrapped_key.resize(
+            metadata::Metadata::calculate_ciphertext_size(
+                data_key.len(),
+                &enc_algo,
+            ),
+            0u8,
+        );
+        let aad = header::get_bare_header(meta);
+        let _ = self._seal_in_place(&enc_algo, &aad, key, &mut wrapped_key)?;
+        Ok(metadata::KeySlot::new(
+            key_deriv_algo,
+            enc_algo,
+            wrapped_key,
+        ))
+    }
+
+    /// Unwrap the data key from the first key slot that the secret opens.
+    ///
+    /// Each key slot is tried in turn, so this method performs one key
+    /// derivation per key slot, in the worst case. If the secret does not
+    /// open any key slot, it returns a decryption error. Else, it returns the
+    /// index of the key slot.
+    fn _unwrap_data_key(
+        &self,
+        meta: &metadata::Metadata,
+        secret: &[u8],
+        data_key: &mut [u8],
     ) -> Result<usize, errors::Error> {
-        let algo: &'static ring::aead::Algorithm;
-        let nonce: [u8; aead::NONCE_SIZE];
+        if data_key.len() != metadata::DATA_KEY_SIZE {
+            return Err(errors::Error::KeySizeMismatch);
+        }
 
-        match enc_algo {
-            metadata::EncryptionAlgorithm::AES256GCM(meta) => {
-                algo = &ring::aead::AES_256_GCM;
-                nonce = meta.nonce;
-            }
-            metadata::EncryptionAlgorithm::ChaCha20Poly1305(meta) => {
-                algo = &ring::aead::CHACHA20_POLY1305;
-                nonce = meta.nonce;
+        let aad = header::get_bare_header(meta);
+        for (i, slot) in meta.key_slots.iter().enumerate() {
+            if self._unwrap_key_slot(&aad, slot, secret, data_key) {
+                return Ok(i);
             }
         }
-        aead::seal_in_place(algo, nonce, self.aad, key, buf)
+        Err(errors::Error::DecryptionError)
     }
 
-    /// Decrypt (open) the data buffer in place.
+    /// Unwrap the data key from a key slot.
     ///
-    /// This method gets the metadata necessary from the `EncryptionAlgorithm`
-    /// enum and calls the respective AEAD wrapper.
-    fn _open_in_place(
+    /// Return whether the secret opens the key slot. A secret that is not
+    /// appropriate for the key derivation algorithm of the key slot, e.g., a
+    /// key with a different size, cannot open it.
+    fn _unwrap_key_slot(
         &self,
-        enc_algo: &metadata::EncryptionAlgorithm,
-        key: &[u8],
-        buf: &mut [u8],
-    ) -> Result<usize, errors::Error> {
-        let algo: &'static ring::aead::Algorithm;
-        let nonce: [u8; aead::NONCE_SIZE];
+        aad: &[u8],
+        slot: &metadata::KeySlot,
+        secret: &[u8],
+        data_key: &mut [u8],
+    ) -> bool {
+        let mut key = Zeroizing::new([0u8; MAX_KEY_SIZE]);
+        let key = &mut key[..Self::_get_enc_key_size(&slot.enc_algo)];
+        if self._derive_key(&slot.key_deriv_algo, secret, key).is_err() {
+            return false;
+        }
 
-        match enc_algo {
-            metadata::EncryptionAlgorithm::AES256GCM(meta) => {
-                algo = &ring::aead::AES_256_GCM;
-                nonce = meta.nonce;
-            }
-            metadata::EncryptionAlgorithm::ChaCha20Poly1305(meta) => {
-                algo = &ring::aead::CHACHA20_POLY1305;
-                nonce = meta.nonce;
+        let mut buf = slot.wrapped_key.clone();
+        match self._open_in_place(&slot.enc_algo, aad, key, &mut buf) {
+            Ok(size) if size == data_key.len() => {
+                data_key.copy_from_slice(&buf[..size]);
+                true
             }
+            _ => false,
         }
-        aead::open_in_place(algo, nonce, self.aad, key, buf)
     }
 
-    /// Create a symmetric key from a secret value.
+    /// Compute the HMAC-SHA256 of a fixed label, with a symmetric key as the
+    /// HMAC key.
+    fn _sign_label(key: &[u8], label: &[u8]) -> ring::hmac::Tag {
+        let hmac_key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, key);
+        ring::hmac::sign(&hmac_key, label)
+    }
+
+    /// Compute the commitment to a symmetric key.
     ///
-    /// This method gets the metadata necessary from the
-    /// `KeyDerivationAlgorithm` enum and calls the respective PBKDF2 wrapper.
-    fn _derive_key(
+    /// The commitment is the HMAC-SHA256 of a fixed label, with the key as
+    /// the HMAC key. Since HMAC is collision-resistant, a ciphertext cannot
+    /// commit to more than one key.
+    pub(crate) fn _get_key_commitment(
+        key: &[u8],
+    ) -> [u8; metadata::KEY_COMMITMENT_SIZE] {
+        let tag = Self::_sign_label(key, header::KEY_COMMITMENT_LABEL);
+        let mut key_commitment = [0u8; metadata::KEY_COMMITMENT_SIZE];
+        key_commitment.copy_from_slice(tag.as_ref());
+        key_commitment
+    }
+
+    /// Compute the key check value of a symmetric key.
+    ///
+    /// The key check value is the HMAC-SHA256 of a fixed label, with the key
+    /// as the HMAC key, truncated to `KEY_CHECK_SIZE` bytes.
+    pub(crate) fn _get_key_check(
+        key: &[u8],
+    ) -> [u8; metadata::KEY_CHECK_SIZE] {
+        let tag = Self::_sign_label(key, header::KEY_CHECK_LABEL);
+        let mut key_check = [0u8; metadata::KEY_CHECK_SIZE];
+        key_check.copy_from_slice(&tag.as_ref()[..metadata::KEY_CHECK_SIZE]);
+        key_check
+    }
+
+    /// Create the symmetric key of the data from a secret value.
+    ///
+    /// If the metadata have key slots, the secret is used to unwrap the data
+    /// key from them. Else, the key is derived from the secret, according to
+    /// the key derivation algorithm of the metadata. If the metadata have a
+    /// key commitment or a key check value, the key is checked against them,
+    /// before it's used for any encryption/decryption. In any case, the
+    /// metadata are first checked against the decryption policy, if any.
+    pub(crate) fn _get_key(
         &self,
-        key_deriv_algo: &metadata::KeyDerivationAlgorithm,
+        meta: &metadata::Metadata,
         secret: &[u8],
         key: &mut [u8],
     ) -> Result<(), errors::Error> {
-        let algo: ring::pbkdf2::Algorithm;
+        self._check_policy(meta)?;
+        self._create_key(meta, secret, key)
+    }
+
+    /// Create the symmetric key of the data from a secret value, without
+    /// checking the metadata against the decryption policy.
+    fn _create_key(
+        &self,
+        meta: &metadata::Metadata,
+        secret: &[u8],
+        key: &mut [u8],
+    ) -> Result<(), errors::Error> {
+        let ct_eq = |a: &[u8], b: &[u8]| {
+            ring::constant_time::verify_slices_are_equal(a, b).is_ok()
+        };
+
+        if meta.key_slots.is_empty() {
+            self._derive_key(&meta.key_deriv_algo, secret, key)?;
+        } else {
+            match self._unwrap_data_key(meta, secret, key) {
+                Ok(_) => (),
+                Err(errors::Error::DecryptionError)
+                    if meta.key_check.is_some() =>
+                {
+                    return Err(errors::Error::WrongKey)
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        if let Some(key_commitment) = &meta.key_commitment {
+            if !ct_eq(key_commitment, &Self::_get_key_commitment(key)) {
+                return Err(errors::Error::KeyCommitmentMismatch);
+            }
+        }
+        if let Some(key_check) = &meta.key_check {
+            if !ct_eq(key_check, &Self::_get_key_check(key)) {
+                return Err(errors::Error::WrongKey);
+            }
+        }
+        Ok(())
+    }
+
+    /// Create the symmetric key of the data from a secret value, and store
+    /// the values that verify it in the metadata.
+    ///
+    /// The key commitment and the key check value are stored if they are
+    /// requested, or if the metadata already have them, in which case they
+    /// are computed anew.
+    pub(crate) fn _add_key_values(
+        &self,
+        meta: &mut metadata::Metadata,
+        secret: &[u8],
+        key: &mut [u8],
+        commit: bool,
+        check: bool,
+    ) -> Result<(), errors::Error> {
+        let commit = commit || meta.key_commitment.is_some();
+        let check = check || meta.key_check.is_some();
+
+        // The policy may require a key commitment, so the metadata are checked
+        // as they will be once the key values have been stored.
+        meta.key_commitment = match commit {
+            true => Some([0u8; metadata::KEY_COMMITMENT_SIZE]),
+            false => None,
+        };
+        meta.key_check = None;
+        self._check_policy(meta)?;
+
+        meta.key_commitment = None;
+        self._create_key(meta, secret, key)?;
+        if commit {
+            meta.key_commitment = Some(Self::_get_key_commitment(key));
+        }
+        if check {
+            meta.key_check = Some(Self::_get_key_check(key));
+        }
+        Ok(())
+    }
+
+    /// Encrypt (seal) the data buffer in place.
+    ///
+    /// This method gets the metadata necessary from the `EncryptionAlgorithm`
+    /// enum and calls the respective AEAD wrapper. XChaCha20-Poly1305 and
+    /// AES-256-GCM-SIV are not supported by `ring`, so they have their own
+    /// wrappers.
+    pub(crate) fn _seal_in_place(
+        &self,
+        enc_algo: &metadata::EncryptionAlgorithm,
+        aad: &[u8],
+        key: &[u8],
+        buf: &mut [u8],
+    ) -> Result<usize, errors::Error> {
+        let algo: &'static ring::aead::Algorithm;
+        let nonce: [u8; aead::NONCE_SIZE];
+
+        match enc_algo {
+            metadata::EncryptionAlgorithm::AES256GCM(meta) => {
+                algo = &ring::aead::AES_256_GCM;
+                nonce = meta.nonce;
+            }
+            metadata::EncryptionAlgorithm::ChaCha20Poly1305(meta) => {
+                algo = &ring::aead::CHACHA20_POLY1305;
+                nonce = meta.nonce;
+            }
+            metadata::EncryptionAlgorithm::XChaCha20Poly1305(meta) => {
+                return xchacha20::seal_in_place(meta.nonce, aad, key, buf);
+            }
+            metadata::EncryptionAlgorithm::AES256GCMSIV(meta) => {
+                return aes_gcm_siv::seal_in_place(meta.nonce, aad, key, buf);
+            }
+        }
+        aead::seal_in_place(algo, nonce, aad, key, buf)
+    }
+
+    /// Decrypt (open) the data buffer in place.
+    ///
+    /// This method gets the metadata necessary from the `EncryptionAlgorithm`
+    /// enum and calls the respective AEAD wrapper. XChaCha20-Poly1305 and
+    /// AES-256-GCM-SIV are not supported by `ring`, so they have their own
+    /// wrappers.
+    pub(crate) fn _open_in_place(
+        &self,
+        enc_algo: &metadata::EncryptionAlgorithm,
+        aad: &[u8],
+        key: &[u8],
+        buf: &mut [u8],
+    ) -> Result<usize, errors::Error> {
+        let algo: &'static ring::aead::Algorithm;
+        let nonce: [u8; aead::NONCE_SIZE];
+
+        match enc_algo {
+            metadata::EncryptionAlgorithm::AES256GCM(meta) => {
+                algo = &ring::aead::AES_256_GCM;
+                nonce = meta.nonce;
+            }
+            metadata::EncryptionAlgorithm::ChaCha20Poly1305(meta) => {
+                algo = &ring::aead::CHACHA20_POLY1305;
+                nonce = meta.nonce;
+            }
+            metadata::EncryptionAlgorithm::XChaCha20Poly1305(meta) => {
+                return xchacha20::open_in_place(meta.nonce, aad, key, buf);
+            }
+            metadata::EncryptionAlgorithm::AES256GCMSIV(meta) => {
+                return aes_gcm_siv::open_in_place(meta.nonce, aad, key, buf);
+            }
+        }
+        aead::open_in_place(algo, nonce, aad, key, buf)
+    }
+
+    /// Create a symmetric key from a secret value.
+    ///
+    /// If the cryptor has a key cache, and the key is derived from a
+    /// passphrase, the key is looked up in the cache first, and is stored in
+    /// it once it has been derived.
+    pub(crate) fn _derive_key(
+        &self,
+        key_deriv_algo: &metadata::KeyDerivationAlgorithm,
+        secret: &[u8],
+        key: &mut [u8],
+    ) -> Result<(), errors::Error> {
+        let key_cache = match self.key_cache {
+            Some(key_cache)
+                if cache::DerivedKeyCache::_is_cacheable(key_deriv_algo) =>
+            {
+                key_cache
+            }
+            _ => {
+                return Self::_derive_uncached_key(key_deriv_algo, secret, key)
+            }
+        };
+
+        if key_cache._get(key_deriv_algo, secret, key) {
+            return Ok(());
+        }
+        Self::_derive_uncached_key(key_deriv_algo, secret, key)?;
+        key_cache._insert(key_deriv_algo, secret, key);
+        Ok(())
+    }
+
+    /// Create a symmetric key from a secret value, without using the key
+    /// cache.
+    ///
+    /// This method gets the metadata necessary from the
+    /// `KeyDerivationAlgorithm` enum and calls the respective PBKDF2,
+    /// Argon2id, scrypt, X25519 or HKDF wrapper. For X25519, the secret value
+    /// is the private key of the recipient, and for HKDF, it's the master
+    /// key.
+    fn _derive_uncached_key(
+        key_deriv_algo: &metadata::KeyDerivationAlgorithm,
+        secret: &[u8],
+        key: &mut [u8],
+    ) -> Result<(), errors::Error> {
+        let algo: ring::pbkdf2::Algorithm;
 
         match key_deriv_algo {
             metadata::KeyDerivationAlgorithm::None => {
@@ -265,93 +955,880 @@ impl<'a> RingCryptor<'a> {
                     key,
                 )
             }
+            metadata::KeyDerivationAlgorithm::Argon2id(meta) => {
+                argon2::derive_key(
+                    meta.memory_cost,
+                    meta.time_cost,
+                    meta.parallelism,
+                    &meta.salt,
+                    secret,
+                    key,
+                )
+            }
+            metadata::KeyDerivationAlgorithm::Scrypt(meta) => {
+                scrypt::derive_key(
+                    meta.log_n,
+                    meta.block_size,
+                    meta.parallelism,
+                    &meta.salt,
+                    secret,
+                    key,
+                )
+            }
+            metadata::KeyDerivationAlgorithm::X25519(meta) => {
+                x25519::derive_key(secret, &meta.ephemeral_public_key, key)
+            }
+            metadata::KeyDerivationAlgorithm::HKDF(meta) => {
+                let algo = match meta.hash_fn {
+                    metadata::HashFunction::SHA256 => ring::hkdf::HKDF_SHA256,
+                    metadata::HashFunction::SHA384 => ring::hkdf::HKDF_SHA384,
+                    metadata::HashFunction::SHA512 => ring::hkdf::HKDF_SHA512,
+                };
+                hkdf::derive_key(algo, &meta.salt, &meta.info, secret, key)
+            }
+        }
+    }
+
+    /// Generate the algorithms for encrypting data for a recipient, and
+    /// derive the key.
+    ///
+    /// The key is derived from a key agreement between a new ephemeral key
+    /// pair and the public key of the recipient, so it can't be derived again
+    /// from the returned metadata, unless the private key of the recipient is
+    /// known. The AES-256-GCM encryption algorithm is used.
+    pub(crate) fn _generate_recipient_algos(
+        public_key: &[u8],
+        key: &mut [u8; MAX_KEY_SIZE],
+    ) -> Result<
+        (
+            metadata::KeyDerivationAlgorithm,
+            metadata::EncryptionAlgorithm,
+        ),
+        errors::Error,
+    > {
+        let enc_meta = metadata::EncryptionMetadata::generate();
+        let enc_algo = metadata::EncryptionAlgorithm::AES256GCM(enc_meta);
+        let key_size = Self::_get_enc_key_size(&enc_algo);
+        let ephemeral_public_key =
+            x25519::derive_ephemeral_key(public_key, &mut key[..key_size])?;
+        let x25519_meta = metadata::X25519Metadata::new(ephemeral_public_key);
+        let key_deriv_algo =
+            metadata::KeyDerivationAlgorithm::X25519(x25519_meta);
+        Ok((key_deriv_algo, enc_algo))
+    }
+
+    /// Encrypt (seal) the data buffer for a recipient, optionally as a
+    /// stream with the provided chunk size.
+    fn _seal_for_recipient(
+        &self,
+        public_key: &[u8],
+        chunk_size: Option<usize>,
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        let mut key = [0u8; MAX_KEY_SIZE];
+        let (key_deriv_algo, enc_algo) =
+            Self::_generate_recipient_algos(public_key, &mut key)?;
+        let key = &key[..Self::_get_enc_key_size(&enc_algo)];
+
+        // NOTE: We can't use `.seal_with_meta()` here, since the key can't be
+        // derived from the metadata without the private key of the
+        // recipient.
+        let meta = match chunk_size {
+            Some(chunk_size) => metadata::Metadata::new_stream(
+                key_deriv_algo,
+                enc_algo,
+                chunk_size,
+            ),
+            None => metadata::Metadata::new(
+                key_deriv_algo,
+                enc_algo,
+                plaintext.len(),
+            ),
+        };
+        self._seal_with_derived_key(&meta, key, plaintext)
+    }
+
+    /// Encrypt (seal) the data buffer using the provided metadata and an
+    /// already derived key.
+    ///
+    /// This method is the counterpart of `.seal_with_meta()`, for cases where
+    /// the key has been derived beforehand, and must not be derived again.
+    fn _seal_with_derived_key(
+        &self,
+        meta: &metadata::Metadata,
+        key: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        let (mut buf, meta_size) = meta.to_buf();
+        if let Some(chunk_size) = meta.chunk_size {
+            let size = metadata::Metadata::calculate_stream_ciphertext_size(
+                plaintext.len(),
+                &meta.enc_algo,
+                chunk_size,
+            );
+            buf.resize(meta_size + size, 0u8);
+        }
+        let ciphertext = &mut buf[meta_size..];
+        ciphertext[..plaintext.len()].copy_from_slice(plaintext);
+
+        let _ = match meta.chunk_size {
+            Some(_) => {
+                let sealer = stream::StreamSealer::_from_key(self, meta, key)?;
+                sealer._seal_in_place(ciphertext)?
+            }
+            None => {
+                let aad = self._get_aad(meta);
+                self._seal_in_place(&meta.enc_algo, &aad, key, ciphertext)?
+            }
+        };
+        Ok(buf)
+    }
+
+    /// Encrypt (seal) the data buffer in place.
+    ///
+    /// This method accepts a metadata instance, a secret value (either a key
+    /// or a passphrase) and a data buffer, that contains the plaintext and
+    /// enough space for the tag.
+    ///
+    /// Depending on the key derivation algorithm, it either creates a
+    /// symmetric key from the secret value, or uses the secret value as a key.
+    /// If the metadata have key slots, the secret value is used to unwrap the
+    /// data key from them instead.
+    /// Then, it seals the data in place, using the encryption algorithm
+    /// specified in the metadata. If the metadata must be authenticated, they
+    /// are serialized and used as associated data, along with the
+    /// user-provided AAD.
+    ///
+    /// This method is much faster than the `seal_with_*` methods that this
+    /// cryptor provides, since it doesn't copy the data buffer. The drawback
+    /// is that the plaintext is not preserved and that the user must create
+    /// the proper buffer layout beforehand.
+    pub fn seal_in_place(
+        &self,
+        meta: &metadata::Metadata,
+        secret: &[u8],
+        buf: &mut [u8],
+    ) -> Result<usize, errors::Error> {
+        if meta.chunk_size.is_some() {
+            let sealer = stream::StreamSealer::new(self, meta, secret)?;
+            return sealer._seal_in_place(buf);
+        }
+
+        let mut key = [0u8; MAX_KEY_SIZE];
+        let mut key = &mut key[..self._get_key_size(meta)];
+
+        self._get_key(meta, secret, &mut key)?;
+        let aad = self._get_aad(meta);
+        self._seal_in_place(&meta.enc_algo, &aad, &key, buf)
+    }
+
+    /// Encrypt (seal) the data buffer using the provided metadata.
+    ///
+    /// This method accepts a metadata instance, a secret value (either a key
+    /// or a passphrase) and the plaintext.
+    ///
+    /// It serializes the metadata instance to a buffer, copies the
+    /// plaintext in it and then seals it in place. This way, the plaintext is
+    /// preserved, at the cost of an extra copy.
+    pub fn seal_with_meta(
+        &self,
+        meta: &metadata::Metadata,
+        secret: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        Cryptor::seal_with_meta(self, meta, secret, plaintext)
+    }
+
+    /// Encrypt (seal) the data buffer in committing mode, using the provided
+    /// metadata.
+    ///
+    /// This method accepts a metadata instance, a secret value (either a key
+    /// or a passphrase) and the plaintext.
+    ///
+    /// It creates the symmetric key from the secret value, stores a
+    /// commitment to this key in the metadata, and then seals the data like
+    /// the `.seal_with_meta()` method, without creating the key again. During
+    /// decryption, the key is checked against the commitment before the AEAD
+    /// decryption takes place, and a different key results to a
+    /// `KeyCommitmentMismatch` error. This way, the ciphertext can be
+    /// decrypted only with a single key.
+    ///
+    /// Note that the key commitment is checked only if it's present in the
+    /// metadata. An attacker that crafts ciphertexts for multiple keys, e.g.,
+    /// for a partitioning oracle attack, can simply omit it. In order to
+    /// thwart such attacks, the receiver must use a decryption policy that
+    /// requires a key commitment (see
+    /// `DecryptionPolicy::require_key_commitment`).
+    pub fn seal_committing(
+        &self,
+        meta: &metadata::Metadata,
+        secret: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        self._seal_with_key_values(meta, secret, plaintext, true, false)
+    }
+
+    /// Encrypt (seal) the data buffer with a key check value, using the
+    /// provided metadata.
+    ///
+    /// This method accepts a metadata instance, a secret value (either a key
+    /// or a passphrase) and the plaintext.
+    ///
+    /// It creates the symmetric key from the secret value, stores a key
+    /// check value for this key in the metadata, and then seals the data like
+    /// the `.seal_with_meta()` method, without creating the key again. During
+    /// decryption, the key is checked against the key check value before the
+    /// AEAD decryption takes place. This way, a wrong secret value results to
+    /// a `WrongKey` error, and a corrupted ciphertext results to a
+    /// `CiphertextCorrupted` error, instead of a `DecryptionError` for both.
+    pub fn seal_with_key_check(
+        &self,
+        meta: &metadata::Metadata,
+        secret: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        self._seal_with_key_values(meta, secret, plaintext, false, true)
+    }
+
+    /// Encrypt (seal) the data buffer, after storing the values that verify
+    /// the key in the metadata.
+    fn _seal_with_key_values(
+        &self,
+        meta: &metadata::Metadata,
+        secret: &[u8],
+        plaintext: &[u8],
+        commit: bool,
+        check: bool,
+    ) -> Result<Vec<u8>, errors::Error> {
+        let mut meta = meta.clone();
+        let mut key = Zeroizing::new([0u8; MAX_KEY_SIZE]);
+        let key = &mut key[..self._get_key_size(&meta)];
+        self._add_key_values(&mut meta, secret, key, commit, check)?;
+        self._seal_with_derived_key(&meta, key, plaintext)
+    }
+
+    /// Encrypt (seal) the data buffer using a symmetric key.
+    ///
+    /// This method accepts a metadata instance, a symmetric key and the
+    /// plaintext.
+    ///
+    /// It generates a metadata instance and then uses the `.seal_with_meta()`
+    /// method to seal the data. The plaintext will be preserved, at the cost
+    /// of an extra copy.
+    pub fn seal_with_key(
+        &self,
+        key: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        let meta = self._generate_meta_for_key(plaintext.len());
+        self.seal_with_meta(&meta, key, plaintext)
+    }
+
+    /// Encrypt (seal) the data buffer using a passphrase.
+    ///
+    /// This method accepts a metadata instance, a passphrase and the
+    /// plaintext.
+    ///
+    /// It generates a metadata instance with the proper key derivation
+    /// algorithm and then uses the `.seal_with_meta()` method to seal the
+    /// data. The plaintext will be preserved, at the cost of an extra copy.
+    pub fn seal_with_passphrase(
+        &self,
+        pass: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        let meta = metadata::Metadata::new(
+            self.generate_key_deriv_algo(),
+            self.generate_enc_algo(),
+            plaintext.len(),
+        );
+        self.seal_with_meta(&meta, pass, plaintext)
+    }
+
+    /// Encrypt (seal) a batch of data buffers using a passphrase.
+    ///
+    /// This method accepts a passphrase and a list of plaintexts.
+    ///
+    /// It generates a single key derivation metadata instance, i.e., a
+    /// single salt, and derives the key only once. Then, it seals each
+    /// plaintext with this key, using a separate metadata instance with a
+    /// unique nonce. Each returned buffer can be decrypted on its own with the
+    /// `.open()` method. If the cryptor has a key cache, the key is stored in
+    /// it, so that the batch can be decrypted without deriving the key again.
+    ///
+    /// Note that the buffers reveal that they have been encrypted with the
+    /// same key, since they share the same salt.
+    pub fn seal_batch_with_passphrase(
+        &self,
+        pass: &[u8],
+        plaintexts: &[&[u8]],
+    ) -> Result<Vec<Vec<u8>>, errors::Error> {
+        let key_deriv_algo = self.generate_key_deriv_algo();
+        let mut key = Zeroizing::new([0u8; MAX_KEY_SIZE]);
+        let mut key_size = 0;
+        let mut bufs = Vec::with_capacity(plaintexts.len());
+
+        for plaintext in plaintexts {
+            let meta = metadata::Metadata::new(
+                key_deriv_algo.clone(),
+                self.generate_enc_algo(),
+                plaintext.len(),
+            );
+            // Derive the key from the metadata of the first plaintext. All
+            // the metadata use the same algorithms, so the key is the same.
+            if key_size == 0 {
+                key_size = self._get_key_size(&meta);
+                self._get_key(&meta, pass, &mut key[..key_size])?;
+            }
+            let key = &key[..key_size];
+            bufs.push(self._seal_with_derived_key(&meta, key, plaintext)?);
+        }
+        Ok(bufs)
+    }
+
+    /// Encrypt (seal) the data buffer using a key that is derived from a
+    /// master key.
+    ///
+    /// This method accepts a master key, a context string and the plaintext.
+    ///
+    /// It generates a metadata instance for the HKDF key derivation
+    /// algorithm, with a random salt and the context string, and then uses
+    /// the `.seal_with_meta()` method to seal the data. This way, each
+    /// message is encrypted with a unique key, and the master key is never
+    /// used directly. Subsystems that share the same master key should use
+    /// different context strings, and decrypt their data with the
+    /// `.open_with_master_key()` method.
+    pub fn seal_with_master_key(
+        &self,
+        master_key: &[u8],
+        info: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        let meta =
+            metadata::Metadata::generate_for_master_key(info, plaintext.len());
+        self.seal_with_meta(&meta, master_key, plaintext)
+    }
+
+    /// Decrypt (open) the data buffer using a key that is derived from a
+    /// master key.
+    ///
+    /// This method accepts a master key, a context string and a data buffer
+    /// that contains the serialized metadata and the ciphertext.
+    ///
+    /// It checks that the data have been encrypted with a key that is derived
+    /// from a master key for the same context string, and then uses the
+    /// `.open()` method to decrypt them. If the data have been encrypted for
+    /// a different context, it returns an `UnknownKey` error. This way, a
+    /// subsystem cannot be tricked into decrypting the data of another
+    /// subsystem that shares the same master key.
+    pub fn open_with_master_key(
+        &self,
+        master_key: &[u8],
+        info: &[u8],
+        buf: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        let (meta, meta_size) = metadata::Metadata::from_buf(buf)?;
+        match &meta.key_deriv_algo {
+            metadata::KeyDerivationAlgorithm::HKDF(hkdf_meta)
+                if hkdf_meta.info == info => {}
+            _ => return Err(errors::Error::UnknownKey),
+        }
+        self.open_with_meta(&meta, master_key, &buf[meta_size..])
+    }
+
+    /// Encrypt (seal) the data buffer for a recipient, using their X25519
+    /// public key.
+    ///
+    /// This method accepts the public key of the recipient and the
+    /// plaintext.
+    ///
+    /// It generates an ephemeral key pair, performs a key agreement with the
+    /// public key of the recipient, and derives the encryption key from the
+    /// shared secret with HKDF. The ephemeral public key is stored in the
+    /// metadata header. This way, the sender needs only the public key of the
+    /// recipient, and only the holder of the respective private key can
+    /// decrypt the data, with the `.open()` method. See the [`x25519`] module
+    /// for more info.
+    ///
+    /// [`x25519`]: ../x25519/index.html
+    pub fn seal_for_recipient(
+        &self,
+        public_key: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        self._seal_for_recipient(public_key, None, plaintext)
+    }
+
+    /// Encrypt (seal) the data buffer deterministically, using a symmetric
+    /// key.
+    ///
+    /// This method accepts a symmetric key and the plaintext. Unlike the
+    /// other `seal_*` methods, it does not use a random nonce. Instead, it
+    /// derives a synthetic nonce from the key, the AAD and the plaintext, and
+    /// encrypts the data with AES-256-GCM-SIV. This way, the same plaintext
+    /// is always encrypted to the same ciphertext, which allows equality
+    /// lookups over encrypted data. The metadata header marks the ciphertext
+    /// as deterministic, and it can be decrypted with the `.open()` method,
+    /// like any other ciphertext.
+    ///
+    /// Note that deterministic encryption reveals whether two ciphertexts
+    /// contain the same plaintext. Use it only if this is acceptable.
+    pub fn seal_deterministic(
+        &self,
+        key: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        if key.len() != aes_gcm_siv::KEY_SIZE {
+            return Err(errors::Error::KeySizeMismatch);
+        }
+
+        let nonce = aes_gcm_siv::synthetic_nonce(key, self.aad, plaintext);
+        let enc_meta = metadata::EncryptionMetadata::new(nonce);
+        let meta =
+            metadata::Metadata::new_deterministic(enc_meta, plaintext.len());
+        self.seal_with_meta(&meta, key, plaintext)
+    }
+
+    /// Encrypt (seal) the data buffer, so that any of the passphrases can
+    /// decrypt it.
+    ///
+    /// This method accepts a list of passphrases and the plaintext.
+    ///
+    /// It generates a random data key and seals the data with it. Then, it
+    /// wraps the data key once per passphrase, with a key that is derived
+    /// from the passphrase, and stores it in a key slot of the metadata
+    /// header. Each key slot has its own key derivation metadata. The data
+    /// can be decrypted with the `.open()` method and any of the passphrases.
+    ///
+    /// Key slots can be added or removed later on, with the
+    /// `.add_key_slot()` and `.remove_key_slot()` methods respectively.
+    pub fn seal_with_passphrases(
+        &self,
+        passes: &[&[u8]],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        if passes.is_empty() {
+            return Err(errors::Error::KeySlotsEmpty);
+        }
+
+        let mut data_key = Zeroizing::new([0u8; metadata::DATA_KEY_SIZE]);
+        rand::fill_buf(&mut data_key[..]);
+        let mut meta = self._generate_meta_for_key(plaintext.len());
+        let buf = self.seal_with_meta(&meta, &data_key[..], plaintext)?;
+        let meta_size = meta.to_header().len();
+
+        for pass in passes {
+            let key_deriv_algo = self.generate_key_deriv_algo();
+            let slot = self._wrap_data_key(
+                &meta,
+                key_deriv_algo,
+                &data_key[..],
+                pass,
+            )?;
+            meta.key_slots.push(slot);
+        }
+
+        let mut new_buf = meta.to_header();
+        new_buf.extend_from_slice(&buf[meta_size..]);
+        Ok(new_buf)
+    }
+
+    /// Encrypt (seal) the data buffer using a key-encryption key (KEK).
+    ///
+    /// This method accepts the identifier of the KEK, the KEK itself and the
+    /// plaintext.
+    ///
+    /// It generates a random data encryption key (DEK) and seals the data
+    /// with it. Then, it wraps the DEK with the KEK, and stores it in a key
+    /// slot of the metadata header, along with the KEK identifier. This way,
+    /// the KEK never touches the data directly, and rotating it only requires
+    /// wrapping the DEK again (see `.rotate_kek()`), instead of encrypting the
+    /// data again. The data can be decrypted with the `.open()` method and
+    /// the KEK.
+    pub fn seal_with_kek(
+        &self,
+        kek_id: &str,
+        kek: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        let mut data_key = Zeroizing::new([0u8; metadata::DATA_KEY_SIZE]);
+        rand::fill_buf(&mut data_key[..]);
+        let mut meta = self._generate_meta_for_key(plaintext.len());
+        let key_deriv_algo = metadata::KeyDerivationAlgorithm::None;
+        let mut slot =
+            self._wrap_data_key(&meta, key_deriv_algo, &data_key[..], kek)?;
+        slot.kek_id = Some(kek_id.to_string());
+
+        let buf = self.seal_with_meta(&meta, &data_key[..], plaintext)?;
+        let meta_size = meta.to_header().len();
+        meta.key_slots.push(slot);
+
+        let mut new_buf = meta.to_header();
+        new_buf.extend_from_slice(&buf[meta_size..]);
+        Ok(new_buf)
+    }
+
+    /// Encrypt (seal) the data buffer using a key from a key provider.
+    ///
+    /// This method accepts a key provider, the identifier of a key in it, and
+    /// the plaintext.
+    ///
+    /// It gets the key from the provider, and uses it as a key-encryption key
+    /// (KEK) for the `.seal_with_kek()` method. The data can be decrypted with
+    /// the `.open_with_provider()` method and the same provider, without
+    /// knowing which key has been used.
+    pub fn seal_with_provider<P: KeyProvider + ?Sized>(
+        &self,
+        provider: &P,
+        key_id: &str,
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        match provider.get_key(key_id) {
+            Some(kek) => self.seal_with_kek(key_id, &kek, plaintext),
+            None => Err(errors::Error::UnknownKey),
+        }
+    }
+
+    /// Decrypt (open) the data buffer using a key provider.
+    ///
+    /// This method accepts a key provider and a data buffer that contains the
+    /// serialized metadata and the ciphertext.
+    ///
+    /// For each key slot that has a KEK identifier, it asks the provider for
+    /// the respective key and tries to unwrap the data key with it. If no key
+    /// slot can be unwrapped, it returns an `UnknownKey` error, if the
+    /// provider has none of the keys, or a decryption error otherwise.
+    pub fn open_with_provider<P: KeyProvider + ?Sized>(
+        &self,
+        provider: &P,
+        buf: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        let (mut meta, meta_size) = metadata::Metadata::from_buf(buf)?;
+        // The key slots are unwrapped directly, so the policy must be
+        // checked before any of them.
+        self._check_policy(&meta)?;
+        let aad = header::get_bare_header(&meta);
+        let mut data_key = Zeroizing::new([0u8; metadata::DATA_KEY_SIZE]);
+        let mut res = Err(errors::Error::UnknownKey);
+
+        for slot in &meta.key_slots {
+            let kek = match slot.kek_id.as_ref() {
+                Some(kek_id) => provider.get_key(kek_id),
+                None => None,
+            };
+            if let Some(kek) = kek {
+                if self._unwrap_key_slot(&aad, slot, &kek, &mut data_key[..]) {
+                    res = Ok(());
+                    break;
+                }
+                res = Err(errors::Error::DecryptionError);
+            }
         }
+        res?;
+
+        // The data key has already been unwrapped, so the key slots are no
+        // longer necessary.
+        meta.key_slots.clear();
+        self.open_with_meta(&meta, &data_key[..], &buf[meta_size..])
     }
 
-    /// Encrypt (seal) the data buffer in place.
+    /// Encrypt (seal) the data buffer using a symmetric key from a keyring.
     ///
-    /// This method accepts a metadata instance, a secret value (either a key
-    /// or a passphrase) and a data buffer, that contains the plaintext and
-    /// enough space for the tag.
+    /// This method accepts a keyring, the identifier of a key in it, and the
+    /// plaintext.
     ///
-    /// Depending on the key derivation algorithm, it either creates a
-    /// symmetric key from the secret value, or uses the secret value as a key.
-    /// Then, it seals the data in place, using the encryption algorithm
-    /// specified in the metadata.
+    /// It generates a metadata instance for the key, stores the key
+    /// identifier in it, and then uses the `.seal_with_meta()` method to seal
+    /// the data. If the entry of the keyring does not have the key size of the
+    /// encryption algorithm, it's treated as a passphrase, and the key is
+    /// derived from it with the key derivation algorithm of the cryptor. The
+    /// data can be decrypted with the `.open_with_keyring()` method and any
+    /// keyring that has the same key. If the keyring does not have the key, it
+    /// returns an `UnknownKey` error.
+    pub fn seal_with_keyring(
+        &self,
+        keyring: &keys::Keyring,
+        key_id: &str,
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        let key = match keyring.get_key(key_id) {
+            Some(key) => key,
+            None => return Err(errors::Error::UnknownKey),
+        };
+
+        let mut meta = self._generate_meta_for_key(plaintext.len());
+        if key.len() != self._get_key_size(&meta) {
+            meta.key_deriv_algo = self.generate_key_deriv_algo();
+        }
+        meta.key_id = Some(key_id.to_string());
+        self.seal_with_meta(&meta, &key, plaintext)
+    }
+
+    /// Decrypt (open) the data buffer using a keyring.
     ///
-    /// This method is much faster than the `seal_with_*` methods that this
-    /// cryptor provides, since it doesn't perform any allocations. The
-    /// drawback is that the plaintext is not preserved and that the user must
-    /// create the proper buffer layout beforehand.
-    pub fn seal_in_place(
+    /// This method accepts a keyring and a data buffer that contains the
+    /// serialized metadata and the ciphertext.
+    ///
+    /// It picks the key (or passphrase) from the keyring, according to the
+    /// key identifier of the metadata, and then uses the `.open_with_meta()`
+    /// method to decrypt the ciphertext. This way, only one key is tried. If
+    /// the metadata have no key identifier, or the keyring does not have the
+    /// respective key, it returns an `UnknownKey` error.
+    pub fn open_with_keyring(
+        &self,
+        keyring: &keys::Keyring,
+        buf: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        let (meta, meta_size) = metadata::Metadata::from_buf(buf)?;
+        let key = match meta.key_id.as_ref() {
+            Some(key_id) => keyring.get_key(key_id),
+            None => None,
+        };
+
+        match key {
+            Some(key) => self.open_with_meta(&meta, &key, &buf[meta_size..]),
+            None => Err(errors::Error::UnknownKey),
+        }
+    }
+
+    /// Check that the metadata can be used with a prepared key.
+    ///
+    /// A prepared key is a symmetric key, so the metadata must not have a key
+    /// derivation algorithm or key slots. Streams are not supported either.
+    /// The metadata are also checked against the decryption policy, if any,
+    /// and against the values that verify the key, if any.
+    fn _check_prepared_key(
         &self,
         meta: &metadata::Metadata,
-        secret: &[u8],
+        key: &keys::PreparedKey,
+    ) -> Result<(), errors::Error> {
+        self._check_policy(meta)?;
+        match meta.key_deriv_algo {
+            metadata::KeyDerivationAlgorithm::None => (),
+            _ => return Err(errors::Error::CryptoParamsUnsupported),
+        }
+        if !meta.key_slots.is_empty() || meta.chunk_size.is_some() {
+            return Err(errors::Error::CryptoParamsUnsupported);
+        }
+        key._verify(meta)
+    }
+
+    /// Encrypt (seal) the data buffer in place, using a prepared key.
+    ///
+    /// This method is the same as `.seal_in_place()`, with the exception that
+    /// it accepts a prepared key instead of a secret value. The metadata must
+    /// not have a key derivation algorithm, and their encryption algorithm
+    /// must be the one that the key has been prepared for. Else, a
+    /// `CryptoParamsUnsupported` error is returned. Streams and key slots
+    /// are not supported.
+    pub fn seal_in_place_with_prepared_key(
+        &self,
+        meta: &metadata::Metadata,
+        key: &keys::PreparedKey,
         buf: &mut [u8],
     ) -> Result<usize, errors::Error> {
-        let mut key = [0u8; MAX_KEY_SIZE];
-        let mut key = &mut key[..self._get_key_size(meta)];
+        self._check_prepared_key(meta, key)?;
+        let aad = self._get_aad(meta);
+        key._crypt_in_place(true, &meta.enc_algo, &aad, buf)
+    }
 
-        self._derive_key(&meta.key_deriv_algo, secret, &mut key)?;
-        self._seal_in_place(&meta.enc_algo, &key, buf)
+    /// Decrypt (open) the data buffer in place, using a prepared key.
+    ///
+    /// This method is the same as `.open_in_place()`, with the exception that
+    /// it accepts a prepared key instead of a secret value. See
+    /// `.seal_in_place_with_prepared_key()` for the metadata that it
+    /// supports.
+    pub fn open_in_place_with_prepared_key(
+        &self,
+        meta: &metadata::Metadata,
+        key: &keys::PreparedKey,
+        buf: &mut [u8],
+    ) -> Result<usize, errors::Error> {
+        self._check_prepared_key(meta, key)?;
+        let aad = self._get_aad(meta);
+        match key._crypt_in_place(false, &meta.enc_algo, &aad, buf) {
+            Err(errors::Error::DecryptionError)
+                if header::is_key_verified(meta) =>
+            {
+                Err(errors::Error::CiphertextCorrupted)
+            }
+            res => res,
+        }
     }
 
-    /// Encrypt (seal) the data buffer using the provided metadata.
+    /// Encrypt (seal) the data buffer using a prepared key.
     ///
-    /// This method accepts a metadata instance, a secret value (either a key
-    /// or a passphrase) and the plaintext.
+    /// This method accepts a prepared key and the plaintext.
     ///
-    /// It serializes the metadata instance to a buffer, copies the
-    /// plaintext in it and then seals it in place. This way, the plaintext is
-    /// preserved, at the cost of an extra copy.
-    pub fn seal_with_meta(
+    /// It generates a metadata instance for the encryption algorithm that the
+    /// key has been prepared for, with a unique nonce, and then seals the
+    /// data like the `.seal_with_key()` method. Since the key has already
+    /// been expanded, this method is faster than `.seal_with_key()` when a
+    /// key is used for a lot of messages. The data can be decrypted with the
+    /// `.open_with_prepared_key()` method, or with the `.open()` method and
+    /// the raw key.
+    pub fn seal_with_prepared_key(
         &self,
-        meta: &metadata::Metadata,
-        secret: &[u8],
+        key: &keys::PreparedKey,
         plaintext: &[u8],
     ) -> Result<Vec<u8>, errors::Error> {
-        // FIXME: Do we need so many `mut` here?
+        let meta = metadata::Metadata::new(
+            metadata::KeyDerivationAlgorithm::None,
+            Self::_generate_enc_algo(key.enc_algo()),
+            plaintext.len(),
+        );
         let (mut buf, meta_size) = meta.to_buf();
-        let mut ciphertext = &mut buf[meta_size..];
-
+        let ciphertext = &mut buf[meta_size..];
         ciphertext[..plaintext.len()].copy_from_slice(plaintext);
-        let _ = self.seal_in_place(meta, secret, &mut ciphertext)?;
+        let _ =
+            self.seal_in_place_with_prepared_key(&meta, key, ciphertext)?;
         Ok(buf)
     }
 
-    /// Encrypt (seal) the data buffer using a symmetric key.
+    /// Decrypt (open) the data buffer using a prepared key.
     ///
-    /// This method accepts a metadata instance, a symmetric key and the
-    /// plaintext.
+    /// This method accepts a prepared key and a data buffer that contains the
+    /// serialized metadata and the ciphertext. See
+    /// `.seal_in_place_with_prepared_key()` for the metadata that it
+    /// supports.
+    pub fn open_with_prepared_key(
+        &self,
+        key: &keys::PreparedKey,
+        buf: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        let (meta, meta_size) = metadata::Metadata::from_buf(buf)?;
+        let mut buf = buf[meta_size..].to_vec();
+        let size =
+            self.open_in_place_with_prepared_key(&meta, key, &mut buf)?;
+        let _ = buf.drain(size..);
+        Ok(buf)
+    }
+
+    /// Rotate the key-encryption key (KEK) of the data buffer.
     ///
-    /// It generates a metadata instance and then uses the `.seal_with_meta()`
-    /// method to seal the data. The plaintext will be preserved, at the cost
-    /// of an extra copy.
-    pub fn seal_with_key(
+    /// This method accepts the old KEK, the identifier of the new KEK, the
+    /// new KEK itself, and a data buffer that contains the serialized
+    /// metadata and the ciphertext.
+    ///
+    /// It unwraps the data key with the old KEK, wraps it again with the new
+    /// one, and replaces the key slot of the old KEK with the new one. The
+    /// ciphertext is not encrypted again; it's copied as is to the returned
+    /// buffer.
+    pub fn rotate_kek(
         &self,
-        key: &[u8],
-        plaintext: &[u8],
+        old_kek: &[u8],
+        new_kek_id: &str,
+        new_kek: &[u8],
+        buf: &[u8],
     ) -> Result<Vec<u8>, errors::Error> {
-        let meta = metadata::Metadata::generate_for_key(plaintext.len());
-        self.seal_with_meta(&meta, key, plaintext)
+        let (mut meta, meta_size) = metadata::Metadata::from_buf(buf)?;
+        if meta.key_slots.is_empty() {
+            return Err(errors::Error::KeySlotsEmpty);
+        }
+        self._check_policy(&meta)?;
+
+        let mut data_key = Zeroizing::new([0u8; metadata::DATA_KEY_SIZE]);
+        let index =
+            self._unwrap_data_key(&meta, old_kek, &mut data_key[..])?;
+        let key_deriv_algo = metadata::KeyDerivationAlgorithm::None;
+        let mut slot = self._wrap_data_key(
+            &meta,
+            key_deriv_algo,
+            &data_key[..],
+            new_kek,
+        )?;
+        slot.kek_id = Some(new_kek_id.to_string());
+        meta.key_slots[index] = slot;
+
+        let mut new_buf = meta.to_header();
+        new_buf.extend_from_slice(&buf[meta_size..]);
+        Ok(new_buf)
     }
 
-    /// Encrypt (seal) the data buffer using a passphrase.
+    /// Find the key slot that a secret value opens.
     ///
-    /// This method accepts a metadata instance, a passphrase and the
-    /// plaintext.
+    /// This method accepts a secret value and a data buffer that contains
+    /// the serialized metadata and the ciphertext. It returns the index of
+    /// the first key slot that the secret value opens, or a decryption error
+    /// if it opens none.
+    pub fn find_key_slot(
+        &self,
+        secret: &[u8],
+        buf: &[u8],
+    ) -> Result<usize, errors::Error> {
+        let (meta, _) = metadata::Metadata::from_buf(buf)?;
+        if meta.key_slots.is_empty() {
+            return Err(errors::Error::KeySlotsEmpty);
+        }
+        self._check_policy(&meta)?;
+
+        let mut data_key = Zeroizing::new([0u8; metadata::DATA_KEY_SIZE]);
+        self._unwrap_data_key(&meta, secret, &mut data_key[..])
+    }
+
+    /// Add a key slot to the data buffer, for a new passphrase.
     ///
-    /// It generates a metadata instance with the proper key derivation
-    /// algorithm and then uses the `.seal_with_meta()` method to seal the
-    /// data. The plaintext will be preserved, at the cost of an extra copy.
-    pub fn seal_with_passphrase(
+    /// This method accepts a secret value that opens one of the existing key
+    /// slots, a new passphrase and a data buffer that contains the serialized
+    /// metadata and the ciphertext.
+    ///
+    /// It unwraps the data key with the secret value, wraps it again with a
+    /// key that is derived from the new passphrase, and appends the new key
+    /// slot to the metadata header. The ciphertext is not encrypted again;
+    /// it's copied as is to the returned buffer.
+    pub fn add_key_slot(
         &self,
+        secret: &[u8],
         pass: &[u8],
-        plaintext: &[u8],
+        buf: &[u8],
     ) -> Result<Vec<u8>, errors::Error> {
-        let meta =
-            metadata::Metadata::generate_for_passphrase(plaintext.len());
-        self.seal_with_meta(&meta, pass, plaintext)
+        let (mut meta, meta_size) = metadata::Metadata::from_buf(buf)?;
+        if meta.key_slots.is_empty() {
+            return Err(errors::Error::KeySlotsEmpty);
+        }
+        self._check_policy(&meta)?;
+
+        let mut data_key = Zeroizing::new([0u8; metadata::DATA_KEY_SIZE]);
+        let _ = self._unwrap_data_key(&meta, secret, &mut data_key[..])?;
+        let key_deriv_algo = self.generate_key_deriv_algo();
+        let slot =
+            self._wrap_data_key(&meta, key_deriv_algo, &data_key[..], pass)?;
+        meta.key_slots.push(slot);
+
+        let mut new_buf = meta.to_header();
+        new_buf.extend_from_slice(&buf[meta_size..]);
+        Ok(new_buf)
+    }
+
+    /// Remove a key slot from the data buffer.
+    ///
+    /// This method accepts the index of a key slot and a data buffer that
+    /// contains the serialized metadata and the ciphertext. It removes the
+    /// key slot from the metadata header, without encrypting the ciphertext
+    /// again. The last key slot cannot be removed, since the data could not
+    /// be decrypted afterwards.
+    ///
+    /// Note that removing a key slot revokes the access of its secret only to
+    /// this copy of the data. Anyone that has already unwrapped the data key
+    /// can still decrypt the ciphertext. If this is a concern, the data must
+    /// be encrypted again with a new data key.
+    pub fn remove_key_slot(
+        &self,
+        index: usize,
+        buf: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        let (mut meta, meta_size) = metadata::Metadata::from_buf(buf)?;
+        if index >= meta.key_slots.len() {
+            return Err(errors::Error::KeySlotNotFound);
+        }
+        if meta.key_slots.len() == 1 {
+            return Err(errors::Error::KeySlotsEmpty);
+        }
+        let _ = meta.key_slots.remove(index);
+
+        let mut new_buf = meta.to_header();
+        new_buf.extend_from_slice(&buf[meta_size..]);
+        Ok(new_buf)
     }
 
     /// Decrypt (open) the data buffer in place.
@@ -362,23 +1839,45 @@ impl<'a> RingCryptor<'a> {
     ///
     /// Depending on the key derivation algorithm, it either creates a
     /// symmetric key from the secret value, or uses the secret value as a key.
+    /// If the metadata have key slots, the secret value is used to unwrap the
+    /// data key from them instead. If the metadata have a key commitment, the
+    /// key is checked against it, and a `KeyCommitmentMismatch` error is
+    /// returned if it doesn't match. Likewise, if the metadata have a key
+    /// check value, a `WrongKey` error is returned if the key doesn't match
+    /// it, and a `CiphertextCorrupted` error is returned if the decryption
+    /// fails with the right key.
     /// Then, it opens the data in place, using the encryption algorithm
-    /// specified in the metadata.
+    /// specified in the metadata. If the metadata must be authenticated, they
+    /// are serialized and used as associated data, along with the
+    /// user-provided AAD.
     ///
     /// This method is much faster than the other `open*` methods that this
-    /// cryptor provides, since it doesn't perform any allocations. The
-    /// drawback is that the ciphertext is not preserved.
+    /// cryptor provides, since it doesn't copy the data buffer. The drawback
+    /// is that the ciphertext is not preserved.
     pub fn open_in_place(
         &self,
         meta: &metadata::Metadata,
         secret: &[u8],
         buf: &mut [u8],
     ) -> Result<usize, errors::Error> {
+        if meta.chunk_size.is_some() {
+            let opener = stream::StreamOpener::new(self, meta, secret)?;
+            return opener._open_in_place(buf);
+        }
+
         let mut key = [0u8; MAX_KEY_SIZE];
         let mut key = &mut key[..self._get_key_size(meta)];
 
-        self._derive_key(&meta.key_deriv_algo, secret, &mut key)?;
-        self._open_in_place(&meta.enc_algo, &key, buf)
+        self._get_key(meta, secret, &mut key)?;
+        let aad = self._get_aad(meta);
+        match self._open_in_place(&meta.enc_algo, &aad, &key, buf) {
+            Err(errors::Error::DecryptionError)
+                if header::is_key_verified(meta) =>
+            {
+                Err(errors::Error::CiphertextCorrupted)
+            }
+            res => res,
+        }
     }
 
     /// Decrypt (open) the data buffer using the provided metadata.
@@ -395,10 +1894,7 @@ impl<'a> RingCryptor<'a> {
         secret: &[u8],
         ciphertext: &[u8],
     ) -> Result<Vec<u8>, errors::Error> {
-        let mut buf = ciphertext.to_vec();
-        let size = self.open_in_place(meta, secret, &mut buf)?;
-        let _ = buf.drain(size..);
-        Ok(buf)
+        Cryptor::open_with_meta(self, meta, secret, ciphertext)
     }
 
     /// Decrypt (open) the data buffer.
@@ -409,33 +1905,409 @@ impl<'a> RingCryptor<'a> {
     /// It deserializes the metadata and extracts the ciphertext from the
     /// buffer. Then, it uses `.open_with_meta()` to decrypt the ciphertext.
     /// The buffer will be preserved, at the cost of an extra copy.
+    ///
+    /// Note that this method does not check the signature of the data, if
+    /// any. Use the `.open_verified()` method for this purpose.
     pub fn open(
         &self,
         secret: &[u8],
         buf: &[u8],
     ) -> Result<Vec<u8>, errors::Error> {
-        let (meta, meta_size) = metadata::Metadata::from_buf(buf)?;
+        Cryptor::open(self, secret, buf)
+    }
+
+    /// Sign the data buffer with an Ed25519 private key.
+    ///
+    /// This method accepts an Ed25519 private key and a data buffer that
+    /// contains the serialized metadata and the ciphertext.
+    ///
+    /// It signs the serialized metadata (without any existing signature) and
+    /// the ciphertext, and stores the signature, along with the public key of
+    /// the signer, in the metadata header. The ciphertext is not encrypted
+    /// again; it's copied as is to the returned buffer. Note that any later
+    /// change to the metadata header, e.g., a new key slot, invalidates the
+    /// signature, so the data must be signed again.
+    pub fn sign(
+        &self,
+        signing_key: &[u8],
+        buf: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        let (mut meta, meta_size) = metadata::Metadata::from_buf(buf)?;
         let ciphertext = &buf[meta_size..];
-        self.open_with_meta(&meta, secret, ciphertext)
+        let data = Self::_get_signed_data(&meta, ciphertext);
+        let signature = ed25519::sign(signing_key, &data)?;
+        let public_key = ed25519::public_key(signing_key)?;
+        meta.signature = Some(metadata::Signature::new(public_key, signature));
+
+        let mut new_buf = meta.to_header();
+        new_buf.extend_from_slice(ciphertext);
+        Ok(new_buf)
+    }
+
+    /// Encrypt (seal) and sign the data buffer using the provided metadata.
+    ///
+    /// This method accepts a metadata instance, a secret value (either a key
+    /// or a passphrase), an Ed25519 private key and the plaintext.
+    ///
+    /// It seals the data with the `.seal_with_meta()` method, and then signs
+    /// them with the `.sign()` method. This way, the recipients can verify
+    /// who has encrypted the data, even if they share the secret value with
+    /// others. The data can be decrypted with the `.open_verified()` method.
+    pub fn seal_signed(
+        &self,
+        meta: &metadata::Metadata,
+        secret: &[u8],
+        signing_key: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        let buf = self.seal_with_meta(meta, secret, plaintext)?;
+        self.sign(signing_key, &buf)
+    }
+
+    /// Verify the signature of the data buffer.
+    ///
+    /// This method accepts a data buffer that contains the serialized
+    /// metadata and the ciphertext. If the data have a valid signature, it
+    /// returns the Ed25519 public key of the signer. It's up to the caller to
+    /// check that they trust this public key.
+    pub fn verify(
+        &self,
+        buf: &[u8],
+    ) -> Result<[u8; metadata::ED25519_KEY_SIZE], errors::Error> {
+        let (meta, meta_size) = metadata::Metadata::from_buf(buf)?;
+        let signature = match meta.signature {
+            Some(signature) => signature,
+            None => return Err(errors::Error::SignatureMissing),
+        };
+        let data = Self::_get_signed_data(&meta, &buf[meta_size..]);
+        ed25519::verify(&signature.public_key, &data, &signature.signature)?;
+        Ok(signature.public_key)
+    }
+
+    /// Verify the signature of the data buffer, and decrypt (open) it.
+    ///
+    /// This method accepts a secret value (either a key or a passphrase) and
+    /// a data buffer that contains the serialized metadata and the
+    /// ciphertext.
+    ///
+    /// It verifies the signature of the data with the `.verify()` method,
+    /// before any decryption takes place, and then decrypts them with the
+    /// `.open()` method. It returns the plaintext, along with the Ed25519
+    /// public key of the signer. It's up to the caller to check that they
+    /// trust this public key.
+    pub fn open_verified(
+        &self,
+        secret: &[u8],
+        buf: &[u8],
+    ) -> Result<(Vec<u8>, [u8; metadata::ED25519_KEY_SIZE]), errors::Error>
+    {
+        let public_key = self.verify(buf)?;
+        let plaintext = self.open(secret, buf)?;
+        Ok((plaintext, public_key))
+    }
+
+    /// Regenerate the algorithms of a metadata instance, in order to encrypt
+    /// its data again.
+    ///
+    /// The returned algorithms have the same parameters as the original ones,
+    /// but a unique salt and nonce. If the metadata have key slots, the key
+    /// derivation algorithm is taken from the key slot that the secret value
+    /// opens, since this is how the secret value is used.
+    pub(crate) fn _regenerate_algos(
+        &self,
+        meta: &metadata::Metadata,
+        header: &[u8],
+        secret: &[u8],
+    ) -> Result<
+        (
+            metadata::KeyDerivationAlgorithm,
+            metadata::EncryptionAlgorithm,
+        ),
+        errors::Error,
+    > {
+        let key_deriv_algo = if meta.key_slots.is_empty() {
+            &meta.key_deriv_algo
+        } else {
+            let index = self.find_key_slot(secret, header)?;
+            &meta.key_slots[index].key_deriv_algo
+        };
+        Ok((key_deriv_algo.regenerate(), meta.enc_algo.regenerate()))
+    }
+
+    /// Encrypt the data buffer again, with a new secret value.
+    ///
+    /// This method accepts the old secret value, the new secret value (either
+    /// a key or a passphrase) and a data buffer that contains the serialized
+    /// metadata and the ciphertext.
+    ///
+    /// It decrypts the data with the old secret value, and encrypts them with
+    /// the new one, using the same key derivation and encryption algorithms,
+    /// but a unique salt and nonce. Data that have been encrypted as a stream
+    /// or deterministically are encrypted the same way again. Key slots and
+    /// the key identifier are not preserved, since they refer to the old
+    /// secret value; use the `.rekey_with_meta()` method to set them. A key
+    /// commitment or a key check value is computed anew for the new key, if
+    /// the data had one. Data that have been encrypted for a recipient are
+    /// encrypted for a new recipient, so the new secret value must be their
+    /// public key. The intermediate plaintext is never returned to the
+    /// caller, and is zeroed before it's dropped.
+    pub fn rekey(
+        &self,
+        old_secret: &[u8],
+        new_secret: &[u8],
+        buf: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        let (meta, _) = metadata::Metadata::from_buf(buf)?;
+        if meta.deterministic {
+            let plaintext = Zeroizing::new(self.open(old_secret, buf)?);
+            return self.seal_deterministic(new_secret, &plaintext);
+        }
+        if let metadata::KeyDerivationAlgorithm::X25519(_) =
+            meta.key_deriv_algo
+        {
+            let plaintext = Zeroizing::new(self.open(old_secret, buf)?);
+            return self._seal_for_recipient(
+                new_secret,
+                meta.chunk_size,
+                &plaintext,
+            );
+        }
+
+        let (key_deriv_algo, enc_algo) =
+            self._regenerate_algos(&meta, buf, old_secret)?;
+        let plaintext = Zeroizing::new(self.open(old_secret, buf)?);
+        let new_meta = match meta.chunk_size {
+            Some(chunk_size) => metadata::Metadata::new_stream(
+                key_deriv_algo,
+                enc_algo,
+                chunk_size,
+            ),
+            None => metadata::Metadata::new(
+                key_deriv_algo,
+                enc_algo,
+                plaintext.len(),
+            ),
+        };
+        self._seal_with_key_values(
+            &new_meta,
+            new_secret,
+            &plaintext,
+            meta.key_commitment.is_some(),
+            meta.key_check.is_some(),
+        )
+    }
+
+    /// Encrypt the data buffer again, with a new secret value and the
+    /// provided metadata.
+    ///
+    /// This method accepts the old secret value, a metadata instance, the new
+    /// secret value (either a key or a passphrase) and a data buffer that
+    /// contains the serialized metadata and the ciphertext.
+    ///
+    /// It decrypts the data with the old secret value, and encrypts them with
+    /// the new one, using the `.seal_with_meta()` method. This way, the
+    /// caller can switch to a different key derivation or encryption
+    /// algorithm. The metadata must have a unique salt and nonce, e.g.,
+    /// freshly generated ones. Since the caller may not know the size of the
+    /// plaintext, the ciphertext size of the metadata is calculated anew. The
+    /// intermediate plaintext is never returned to the caller, and is zeroed
+    /// before it's dropped.
+    pub fn rekey_with_meta(
+        &self,
+        old_secret: &[u8],
+        meta: &metadata::Metadata,
+        new_secret: &[u8],
+        buf: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        let plaintext = Zeroizing::new(self.open(old_secret, buf)?);
+        let mut meta = meta.clone();
+        if meta.chunk_size.is_none() {
+            meta.ciphertext_size =
+                metadata::Metadata::calculate_ciphertext_size(
+                    plaintext.len(),
+                    &meta.enc_algo,
+                );
+        }
+        self.seal_with_meta(&meta, new_secret, &plaintext)
+    }
+}
+
+#[cfg(feature = "ring")]
+impl<'a> Cryptor for RingCryptor<'a> {
+    fn seal_in_place(
+        &self,
+        meta: &metadata::Metadata,
+        secret: &[u8],
+        buf: &mut [u8],
+    ) -> Result<usize, errors::Error> {
+        RingCryptor::seal_in_place(self, meta, secret, buf)
+    }
+
+    fn open_in_place(
+        &self,
+        meta: &metadata::Metadata,
+        secret: &[u8],
+        buf: &mut [u8],
+    ) -> Result<usize, errors::Error> {
+        RingCryptor::open_in_place(self, meta, secret, buf)
+    }
+
+    fn seal_with_key(
+        &self,
+        key: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        RingCryptor::seal_with_key(self, key, plaintext)
+    }
+
+    fn seal_with_passphrase(
+        &self,
+        pass: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        RingCryptor::seal_with_passphrase(self, pass, plaintext)
+    }
+}
+
+/// A builder for a `RingCryptor` instance.
+///
+/// The builder sets the algorithms and parameters that the cryptor uses when
+/// it generates the metadata, so that they can be configured in a single
+/// place. It's created with `RingCryptor::builder()`, and the cryptor is
+/// created with the `.build()` method, which checks the parameters.
+///
+/// The salt size of the key derivation algorithms can be configured too,
+/// within the `metadata::MIN_SALT_SIZE..=metadata::MAX_SALT_SIZE` range,
+/// i.e., 16 to 64 bytes. By default, each key derivation algorithm uses its
+/// default salt size, which is 32 bytes.
+#[cfg(feature = "ring")]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RingCryptorBuilder<'a> {
+    cryptor: RingCryptor<'a>,
+}
+
+#[cfg(feature = "ring")]
+impl<'a> RingCryptorBuilder<'a> {
+    /// Specify the additional associated data (AAD) to be used.
+    ///
+    /// See `RingCryptor::with_aad()` for more info.
+    pub fn aad(mut self, aad: &'a [u8]) -> Self {
+        self.cryptor.aad = aad;
+        self
+    }
+
+    /// Specify the decryption policy to be used.
+    ///
+    /// See `RingCryptor::with_policy()` for more info.
+    pub fn policy(mut self, policy: &'a policy::DecryptionPolicy) -> Self {
+        self.cryptor.policy = Some(policy);
+        self
+    }
+
+    /// Specify the cache for the keys that are derived from passphrases.
+    ///
+    /// See `RingCryptor::with_key_cache()` for more info.
+    pub fn key_cache(mut self, key_cache: &'a cache::DerivedKeyCache) -> Self {
+        self.cryptor.key_cache = Some(key_cache);
+        self
+    }
+
+    /// Specify the encryption algorithm for the generated metadata.
+    pub fn enc_algo(mut self, enc_algo: policy::EncryptionKind) -> Self {
+        self.cryptor.enc_algo = enc_algo;
+        self
+    }
+
+    /// Specify the key derivation algorithm for passphrases.
+    ///
+    /// Only the PBKDF2, Argon2id and scrypt algorithms can derive a key from
+    /// a passphrase. Argon2id and scrypt use their default cost parameters.
+    pub fn key_deriv_algo(
+        mut self,
+        key_deriv_algo: policy::KeyDerivationKind,
+    ) -> Self {
+        self.cryptor.key_deriv_algo = key_deriv_algo;
+        self
+    }
+
+    /// Specify the hash function for the PBKDF2 key derivation algorithm.
+    pub fn hash_fn(mut self, hash_fn: metadata::HashFunction) -> Self {
+        self.cryptor.hash_fn = hash_fn;
+        self
+    }
+
+    /// Specify the number of iterations for the PBKDF2 key derivation
+    /// algorithm.
+    pub fn iterations(mut self, iterations: usize) -> Self {
+        self.cryptor.iterations = iterations;
+        self
+    }
+
+    /// Specify the size of the salt for the key derivation algorithm.
+    ///
+    /// The size must be between `metadata::MIN_SALT_SIZE` and
+    /// `metadata::MAX_SALT_SIZE`. If it's not specified, each key derivation
+    /// algorithm uses its default salt size.
+    pub fn salt_size(mut self, salt_size: usize) -> Self {
+        self.cryptor.salt_size = Some(salt_size);
+        self
+    }
+
+    /// Create the cryptor instance.
+    ///
+    /// This method returns an error if the key derivation algorithm cannot
+    /// derive a key from a passphrase, if the number of PBKDF2 iterations is
+    /// zero or does not fit in 32 bits, or if the salt size is out of the
+    /// accepted range.
+    pub fn build(self) -> Result<RingCryptor<'a>, errors::Error> {
+        match self.cryptor.key_deriv_algo {
+            policy::KeyDerivationKind::PBKDF2
+            | policy::KeyDerivationKind::Argon2id
+            | policy::KeyDerivationKind::Scrypt => (),
+            _ => return Err(errors::Error::CryptoParamsUnsupported),
+        }
+        if self.cryptor.iterations == 0 {
+            return Err(errors::Error::CryptoParamsWeak);
+        }
+        if self.cryptor.iterations > u32::MAX as usize {
+            return Err(errors::Error::CryptoParamsUnsupported);
+        }
+        match self.cryptor.salt_size {
+            Some(size) if size < metadata::MIN_SALT_SIZE => {
+                return Err(errors::Error::CryptoParamsWeak);
+            }
+            Some(size) if size > metadata::MAX_SALT_SIZE => {
+                return Err(errors::Error::CryptoParamsUnsupported);
+            }
+            _ => (),
+        }
+        Ok(self.cryptor)
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "ring"))]
 mod tests {
     use super::*;
+    use protobuf::Message;
 
     /// Simplified options for the key derivation algorithm, used only in the
     /// tests.
+    #[derive(Clone, Copy)]
     enum KeyOpts {
         None,
         PBKDF2,
+        Argon2id,
+        Scrypt,
     }
 
     /// Simplified options for the encryption algorithm, used only in the
     /// tests.
+    #[derive(Clone, Copy)]
     enum EncOpts {
         AES,
         ChaCha,
+        XChaCha,
+        AESSIV,
     }
 
     /// Generate a metadata struct for the tests, based on the selected key
@@ -454,6 +2326,18 @@ mod tests {
                 key_deriv_meta.iterations = 1;
                 metadata::KeyDerivationAlgorithm::PBKDF2(key_deriv_meta)
             }
+            KeyOpts::Argon2id => {
+                let mut argon2_meta = metadata::Argon2idMetadata::generate();
+                argon2_meta.memory_cost = 8;
+                argon2_meta.time_cost = 1;
+                argon2_meta.parallelism = 1;
+                metadata::KeyDerivationAlgorithm::Argon2id(argon2_meta)
+            }
+            KeyOpts::Scrypt => {
+                let mut scrypt_meta = metadata::ScryptMetadata::generate();
+                scrypt_meta.log_n = 1;
+                metadata::KeyDerivationAlgorithm::Scrypt(scrypt_meta)
+            }
         };
 
         // Encryption algorithm.
@@ -463,6 +2347,13 @@ mod tests {
             EncOpts::ChaCha => {
                 metadata::EncryptionAlgorithm::ChaCha20Poly1305(enc_meta)
             }
+            EncOpts::AESSIV => {
+                metadata::EncryptionAlgorithm::AES256GCMSIV(enc_meta)
+            }
+            EncOpts::XChaCha => {
+                let enc_meta = metadata::XChaCha20Poly1305Metadata::generate();
+                metadata::EncryptionAlgorithm::XChaCha20Poly1305(enc_meta)
+            }
         };
 
         metadata::Metadata::new(key_algo, enc_algo, size)
@@ -488,24 +2379,24 @@ mod tests {
         //
         // PBKDF2 key derivation and AES-256-GCM encryption, with passphrase.
         let meta1 =
-            generate_meta(plaintext.len(), KeyOpts::PBKDF2, EncOpts::AES);
+            &generate_meta(plaintext.len(), KeyOpts::PBKDF2, EncOpts::AES);
         let key1 = "My passphrase 1".as_bytes();
 
         // PBKDF2 key derivation and ChaCha20-Poly1305 encryption, with
         // passphrase.
         let meta2 =
-            generate_meta(plaintext.len(), KeyOpts::PBKDF2, EncOpts::ChaCha);
+            &generate_meta(plaintext.len(), KeyOpts::PBKDF2, EncOpts::ChaCha);
         let key2 = key1.clone();
 
         // No key derivation and AES-256-GCM encryption, with symmetric key.
         let meta3 =
-            generate_meta(plaintext.len(), KeyOpts::None, EncOpts::AES);
+            &generate_meta(plaintext.len(), KeyOpts::None, EncOpts::AES);
         let key3 = vec![9u8; ring::aead::AES_256_GCM.key_len()];
 
         // No key derivation and ChaCha20-Poly1305 encryption, with symmetric
         // key.
         let meta4 =
-            generate_meta(plaintext.len(), KeyOpts::None, EncOpts::ChaCha);
+            &generate_meta(plaintext.len(), KeyOpts::None, EncOpts::ChaCha);
         let key4 = key3.clone();
 
         // Test that the encryption operation returns the expected errors for
@@ -660,4 +2551,1256 @@ mod tests {
         assert!(res.is_ok());
         assert_eq!(data, res.unwrap());
     }
+
+    #[test]
+    fn test_seal_open_header() {
+        let plaintext = "The cake is a lie".as_bytes();
+        let key = vec![9u8; ring::aead::AES_256_GCM.key_len()];
+        let dec_err = Err(errors::Error::DecryptionError);
+        let invalid_err = Err(errors::Error::MetadataInvalid);
+        let cryptor = RingCryptor::new();
+
+        let ciphertext = cryptor.seal_with_key(&key, plaintext).unwrap();
+        let (meta, meta_size) =
+            metadata::Metadata::from_buf(&ciphertext).unwrap();
+        assert!(meta.authenticated_header);
+
+        // Check that tampering with the values of the metadata header makes
+        // the decryption fail.
+        let mut bad_meta = meta.clone();
+        bad_meta.ciphertext_size += 1;
+        let (mut buf, bad_meta_size) = bad_meta.to_buf();
+        buf[bad_meta_size..bad_meta_size + meta.ciphertext_size]
+            .copy_from_slice(&ciphertext[meta_size..]);
+        assert_eq!(cryptor.open(&key, &buf), dec_err);
+
+        // Check that downgrading the metadata header to an unauthenticated one
+        // makes the decryption fail.
+        let mut bad_meta = meta.clone();
+        bad_meta.authenticated_header = false;
+        let mut buf = bad_meta.to_header();
+        buf.extend_from_slice(&ciphertext[meta_size..]);
+        assert_eq!(cryptor.open(&key, &buf), dec_err);
+
+        // Check that appending unknown fields to the metadata header is
+        // detected.
+        let mut proto_meta = meta.to_proto();
+        proto_meta.mut_unknown_fields().add_varint(99, 1);
+        let mut buf = proto_meta.write_length_delimited_to_bytes().unwrap();
+        buf.extend_from_slice(&ciphertext[meta_size..]);
+        assert_eq!(cryptor.open(&key, &buf), invalid_err);
+
+        // Check that unauthenticated metadata headers, which are created by
+        // older versions of this library, can still be decrypted.
+        let mut legacy_meta = meta.clone();
+        legacy_meta.authenticated_header = false;
+        let ciphertext = cryptor
+            .seal_with_meta(&legacy_meta, &key, plaintext)
+            .unwrap();
+        assert_eq!(cryptor.open(&key, &ciphertext), Ok(plaintext.to_vec()));
+
+        // Check that the same holds for metadata headers without the magic
+        // bytes and the format version, which older versions of this library
+        // did not write.
+        let proto_meta = legacy_meta.to_proto();
+        let mut buf = proto_meta.write_length_delimited_to_bytes().unwrap();
+        let meta_size = legacy_meta.to_header().len();
+        buf.extend_from_slice(&ciphertext[meta_size..]);
+        assert_eq!(cryptor.open(&key, &buf), Ok(plaintext.to_vec()));
+    }
+
+    #[test]
+    fn test_seal_open_kdfs() {
+        let plaintext = "The cake is a lie".as_bytes();
+        let pass = "My passphrase".as_bytes();
+        let wrong_pass = "Wrong passphrase".as_bytes();
+        let dec_err = Err(errors::Error::DecryptionError);
+        let cryptor = RingCryptor::new();
+
+        for (key_opts, enc_opts) in &[
+            (KeyOpts::Argon2id, EncOpts::AES),
+            (KeyOpts::Argon2id, EncOpts::ChaCha),
+            (KeyOpts::Scrypt, EncOpts::AES),
+            (KeyOpts::Scrypt, EncOpts::ChaCha),
+        ] {
+            let meta = generate_meta(plaintext.len(), *key_opts, *enc_opts);
+            let ciphertext = cryptor.seal_with_meta(&meta, pass, plaintext);
+            assert!(ciphertext.is_ok());
+            let ciphertext = ciphertext.unwrap();
+
+            // Check that a wrong passphrase results in a decryption error.
+            let res = cryptor.open(wrong_pass, &ciphertext);
+            assert_eq!(res, dec_err);
+
+            // Check that the correct passphrase decrypts the ciphertext.
+            let res = cryptor.open(pass, &ciphertext);
+            assert_eq!(res, Ok(plaintext.to_vec()));
+        }
+    }
+
+    #[test]
+    fn test_seal_open_xchacha() {
+        let plaintext = "The cake is a lie".as_bytes();
+        let pass = "My passphrase".as_bytes();
+        let key = [9u8; xchacha20::KEY_SIZE];
+        let dec_err = Err(errors::Error::DecryptionError);
+        let key_err = Err(errors::Error::KeySizeMismatch);
+        let cryptor = RingCryptor::new().with_aad("My context".as_bytes());
+
+        for (key_opts, secret) in
+            &[(KeyOpts::None, &key[..]), (KeyOpts::PBKDF2, pass)]
+        {
+            let meta =
+                generate_meta(plaintext.len(), *key_opts, EncOpts::XChaCha);
+            let ciphertext = cryptor.seal_with_meta(&meta, secret, plaintext);
+            assert!(ciphertext.is_ok());
+            let ciphertext = ciphertext.unwrap();
+
+            // Check that the ciphertext is bound to the AAD.
+            let res = RingCryptor::new().open(secret, &ciphertext);
+            assert_eq!(res, dec_err);
+
+            // Check that the correct secret decrypts the ciphertext.
+            let res = cryptor.open(secret, &ciphertext);
+            assert_eq!(res, Ok(plaintext.to_vec()));
+        }
+
+        // Check that a key with the wrong size is rejected.
+        let meta =
+            generate_meta(plaintext.len(), KeyOpts::None, EncOpts::XChaCha);
+        let res = cryptor.seal_with_meta(&meta, &key[1..], plaintext);
+        assert_eq!(res, key_err);
+    }
+
+    #[test]
+    fn test_seal_open_aes_siv() {
+        let plaintext = "The cake is a lie".as_bytes();
+        let key = [9u8; aes_gcm_siv::KEY_SIZE];
+        let dec_err = Err(errors::Error::DecryptionError);
+        let cryptor = RingCryptor::new();
+
+        // Check that reusing the same metadata, and therefore the same nonce,
+        // produces the same ciphertext for the same plaintext, but a
+        // different one for a different plaintext.
+        let meta =
+            generate_meta(plaintext.len(), KeyOpts::None, EncOpts::AESSIV);
+        let ciphertext1 =
+            cryptor.seal_with_meta(&meta, &key, plaintext).unwrap();
+        let ciphertext2 =
+            cryptor.seal_with_meta(&meta, &key, plaintext).unwrap();
+        let ciphertext3 = cryptor
+            .seal_with_meta(&meta, &key, "The cake is a pie".as_bytes())
+            .unwrap();
+        assert_eq!(ciphertext1, ciphertext2);
+        assert_ne!(ciphertext1, ciphertext3);
+
+        // Check that the ciphertext can be decrypted only with the correct
+        // key.
+        let res = cryptor.open(&[1u8; aes_gcm_siv::KEY_SIZE], &ciphertext1);
+        assert_eq!(res, dec_err);
+        let res = cryptor.open(&key, &ciphertext1);
+        assert_eq!(res, Ok(plaintext.to_vec()));
+    }
+
+    #[test]
+    fn test_seal_deterministic() {
+        let plaintext = "The cake is a lie".as_bytes();
+        let key = [9u8; aes_gcm_siv::KEY_SIZE];
+        let cryptor = RingCryptor::new();
+
+        // Check that equal plaintexts produce equal ciphertexts, and that
+        // different plaintexts, keys or AAD produce different ones.
+        let ciphertext = cryptor.seal_deterministic(&key, plaintext).unwrap();
+        let res = cryptor.seal_deterministic(&key, plaintext);
+        assert_eq!(res, Ok(ciphertext.clone()));
+        let res =
+            cryptor.seal_deterministic(&key, "The cake is a pie".as_bytes());
+        assert_ne!(res, Ok(ciphertext.clone()));
+        let res = cryptor.seal_deterministic(&[1u8; 32], plaintext);
+        assert_ne!(res, Ok(ciphertext.clone()));
+        let res = cryptor.with_aad(b"aad").seal_deterministic(&key, plaintext);
+        assert_ne!(res, Ok(ciphertext.clone()));
+
+        // Check that the header marks the ciphertext as deterministic, and
+        // that it can be decrypted transparently.
+        let (meta, _) = metadata::Metadata::from_buf(&ciphertext).unwrap();
+        assert!(meta.deterministic);
+        assert_eq!(cryptor.open(&key, &ciphertext), Ok(plaintext.to_vec()));
+        let res = cryptor.open(&[1u8; 32], &ciphertext);
+        assert_eq!(res, Err(errors::Error::DecryptionError));
+
+        // Check that the deterministic flag is authenticated.
+        let mut bad_meta = meta.clone();
+        bad_meta.deterministic = false;
+        let mut buf = bad_meta.to_header();
+        buf.extend_from_slice(&ciphertext[meta.to_header().len()..]);
+        let res = cryptor.open(&key, &buf);
+        assert_eq!(res, Err(errors::Error::DecryptionError));
+
+        // Check that only symmetric keys of the proper size are accepted.
+        let res = cryptor.seal_deterministic(&key[1..], plaintext);
+        assert_eq!(res, Err(errors::Error::KeySizeMismatch));
+    }
+
+    #[test]
+    fn test_key_slots() {
+        let plaintext = "The cake is a lie".as_bytes();
+        let pass1 = "My passphrase 1".as_bytes();
+        let pass2 = "My passphrase 2".as_bytes();
+        let pass3 = "My passphrase 3".as_bytes();
+        let wrong_pass = "Wrong passphrase".as_bytes();
+        let dec_err = Err(errors::Error::DecryptionError);
+        let cryptor = RingCryptor::new().with_aad(b"aad");
+
+        // Check that sealing with no passphrases is not allowed.
+        let res = cryptor.seal_with_passphrases(&[], plaintext);
+        assert_eq!(res, Err(errors::Error::KeySlotsEmpty));
+
+        // Check that any of the passphrases can decrypt the data, and that
+        // each key slot has its own key derivation metadata.
+        let buf = cryptor
+            .seal_with_passphrases(&[pass1, pass2], plaintext)
+            .unwrap();
+        let (meta, _) = metadata::Metadata::from_buf(&buf).unwrap();
+        assert_eq!(meta.key_slots.len(), 2);
+        assert_ne!(meta.key_slots[0], meta.key_slots[1]);
+        assert_eq!(cryptor.open(pass1, &buf), Ok(plaintext.to_vec()));
+        assert_eq!(cryptor.open(pass2, &buf), Ok(plaintext.to_vec()));
+        assert_eq!(cryptor.open(wrong_pass, &buf), dec_err);
+        assert_eq!(cryptor.find_key_slot(pass2, &buf), Ok(1));
+        let res = cryptor.find_key_slot(wrong_pass, &buf);
+        assert_eq!(res, Err(errors::Error::DecryptionError));
+
+        // Check that a key slot can be added only with a valid passphrase,
+        // and that the ciphertext is not encrypted again.
+        let res = cryptor.add_key_slot(wrong_pass, pass3, &buf);
+        assert_eq!(res, dec_err);
+        let buf2 = cryptor.add_key_slot(pass2, pass3, &buf).unwrap();
+        let (meta2, meta_size2) = metadata::Metadata::from_buf(&buf2).unwrap();
+        let meta_size = meta.to_header().len();
+        assert_eq!(meta2.key_slots.len(), 3);
+        assert_eq!(buf[meta_size..], buf2[meta_size2..]);
+        assert_eq!(cryptor.open(pass3, &buf2), Ok(plaintext.to_vec()));
+
+        // Check that a key slot can be removed, and that its passphrase can
+        // no longer decrypt the data.
+        let res = cryptor.remove_key_slot(3, &buf2);
+        assert_eq!(res, Err(errors::Error::KeySlotNotFound));
+        let buf3 = cryptor.remove_key_slot(0, &buf2).unwrap();
+        assert_eq!(cryptor.open(pass1, &buf3), dec_err);
+        assert_eq!(cryptor.open(pass2, &buf3), Ok(plaintext.to_vec()));
+        assert_eq!(cryptor.open(pass3, &buf3), Ok(plaintext.to_vec()));
+        let buf4 = cryptor.remove_key_slot(0, &buf3).unwrap();
+        let res = cryptor.remove_key_slot(0, &buf4);
+        assert_eq!(res, Err(errors::Error::KeySlotsEmpty));
+
+        // Check that a key slot cannot be moved to data that have been
+        // encrypted with a different data key.
+        let other_buf =
+            cryptor.seal_with_passphrases(&[pass1], plaintext).unwrap();
+        let (mut other_meta, other_meta_size) =
+            metadata::Metadata::from_buf(&other_buf).unwrap();
+        other_meta.key_slots.push(meta.key_slots[1].clone());
+        let mut bad_buf = other_meta.to_header();
+        bad_buf.extend_from_slice(&other_buf[other_meta_size..]);
+        assert_eq!(cryptor.open(pass2, &bad_buf), dec_err);
+
+        // Check that data without key slots are handled properly.
+        let buf = cryptor.seal_with_passphrase(pass1, plaintext).unwrap();
+        let res = cryptor.add_key_slot(pass1, pass2, &buf);
+        assert_eq!(res, Err(errors::Error::KeySlotsEmpty));
+        let res = cryptor.find_key_slot(pass1, &buf);
+        assert_eq!(res, Err(errors::Error::KeySlotsEmpty));
+    }
+
+    #[test]
+    fn test_seal_with_kek() {
+        let plaintext = "The cake is a lie".as_bytes();
+        let kek1 = [1u8; 32];
+        let kek2 = [2u8; 32];
+        let dec_err = Err(errors::Error::DecryptionError);
+        let cryptor = RingCryptor::new();
+
+        // Check that the KEK must have the proper size.
+        let res = cryptor.seal_with_kek("kek-1", &kek1[1..], plaintext);
+        assert_eq!(res, Err(errors::Error::KeySizeMismatch));
+
+        // Check that the data are encrypted with a DEK, which is wrapped by
+        // the KEK, and that the KEK identifier is stored in the header.
+        let buf = cryptor.seal_with_kek("kek-1", &kek1, plaintext).unwrap();
+        let (meta, meta_size) = metadata::Metadata::from_buf(&buf).unwrap();
+        assert_eq!(meta.key_slots.len(), 1);
+        assert_eq!(meta.key_slots[0].kek_id, Some("kek-1".to_string()));
+        assert_eq!(
+            meta.key_slots[0].key_deriv_algo,
+            metadata::KeyDerivationAlgorithm::None
+        );
+        assert_eq!(cryptor.open(&kek1, &buf), Ok(plaintext.to_vec()));
+        assert_eq!(cryptor.open(&kek2, &buf), dec_err);
+
+        // Check that rotating the KEK rewraps only the header.
+        let res = cryptor.rotate_kek(&kek2, "kek-2", &kek2, &buf);
+        assert_eq!(res, Err(errors::Error::DecryptionError));
+        let buf2 = cryptor.rotate_kek(&kek1, "kek-2", &kek2, &buf).unwrap();
+        let (meta2, meta_size2) = metadata::Metadata::from_buf(&buf2).unwrap();
+        assert_eq!(meta2.key_slots.len(), 1);
+        assert_eq!(meta2.key_slots[0].kek_id, Some("kek-2".to_string()));
+        assert_eq!(buf[meta_size..], buf2[meta_size2..]);
+        assert_eq!(cryptor.open(&kek1, &buf2), dec_err);
+        assert_eq!(cryptor.open(&kek2, &buf2), Ok(plaintext.to_vec()));
+
+        // Check that data without key slots cannot be rotated.
+        let buf = cryptor.seal_with_key(&kek1, plaintext).unwrap();
+        let res = cryptor.rotate_kek(&kek1, "kek-2", &kek2, &buf);
+        assert_eq!(res, Err(errors::Error::KeySlotsEmpty));
+    }
+
+    #[test]
+    fn test_seal_open_with_provider() {
+        let plaintext = "The cake is a lie".as_bytes();
+        let dir = assert_fs::TempDir::new().unwrap();
+        let mut keystore = keys::FileKeyStore::create(dir.path().join("keys"));
+        keystore.generate_key("kek-1").unwrap();
+        keystore.generate_key("kek-2").unwrap();
+        let cryptor = RingCryptor::new();
+        let not_found_err = Err(errors::Error::UnknownKey);
+
+        // Check that unknown keys are detected.
+        let res = cryptor.seal_with_provider(&keystore, "kek-3", plaintext);
+        assert_eq!(res, not_found_err);
+
+        // Check that the data can be decrypted with just the provider, or
+        // with the key itself.
+        let buf = cryptor
+            .seal_with_provider(&keystore, "kek-2", plaintext)
+            .unwrap();
+        let res = cryptor.open_with_provider(&keystore, &buf);
+        assert_eq!(res, Ok(plaintext.to_vec()));
+        let kek = keystore.get_key("kek-2").unwrap();
+        assert_eq!(cryptor.open(&kek, &buf), Ok(plaintext.to_vec()));
+
+        // Check that the provider must have the key, and that the key must
+        // be the correct one.
+        let _ = keystore.remove_key("kek-2");
+        let res = cryptor.open_with_provider(&keystore, &buf);
+        assert_eq!(res, not_found_err);
+        keystore.generate_key("kek-2").unwrap();
+        let res = cryptor.open_with_provider(&keystore, &buf);
+        assert_eq!(res, Err(errors::Error::DecryptionError));
+
+        // Check that data without key slots cannot be decrypted with a
+        // provider.
+        let buf = cryptor.seal_with_key(&kek, plaintext).unwrap();
+        let res = cryptor.open_with_provider(&keystore, &buf);
+        assert_eq!(res, not_found_err);
+    }
+
+    #[test]
+    fn test_seal_open_with_keyring() {
+        let plaintext = "The cake is a lie".as_bytes();
+        let pass = "My passphrase".as_bytes();
+        let unknown_err = Err(errors::Error::UnknownKey);
+        let cryptor = RingCryptor::new();
+        let mut keyring = keys::Keyring::new();
+        keyring.add_key("key-1", &[1u8; 32]);
+        keyring.add_key("key-2", &[2u8; 32]);
+        keyring.add_key("pass-1", pass);
+
+        // Check that unknown keys are detected.
+        let res = cryptor.seal_with_keyring(&keyring, "key-3", plaintext);
+        assert_eq!(res, unknown_err);
+
+        // Check that the key identifier is stored in the header, and that
+        // the right key is picked during decryption.
+        let buf = cryptor
+            .seal_with_keyring(&keyring, "key-2", plaintext)
+            .unwrap();
+        let (meta, _) = metadata::Metadata::from_buf(&buf).unwrap();
+        assert_eq!(meta.key_id, Some("key-2".to_string()));
+        let res = cryptor.open_with_keyring(&keyring, &buf);
+        assert_eq!(res, Ok(plaintext.to_vec()));
+        assert_eq!(cryptor.open(&[2u8; 32], &buf), Ok(plaintext.to_vec()));
+
+        // Check that passphrases can be picked too, and that the key is
+        // derived from them with the key derivation algorithm of the cryptor.
+        let buf = cryptor
+            .seal_with_keyring(&keyring, "pass-1", plaintext)
+            .unwrap();
+        let (meta, _) = metadata::Metadata::from_buf(&buf).unwrap();
+        assert_eq!(meta.key_id, Some("pass-1".to_string()));
+        match meta.key_deriv_algo {
+            metadata::KeyDerivationAlgorithm::PBKDF2(_) => (),
+            _ => panic!("Unexpected key derivation algorithm"),
+        }
+        let res = cryptor.open_with_keyring(&keyring, &buf);
+        assert_eq!(res, Ok(plaintext.to_vec()));
+        assert_eq!(cryptor.open(pass, &buf), Ok(plaintext.to_vec()));
+
+        let mut meta =
+            metadata::Metadata::generate_for_passphrase(plaintext.len());
+        if let metadata::KeyDerivationAlgorithm::PBKDF2(ref mut kd_meta) =
+            meta.key_deriv_algo
+        {
+            kd_meta.iterations = 1;
+        }
+        meta.key_id = Some("pass-1".to_string());
+        let buf = cryptor.seal_with_meta(&meta, pass, plaintext).unwrap();
+        let res = cryptor.open_with_keyring(&keyring, &buf);
+        assert_eq!(res, Ok(plaintext.to_vec()));
+
+        // Check that a keyring without the key, or a header without a key
+        // identifier, results to an `UnknownKey` error.
+        let buf = cryptor
+            .seal_with_keyring(&keyring, "key-1", plaintext)
+            .unwrap();
+        let _ = keyring.remove_key("key-1");
+        assert_eq!(cryptor.open_with_keyring(&keyring, &buf), unknown_err);
+        let buf = cryptor.seal_with_key(&[2u8; 32], plaintext).unwrap();
+        assert_eq!(cryptor.open_with_keyring(&keyring, &buf), unknown_err);
+
+        // Check that the key identifier is authenticated.
+        keyring.add_key("key-1", &[2u8; 32]);
+        let buf = cryptor
+            .seal_with_keyring(&keyring, "key-2", plaintext)
+            .unwrap();
+        let (mut meta, meta_size) =
+            metadata::Metadata::from_buf(&buf).unwrap();
+        meta.key_id = Some("key-1".to_string());
+        let mut bad_buf = meta.to_header();
+        bad_buf.extend_from_slice(&buf[meta_size..]);
+        let res = cryptor.open_with_keyring(&keyring, &bad_buf);
+        assert_eq!(res, Err(errors::Error::DecryptionError));
+    }
+
+    #[test]
+    fn test_rekey() {
+        let plaintext = "The cake is a lie".as_bytes();
+        let old_key = [1u8; 32];
+        let new_key = [2u8; 32];
+        let pass = "My passphrase".as_bytes();
+        let dec_err = Err(errors::Error::DecryptionError);
+        let cryptor = RingCryptor::new();
+
+        // Check that data that are encrypted in various ways can be encrypted
+        // again with a new secret value, with the same algorithms but a
+        // unique salt and nonce.
+        let key_meta = generate_meta(17, KeyOpts::PBKDF2, EncOpts::XChaCha);
+        let stream_meta = metadata::Metadata::new_stream(
+            metadata::KeyDerivationAlgorithm::None,
+            metadata::EncryptionAlgorithm::ChaCha20Poly1305(
+                metadata::EncryptionMetadata::generate(),
+            ),
+            5,
+        );
+        for (meta, old_secret, new_secret) in &[
+            (key_meta, pass, &pass[1..]),
+            (stream_meta, &old_key[..], &new_key[..]),
+        ] {
+            let buf =
+                cryptor.seal_with_meta(meta, old_secret, plaintext).unwrap();
+            let new_buf = cryptor.rekey(old_secret, new_secret, &buf).unwrap();
+            assert_eq!(cryptor.open(old_secret, &new_buf), dec_err);
+            let res = cryptor.open(new_secret, &new_buf);
+            assert_eq!(res, Ok(plaintext.to_vec()));
+
+            let (new_meta, _) =
+                metadata::Metadata::from_buf(&new_buf).unwrap();
+            assert_eq!(new_meta.chunk_size, meta.chunk_size);
+            assert_eq!(new_meta.ciphertext_size, meta.ciphertext_size);
+            assert_ne!(new_meta.enc_algo, meta.enc_algo);
+            assert_eq!(
+                new_meta.enc_algo.to_proto().algo,
+                meta.enc_algo.to_proto().algo
+            );
+            if let metadata::KeyDerivationAlgorithm::PBKDF2(kd_meta) =
+                &meta.key_deriv_algo
+            {
+                let new_kd_meta = match new_meta.key_deriv_algo {
+                    metadata::KeyDerivationAlgorithm::PBKDF2(m) => m,
+                    _ => panic!("Unexpected key derivation algorithm"),
+                };
+                assert_eq!(new_kd_meta.iterations, kd_meta.iterations);
+                assert_ne!(new_kd_meta.salt, kd_meta.salt);
+            }
+        }
+
+        // Check that deterministic encryption is preserved.
+        let buf = cryptor.seal_deterministic(&old_key, plaintext).unwrap();
+        let new_buf = cryptor.rekey(&old_key, &new_key, &buf).unwrap();
+        let exp_buf = cryptor.seal_deterministic(&new_key, plaintext).unwrap();
+        assert_eq!(new_buf, exp_buf);
+
+        // Check that data with key slots are encrypted with the key
+        // derivation algorithm of the key slot that the old secret opens.
+        let buf = cryptor.seal_with_kek("kek-1", &old_key, plaintext).unwrap();
+        let new_buf = cryptor.rekey(&old_key, &new_key, &buf).unwrap();
+        let (new_meta, _) = metadata::Metadata::from_buf(&new_buf).unwrap();
+        assert!(new_meta.key_slots.is_empty());
+        assert_eq!(
+            new_meta.key_deriv_algo,
+            metadata::KeyDerivationAlgorithm::None
+        );
+        let res = cryptor.open(&new_key, &new_buf);
+        assert_eq!(res, Ok(plaintext.to_vec()));
+
+        // Check that a wrong old secret value is detected.
+        let res = cryptor.rekey(&new_key, &old_key, &buf);
+        assert_eq!(res, dec_err);
+
+        // Check that the key commitment and the key check value are computed
+        // anew for the new secret value.
+        let meta = generate_meta(plaintext.len(), KeyOpts::None, EncOpts::AES);
+        let buf = cryptor.seal_committing(&meta, &old_key, plaintext).unwrap();
+        let new_buf = cryptor.rekey(&old_key, &new_key, &buf).unwrap();
+        let (new_meta, _) = metadata::Metadata::from_buf(&new_buf).unwrap();
+        let key_commitment = RingCryptor::_get_key_commitment(&new_key);
+        assert_eq!(new_meta.key_commitment, Some(key_commitment));
+        assert_eq!(new_meta.key_check, None);
+        let res = cryptor.open(&old_key, &new_buf);
+        assert_eq!(res, Err(errors::Error::KeyCommitmentMismatch));
+        let res = cryptor.open(&new_key, &new_buf);
+        assert_eq!(res, Ok(plaintext.to_vec()));
+
+        let buf = cryptor
+            .seal_with_key_check(&meta, &old_key, plaintext)
+            .unwrap();
+        let new_buf = cryptor.rekey(&old_key, &new_key, &buf).unwrap();
+        let (new_meta, _) = metadata::Metadata::from_buf(&new_buf).unwrap();
+        let key_check = RingCryptor::_get_key_check(&new_key);
+        assert_eq!(new_meta.key_check, Some(key_check));
+        assert_eq!(new_meta.key_commitment, None);
+        let res = cryptor.open(&old_key, &new_buf);
+        assert_eq!(res, Err(errors::Error::WrongKey));
+        let res = cryptor.open(&new_key, &new_buf);
+        assert_eq!(res, Ok(plaintext.to_vec()));
+
+        // Check that the algorithms can be switched, and that the ciphertext
+        // size is calculated anew.
+        let buf = cryptor.seal_with_key(&old_key, plaintext).unwrap();
+        let mut meta = generate_meta(0, KeyOpts::None, EncOpts::AESSIV);
+        meta.key_id = Some("key-2".to_string());
+        let new_buf = cryptor
+            .rekey_with_meta(&old_key, &meta, &new_key, &buf)
+            .unwrap();
+        let (new_meta, _) = metadata::Metadata::from_buf(&new_buf).unwrap();
+        assert_eq!(new_meta.enc_algo, meta.enc_algo);
+        assert_eq!(new_meta.key_id, meta.key_id);
+        assert_eq!(new_meta.ciphertext_size, plaintext.len() + 16);
+        let res = cryptor.open(&new_key, &new_buf);
+        assert_eq!(res, Ok(plaintext.to_vec()));
+    }
+
+    #[test]
+    fn test_seal_for_recipient() {
+        let plaintext = "The cake is a lie".as_bytes();
+        let private_key = x25519::generate_private_key();
+        let public_key = x25519::public_key(&private_key).unwrap();
+        let dec_err = Err(errors::Error::DecryptionError);
+        let cryptor = RingCryptor::new();
+
+        // Check that the data can be decrypted only with the private key of
+        // the recipient, and that the ephemeral public key is stored in the
+        // header.
+        let buf = cryptor.seal_for_recipient(&public_key, plaintext).unwrap();
+        let (meta, meta_size) = metadata::Metadata::from_buf(&buf).unwrap();
+        let x25519_meta = match meta.key_deriv_algo {
+            metadata::KeyDerivationAlgorithm::X25519(m) => m,
+            _ => panic!("Unexpected key derivation algorithm"),
+        };
+        assert_ne!(x25519_meta.ephemeral_public_key, public_key);
+        let res = cryptor.open(&private_key, &buf);
+        assert_eq!(res, Ok(plaintext.to_vec()));
+        assert_eq!(cryptor.open(&public_key, &buf), dec_err);
+        let other_private_key = x25519::generate_private_key();
+        assert_eq!(cryptor.open(&other_private_key, &buf), dec_err);
+
+        // Check that the ephemeral public key is authenticated.
+        let mut bad_meta = meta.clone();
+        let mut bad_x25519_meta = x25519_meta;
+        bad_x25519_meta.ephemeral_public_key =
+            x25519::public_key(&other_private_key).unwrap();
+        bad_meta.key_deriv_algo =
+            metadata::KeyDerivationAlgorithm::X25519(bad_x25519_meta);
+        let mut bad_buf = bad_meta.to_header();
+        bad_buf.extend_from_slice(&buf[meta_size..]);
+        assert_eq!(cryptor.open(&private_key, &bad_buf), dec_err);
+
+        // Check that invalid public keys are rejected.
+        let res = cryptor.seal_for_recipient(&public_key[1..], plaintext);
+        assert_eq!(res, Err(errors::Error::KeySizeMismatch));
+        let res = cryptor.seal_for_recipient(&[0; 32], plaintext);
+        assert_eq!(res, Err(errors::Error::CryptoParamsWeak));
+
+        // Check that the data can be encrypted again for a new recipient.
+        let new_private_key = x25519::generate_private_key();
+        let new_public_key = x25519::public_key(&new_private_key).unwrap();
+        let new_buf =
+            cryptor.rekey(&private_key, &new_public_key, &buf).unwrap();
+        assert_eq!(cryptor.open(&private_key, &new_buf), dec_err);
+        let res = cryptor.open(&new_private_key, &new_buf);
+        assert_eq!(res, Ok(plaintext.to_vec()));
+    }
+
+    #[test]
+    fn test_seal_signed() {
+        let plaintext = "The cake is a lie".as_bytes();
+        let key = [1u8; 32];
+        let signing_key = ed25519::generate_private_key();
+        let public_key = ed25519::public_key(&signing_key).unwrap();
+        let sig_err = Err(errors::Error::SignatureInvalid);
+        let cryptor = RingCryptor::new();
+        let meta = metadata::Metadata::generate_for_key(plaintext.len());
+
+        // Check that signed data can be verified and decrypted, and that the
+        // public key of the signer is returned.
+        let buf = cryptor
+            .seal_signed(&meta, &key, &signing_key, plaintext)
+            .unwrap();
+        let (signed_meta, meta_size) =
+            metadata::Metadata::from_buf(&buf).unwrap();
+        assert_eq!(signed_meta.signature.unwrap().public_key, public_key);
+        let res = cryptor.open_verified(&key, &buf);
+        assert_eq!(res, Ok((plaintext.to_vec(), public_key)));
+        assert_eq!(cryptor.open(&key, &buf), Ok(plaintext.to_vec()));
+
+        // Check that unsigned data are rejected.
+        let unsigned_buf = cryptor.seal_with_meta(&meta, &key, plaintext);
+        let res = cryptor.open_verified(&key, &unsigned_buf.unwrap());
+        assert_eq!(res, Err(errors::Error::SignatureMissing));
+
+        // Check that the signature covers the ciphertext, before any
+        // decryption takes place.
+        let mut bad_buf = buf.clone();
+        let last = bad_buf.len() - 1;
+        bad_buf[last] ^= 1;
+        assert_eq!(cryptor.open_verified(&[0; 32], &bad_buf), sig_err);
+
+        // Check that the signature covers the metadata header.
+        let mut bad_meta = signed_meta.clone();
+        bad_meta.key_id = Some("key-1".to_string());
+        let mut bad_buf = bad_meta.to_header();
+        bad_buf.extend_from_slice(&buf[meta_size..]);
+        assert_eq!(cryptor.open_verified(&key, &bad_buf), sig_err);
+
+        // Check that a signature cannot be replaced by another signer without
+        // their private key.
+        let mut bad_meta = signed_meta;
+        let other_key = ed25519::generate_private_key();
+        let mut bad_sig = bad_meta.signature.unwrap();
+        bad_sig.public_key = ed25519::public_key(&other_key).unwrap();
+        bad_meta.signature = Some(bad_sig);
+        let mut bad_buf = bad_meta.to_header();
+        bad_buf.extend_from_slice(&buf[meta_size..]);
+        assert_eq!(cryptor.open_verified(&key, &bad_buf), sig_err);
+
+        // Check that the data can be signed again by another signer, and
+        // that data with key slots can be signed too.
+        let new_buf = cryptor.sign(&other_key, &buf).unwrap();
+        let (_, other_public_key) =
+            cryptor.open_verified(&key, &new_buf).unwrap();
+        assert_eq!(other_public_key, ed25519::public_key(&other_key).unwrap());
+        let passes: &[&[u8]] = &[b"pass1", b"pass2"];
+        let buf = cryptor.seal_with_passphrases(passes, plaintext).unwrap();
+        let buf = cryptor.sign(&signing_key, &buf).unwrap();
+        let res = cryptor.open_verified(b"pass2", &buf);
+        assert_eq!(res, Ok((plaintext.to_vec(), public_key)));
+    }
+
+    #[test]
+    fn test_seal_open_with_master_key() {
+        let plaintext = "The cake is a lie".as_bytes();
+        let master_key = [1u8; 32];
+        let dec_err = Err(errors::Error::DecryptionError);
+        let key_err = Err(errors::Error::UnknownKey);
+        let cryptor = RingCryptor::new();
+
+        // Check that each message is encrypted with a unique key, which is
+        // derived from the master key and the context string in the header.
+        let buf1 = cryptor
+            .seal_with_master_key(&master_key, b"billing", plaintext)
+            .unwrap();
+        let buf2 = cryptor
+            .seal_with_master_key(&master_key, b"billing", plaintext)
+            .unwrap();
+        let (meta1, meta_size) = metadata::Metadata::from_buf(&buf1).unwrap();
+        let (meta2, _) = metadata::Metadata::from_buf(&buf2).unwrap();
+        let hkdf_meta = match &meta1.key_deriv_algo {
+            metadata::KeyDerivationAlgorithm::HKDF(m) => m.clone(),
+            _ => panic!("Unexpected key derivation algorithm"),
+        };
+        assert_eq!(hkdf_meta.info, b"billing");
+        assert_ne!(meta1.key_deriv_algo, meta2.key_deriv_algo);
+        let mut key1 = [0u8; 32];
+        let mut key2 = [0u8; 32];
+        cryptor
+            ._derive_key(&meta1.key_deriv_algo, &master_key, &mut key1)
+            .unwrap();
+        cryptor
+            ._derive_key(&meta2.key_deriv_algo, &master_key, &mut key2)
+            .unwrap();
+        assert_ne!(key1, key2);
+        assert_ne!(key1, master_key);
+
+        // Check that the data can be decrypted with the master key, and only
+        // for the same context string.
+        let res = cryptor.open_with_master_key(&master_key, b"billing", &buf1);
+        assert_eq!(res, Ok(plaintext.to_vec()));
+        assert_eq!(cryptor.open(&master_key, &buf1), Ok(plaintext.to_vec()));
+        let res = cryptor.open_with_master_key(&master_key, b"audit", &buf1);
+        assert_eq!(res, key_err);
+        let res = cryptor.open_with_master_key(&[2; 32], b"billing", &buf1);
+        assert_eq!(res, dec_err);
+        let buf = cryptor.seal_with_key(&master_key, plaintext).unwrap();
+        let res = cryptor.open_with_master_key(&master_key, b"billing", &buf);
+        assert_eq!(res, key_err);
+
+        // Check that the context string is authenticated.
+        let mut bad_meta = meta1.clone();
+        let mut bad_hkdf_meta = hkdf_meta;
+        bad_hkdf_meta.info = b"audit".to_vec();
+        bad_meta.key_deriv_algo =
+            metadata::KeyDerivationAlgorithm::HKDF(bad_hkdf_meta);
+        let mut bad_buf = bad_meta.to_header();
+        bad_buf.extend_from_slice(&buf1[meta_size..]);
+        let res =
+            cryptor.open_with_master_key(&master_key, b"audit", &bad_buf);
+        assert_eq!(res, dec_err);
+
+        // Check that short master keys are rejected.
+        let res =
+            cryptor.seal_with_master_key(&[1; 16], b"billing", plaintext);
+        assert_eq!(res, Err(errors::Error::KeySizeMismatch));
+    }
+
+    #[test]
+    fn test_seal_committing() {
+        let plaintext = "The cake is a lie".as_bytes();
+        let key = [1u8; 32];
+        let commit_err = Err(errors::Error::KeyCommitmentMismatch);
+        let cryptor = RingCryptor::new();
+
+        // Check that the key commitment is stored in the header, and that
+        // only the committed key can decrypt the data.
+        let meta = generate_meta(plaintext.len(), KeyOpts::None, EncOpts::AES);
+        let buf = cryptor.seal_committing(&meta, &key, plaintext).unwrap();
+        let (new_meta, meta_size) =
+            metadata::Metadata::from_buf(&buf).unwrap();
+        let key_commitment = RingCryptor::_get_key_commitment(&key);
+        assert_eq!(new_meta.key_commitment, Some(key_commitment));
+        assert_eq!(cryptor.open(&key, &buf), Ok(plaintext.to_vec()));
+        assert_eq!(cryptor.open(&[2; 32], &buf), commit_err);
+
+        // Check that the key commitment is checked before the decryption, so
+        // that a key that does not match it is rejected, even if the
+        // ciphertext is valid for this key.
+        let mut bad_meta = new_meta.clone();
+        bad_meta.key_commitment =
+            Some(RingCryptor::_get_key_commitment(&[2; 32]));
+        let mut bad_buf = bad_meta.to_header();
+        bad_buf.extend_from_slice(&buf[meta_size..]);
+        assert_eq!(cryptor.open(&key, &bad_buf), commit_err);
+        let mut bad_buf = buf.clone();
+        let last = bad_buf.len() - 1;
+        bad_buf[last] ^= 1;
+        let res = cryptor.open(&key, &bad_buf);
+        assert_eq!(res, Err(errors::Error::CiphertextCorrupted));
+
+        // Check that the committing mode works with key derivation, streams
+        // and key slots.
+        let pass = "password".as_bytes();
+        let meta =
+            generate_meta(plaintext.len(), KeyOpts::PBKDF2, EncOpts::ChaCha);
+        let buf = cryptor.seal_committing(&meta, pass, plaintext).unwrap();
+        assert_eq!(cryptor.open(pass, &buf), Ok(plaintext.to_vec()));
+        assert_eq!(cryptor.open(b"pass", &buf), commit_err);
+
+        let meta = metadata::Metadata::new_stream(
+            metadata::KeyDerivationAlgorithm::None,
+            metadata::EncryptionAlgorithm::AES256GCM(
+                metadata::EncryptionMetadata::generate(),
+            ),
+            4,
+        );
+        let buf = cryptor.seal_committing(&meta, &key, plaintext).unwrap();
+        assert_eq!(cryptor.open(&key, &buf), Ok(plaintext.to_vec()));
+        assert_eq!(cryptor.open(&[2; 32], &buf), commit_err);
+
+        // NOTE: The key slots authenticate the nonce of the data, so we must
+        // reuse the metadata as is. This is safe only because the plaintext
+        // is the same.
+        let passes: &[&[u8]] = &[b"pass1", b"pass2"];
+        let buf = cryptor.seal_with_passphrases(passes, plaintext).unwrap();
+        let (meta, _) = metadata::Metadata::from_buf(&buf).unwrap();
+        let buf = cryptor.seal_committing(&meta, b"pass2", plaintext).unwrap();
+        let (meta, _) = metadata::Metadata::from_buf(&buf).unwrap();
+        assert!(meta.key_commitment.is_some());
+        assert_eq!(cryptor.open(b"pass1", &buf), Ok(plaintext.to_vec()));
+        let buf = cryptor.add_key_slot(b"pass1", b"pass3", &buf).unwrap();
+        assert_eq!(cryptor.open(b"pass3", &buf), Ok(plaintext.to_vec()));
+
+        // Check that a policy can require a key commitment, so that it cannot
+        // be stripped from the metadata.
+        let mut policy = policy::DecryptionPolicy::new();
+        policy.require_key_commitment = true;
+        let strict_cryptor = RingCryptor::new().with_policy(&policy);
+        let policy_err = Err(errors::Error::PolicyViolation);
+        let meta =
+            metadata::Metadata::generate_for_passphrase(plaintext.len());
+        let buf = strict_cryptor
+            .seal_committing(&meta, b"pass", plaintext)
+            .unwrap();
+        assert_eq!(strict_cryptor.open(b"pass", &buf), Ok(plaintext.to_vec()));
+        let (mut meta, meta_size) =
+            metadata::Metadata::from_buf(&buf).unwrap();
+        meta.key_commitment = None;
+        let mut stripped_buf = meta.to_header();
+        stripped_buf.extend_from_slice(&buf[meta_size..]);
+        assert_eq!(strict_cryptor.open(b"pass", &stripped_buf), policy_err);
+        assert_eq!(
+            strict_cryptor.seal_with_meta(&meta, b"pass", plaintext),
+            policy_err
+        );
+        assert_eq!(
+            strict_cryptor.seal_with_passphrases(passes, plaintext),
+            policy_err
+        );
+    }
+
+    #[test]
+    fn test_seal_with_key_check() {
+        let plaintext = "The cake is a lie".as_bytes();
+        let pass = "password".as_bytes();
+        let wrong_key_err = Err(errors::Error::WrongKey);
+        let corrupted_err = Err(errors::Error::CiphertextCorrupted);
+        let cryptor = RingCryptor::new();
+
+        // Check that the key check value is stored in the header, and that a
+        // wrong passphrase can be told apart from a corrupted ciphertext.
+        let meta =
+            generate_meta(plaintext.len(), KeyOpts::PBKDF2, EncOpts::AES);
+        let buf = cryptor.seal_with_key_check(&meta, pass, plaintext).unwrap();
+        let (new_meta, _) = metadata::Metadata::from_buf(&buf).unwrap();
+        assert!(new_meta.key_check.is_some());
+        assert_eq!(new_meta.key_commitment, None);
+        assert_eq!(cryptor.open(pass, &buf), Ok(plaintext.to_vec()));
+        assert_eq!(cryptor.open(b"pass", &buf), wrong_key_err);
+        let mut bad_buf = buf.clone();
+        let last = bad_buf.len() - 1;
+        bad_buf[last] ^= 1;
+        assert_eq!(cryptor.open(pass, &bad_buf), corrupted_err);
+
+        // Check that data without a key check value still return the generic
+        // decryption error.
+        let buf = cryptor.seal_with_meta(&meta, pass, plaintext).unwrap();
+        let res = cryptor.open(b"pass", &buf);
+        assert_eq!(res, Err(errors::Error::DecryptionError));
+
+        // Check that the key check value works with streams.
+        let meta = metadata::Metadata::new_stream(
+            metadata::KeyDerivationAlgorithm::None,
+            metadata::EncryptionAlgorithm::AES256GCM(
+                metadata::EncryptionMetadata::generate(),
+            ),
+            4,
+        );
+        let key = [1u8; 32];
+        let buf = cryptor.seal_with_key_check(&meta, &key, plaintext).unwrap();
+        assert_eq!(cryptor.open(&key, &buf), Ok(plaintext.to_vec()));
+        assert_eq!(cryptor.open(&[2; 32], &buf), wrong_key_err);
+        let mut bad_buf = buf.clone();
+        let last = bad_buf.len() - 1;
+        bad_buf[last] ^= 1;
+        assert_eq!(cryptor.open(&key, &bad_buf), corrupted_err);
+
+        // Check that the key check value works with key slots, and that it's
+        // kept when a key slot is added.
+        let passes: &[&[u8]] = &[b"pass1", b"pass2"];
+        let buf = cryptor.seal_with_passphrases(passes, plaintext).unwrap();
+        let (meta, _) = metadata::Metadata::from_buf(&buf).unwrap();
+        let buf = cryptor
+            .seal_with_key_check(&meta, b"pass1", plaintext)
+            .unwrap();
+        assert_eq!(cryptor.open(b"pass2", &buf), Ok(plaintext.to_vec()));
+        assert_eq!(cryptor.open(b"pass3", &buf), wrong_key_err);
+        let buf = cryptor.add_key_slot(b"pass1", b"pass3", &buf).unwrap();
+        assert_eq!(cryptor.open(b"pass3", &buf), Ok(plaintext.to_vec()));
+        assert_eq!(cryptor.open(b"pass4", &buf), wrong_key_err);
+    }
+
+    #[test]
+    fn test_builder() {
+        let plaintext = "The cake is a lie".as_bytes();
+        let pass = "password".as_bytes();
+        let key = [1u8; 32];
+
+        // Check that the default cryptor generates the same algorithms as the
+        // default metadata.
+        let cryptor = RingCryptor::builder().build().unwrap();
+        assert_eq!(cryptor, RingCryptor::new());
+        let buf = cryptor.seal_with_passphrase(pass, plaintext).unwrap();
+        let (meta, _) = metadata::Metadata::from_buf(&buf).unwrap();
+        match meta.key_deriv_algo {
+            metadata::KeyDerivationAlgorithm::PBKDF2(meta) => {
+                assert_eq!(meta.hash_fn, metadata::PBKDF2_DEFAULT_HASH_FN);
+                assert_eq!(
+                    meta.iterations,
+                    metadata::PBKDF2_DEFAULT_ITERATIONS
+                );
+            }
+            _ => panic!("Unexpected key derivation algorithm"),
+        }
+        match meta.enc_algo {
+            metadata::EncryptionAlgorithm::AES256GCM(_) => (),
+            _ => panic!("Unexpected encryption algorithm"),
+        }
+
+        // Check that the configured algorithms and parameters are used by the
+        // `.seal_with_key()` and `.seal_with_passphrase()` methods.
+        let cryptor = RingCryptor::builder()
+            .enc_algo(policy::EncryptionKind::XChaCha20Poly1305)
+            .hash_fn(metadata::HashFunction::SHA384)
+            .iterations(10)
+            .aad(b"aad")
+            .build()
+            .unwrap();
+        let buf = cryptor.seal_with_passphrase(pass, plaintext).unwrap();
+        let (meta, _) = metadata::Metadata::from_buf(&buf).unwrap();
+        match meta.key_deriv_algo {
+            metadata::KeyDerivationAlgorithm::PBKDF2(meta) => {
+                assert_eq!(meta.hash_fn, metadata::HashFunction::SHA384);
+                assert_eq!(meta.iterations, 10);
+            }
+            _ => panic!("Unexpected key derivation algorithm"),
+        }
+        match meta.enc_algo {
+            metadata::EncryptionAlgorithm::XChaCha20Poly1305(_) => (),
+            _ => panic!("Unexpected encryption algorithm"),
+        }
+        assert_eq!(cryptor.open(pass, &buf), Ok(plaintext.to_vec()));
+        let res = RingCryptor::new().open(pass, &buf);
+        assert_eq!(res, Err(errors::Error::DecryptionError));
+
+        let buf = cryptor.seal_with_key(&key, plaintext).unwrap();
+        let (meta, _) = metadata::Metadata::from_buf(&buf).unwrap();
+        assert_eq!(
+            meta.key_deriv_algo,
+            metadata::KeyDerivationAlgorithm::None
+        );
+        match meta.enc_algo {
+            metadata::EncryptionAlgorithm::XChaCha20Poly1305(_) => (),
+            _ => panic!("Unexpected encryption algorithm"),
+        }
+        assert_eq!(cryptor.open(&key, &buf), Ok(plaintext.to_vec()));
+
+        // Check that the key derivation algorithm applies to key slots too.
+        let cryptor = RingCryptor::builder()
+            .key_deriv_algo(policy::KeyDerivationKind::Scrypt)
+            .build()
+            .unwrap();
+        let algo = cryptor.generate_key_deriv_algo();
+        assert_eq!(
+            policy::KeyDerivationKind::of(&algo),
+            policy::KeyDerivationKind::Scrypt
+        );
+
+        // Check that the salt size applies to every key derivation algorithm
+        // for passphrases.
+        for &kind in &[
+            policy::KeyDerivationKind::PBKDF2,
+            policy::KeyDerivationKind::Argon2id,
+            policy::KeyDerivationKind::Scrypt,
+        ] {
+            let cryptor = RingCryptor::builder()
+                .key_deriv_algo(kind)
+                .salt_size(metadata::MIN_SALT_SIZE)
+                .build()
+                .unwrap();
+            let salt_size = match cryptor.generate_key_deriv_algo() {
+                metadata::KeyDerivationAlgorithm::PBKDF2(meta) => {
+                    meta.salt.len()
+                }
+                metadata::KeyDerivationAlgorithm::Argon2id(meta) => {
+                    meta.salt.len()
+                }
+                metadata::KeyDerivationAlgorithm::Scrypt(meta) => {
+                    meta.salt.len()
+                }
+                _ => panic!("Unexpected key derivation algorithm"),
+            };
+            assert_eq!(salt_size, metadata::MIN_SALT_SIZE);
+        }
+        let cryptor = RingCryptor::builder()
+            .iterations(10)
+            .salt_size(metadata::MAX_SALT_SIZE)
+            .build()
+            .unwrap();
+        let buf = cryptor.seal_with_passphrase(pass, plaintext).unwrap();
+        let (meta, _) = metadata::Metadata::from_buf(&buf).unwrap();
+        match meta.key_deriv_algo {
+            metadata::KeyDerivationAlgorithm::PBKDF2(meta) => {
+                assert_eq!(meta.salt.len(), metadata::MAX_SALT_SIZE);
+            }
+            _ => panic!("Unexpected key derivation algorithm"),
+        }
+        assert_eq!(cryptor.open(pass, &buf), Ok(plaintext.to_vec()));
+
+        // Check that invalid parameters are rejected.
+        let res = RingCryptor::builder()
+            .key_deriv_algo(policy::KeyDerivationKind::HKDF)
+            .build();
+        assert_eq!(res, Err(errors::Error::CryptoParamsUnsupported));
+        let res = RingCryptor::builder().iterations(0).build();
+        assert_eq!(res, Err(errors::Error::CryptoParamsWeak));
+        if usize::MAX > u32::MAX as usize {
+            let res = RingCryptor::builder().iterations(usize::MAX).build();
+            assert_eq!(res, Err(errors::Error::CryptoParamsUnsupported));
+        }
+        let min_salt_size = metadata::MIN_SALT_SIZE;
+        let res = RingCryptor::builder().salt_size(min_salt_size - 1).build();
+        assert_eq!(res, Err(errors::Error::CryptoParamsWeak));
+        let max_salt_size = metadata::MAX_SALT_SIZE;
+        let res = RingCryptor::builder().salt_size(max_salt_size + 1).build();
+        assert_eq!(res, Err(errors::Error::CryptoParamsUnsupported));
+    }
+
+    #[test]
+    fn test_open_with_policy() {
+        let plaintext = "The cake is a lie".as_bytes();
+        let pass = "password".as_bytes();
+        let policy_err = Err(errors::Error::PolicyViolation);
+        let mut policy = policy::DecryptionPolicy::new();
+        let cryptor = RingCryptor::new();
+
+        // Check that data within the policy can be decrypted.
+        let meta =
+            generate_meta(plaintext.len(), KeyOpts::PBKDF2, EncOpts::AES);
+        let buf = cryptor.seal_with_meta(&meta, pass, plaintext).unwrap();
+        let res = RingCryptor::new().with_policy(&policy).open(pass, &buf);
+        assert_eq!(res, Ok(plaintext.to_vec()));
+
+        // Check that a header with a huge number of iterations is rejected
+        // before the key derivation takes place, else this test would never
+        // finish.
+        let (mut bad_meta, meta_size) =
+            metadata::Metadata::from_buf(&buf).unwrap();
+        match &mut bad_meta.key_deriv_algo {
+            metadata::KeyDerivationAlgorithm::PBKDF2(meta) => {
+                meta.iterations = u32::MAX as usize;
+            }
+            _ => panic!("Unexpected key derivation algorithm"),
+        }
+        let mut bad_buf = bad_meta.to_header();
+        bad_buf.extend_from_slice(&buf[meta_size..]);
+        let policy_cryptor = RingCryptor::new().with_policy(&policy);
+        assert_eq!(policy_cryptor.open(pass, &bad_buf), policy_err);
+
+        // Check that the policy is kept along with the AAD, and that it
+        // applies to encryption as well.
+        policy.min_iterations = 2;
+        let policy_cryptor =
+            RingCryptor::new().with_policy(&policy).with_aad(b"aad");
+        assert_eq!(policy_cryptor.open(pass, &buf), policy_err);
+        let res = policy_cryptor.seal_with_meta(&meta, pass, plaintext);
+        assert_eq!(res, policy_err);
+
+        // Check that the algorithms of the key slots are checked as well.
+        let passes: &[&[u8]] = &[b"pass1", b"pass2"];
+        let buf = cryptor.seal_with_passphrases(passes, plaintext).unwrap();
+        let mut policy = policy::DecryptionPolicy::new();
+        let policy_cryptor = RingCryptor::new().with_policy(&policy);
+        let res = policy_cryptor.open(b"pass1", &buf);
+        assert_eq!(res, Ok(plaintext.to_vec()));
+        policy.allowed_key_deriv_algos = vec![policy::KeyDerivationKind::None];
+        let policy_cryptor = RingCryptor::new().with_policy(&policy);
+        assert_eq!(policy_cryptor.open(b"pass1", &buf), policy_err);
+        let res = policy_cryptor.add_key_slot(b"pass1", b"pass3", &buf);
+        assert_eq!(res, policy_err);
+
+        // Check that streams are checked as well.
+        let meta = metadata::Metadata::new_stream(
+            metadata::KeyDerivationAlgorithm::None,
+            metadata::EncryptionAlgorithm::ChaCha20Poly1305(
+                metadata::EncryptionMetadata::generate(),
+            ),
+            4,
+        );
+        let key = [1u8; 32];
+        let buf = cryptor.seal_with_meta(&meta, &key, plaintext).unwrap();
+        let mut policy = policy::DecryptionPolicy::new();
+        policy.allowed_enc_algos = vec![policy::EncryptionKind::AES256GCM];
+        let policy_cryptor = RingCryptor::new().with_policy(&policy);
+        assert_eq!(policy_cryptor.open(&key, &buf), policy_err);
+
+        // Check that the key slots of a key provider are checked before they
+        // are unwrapped, else this test would never finish.
+        let mut keyring = keys::Keyring::new();
+        keyring.add_key("kek", &key);
+        let buf = cryptor.seal_with_provider(&keyring, "kek", plaintext);
+        let buf = buf.unwrap();
+        let (mut bad_meta, meta_size) =
+            metadata::Metadata::from_buf(&buf).unwrap();
+        let mut key_deriv_meta = metadata::KeyDerivationMetadata::generate();
+        key_deriv_meta.iterations = u32::MAX as usize;
+        bad_meta.key_slots[0].key_deriv_algo =
+            metadata::KeyDerivationAlgorithm::PBKDF2(key_deriv_meta);
+        let mut bad_buf = bad_meta.to_header();
+        bad_buf.extend_from_slice(&buf[meta_size..]);
+        let policy = policy::DecryptionPolicy::new();
+        let policy_cryptor = RingCryptor::new().with_policy(&policy);
+        let res = policy_cryptor.open_with_provider(&keyring, &buf);
+        assert_eq!(res, Ok(plaintext.to_vec()));
+        let res = policy_cryptor.open_with_provider(&keyring, &bad_buf);
+        assert_eq!(res, policy_err);
+    }
+
+    #[test]
+    fn test_key_cache() {
+        let plaintexts: [&[u8]; 3] = [b"The cake", b"is a", b"lie"];
+        let pass = "password".as_bytes();
+        let dec_err = Err(errors::Error::DecryptionError);
+        let key_cache = cache::DerivedKeyCache::default();
+        let cryptor = RingCryptor::builder()
+            .iterations(1)
+            .key_cache(&key_cache)
+            .build()
+            .unwrap();
+
+        // Check that a batch shares the key derivation metadata, but not the
+        // nonces, and that the key is cached.
+        let bufs = cryptor.seal_batch_with_passphrase(pass, &plaintexts);
+        let bufs = bufs.unwrap();
+        assert_eq!(bufs.len(), plaintexts.len());
+        assert_eq!(key_cache.len(), 1);
+        let (meta1, _) = metadata::Metadata::from_buf(&bufs[0]).unwrap();
+        let (meta2, _) = metadata::Metadata::from_buf(&bufs[1]).unwrap();
+        assert_eq!(meta1.key_deriv_algo, meta2.key_deriv_algo);
+        assert_ne!(meta1.enc_algo, meta2.enc_algo);
+
+        // Check that the batch can be decrypted with and without the cache,
+        // and that the cached key is not used for a different passphrase.
+        for (buf, plaintext) in bufs.iter().zip(&plaintexts) {
+            assert_eq!(cryptor.open(pass, buf), Ok(plaintext.to_vec()));
+            let res = RingCryptor::new().open(pass, buf);
+            assert_eq!(res, Ok(plaintext.to_vec()));
+            assert_eq!(cryptor.open(b"pass", buf), dec_err);
+        }
+        assert_eq!(key_cache.len(), 2);
+
+        // Check that the cryptor uses the cached key, by planting a different
+        // key in the cache.
+        key_cache.clear();
+        key_cache._insert(&meta1.key_deriv_algo, pass, &[1; 32]);
+        assert_eq!(cryptor.open(pass, &bufs[0]), dec_err);
+        key_cache.clear();
+        assert_eq!(cryptor.open(pass, &bufs[0]), Ok(plaintexts[0].to_vec()));
+
+        // Check that keys that are not derived from passphrases are not
+        // cached, and that an empty batch is allowed.
+        key_cache.clear();
+        let key = [1u8; 32];
+        let buf = cryptor.seal_with_key(&key, plaintexts[0]).unwrap();
+        assert_eq!(cryptor.open(&key, &buf), Ok(plaintexts[0].to_vec()));
+        assert!(key_cache.is_empty());
+        let res = cryptor.seal_batch_with_passphrase(pass, &[]);
+        assert_eq!(res, Ok(Vec::new()));
+    }
+
+    #[test]
+    fn test_prepared_key() {
+        let plaintext = "The cake is a lie".as_bytes();
+        let aad = "My encryption context".as_bytes();
+        let key = [1u8; 32];
+        let unsupported_err = Err(errors::Error::CryptoParamsUnsupported);
+        let cryptor = RingCryptor::new().with_aad(aad);
+        let kinds = [
+            policy::EncryptionKind::AES256GCM,
+            policy::EncryptionKind::ChaCha20Poly1305,
+            policy::EncryptionKind::XChaCha20Poly1305,
+            policy::EncryptionKind::AES256GCMSIV,
+        ];
+
+        // Check that the key size is checked.
+        let res = keys::PreparedKey::new(kinds[0], &key[..16]);
+        assert_eq!(res.unwrap_err(), errors::Error::KeySizeMismatch);
+
+        for &kind in &kinds {
+            let prepared_key = keys::PreparedKey::new(kind, &key).unwrap();
+            let wrong_key = keys::PreparedKey::new(kind, &[2; 32]).unwrap();
+            assert_eq!(prepared_key.enc_algo(), kind);
+
+            // Check that the data are interoperable with the raw key, and
+            // that the nonces are unique per message.
+            let buf1 =
+                cryptor.seal_with_prepared_key(&prepared_key, plaintext);
+            let buf1 = buf1.unwrap();
+            let buf2 =
+                cryptor.seal_with_prepared_key(&prepared_key, plaintext);
+            assert_ne!(buf1, buf2.unwrap());
+            let (meta, _) = metadata::Metadata::from_buf(&buf1).unwrap();
+            assert_eq!(policy::EncryptionKind::of(&meta.enc_algo), kind);
+            assert_eq!(cryptor.open(&key, &buf1), Ok(plaintext.to_vec()));
+            let meta = metadata::Metadata::new(
+                metadata::KeyDerivationAlgorithm::None,
+                RingCryptor::_generate_enc_algo(kind),
+                plaintext.len(),
+            );
+            let buf = cryptor.seal_with_meta(&meta, &key, plaintext).unwrap();
+            let res = cryptor.open_with_prepared_key(&prepared_key, &buf);
+            assert_eq!(res, Ok(plaintext.to_vec()));
+
+            // Check that a wrong key, a different AAD and corrupted data are
+            // detected.
+            let res = cryptor.open_with_prepared_key(&wrong_key, &buf);
+            assert_eq!(res, Err(errors::Error::DecryptionError));
+            let res =
+                RingCryptor::new().open_with_prepared_key(&prepared_key, &buf);
+            assert_eq!(res, Err(errors::Error::DecryptionError));
+            let mut bad_buf = buf.clone();
+            let last = bad_buf.len() - 1;
+            bad_buf[last] ^= 1;
+            let res = cryptor.open_with_prepared_key(&prepared_key, &bad_buf);
+            assert_eq!(res, Err(errors::Error::DecryptionError));
+        }
+
+        // Check that the values that verify the key are checked.
+        let prepared_key = keys::PreparedKey::new(kinds[0], &key).unwrap();
+        let wrong_key = keys::PreparedKey::new(kinds[0], &[2; 32]).unwrap();
+        let meta = generate_meta(plaintext.len(), KeyOpts::None, EncOpts::AES);
+        let buf = cryptor.seal_with_key_check(&meta, &key, plaintext).unwrap();
+        let res = cryptor.open_with_prepared_key(&prepared_key, &buf);
+        assert_eq!(res, Ok(plaintext.to_vec()));
+        let res = cryptor.open_with_prepared_key(&wrong_key, &buf);
+        assert_eq!(res, Err(errors::Error::WrongKey));
+        let mut bad_buf = buf.clone();
+        let last = bad_buf.len() - 1;
+        bad_buf[last] ^= 1;
+        let res = cryptor.open_with_prepared_key(&prepared_key, &bad_buf);
+        assert_eq!(res, Err(errors::Error::CiphertextCorrupted));
+        let buf = cryptor.seal_committing(&meta, &key, plaintext).unwrap();
+        let res = cryptor.open_with_prepared_key(&wrong_key, &buf);
+        assert_eq!(res, Err(errors::Error::KeyCommitmentMismatch));
+
+        // Check that a different algorithm, key derivation and streams are
+        // not supported.
+        let meta =
+            generate_meta(plaintext.len(), KeyOpts::None, EncOpts::ChaCha);
+        let buf = cryptor.seal_with_meta(&meta, &key, plaintext).unwrap();
+        let res = cryptor.open_with_prepared_key(&prepared_key, &buf);
+        assert_eq!(res, unsupported_err);
+        let meta =
+            generate_meta(plaintext.len(), KeyOpts::PBKDF2, EncOpts::AES);
+        let buf = cryptor.seal_with_meta(&meta, &key, plaintext).unwrap();
+        let res = cryptor.open_with_prepared_key(&prepared_key, &buf);
+        assert_eq!(res, unsupported_err);
+        let mut meta =
+            generate_meta(plaintext.len(), KeyOpts::None, EncOpts::AES);
+        meta.chunk_size = Some(4);
+        let mut buf = vec![0u8; 64];
+        let res = cryptor.seal_in_place_with_prepared_key(
+            &meta,
+            &prepared_key,
+            &mut buf,
+        );
+        assert_eq!(res, Err(errors::Error::CryptoParamsUnsupported));
+    }
 }
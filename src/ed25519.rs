@@ -0,0 +1,139 @@
+//! # Ed25519 helpers
+//!
+//! This module contains helpers for signing data with an Ed25519 key, and
+//! verifying their signatures. Tindercrypt uses them to sign the encrypted
+//! data, so that the recipients can verify who has encrypted them. This is
+//! useful when the secret of the encryption is shared by a group of users,
+//! since any of them can encrypt data that the rest can decrypt.
+//!
+//! The private keys of this module are the 32-byte seeds of [RFC 8032], and
+//! the signing/verification is performed by the `ring::signature` module. You
+//! are advised to not use these low-level functions directly, and instead use
+//! the functions provided by the [`cryptors`] module.
+//!
+//! ## Examples
+//!
+//! ```
+//! use tindercrypt::ed25519;
+//!
+//! // The signer creates a key pair, and shares the public key.
+//! let private_key = ed25519::generate_private_key();
+//! let public_key = ed25519::public_key(&private_key)?;
+//!
+//! // The signer signs a message, and anyone with the public key can verify
+//! // the signature.
+//! let signature = ed25519::sign(&private_key, b"The cake is a lie")?;
+//! ed25519::verify(&public_key, b"The cake is a lie", &signature)?;
+//! assert!(ed25519::verify(&public_key, b"The cake", &signature).is_err());
+//!
+//! # use tindercrypt::errors;
+//! # Ok::<(), errors::Error>(())
+//! ```
+//!
+//! [RFC 8032]: https://tools.ietf.org/html/rfc8032
+//! [`cryptors`]: ../cryptors/index.html
+
+use crate::errors;
+use crate::metadata;
+use crate::rand;
+use ring::signature::{self, KeyPair};
+
+/// The size of the Ed25519 public and private keys.
+pub const KEY_SIZE: usize = metadata::ED25519_KEY_SIZE;
+
+/// The size of the Ed25519 signatures.
+pub const SIGNATURE_SIZE: usize = metadata::ED25519_SIGNATURE_SIZE;
+
+/// Create an Ed25519 key pair from a private key.
+fn _key_pair(
+    private_key: &[u8],
+) -> Result<signature::Ed25519KeyPair, errors::Error> {
+    if private_key.len() != KEY_SIZE {
+        return Err(errors::Error::KeySizeMismatch);
+    }
+    match signature::Ed25519KeyPair::from_seed_unchecked(private_key) {
+        Ok(key_pair) => Ok(key_pair),
+        Err(_) => Err(errors::Error::KeySizeMismatch),
+    }
+}
+
+/// Generate a random Ed25519 private key.
+pub fn generate_private_key() -> [u8; KEY_SIZE] {
+    let mut private_key = [0u8; KEY_SIZE];
+    rand::fill_buf(&mut private_key);
+    private_key
+}
+
+/// Compute the Ed25519 public key of a private key.
+///
+/// This function returns an error if the private key does not have the
+/// expected size.
+pub fn public_key(
+    private_key: &[u8],
+) -> Result<[u8; KEY_SIZE], errors::Error> {
+    let mut public_key = [0u8; KEY_SIZE];
+    public_key.copy_from_slice(_key_pair(private_key)?.public_key().as_ref());
+    Ok(public_key)
+}
+
+/// Sign a message with an Ed25519 private key.
+///
+/// This function returns an error if the private key does not have the
+/// expected size.
+pub fn sign(
+    private_key: &[u8],
+    message: &[u8],
+) -> Result<[u8; SIGNATURE_SIZE], errors::Error> {
+    let mut signature = [0u8; SIGNATURE_SIZE];
+    signature.copy_from_slice(_key_pair(private_key)?.sign(message).as_ref());
+    Ok(signature)
+}
+
+/// Verify the Ed25519 signature of a message.
+///
+/// This function returns an error if the signature is not valid for the
+/// message and the public key.
+pub fn verify(
+    public_key: &[u8],
+    message: &[u8],
+    signature: &[u8],
+) -> Result<(), errors::Error> {
+    let public_key =
+        signature::UnparsedPublicKey::new(&signature::ED25519, public_key);
+    match public_key.verify(message, signature) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(errors::Error::SignatureInvalid),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ed25519_sign_verify() {
+        let sig_err = Err(errors::Error::SignatureInvalid);
+        let message = b"message";
+        let private_key = generate_private_key();
+        let pub_key = public_key(&private_key).unwrap();
+
+        // Check that Ed25519 signatures are deterministic, and that they can
+        // be verified with the public key.
+        let signature = sign(&private_key, message).unwrap();
+        assert_eq!(sign(&private_key, message), Ok(signature));
+        assert_eq!(verify(&pub_key, message, &signature), Ok(()));
+
+        // Check that a signature for a different message, or from a different
+        // private key, is rejected.
+        assert_eq!(verify(&pub_key, b"messagf", &signature), sig_err);
+        let signature2 = sign(&[1; 32], message).unwrap();
+        assert_eq!(verify(&pub_key, message, &signature2), sig_err);
+        assert_eq!(verify(&pub_key, message, &signature[1..]), sig_err);
+        assert_eq!(verify(&pub_key[1..], message, &signature), sig_err);
+
+        // Check that private keys with wrong sizes are rejected.
+        let size_err = errors::Error::KeySizeMismatch;
+        assert_eq!(public_key(&private_key[1..]), Err(size_err));
+        assert_eq!(sign(&[], message), Err(size_err));
+    }
+}
@@ -1,5 +1,6 @@
 //! # Tindercrypt errors
 
+use std::error;
 use std::fmt;
 
 /// The errors that can be returned by the library.
@@ -13,6 +14,9 @@ pub enum Error {
     KeySizeMismatch,
     /// The provided parameters to a crypto function are weak.
     CryptoParamsWeak,
+    /// The provided parameters to a crypto function are out of the range that
+    /// it supports.
+    CryptoParamsUnsupported,
     /// Could not decrypt the data, e.g., due to a bad key, wrong nonce,
     /// corrupted tag.
     DecryptionError,
@@ -20,6 +24,34 @@ pub enum Error {
     MetadataMissing,
     /// The metadata header of the encrypted buffer contains invalid values.
     MetadataInvalid,
+    /// The encrypted buffer has been created with a format version that this
+    /// library does not support.
+    UnsupportedVersion,
+    /// The provided chunk does not have the expected size for the stream.
+    ChunkSizeMismatch,
+    /// The stream has more chunks than the nonce counter can address.
+    StreamTooLong,
+    /// The requested key slot does not exist.
+    KeySlotNotFound,
+    /// The encrypted data must have at least one key slot for this action.
+    KeySlotsEmpty,
+    /// None of the available keys matches the key identifier of the data.
+    UnknownKey,
+    /// The encrypted data do not have a signature.
+    SignatureMissing,
+    /// The signature of the encrypted data is not valid.
+    SignatureInvalid,
+    /// The key does not match the key commitment of the encrypted data.
+    KeyCommitmentMismatch,
+    /// The provided key or passphrase does not match the key check value of
+    /// the encrypted data.
+    WrongKey,
+    /// The key of the encrypted data is correct, but the ciphertext or its
+    /// metadata are corrupted.
+    CiphertextCorrupted,
+    /// The metadata of the encrypted data are not allowed by the decryption
+    /// policy.
+    PolicyViolation,
 }
 
 impl fmt::Display for Error {
@@ -40,6 +72,10 @@ impl fmt::Display for Error {
                 f,
                 "The provided parameters for the encryption are too weak"
             ),
+            Error::CryptoParamsUnsupported => write!(
+                f,
+                "The provided parameters for the encryption are not supported"
+            ),
             Error::DecryptionError => {
                 write!(f, "Could not decrypt the ciphertext")
             }
@@ -50,6 +86,56 @@ impl fmt::Display for Error {
             Error::MetadataInvalid => {
                 write!(f, "The provided buffer has an invalid metadata header")
             }
+            Error::UnsupportedVersion => write!(
+                f,
+                "The provided buffer has an unsupported format version"
+            ),
+            Error::ChunkSizeMismatch => write!(
+                f,
+                "The provided chunk does not have the expected size for the \
+                 stream"
+            ),
+            Error::StreamTooLong => {
+                write!(
+                    f,
+                    "The stream has exceeded the maximum number of chunks"
+                )
+            }
+            Error::KeySlotNotFound => {
+                write!(f, "The requested key slot does not exist")
+            }
+            Error::KeySlotsEmpty => {
+                write!(f, "The encrypted data must have at least one key slot")
+            }
+            Error::UnknownKey => write!(
+                f,
+                "None of the available keys matches the encrypted data"
+            ),
+            Error::SignatureMissing => {
+                write!(f, "The encrypted data have not been signed")
+            }
+            Error::SignatureInvalid => {
+                write!(f, "The signature of the encrypted data is not valid")
+            }
+            Error::KeyCommitmentMismatch => write!(
+                f,
+                "The key does not match the key commitment of the encrypted \
+                 data"
+            ),
+            Error::WrongKey => {
+                write!(f, "The provided key or passphrase is wrong")
+            }
+            Error::CiphertextCorrupted => write!(
+                f,
+                "The ciphertext is corrupted or has been tampered with"
+            ),
+            Error::PolicyViolation => write!(
+                f,
+                "The metadata of the encrypted data are not allowed by the \
+                 decryption policy"
+            ),
         }
     }
 }
+
+impl error::Error for Error {}
@@ -0,0 +1,68 @@
+//! # Backend-neutral helpers for the metadata header
+//!
+//! The cryptors of this crate use different cryptographic backends, but they
+//! must produce the same wire format. This module holds the parts of the
+//! encryption/decryption logic that do not depend on a backend, i.e., the
+//! data that each cryptor authenticates, and the labels of the MACs that it
+//! stores in the metadata header.
+
+use crate::metadata;
+
+/// The HMAC message for the key commitment, which binds it to this scheme.
+pub(crate) const KEY_COMMITMENT_LABEL: &[u8] = b"tindercrypt key commitment";
+
+/// The HMAC message for the key check value, which binds it to this scheme.
+pub(crate) const KEY_CHECK_LABEL: &[u8] = b"tindercrypt key check";
+
+/// Get the associated data for the encryption/decryption.
+///
+/// If the metadata header must be authenticated, the associated data consist
+/// of the serialized metadata (without their key slots, signature, key
+/// commitment and key check value), followed by the user-provided AAD. Else,
+/// they consist only of the user-provided AAD.
+pub(crate) fn get_aad(meta: &metadata::Metadata, aad: &[u8]) -> Vec<u8> {
+    if !meta.authenticated_header {
+        return aad.to_vec();
+    }
+
+    let mut header = get_bare_header(meta);
+    header.extend_from_slice(aad);
+    header
+}
+
+/// Serialize the metadata into a header, without their key slots, signature,
+/// key commitment and key check value.
+///
+/// The key slots are not authenticated along with the data, so that they can
+/// be added or removed without encrypting the data again. Instead, each key
+/// slot authenticates this header when it wraps the data key, so that it
+/// cannot be moved to other data. Likewise, the signature is not
+/// authenticated, so that the data can be signed after their encryption.
+/// Finally, the key commitment and the key check value are checked directly
+/// against the key, so they don't need to be authenticated, and they can be
+/// computed after the data key has been unwrapped from a key slot.
+pub(crate) fn get_bare_header(meta: &metadata::Metadata) -> Vec<u8> {
+    if meta.key_slots.is_empty()
+        && meta.signature.is_none()
+        && meta.key_commitment.is_none()
+        && meta.key_check.is_none()
+    {
+        return meta.to_header();
+    }
+
+    let mut meta = meta.clone();
+    meta.key_slots.clear();
+    meta.signature = None;
+    meta.key_commitment = None;
+    meta.key_check = None;
+    meta.to_header()
+}
+
+/// Check if the key of the data is verified before their decryption.
+///
+/// If the metadata have a key commitment or a key check value, a key that
+/// passes the check is the correct one, so any decryption error is due to a
+/// corrupted ciphertext.
+pub(crate) fn is_key_verified(meta: &metadata::Metadata) -> bool {
+    meta.key_commitment.is_some() || meta.key_check.is_some()
+}
@@ -0,0 +1,125 @@
+//! # HKDF helpers
+//!
+//! This module contains helpers for the HKDF algorithm.
+
+use crate::errors;
+use ring::hkdf;
+
+/// The output length of HKDF, in a form that `ring` accepts.
+struct KeyLen(usize);
+
+impl hkdf::KeyType for KeyLen {
+    fn len(&self) -> usize {
+        self.0
+    }
+}
+
+/// Cryptographically create a symmetric key from a master key.
+///
+/// Create a symmetric key from a master key, based on various HKDF
+/// parameters; an HMAC function, a salt and a context string (`info`). Keys
+/// that are derived with different salts or context strings are independent
+/// of each other.
+///
+/// Unlike the rest of the key derivation algorithms, HKDF is fast, and does
+/// not make brute-force attacks any harder. Therefore, the master key must be
+/// a uniformly random key, and not a passphrase. For this reason, this method
+/// returns an error if the master key is shorter than the derived key. Also,
+/// it returns an error if there's no salt, or if the user has not provided a
+/// buffer for the key.
+///
+/// ## Examples
+///
+/// A safe method to derive a key with HKDF is the following:
+///
+/// ```
+/// use tindercrypt::hkdf::derive_key;
+/// use tindercrypt::rand::fill_buf;
+/// use ring::hkdf;
+///
+/// let digest_algo = hkdf::HKDF_SHA256;
+/// let mut salt = [0u8; 32];
+/// let info = "My application context".as_bytes();
+/// let mut master_key = [0u8; 32];
+/// let mut key = [0u8; 32];
+///
+/// fill_buf(&mut salt);
+/// fill_buf(&mut master_key);
+/// derive_key(digest_algo, &salt, info, &master_key, &mut key);
+/// ```
+pub fn derive_key(
+    digest_algo: hkdf::Algorithm,
+    salt: &[u8],
+    info: &[u8],
+    secret: &[u8],
+    key: &mut [u8],
+) -> Result<(), errors::Error> {
+    if salt.is_empty() {
+        return Err(errors::Error::CryptoParamsWeak);
+    }
+
+    if key.is_empty() || secret.len() < key.len() {
+        return Err(errors::Error::KeySizeMismatch);
+    }
+
+    let info = [info];
+    let prk = hkdf::Salt::new(digest_algo, salt).extract(secret);
+    let okm = match prk.expand(&info, KeyLen(key.len())) {
+        Ok(okm) => okm,
+        Err(_) => return Err(errors::Error::KeySizeMismatch),
+    };
+    match okm.fill(key) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(errors::Error::KeySizeMismatch),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hkdf_derive_key() {
+        let salt = [9; 10];
+        let secret = [99; 32];
+        let mut key1 = [0u8; 32];
+        let mut key2 = [0u8; 32];
+        let mut key3 = [0u8; 32];
+        let mut key4 = [0u8; 32];
+        let mut res: Result<(), errors::Error>;
+        let params_err = Err(errors::Error::CryptoParamsWeak);
+        let size_err = Err(errors::Error::KeySizeMismatch);
+        let algo = hkdf::HKDF_SHA256;
+
+        // Check that weak parameters and short buffers are reported as
+        // errors.
+        res = derive_key(algo, &[], b"info", &secret, &mut key1);
+        assert_eq!(res, params_err);
+        res = derive_key(algo, &salt, b"info", &secret[1..], &mut key1);
+        assert_eq!(res, size_err);
+        res = derive_key(algo, &salt, b"info", &secret, &mut []);
+        assert_eq!(res, size_err);
+        let mut huge_key = vec![0u8; 255 * 32 + 1];
+        res =
+            derive_key(algo, &salt, b"info", &huge_key.clone(), &mut huge_key);
+        assert_eq!(res, size_err);
+
+        // Check that the derived key is deterministic, and that it changes
+        // if the salt, the context string or the hash function change.
+        res = derive_key(algo, &salt, b"info", &secret, &mut key1);
+        assert_eq!(res, Ok(()));
+        res = derive_key(algo, &salt, b"info", &secret, &mut key2);
+        assert_eq!(res, Ok(()));
+        assert_eq!(key1, key2);
+        res = derive_key(algo, &[8; 10], b"info", &secret, &mut key2);
+        assert_eq!(res, Ok(()));
+        assert_ne!(key1, key2);
+        res = derive_key(algo, &salt, b"infp", &secret, &mut key3);
+        assert_eq!(res, Ok(()));
+        assert_ne!(key1, key3);
+        res =
+            derive_key(hkdf::HKDF_SHA512, &salt, b"info", &secret, &mut key4);
+        assert_eq!(res, Ok(()));
+        assert_ne!(key1, key4);
+    }
+}
@@ -0,0 +1,954 @@
+//! # I/O adapters for encryption/decryption
+//!
+//! This module provides the [`EncryptWriter`] and [`DecryptReader`] structs,
+//! which implement the `std::io::Write` and `std::io::Read` traits
+//! respectively. They can be plugged into any I/O pipeline (e.g.,
+//! `io::copy()`, `BufWriter`, compression encoders, archive builders), and
+//! encrypt/decrypt the data as they flow through, without buffering them all
+//! in memory.
+//!
+//! Under the hood, these adapters use the streaming format of the [`stream`]
+//! module, so the memory they use is bounded by the chunk size of the stream.
+//!
+//! This module also provides the [`rekey`] function, which encrypts a stream
+//! again with a new secret value, without exposing the plaintext to the
+//! caller.
+//!
+//! Since these adapters implement the I/O traits, they return
+//! `std::io::Error`s. If an error has been caused by the encryption process,
+//! its kind is `InvalidData` and it wraps the respective Tindercrypt
+//! [error], which can be retrieved with `io::Error::get_ref()`.
+//!
+//! ## Examples
+//!
+//! ```
+//! use std::io::{self, Read, Write};
+//! use tindercrypt::cryptors::RingCryptor;
+//! use tindercrypt::io::{DecryptReader, EncryptWriter};
+//!
+//! let plaintext = "The cake is a lie".as_bytes();
+//! let pass = "My secret passphrase".as_bytes();
+//! let cryptor = RingCryptor::new();
+//!
+//! // Encrypt the plaintext and write it to a buffer.
+//! let mut writer = EncryptWriter::with_passphrase(&cryptor, pass, Vec::new())?;
+//! # // Lower the number of iterations for the tests.
+//! # let mut writer = {
+//! #     let mut key_meta = tindercrypt::metadata::KeyDerivationMetadata::generate();
+//! #     key_meta.iterations = 1;
+//! #     let key_algo = tindercrypt::metadata::KeyDerivationAlgorithm::PBKDF2(key_meta);
+//! #     let enc_meta = tindercrypt::metadata::EncryptionMetadata::generate();
+//! #     let enc_algo = tindercrypt::metadata::EncryptionAlgorithm::AES256GCM(enc_meta);
+//! #     let meta = tindercrypt::metadata::Metadata::new_stream(key_algo, enc_algo, 4);
+//! #     EncryptWriter::new(&cryptor, &meta, pass, Vec::new())?
+//! # };
+//! io::copy(&mut &plaintext[..], &mut writer)?;
+//! let ciphertext = writer.finish()?;
+//!
+//! // Decrypt the ciphertext from the buffer.
+//! let mut reader = DecryptReader::new(&cryptor, pass, &ciphertext[..])?;
+//! let mut plaintext2 = Vec::new();
+//! reader.read_to_end(&mut plaintext2)?;
+//! assert_eq!(plaintext2, plaintext);
+//!
+//! // The ciphertext can also be decrypted in one go.
+//! assert_eq!(cryptor.open(pass, &ciphertext).unwrap(), plaintext);
+//!
+//! # Ok::<(), io::Error>(())
+//! ```
+//!
+//! [`EncryptWriter`]: struct.EncryptWriter.html
+//! [`DecryptReader`]: struct.DecryptReader.html
+//! [`rekey`]: fn.rekey.html
+//! [`stream`]: ../stream/index.html
+//! [error]: ../errors/enum.Error.html
+
+use crate::cryptors::{RingCryptor, MAX_KEY_SIZE};
+use crate::errors;
+use crate::metadata;
+use crate::stream::{StreamOpener, StreamSealer};
+use std::cmp;
+use std::io::{self, Read, Write};
+use zeroize::{Zeroize, Zeroizing};
+
+/// The maximum size of a metadata header that the `DecryptReader` will
+/// accept.
+///
+/// This limit protects the reader from allocating arbitrarily large buffers,
+/// due to a corrupted header.
+pub const MAX_HEADER_SIZE: usize = 64 * 1024;
+
+/// The maximum size of a varint, as defined by the Protocol Buffers spec.
+const MAX_VARINT_SIZE: usize = 10;
+
+/// Convert a Tindercrypt error to an I/O error.
+pub(crate) fn _to_io_error(err: errors::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+/// Read from a reader until the buffer is full, or EOF is reached.
+///
+/// Return the number of bytes that have been read.
+fn _read_full<R: Read>(inner: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut size = 0;
+    while size < buf.len() {
+        match inner.read(&mut buf[size..]) {
+            Ok(0) => break,
+            Ok(n) => size += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(size)
+}
+
+/// Parse the size of a serialized metadata header from its first bytes.
+///
+/// The metadata header consists of the magic bytes, the format version and a
+/// length-delimited Protocol Buffers message (legacy headers have just the
+/// latter). This function parses the varint that holds the length of the
+/// message, and returns the total size of the header, or `None` if more
+/// bytes are needed to parse the varint.
+///
+/// The provided bytes must include the header prefix, if there is one.
+pub(crate) fn _parse_header_size(
+    header: &[u8],
+) -> Result<Option<usize>, errors::Error> {
+    let prefix_size = metadata::Metadata::_get_prefix_size(header)?;
+    let mut msg_size: u64 = 0;
+
+    for (i, byte) in header[prefix_size..].iter().enumerate() {
+        if i == MAX_VARINT_SIZE {
+            return Err(errors::Error::MetadataMissing);
+        }
+        msg_size |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            if msg_size > MAX_HEADER_SIZE as u64 {
+                return Err(errors::Error::MetadataMissing);
+            }
+            return Ok(Some(prefix_size + i + 1 + msg_size as usize));
+        }
+    }
+    Ok(None)
+}
+
+/// Read a serialized metadata header from a reader.
+///
+/// This function reads the header prefix, the varint that holds the length of
+/// the Protocol Buffers message, and then the message itself. It does not
+/// read more bytes than the header has, so that the reader is left at the
+/// start of the ciphertext.
+///
+/// If the reader reaches EOF before the end of the header, the bytes that
+/// have been read so far are returned, so that the caller can report the same
+/// error as `Metadata::from_buf()` would.
+fn _read_header<R: Read>(inner: &mut R) -> io::Result<Vec<u8>> {
+    let mut header = vec![0u8; metadata::HEADER_PREFIX_SIZE];
+    let size = _read_full(inner, &mut header)?;
+    header.truncate(size);
+    if size < metadata::HEADER_PREFIX_SIZE {
+        return Ok(header);
+    }
+
+    // Read the varint, byte by byte.
+    let header_size = loop {
+        match _parse_header_size(&header) {
+            Ok(Some(size)) => break size,
+            Ok(None) => (),
+            Err(err) => return Err(_to_io_error(err)),
+        }
+        let mut byte = [0u8; 1];
+        if _read_full(inner, &mut byte)? == 0 {
+            return Ok(header);
+        }
+        header.push(byte[0]);
+    };
+
+    // NOTE: The bytes that we have read may exceed the header size only for
+    // legacy headers that are too small to be valid, in which case the
+    // decryption will fail anyway.
+    let read_size = header.len();
+    if header_size <= read_size {
+        header.truncate(header_size);
+        return Ok(header);
+    }
+
+    header.resize(header_size, 0u8);
+    let size = _read_full(inner, &mut header[read_size..])?;
+    header.truncate(read_size + size);
+    Ok(header)
+}
+
+/// A writer that encrypts the data that are written to it.
+///
+/// The encrypted data, along with their metadata header, are written to an
+/// inner writer. The data are encrypted as a stream, so they are buffered
+/// until a whole chunk can be encrypted.
+///
+/// The user must call the `.finish()` method once all the data have been
+/// written, so that the last chunk is encrypted and written to the inner
+/// writer. If the writer is dropped without calling `.finish()`, the
+/// encrypted stream will be incomplete, and its decryption will fail.
+#[derive(Debug)]
+pub struct EncryptWriter<'a, W: Write> {
+    inner: W,
+    sealer: StreamSealer<'a>,
+    header: Vec<u8>,
+    buf: Vec<u8>,
+}
+
+impl<'a, W: Write> EncryptWriter<'a, W> {
+    /// Create a new encrypting writer, using the provided metadata.
+    ///
+    /// This method accepts a cryptor, the metadata for a stream (see
+    /// `Metadata::new_stream()`), a secret value (either a key or a
+    /// passphrase) and the inner writer, where the encrypted data will be
+    /// written to. It returns an error if the metadata do not describe a
+    /// stream, or if the key derivation fails.
+    pub fn new(
+        cryptor: &RingCryptor<'a>,
+        meta: &metadata::Metadata,
+        secret: &[u8],
+        inner: W,
+    ) -> io::Result<Self> {
+        let sealer = match StreamSealer::new(cryptor, meta, secret) {
+            Ok(sealer) => sealer,
+            Err(err) => return Err(_to_io_error(err)),
+        };
+        Ok(Self {
+            inner,
+            buf: Vec::with_capacity(sealer.chunk_size()),
+            sealer,
+            header: meta.to_header(),
+        })
+    }
+
+    /// Create a new encrypting writer with a key check value, using the
+    /// provided metadata.
+    ///
+    /// This method is like `EncryptWriter::new()`, but it also stores a key
+    /// check value in the metadata header. See
+    /// `RingCryptor::seal_with_key_check()` for more info.
+    pub fn with_key_check(
+        cryptor: &RingCryptor<'a>,
+        meta: &metadata::Metadata,
+        secret: &[u8],
+        inner: W,
+    ) -> io::Result<Self> {
+        Self::_with_key_values(cryptor, meta, secret, inner, false, true)
+    }
+
+    /// Create a new encrypting writer that stores a key commitment and/or a
+    /// key check value in the metadata header.
+    fn _with_key_values(
+        cryptor: &RingCryptor<'a>,
+        meta: &metadata::Metadata,
+        secret: &[u8],
+        inner: W,
+        commit: bool,
+        check: bool,
+    ) -> io::Result<Self> {
+        let mut meta = meta.clone();
+        let mut key = Zeroizing::new([0u8; MAX_KEY_SIZE]);
+        let key = &mut key[..cryptor._get_key_size(&meta)];
+        if let Err(err) =
+            cryptor._add_key_values(&mut meta, secret, key, commit, check)
+        {
+            return Err(_to_io_error(err));
+        }
+        Self::_from_key(cryptor, &meta, key, inner)
+    }
+
+    /// Create a new encrypting writer, using a symmetric key.
+    ///
+    /// It generates the metadata for a stream, with no key derivation, the
+    /// encryption algorithm of the cryptor and the default chunk size.
+    pub fn with_key(
+        cryptor: &RingCryptor<'a>,
+        key: &[u8],
+        inner: W,
+    ) -> io::Result<Self> {
+        let meta = metadata::Metadata::new_stream(
+            metadata::KeyDerivationAlgorithm::None,
+            cryptor.generate_enc_algo(),
+            metadata::STREAM_DEFAULT_CHUNK_SIZE,
+        );
+        Self::new(cryptor, &meta, key, inner)
+    }
+
+    /// Create a new encrypting writer, using a passphrase.
+    ///
+    /// It generates the metadata for a stream, with the key derivation and
+    /// encryption algorithms of the cryptor and the default chunk size.
+    pub fn with_passphrase(
+        cryptor: &RingCryptor<'a>,
+        pass: &[u8],
+        inner: W,
+    ) -> io::Result<Self> {
+        let meta = metadata::Metadata::new_stream(
+            cryptor.generate_key_deriv_algo(),
+            cryptor.generate_enc_algo(),
+            metadata::STREAM_DEFAULT_CHUNK_SIZE,
+        );
+        Self::new(cryptor, &meta, pass, inner)
+    }
+
+    /// Create a new encrypting writer, for a recipient with an X25519 public
+    /// key.
+    ///
+    /// It generates the metadata for a stream, with an X25519 key agreement,
+    /// the AES-256-GCM encryption algorithm and the default chunk size. See
+    /// `RingCryptor::seal_for_recipient()` for more info.
+    pub fn for_recipient(
+        cryptor: &RingCryptor<'a>,
+        public_key: &[u8],
+        inner: W,
+    ) -> io::Result<Self> {
+        let chunk_size = metadata::STREAM_DEFAULT_CHUNK_SIZE;
+        Self::_for_recipient(cryptor, public_key, chunk_size, inner)
+    }
+
+    /// Create a new encrypting writer for a recipient, with the provided
+    /// chunk size.
+    fn _for_recipient(
+        cryptor: &RingCryptor<'a>,
+        public_key: &[u8],
+        chunk_size: usize,
+        inner: W,
+    ) -> io::Result<Self> {
+        let mut key = Zeroizing::new([0u8; MAX_KEY_SIZE]);
+        let (key_deriv_algo, enc_algo) =
+            match RingCryptor::_generate_recipient_algos(public_key, &mut key)
+            {
+                Ok(algos) => algos,
+                Err(err) => return Err(_to_io_error(err)),
+            };
+        let meta = metadata::Metadata::new_stream(
+            key_deriv_algo,
+            enc_algo,
+            chunk_size,
+        );
+        let key = &key[..cryptor._get_key_size(&meta)];
+        Self::_from_key(cryptor, &meta, key, inner)
+    }
+
+    /// Create a new encrypting writer, using an already derived key.
+    fn _from_key(
+        cryptor: &RingCryptor<'a>,
+        meta: &metadata::Metadata,
+        key: &[u8],
+        inner: W,
+    ) -> io::Result<Self> {
+        let sealer = match StreamSealer::_from_key(cryptor, meta, key) {
+            Ok(sealer) => sealer,
+            Err(err) => return Err(_to_io_error(err)),
+        };
+        Ok(Self {
+            inner,
+            buf: Vec::with_capacity(sealer.chunk_size()),
+            sealer,
+            header: meta.to_header(),
+        })
+    }
+
+    /// Get a reference to the inner writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Write the metadata header to the inner writer, if it has not been
+    /// written yet.
+    fn _write_header(&mut self) -> io::Result<()> {
+        if !self.header.is_empty() {
+            self.inner.write_all(&self.header)?;
+            self.header.clear();
+        }
+        Ok(())
+    }
+
+    /// Encrypt the last chunk, and return the inner writer.
+    ///
+    /// This method must be called once all the data have been written.
+    /// Else, the encrypted stream will be incomplete.
+    pub fn finish(mut self) -> io::Result<W> {
+        self._write_header()?;
+        let chunk = match self.sealer.seal_last_chunk(&self.buf) {
+            Ok(chunk) => chunk,
+            Err(err) => return Err(_to_io_error(err)),
+        };
+        self.buf.zeroize();
+        self.inner.write_all(&chunk)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<'a, W: Write> Write for EncryptWriter<'a, W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self._write_header()?;
+
+        // NOTE: We can't encrypt a full chunk as soon as it's buffered, since
+        // it may be the last one. So, we wait until more data arrive.
+        let chunk_size = self.sealer.chunk_size();
+        if self.buf.len() == chunk_size && !data.is_empty() {
+            let chunk = match self.sealer.seal_chunk(&self.buf) {
+                Ok(chunk) => chunk,
+                Err(err) => return Err(_to_io_error(err)),
+            };
+            self.inner.write_all(&chunk)?;
+            self.buf.zeroize();
+        }
+
+        let size = cmp::min(data.len(), chunk_size - self.buf.len());
+        self.buf.extend_from_slice(&data[..size]);
+        Ok(size)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self._write_header()?;
+        self.inner.flush()
+    }
+}
+
+/// The decryption state of a `DecryptReader`.
+#[derive(Debug)]
+enum DecryptState<'a> {
+    /// The data are encrypted as a stream, and are decrypted chunk by chunk.
+    Stream(StreamOpener<'a>),
+    /// All the data have been decrypted.
+    Done,
+}
+
+/// A reader that decrypts the data that are read from an inner reader.
+///
+/// The reader expects a metadata header, followed by the ciphertext. If the
+/// data have been encrypted as a stream, they are decrypted chunk by chunk,
+/// so that the memory usage stays bounded. Else, the ciphertext is read and
+/// decrypted in one go when the reader is created, so that data that have
+/// been encrypted with the `.seal_*()` methods of the cryptor can also be
+/// decrypted.
+///
+/// Note that the decrypted chunks of a stream are returned as soon as they
+/// are authenticated. If the stream has been tampered with or truncated,
+/// the error will be returned after the preceding chunks have been read.
+#[derive(Debug)]
+pub struct DecryptReader<'a, R: Read> {
+    inner: R,
+    cryptor: RingCryptor<'a>,
+    meta: metadata::Metadata,
+    state: DecryptState<'a>,
+    ct_buf: Vec<u8>,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl<'a, R: Read> DecryptReader<'a, R> {
+    /// Create a new decrypting reader.
+    ///
+    /// This method accepts a cryptor, a secret value (either a key or a
+    /// passphrase) and the inner reader, where the encrypted data will be read
+    /// from. It reads the metadata header from the inner reader and derives
+    /// the key, so it returns an error if the header is missing or invalid,
+    /// or if the key derivation fails. If the data have not been encrypted as
+    /// a stream, it also decrypts them, and returns any decryption error.
+    pub fn new(
+        cryptor: &RingCryptor<'a>,
+        secret: &[u8],
+        mut inner: R,
+    ) -> io::Result<Self> {
+        let header = _read_header(&mut inner)?;
+        let meta = match metadata::Metadata::from_buf(&header) {
+            Ok((meta, _)) => meta,
+            Err(err) => return Err(_to_io_error(err)),
+        };
+
+        let mut reader = Self {
+            inner,
+            cryptor: *cryptor,
+            meta: meta.clone(),
+            state: DecryptState::Done,
+            ct_buf: Vec::new(),
+            buf: Vec::new(),
+            pos: 0,
+        };
+
+        match meta.chunk_size {
+            Some(_) => match StreamOpener::new(cryptor, &meta, secret) {
+                Ok(opener) => reader.state = DecryptState::Stream(opener),
+                Err(err) => return Err(_to_io_error(err)),
+            },
+            None => reader._open_whole(secret)?,
+        }
+        Ok(reader)
+    }
+
+    /// Get the metadata of the encrypted data.
+    pub fn metadata(&self) -> &metadata::Metadata {
+        &self.meta
+    }
+
+    /// Get a reference to the inner reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Read and decrypt the next chunk of a stream.
+    ///
+    /// In order to know if a chunk is the last one, we read one byte more
+    /// than the size of an encrypted chunk, and carry it over to the next
+    /// chunk.
+    fn _open_next_chunk(&mut self) -> io::Result<()> {
+        let opener = match &self.state {
+            DecryptState::Stream(opener) => opener,
+            _ => unreachable!(),
+        };
+        let ct_chunk_size = opener.encrypted_chunk_size();
+        let carried_size = self.ct_buf.len();
+        self.ct_buf.resize(ct_chunk_size + 1, 0u8);
+        let size = carried_size
+            + _read_full(&mut self.inner, &mut self.ct_buf[carried_size..])?;
+        self.ct_buf.truncate(size);
+
+        let res = if size > ct_chunk_size {
+            let opener = match &mut self.state {
+                DecryptState::Stream(opener) => opener,
+                _ => unreachable!(),
+            };
+            let res = opener.open_chunk(&self.ct_buf[..ct_chunk_size]);
+            let _ = self.ct_buf.drain(..ct_chunk_size);
+            res
+        } else {
+            let opener =
+                match std::mem::replace(&mut self.state, DecryptState::Done) {
+                    DecryptState::Stream(opener) => opener,
+                    _ => unreachable!(),
+                };
+            let res = opener.open_last_chunk(&self.ct_buf);
+            self.ct_buf.clear();
+            res
+        };
+
+        match res {
+            Ok(buf) => {
+                self.buf.zeroize();
+                self.buf = buf;
+                self.pos = 0;
+                Ok(())
+            }
+            Err(err) => {
+                self.state = DecryptState::Done;
+                Err(_to_io_error(err))
+            }
+        }
+    }
+
+    /// Read and decrypt the whole ciphertext, for data that have not been
+    /// encrypted as a stream.
+    fn _open_whole(&mut self, secret: &[u8]) -> io::Result<()> {
+        let ciphertext_size = self.meta.ciphertext_size;
+
+        // NOTE: We don't allocate the buffer beforehand, since the ciphertext
+        // size comes from an untrusted header.
+        let mut buf = Vec::new();
+        let size = (&mut self.inner)
+            .take(ciphertext_size as u64)
+            .read_to_end(&mut buf)?;
+        if size != ciphertext_size {
+            return Err(_to_io_error(errors::Error::BufferTooSmall));
+        }
+
+        match self.cryptor.open_in_place(&self.meta, secret, &mut buf) {
+            Ok(size) => {
+                buf.truncate(size);
+                self.buf = buf;
+                self.pos = 0;
+                Ok(())
+            }
+            Err(err) => Err(_to_io_error(err)),
+        }
+    }
+}
+
+impl<'a, R: Read> Read for DecryptReader<'a, R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.buf.len() {
+            match self.state {
+                DecryptState::Stream(_) => self._open_next_chunk()?,
+                DecryptState::Done => return Ok(0),
+            }
+        }
+
+        let size = cmp::min(out.len(), self.buf.len() - self.pos);
+        out[..size].copy_from_slice(&self.buf[self.pos..self.pos + size]);
+        self.pos += size;
+        Ok(size)
+    }
+}
+
+impl<'a, R: Read> Drop for DecryptReader<'a, R> {
+    fn drop(&mut self) {
+        self.buf.zeroize();
+    }
+}
+
+/// Encrypt the data of a reader again, with a new secret value, and write
+/// them to a writer.
+///
+/// This function accepts a cryptor, the old secret value, the new secret
+/// value (either a key or a passphrase), the inner reader, where the
+/// encrypted data will be read from, and the inner writer, where the
+/// re-encrypted data will be written to.
+///
+/// It's the streaming equivalent of `RingCryptor::rekey()`. The data are
+/// decrypted with the old secret value, and are encrypted as a stream with
+/// the new one, using the same key derivation and encryption algorithms, but
+/// a unique salt and nonce. Data that have not been encrypted as a stream
+/// are encrypted as a stream with the default chunk size. A key commitment or
+/// a key check value is computed anew for the new key, if the data had one.
+/// Data that have been encrypted for a recipient are encrypted for a new
+/// recipient, so the new secret value must be their public key. The
+/// intermediate plaintext is never returned to the caller, and is zeroed once
+/// it has been encrypted. If all goes well, the inner writer is returned.
+pub fn rekey<'a, R: Read, W: Write>(
+    cryptor: &RingCryptor<'a>,
+    old_secret: &[u8],
+    new_secret: &[u8],
+    mut inner: R,
+    out: W,
+) -> io::Result<W> {
+    let header = _read_header(&mut inner)?;
+    let meta = match metadata::Metadata::from_buf(&header) {
+        Ok((meta, _)) => meta,
+        Err(err) => return Err(_to_io_error(err)),
+    };
+    let chunk_size = meta
+        .chunk_size
+        .unwrap_or(metadata::STREAM_DEFAULT_CHUNK_SIZE);
+    let inner = header.as_slice().chain(inner);
+
+    if let metadata::KeyDerivationAlgorithm::X25519(_) = meta.key_deriv_algo {
+        let reader = DecryptReader::new(cryptor, old_secret, inner)?;
+        let writer = EncryptWriter::_for_recipient(
+            cryptor, new_secret, chunk_size, out,
+        )?;
+        return _rekey_copy(reader, writer);
+    }
+
+    let (key_deriv_algo, enc_algo) =
+        match cryptor._regenerate_algos(&meta, &header, old_secret) {
+            Ok(algos) => algos,
+            Err(err) => return Err(_to_io_error(err)),
+        };
+
+    let new_meta =
+        metadata::Metadata::new_stream(key_deriv_algo, enc_algo, chunk_size);
+    let reader = DecryptReader::new(cryptor, old_secret, inner)?;
+    let writer = EncryptWriter::_with_key_values(
+        cryptor,
+        &new_meta,
+        new_secret,
+        out,
+        meta.key_commitment.is_some(),
+        meta.key_check.is_some(),
+    )?;
+    _rekey_copy(reader, writer)
+}
+
+/// Encrypt the data of a reader again, with a new secret value and the
+/// provided metadata, and write them to a writer.
+///
+/// This function is similar to `rekey()`, but it accepts the metadata for
+/// the new stream (see `Metadata::new_stream()`), so that the caller can
+/// switch to a different key derivation or encryption algorithm, or chunk
+/// size. The metadata must have a unique salt and nonce, e.g., freshly
+/// generated ones.
+pub fn rekey_with_meta<'a, R: Read, W: Write>(
+    cryptor: &RingCryptor<'a>,
+    old_secret: &[u8],
+    meta: &metadata::Metadata,
+    new_secret: &[u8],
+    inner: R,
+    out: W,
+) -> io::Result<W> {
+    let reader = DecryptReader::new(cryptor, old_secret, inner)?;
+    let writer = EncryptWriter::new(cryptor, meta, new_secret, out)?;
+    _rekey_copy(reader, writer)
+}
+
+/// Copy the decrypted data of a reader to an encrypting writer, and return
+/// the inner writer.
+///
+/// The intermediate buffer is zeroed once the copy is over.
+fn _rekey_copy<R: Read, W: Write>(
+    mut reader: DecryptReader<'_, R>,
+    mut writer: EncryptWriter<'_, W>,
+) -> io::Result<W> {
+    let mut buf = Zeroizing::new(vec![0u8; writer.sealer.chunk_size()]);
+
+    loop {
+        let size = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(size) => size,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        writer.write_all(&buf[..size])?;
+    }
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::x25519;
+
+    /// Generate metadata for a stream with the provided chunk size.
+    fn generate_meta(chunk_size: usize) -> metadata::Metadata {
+        let enc_meta = metadata::EncryptionMetadata::generate();
+        let enc_algo = metadata::EncryptionAlgorithm::AES256GCM(enc_meta);
+        let key_algo = metadata::KeyDerivationAlgorithm::None;
+        metadata::Metadata::new_stream(key_algo, enc_algo, chunk_size)
+    }
+
+    /// Get the Tindercrypt error that an I/O error wraps.
+    fn tc_error(err: io::Error) -> errors::Error {
+        let inner = err.get_ref().unwrap();
+        *inner.downcast_ref::<errors::Error>().unwrap()
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let key = [9u8; 32];
+        let cryptor = RingCryptor::new();
+        let meta = generate_meta(4);
+
+        // Check that data of various sizes can be encrypted and decrypted,
+        // regardless of how they are split into writes and reads.
+        for size in 0..13 {
+            let plaintext: Vec<u8> = (0..size as u8).collect();
+            for write_size in 1..6 {
+                let mut writer =
+                    EncryptWriter::new(&cryptor, &meta, &key, Vec::new())
+                        .unwrap();
+                for data in plaintext.chunks(write_size) {
+                    writer.write_all(data).unwrap();
+                }
+                let ciphertext = writer.finish().unwrap();
+                let res = cryptor.open(&key, &ciphertext);
+                assert_eq!(res, Ok(plaintext.clone()));
+
+                let mut reader =
+                    DecryptReader::new(&cryptor, &key, &ciphertext[..])
+                        .unwrap();
+                let mut res = Vec::new();
+                let _ = reader.read_to_end(&mut res).unwrap();
+                assert_eq!(res, plaintext);
+            }
+        }
+
+        // Check that data which have not been encrypted as a stream can be
+        // decrypted as well.
+        let plaintext = "The cake is a lie".as_bytes();
+        let ciphertext = cryptor.seal_with_key(&key, plaintext).unwrap();
+        let mut reader =
+            DecryptReader::new(&cryptor, &key, &ciphertext[..]).unwrap();
+        let mut res = Vec::new();
+        let _ = reader.read_to_end(&mut res).unwrap();
+        assert_eq!(res, plaintext);
+    }
+
+    #[test]
+    fn test_rekey() {
+        let old_key = [9u8; 32];
+        let new_key = [1u8; 32];
+        let plaintext: Vec<u8> = (0..13).collect();
+        let cryptor = RingCryptor::new();
+        let meta = generate_meta(4);
+
+        // Check that a stream is encrypted again with the new key, using the
+        // same chunk size, but a unique nonce.
+        let ciphertext =
+            cryptor.seal_with_meta(&meta, &old_key, &plaintext).unwrap();
+        let new_ciphertext =
+            rekey(&cryptor, &old_key, &new_key, &ciphertext[..], Vec::new())
+                .unwrap();
+        let res = cryptor.open(&new_key, &new_ciphertext);
+        assert_eq!(res, Ok(plaintext.clone()));
+        let res = cryptor.open(&old_key, &new_ciphertext);
+        assert_eq!(res, Err(errors::Error::DecryptionError));
+        let (new_meta, _) =
+            metadata::Metadata::from_buf(&new_ciphertext).unwrap();
+        assert_eq!(new_meta.chunk_size, Some(4));
+        assert_ne!(new_meta.enc_algo, meta.enc_algo);
+
+        // Check that data which have not been encrypted as a stream are
+        // encrypted as a stream with the default chunk size.
+        let ciphertext = cryptor.seal_with_key(&old_key, &plaintext).unwrap();
+        let new_ciphertext =
+            rekey(&cryptor, &old_key, &new_key, &ciphertext[..], Vec::new())
+                .unwrap();
+        let res = cryptor.open(&new_key, &new_ciphertext);
+        assert_eq!(res, Ok(plaintext.clone()));
+        let (new_meta, _) =
+            metadata::Metadata::from_buf(&new_ciphertext).unwrap();
+        assert_eq!(
+            new_meta.chunk_size,
+            Some(metadata::STREAM_DEFAULT_CHUNK_SIZE)
+        );
+
+        // Check that the chunk size can be switched.
+        let new_meta = generate_meta(5);
+        let new_ciphertext = rekey_with_meta(
+            &cryptor,
+            &old_key,
+            &new_meta,
+            &new_key,
+            &ciphertext[..],
+            Vec::new(),
+        )
+        .unwrap();
+        let res = cryptor.open(&new_key, &new_ciphertext);
+        assert_eq!(res, Ok(plaintext.clone()));
+
+        // Check that a wrong old key is detected.
+        let err =
+            rekey(&cryptor, &new_key, &old_key, &ciphertext[..], Vec::new())
+                .unwrap_err();
+        assert_eq!(tc_error(err), errors::Error::DecryptionError);
+
+        // Check that the key commitment and the key check value are computed
+        // anew for the new key.
+        let ciphertext = cryptor
+            .seal_committing(&meta, &old_key, &plaintext)
+            .unwrap();
+        let new_ciphertext =
+            rekey(&cryptor, &old_key, &new_key, &ciphertext[..], Vec::new())
+                .unwrap();
+        let (new_meta, _) =
+            metadata::Metadata::from_buf(&new_ciphertext).unwrap();
+        let key_commitment = RingCryptor::_get_key_commitment(&new_key);
+        assert_eq!(new_meta.key_commitment, Some(key_commitment));
+        let res = cryptor.open(&new_key, &new_ciphertext);
+        assert_eq!(res, Ok(plaintext.clone()));
+
+        let ciphertext = cryptor
+            .seal_with_key_check(&meta, &old_key, &plaintext)
+            .unwrap();
+        let new_ciphertext =
+            rekey(&cryptor, &old_key, &new_key, &ciphertext[..], Vec::new())
+                .unwrap();
+        let (new_meta, _) =
+            metadata::Metadata::from_buf(&new_ciphertext).unwrap();
+        let key_check = RingCryptor::_get_key_check(&new_key);
+        assert_eq!(new_meta.key_check, Some(key_check));
+        let res = cryptor.open(&old_key, &new_ciphertext);
+        assert_eq!(res, Err(errors::Error::WrongKey));
+    }
+
+    #[test]
+    fn test_encrypt_for_recipient() {
+        let plaintext: Vec<u8> = (0..13).collect();
+        let private_key = x25519::generate_private_key();
+        let public_key = x25519::public_key(&private_key).unwrap();
+        let cryptor = RingCryptor::new();
+
+        // Check that a stream can be encrypted for a recipient, and
+        // decrypted with their private key.
+        let mut writer =
+            EncryptWriter::for_recipient(&cryptor, &public_key, Vec::new())
+                .unwrap();
+        writer.write_all(&plaintext).unwrap();
+        let ciphertext = writer.finish().unwrap();
+        let mut reader =
+            DecryptReader::new(&cryptor, &private_key, &ciphertext[..])
+                .unwrap();
+        let mut res = Vec::new();
+        let _ = reader.read_to_end(&mut res).unwrap();
+        assert_eq!(res, plaintext);
+
+        // Check that the stream can be encrypted again for a new recipient,
+        // with the same chunk size.
+        let new_private_key = x25519::generate_private_key();
+        let new_public_key = x25519::public_key(&new_private_key).unwrap();
+        let new_ciphertext = rekey(
+            &cryptor,
+            &private_key,
+            &new_public_key,
+            &ciphertext[..],
+            Vec::new(),
+        )
+        .unwrap();
+        let res = cryptor.open(&new_private_key, &new_ciphertext);
+        assert_eq!(res, Ok(plaintext.clone()));
+        let res = cryptor.open(&private_key, &new_ciphertext);
+        assert_eq!(res, Err(errors::Error::DecryptionError));
+        let (new_meta, _) =
+            metadata::Metadata::from_buf(&new_ciphertext).unwrap();
+        assert_eq!(
+            new_meta.chunk_size,
+            Some(metadata::STREAM_DEFAULT_CHUNK_SIZE)
+        );
+
+        // Check that invalid public keys are rejected.
+        let err = EncryptWriter::for_recipient(&cryptor, &[0; 32], Vec::new())
+            .unwrap_err();
+        assert_eq!(tc_error(err), errors::Error::CryptoParamsWeak);
+    }
+
+    #[test]
+    fn test_decrypt_errors() {
+        let key = [9u8; 32];
+        let wrong_key = [1u8; 32];
+        let cryptor = RingCryptor::new();
+        let meta = generate_meta(4);
+
+        let mut writer =
+            EncryptWriter::new(&cryptor, &meta, &key, Vec::new()).unwrap();
+        writer.write_all(&[1u8; 10]).unwrap();
+        let ciphertext = writer.finish().unwrap();
+        let meta_size = meta.to_header().len();
+
+        // Check that a missing or truncated header is detected, and that the
+        // reported error is the same as the one of the cryptor.
+        for buf in &[&[][..], &[99][..], &ciphertext[..meta_size - 1]] {
+            let err = DecryptReader::new(&cryptor, &key, *buf).unwrap_err();
+            let res = cryptor.open(&key, buf);
+            assert_eq!(Err(tc_error(err)), res);
+        }
+
+        // Check that a header larger than the maximum size is rejected.
+        let mut buf = ciphertext[..metadata::HEADER_PREFIX_SIZE].to_vec();
+        buf.extend_from_slice(&[0xff, 0xff, 0xff, 0x7f]);
+        let err = DecryptReader::new(&cryptor, &key, &buf[..]).unwrap_err();
+        assert_eq!(tc_error(err), errors::Error::MetadataMissing);
+
+        // Check that an unsupported format version is detected.
+        let mut buf = ciphertext.clone();
+        buf[metadata::MAGIC.len()] += 1;
+        let err = DecryptReader::new(&cryptor, &key, &buf[..]).unwrap_err();
+        assert_eq!(tc_error(err), errors::Error::UnsupportedVersion);
+
+        // Check that a wrong key is detected.
+        let mut reader =
+            DecryptReader::new(&cryptor, &wrong_key, &ciphertext[..]).unwrap();
+        let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(tc_error(err), errors::Error::DecryptionError);
+
+        // Check that a truncated stream is detected, after the intact chunks
+        // have been read.
+        let truncated = &ciphertext[..ciphertext.len() - 1];
+        let mut reader =
+            DecryptReader::new(&cryptor, &key, truncated).unwrap();
+        let mut res = Vec::new();
+        let err = reader.read_to_end(&mut res).unwrap_err();
+        assert_eq!(tc_error(err), errors::Error::DecryptionError);
+        assert_eq!(res, vec![1u8; 8]);
+
+        // Check that a truncated ciphertext, which has not been encrypted as a
+        // stream, is detected.
+        let ciphertext = cryptor.seal_with_key(&key, &[1u8; 10]).unwrap();
+        let truncated = &ciphertext[..ciphertext.len() - 1];
+        let err = DecryptReader::new(&cryptor, &key, truncated).unwrap_err();
+        assert_eq!(tc_error(err), errors::Error::BufferTooSmall);
+    }
+}
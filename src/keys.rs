@@ -0,0 +1,620 @@
+//! # Key providers
+//!
+//! Instead of passing raw keys to the cryptors, the user can store their keys
+//! in a key provider, and refer to them by an identifier. A key provider is
+//! any type that implements the [`KeyProvider`] trait, e.g., a local keystore
+//! file, or a client for a key management service (KMS).
+//!
+//! Key providers are used for envelope encryption: the data are encrypted
+//! with a random data key, which is wrapped with a key-encryption key (KEK)
+//! from the provider. The identifier of the KEK is stored in the metadata
+//! header, so that the data can be decrypted with just the provider, see
+//! [`RingCryptor::seal_with_provider`] and
+//! [`RingCryptor::open_with_provider`].
+//!
+//! This module also provides [`FileKeyStore`], a key provider that is backed
+//! by a local file, and [`Keyring`], an in-memory collection of keys. The
+//! latter is meant for data that are encrypted directly with one of several
+//! live keys, e.g., during a key rotation. The identifier of the key is
+//! stored in the metadata header, so that the right key is picked during
+//! decryption, instead of trying every key.
+//!
+//! Finally, this module provides [`PreparedKey`], a symmetric key that has
+//! been prepared once for an encryption algorithm, so that it can encrypt a
+//! lot of messages without expanding the key for each one of them.
+//!
+//! ## Examples
+//!
+//! ```
+//! use tindercrypt::cryptors::RingCryptor;
+//! use tindercrypt::keys::FileKeyStore;
+//!
+//! # let dir = assert_fs::TempDir::new().unwrap();
+//! # let path = dir.path().join("keystore");
+//! let plaintext = "The cake is a lie".as_bytes();
+//! let cryptor = RingCryptor::new();
+//!
+//! // Create a keystore file with a random key.
+//! let mut keystore = FileKeyStore::create(&path);
+//! keystore.generate_key("kek-1")?;
+//! keystore.save()?;
+//!
+//! // Encrypt the data with the key, and decrypt them with just the keystore.
+//! let keystore = FileKeyStore::open(&path)?;
+//! let ciphertext = cryptor.seal_with_provider(&keystore, "kek-1", plaintext)?;
+//! assert_eq!(cryptor.open_with_provider(&keystore, &ciphertext)?, plaintext);
+//!
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! [`KeyProvider`]: trait.KeyProvider.html
+//! [`FileKeyStore`]: struct.FileKeyStore.html
+//! [`Keyring`]: struct.Keyring.html
+//! [`PreparedKey`]: struct.PreparedKey.html
+//! [`RingCryptor::seal_with_provider`]: ../cryptors/struct.RingCryptor.html#method.seal_with_provider
+//! [`RingCryptor::open_with_provider`]: ../cryptors/struct.RingCryptor.html#method.open_with_provider
+
+use crate::aead;
+use crate::aes_gcm_siv;
+use crate::cryptors::RingCryptor;
+use crate::errors;
+use crate::metadata;
+use crate::policy;
+use crate::rand;
+use crate::xchacha20;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+#[cfg(target_family = "unix")]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+/// A provider of keys, which are referred to by an identifier.
+pub trait KeyProvider {
+    /// Get the key with the provided identifier.
+    ///
+    /// Return `None` if the provider does not have such a key. The key is
+    /// zeroed when it's dropped.
+    fn get_key(&self, key_id: &str) -> Option<Zeroizing<Vec<u8>>>;
+}
+
+/// An in-memory collection of keys, which are referred to by an identifier.
+///
+/// The keys can be symmetric keys or passphrases. A keyring can be used to
+/// encrypt data with one of its keys, via [`RingCryptor::seal_with_keyring`].
+/// The identifier of the key is stored in the metadata header, so that
+/// [`RingCryptor::open_with_keyring`] can pick the right key. A keyring is
+/// also a key provider, so it can be used for envelope encryption too.
+///
+/// ```
+/// use tindercrypt::cryptors::RingCryptor;
+/// use tindercrypt::keys::Keyring;
+///
+/// let plaintext = "The cake is a lie".as_bytes();
+/// let cryptor = RingCryptor::new();
+/// let mut keyring = Keyring::new();
+/// keyring.add_key("key-2019", &[1u8; 32]);
+/// keyring.add_key("key-2020", &[2u8; 32]);
+///
+/// let ciphertext = cryptor.seal_with_keyring(&keyring, "key-2020", plaintext)?;
+/// assert_eq!(cryptor.open_with_keyring(&keyring, &ciphertext)?, plaintext);
+///
+/// # use tindercrypt::errors;
+/// # Ok::<(), errors::Error>(())
+/// ```
+///
+/// [`RingCryptor::seal_with_keyring`]: ../cryptors/struct.RingCryptor.html#method.seal_with_keyring
+/// [`RingCryptor::open_with_keyring`]: ../cryptors/struct.RingCryptor.html#method.open_with_keyring
+#[derive(Clone, Default)]
+pub struct Keyring {
+    keys: BTreeMap<String, Zeroizing<Vec<u8>>>,
+}
+
+impl fmt::Debug for Keyring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // NOTE: Do not print the keys, just their identifiers.
+        f.debug_struct("Keyring")
+            .field("key_ids", &self.keys.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl Keyring {
+    /// Create an empty keyring.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the identifiers of the keys in the keyring.
+    pub fn key_ids(&self) -> Vec<&str> {
+        self.keys.keys().map(String::as_str).collect()
+    }
+
+    /// Add a key to the keyring, or replace an existing one.
+    pub fn add_key(&mut self, key_id: &str, key: &[u8]) {
+        let key = Zeroizing::new(key.to_vec());
+        let _ = self.keys.insert(key_id.to_string(), key);
+    }
+
+    /// Remove a key from the keyring.
+    ///
+    /// Return whether the key existed.
+    pub fn remove_key(&mut self, key_id: &str) -> bool {
+        self.keys.remove(key_id).is_some()
+    }
+}
+
+impl KeyProvider for Keyring {
+    fn get_key(&self, key_id: &str) -> Option<Zeroizing<Vec<u8>>> {
+        self.keys.get(key_id).cloned()
+    }
+}
+
+/// Encode a buffer as a lowercase hex string.
+fn _to_hex(buf: &[u8]) -> String {
+    buf.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decode a hex string into a buffer.
+fn _from_hex(s: &str) -> Option<Vec<u8>> {
+    s.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [hi, lo] => {
+                let hi = char::from(*hi).to_digit(16)?;
+                let lo = char::from(*lo).to_digit(16)?;
+                Some((hi * 16 + lo) as u8)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Check that a key identifier can be stored in a keystore file.
+fn _check_key_id(key_id: &str) -> io::Result<()> {
+    if key_id.is_empty() || key_id.starts_with('#') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "key identifiers must not be empty or start with '#'",
+        ));
+    }
+    if key_id.chars().any(char::is_whitespace) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "key identifiers must not contain whitespace",
+        ));
+    }
+    Ok(())
+}
+
+/// A key provider that is backed by a local keystore file.
+///
+/// The keystore file is a text file, with one key per line. Each line holds
+/// the identifier of the key and the hex-encoded key, separated by
+/// whitespace. Empty lines and lines that start with `#` are ignored. For
+/// instance:
+///
+/// ```text
+/// # Key-encryption keys for the backups.
+/// backups-2020 8f1c...
+/// backups-2021 42aa...
+/// ```
+///
+/// The keys are loaded in memory when the keystore is opened, and are written
+/// back to the file only when the `.save()` method is called. The file is
+/// created with read-write rights by the owner only.
+#[derive(Clone)]
+pub struct FileKeyStore {
+    path: PathBuf,
+    keys: BTreeMap<String, Zeroizing<Vec<u8>>>,
+}
+
+impl fmt::Debug for FileKeyStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // NOTE: Do not print the keys, just their identifiers.
+        f.debug_struct("FileKeyStore")
+            .field("path", &self.path)
+            .field("key_ids", &self.keys.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl FileKeyStore {
+    /// Create an empty keystore, which will be stored in the provided path.
+    ///
+    /// The file is not created until the `.save()` method is called.
+    pub fn create<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            keys: BTreeMap::new(),
+        }
+    }
+
+    /// Open an existing keystore file and load its keys.
+    ///
+    /// This method returns an error if the file cannot be read, or if it
+    /// contains invalid lines or duplicate key identifiers.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut keystore = Self::create(path);
+        let contents = Zeroizing::new(fs::read_to_string(&keystore.path)?);
+
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid_line = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid keystore line: {}", i + 1),
+                )
+            };
+            let mut fields = line.split_whitespace();
+            let (key_id, key) = match (fields.next(), fields.next()) {
+                (Some(key_id), Some(key)) => (key_id, key),
+                _ => return Err(invalid_line()),
+            };
+            if fields.next().is_some() {
+                return Err(invalid_line());
+            }
+            let key = _from_hex(key).ok_or_else(invalid_line)?;
+            let key = Zeroizing::new(key);
+            if keystore.keys.insert(key_id.to_string(), key).is_some() {
+                return Err(invalid_line());
+            }
+        }
+        Ok(keystore)
+    }
+
+    /// Get the path of the keystore file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Get the identifiers of the keys in the keystore.
+    pub fn key_ids(&self) -> Vec<&str> {
+        self.keys.keys().map(String::as_str).collect()
+    }
+
+    /// Add a key to the keystore, or replace an existing one.
+    ///
+    /// This method returns an error if the key identifier is empty, starts
+    /// with `#` or contains whitespace, since it could not be stored in the
+    /// keystore file.
+    pub fn add_key(&mut self, key_id: &str, key: &[u8]) -> io::Result<()> {
+        _check_key_id(key_id)?;
+        let key = Zeroizing::new(key.to_vec());
+        let _ = self.keys.insert(key_id.to_string(), key);
+        Ok(())
+    }
+
+    /// Generate a random key and add it to the keystore.
+    ///
+    /// The key has the proper size for wrapping data keys.
+    pub fn generate_key(&mut self, key_id: &str) -> io::Result<()> {
+        let mut key = Zeroizing::new([0u8; metadata::DATA_KEY_SIZE]);
+        rand::fill_buf(&mut key[..]);
+        self.add_key(key_id, &key[..])
+    }
+
+    /// Remove a key from the keystore.
+    ///
+    /// Return whether the key existed.
+    pub fn remove_key(&mut self, key_id: &str) -> bool {
+        self.keys.remove(key_id).is_some()
+    }
+
+    /// Write the keys of the keystore to its file.
+    ///
+    /// The file is overwritten, if it exists.
+    pub fn save(&self) -> io::Result<()> {
+        // NOTE: See the `_create_file()` function of the CLI for why the file
+        // mode is set only on Unix platforms.
+        let mut open_opts = fs::OpenOptions::new();
+        let _ = open_opts.write(true).create(true).truncate(true);
+        #[cfg(target_family = "unix")]
+        let _ = open_opts.mode(0o600);
+
+        let mut f = open_opts.open(&self.path)?;
+        for (key_id, key) in &self.keys {
+            let key = Zeroizing::new(_to_hex(key));
+            writeln!(f, "{} {}", key_id, *key)?;
+        }
+        f.sync_all()
+    }
+}
+
+impl KeyProvider for FileKeyStore {
+    fn get_key(&self, key_id: &str) -> Option<Zeroizing<Vec<u8>>> {
+        self.keys.get(key_id).cloned()
+    }
+}
+
+/// The cipher of a prepared key.
+enum PreparedCipher {
+    /// A `ring` key, whose key schedule has been expanded.
+    Ring(Box<ring::aead::LessSafeKey>),
+    /// A raw key, for the algorithms that derive a subkey per nonce.
+    Raw(Zeroizing<[u8; metadata::DATA_KEY_SIZE]>),
+}
+
+/// A symmetric key that has been prepared for an encryption algorithm.
+///
+/// The `ring` AEAD functions expand the key (e.g., the AES key schedule) on
+/// every call, which is a significant cost if a key is used for a lot of
+/// small messages. A prepared key is created once from a key and an
+/// encryption algorithm, and holds the expanded key, so that it can be used
+/// for any number of messages via [`RingCryptor::seal_with_prepared_key`]
+/// and [`RingCryptor::open_with_prepared_key`]. The nonces and the metadata
+/// headers are still generated per message.
+///
+/// XChaCha20-Poly1305 and AES-256-GCM-SIV derive a subkey per nonce, so there
+/// is nothing to expand beforehand. Prepared keys for these algorithms just
+/// hold the key.
+///
+/// ```
+/// use tindercrypt::cryptors::RingCryptor;
+/// use tindercrypt::keys::PreparedKey;
+/// use tindercrypt::policy::EncryptionKind;
+///
+/// let plaintext = "The cake is a lie".as_bytes();
+/// let cryptor = RingCryptor::new();
+/// let key = PreparedKey::new(EncryptionKind::AES256GCM, &[1u8; 32])?;
+///
+/// let ciphertext = cryptor.seal_with_prepared_key(&key, plaintext)?;
+/// assert_eq!(cryptor.open_with_prepared_key(&key, &ciphertext)?, plaintext);
+/// assert_eq!(cryptor.open(&[1u8; 32], &ciphertext)?, plaintext);
+///
+/// # use tindercrypt::errors;
+/// # Ok::<(), errors::Error>(())
+/// ```
+///
+/// [`RingCryptor::seal_with_prepared_key`]: ../cryptors/struct.RingCryptor.html#method.seal_with_prepared_key
+/// [`RingCryptor::open_with_prepared_key`]: ../cryptors/struct.RingCryptor.html#method.open_with_prepared_key
+pub struct PreparedKey {
+    enc_algo: policy::EncryptionKind,
+    cipher: PreparedCipher,
+    key_commitment: [u8; metadata::KEY_COMMITMENT_SIZE],
+    key_check: [u8; metadata::KEY_CHECK_SIZE],
+}
+
+impl fmt::Debug for PreparedKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // NOTE: Do not print the key, just its algorithm.
+        f.debug_struct("PreparedKey")
+            .field("enc_algo", &self.enc_algo)
+            .finish()
+    }
+}
+
+impl PreparedKey {
+    /// Prepare a symmetric key for an encryption algorithm.
+    ///
+    /// This method returns an error if the key size does not match the one
+    /// that the algorithm expects.
+    pub fn new(
+        enc_algo: policy::EncryptionKind,
+        key: &[u8],
+    ) -> Result<Self, errors::Error> {
+        if key.len() != metadata::DATA_KEY_SIZE {
+            return Err(errors::Error::KeySizeMismatch);
+        }
+
+        let ring_algo = match enc_algo {
+            policy::EncryptionKind::AES256GCM => {
+                Some(&ring::aead::AES_256_GCM)
+            }
+            policy::EncryptionKind::ChaCha20Poly1305 => {
+                Some(&ring::aead::CHACHA20_POLY1305)
+            }
+            _ => None,
+        };
+        let cipher = match ring_algo {
+            Some(algo) => {
+                let unbound_key = match ring::aead::UnboundKey::new(algo, key)
+                {
+                    Ok(unbound_key) => unbound_key,
+                    Err(_) => return Err(errors::Error::KeySizeMismatch),
+                };
+                PreparedCipher::Ring(Box::new(ring::aead::LessSafeKey::new(
+                    unbound_key,
+                )))
+            }
+            None => {
+                let mut raw_key =
+                    Zeroizing::new([0u8; metadata::DATA_KEY_SIZE]);
+                raw_key.copy_from_slice(key);
+                PreparedCipher::Raw(raw_key)
+            }
+        };
+
+        Ok(Self {
+            enc_algo,
+            cipher,
+            key_commitment: RingCryptor::_get_key_commitment(key),
+            key_check: RingCryptor::_get_key_check(key),
+        })
+    }
+
+    /// Get the encryption algorithm that the key has been prepared for.
+    pub fn enc_algo(&self) -> policy::EncryptionKind {
+        self.enc_algo
+    }
+
+    /// Check the values that verify the key in the metadata, if any.
+    ///
+    /// The values have been computed when the key was prepared, so they can
+    /// be checked without touching the key.
+    pub(crate) fn _verify(
+        &self,
+        meta: &metadata::Metadata,
+    ) -> Result<(), errors::Error> {
+        let ct_eq = |a: &[u8], b: &[u8]| {
+            ring::constant_time::verify_slices_are_equal(a, b).is_ok()
+        };
+        if let Some(key_commitment) = &meta.key_commitment {
+            if !ct_eq(key_commitment, &self.key_commitment) {
+                return Err(errors::Error::KeyCommitmentMismatch);
+            }
+        }
+        if let Some(key_check) = &meta.key_check {
+            if !ct_eq(key_check, &self.key_check) {
+                return Err(errors::Error::WrongKey);
+            }
+        }
+        Ok(())
+    }
+
+    /// Encrypt (seal) or decrypt (open) the data buffer in place.
+    ///
+    /// This method returns an error if the encryption algorithm is not the
+    /// one that the key has been prepared for.
+    pub(crate) fn _crypt_in_place(
+        &self,
+        seal: bool,
+        enc_algo: &metadata::EncryptionAlgorithm,
+        aad: &[u8],
+        buf: &mut [u8],
+    ) -> Result<usize, errors::Error> {
+        if policy::EncryptionKind::of(enc_algo) != self.enc_algo {
+            return Err(errors::Error::CryptoParamsUnsupported);
+        }
+
+        match (&self.cipher, enc_algo) {
+            (
+                PreparedCipher::Ring(key),
+                metadata::EncryptionAlgorithm::AES256GCM(meta),
+            )
+            | (
+                PreparedCipher::Ring(key),
+                metadata::EncryptionAlgorithm::ChaCha20Poly1305(meta),
+            ) => {
+                if seal {
+                    aead::seal_in_place_with_key(key, meta.nonce, aad, buf)
+                } else {
+                    aead::open_in_place_with_key(key, meta.nonce, aad, buf)
+                }
+            }
+            (
+                PreparedCipher::Raw(key),
+                metadata::EncryptionAlgorithm::XChaCha20Poly1305(meta),
+            ) => {
+                if seal {
+                    xchacha20::seal_in_place(meta.nonce, aad, &key[..], buf)
+                } else {
+                    xchacha20::open_in_place(meta.nonce, aad, &key[..], buf)
+                }
+            }
+            (
+                PreparedCipher::Raw(key),
+                metadata::EncryptionAlgorithm::AES256GCMSIV(meta),
+            ) => {
+                if seal {
+                    aes_gcm_siv::seal_in_place(meta.nonce, aad, &key[..], buf)
+                } else {
+                    aes_gcm_siv::open_in_place(meta.nonce, aad, &key[..], buf)
+                }
+            }
+            // NOTE: The cipher always matches the algorithm that the key has
+            // been prepared for, which we have checked above.
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(_to_hex(&[0x00, 0x1f, 0xff]), "001fff");
+        assert_eq!(_from_hex("001fFF"), Some(vec![0x00, 0x1f, 0xff]));
+        assert_eq!(_from_hex(""), Some(vec![]));
+        assert_eq!(_from_hex("001"), None);
+        assert_eq!(_from_hex("0g"), None);
+        assert_eq!(_from_hex("é0"), None);
+    }
+
+    #[test]
+    fn test_keyring() {
+        let mut keyring = Keyring::new();
+        keyring.add_key("key-1", &[1; 32]);
+        keyring.add_key("key-2", b"passphrase");
+        keyring.add_key("key-1", &[3; 32]);
+        assert_eq!(keyring.key_ids(), vec!["key-1", "key-2"]);
+        assert_eq!(
+            keyring.get_key("key-1"),
+            Some(Zeroizing::new(vec![3; 32]))
+        );
+        assert_eq!(
+            keyring.get_key("key-2"),
+            Some(Zeroizing::new(b"passphrase".to_vec()))
+        );
+        assert!(keyring.remove_key("key-1"));
+        assert!(!keyring.remove_key("key-1"));
+        assert_eq!(keyring.get_key("key-1"), None);
+        assert!(!format!("{:?}", keyring).contains("passphrase"));
+    }
+
+    #[test]
+    fn test_file_keystore() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let path = dir.path().join("keystore");
+
+        // Check that opening a missing keystore file fails.
+        let err = FileKeyStore::open(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        // Check that invalid key identifiers are rejected.
+        let mut keystore = FileKeyStore::create(&path);
+        for key_id in &["", "#key", "my key", "key\n"] {
+            let err = keystore.add_key(key_id, &[1; 32]).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        }
+
+        // Check that the keys are stored to and loaded from the file.
+        keystore.add_key("key-1", &[1; 32]).unwrap();
+        keystore.add_key("key-2", &[2; 16]).unwrap();
+        keystore.generate_key("key-3").unwrap();
+        assert!(keystore.remove_key("key-2"));
+        assert!(!keystore.remove_key("key-2"));
+        keystore.save().unwrap();
+
+        let keystore2 = FileKeyStore::open(&path).unwrap();
+        assert_eq!(keystore2.path(), path.as_path());
+        assert_eq!(keystore2.key_ids(), vec!["key-1", "key-3"]);
+        assert_eq!(
+            keystore2.get_key("key-1"),
+            Some(Zeroizing::new(vec![1; 32]))
+        );
+        assert_eq!(keystore2.get_key("key-2"), None);
+        assert_eq!(keystore2.get_key("key-3"), keystore.get_key("key-3"));
+        assert_eq!(keystore2.get_key("key-3").unwrap().len(), 32);
+
+        // Check that the keys are not printed.
+        let debug = format!("{:?}", keystore2);
+        assert!(debug.contains("key-1"));
+        assert!(!debug.contains(&_to_hex(&[1; 32])));
+
+        // Check that comments and empty lines are ignored, and that invalid
+        // lines are detected.
+        fs::write(&path, "# comment\n\n  key-1 0101  \n").unwrap();
+        let keystore = FileKeyStore::open(&path).unwrap();
+        assert_eq!(
+            keystore.get_key("key-1"),
+            Some(Zeroizing::new(vec![1, 1]))
+        );
+
+        for contents in
+            &["key-1\n", "key-1 01 02\n", "key-1 0g\n", "a 01\na 02"]
+        {
+            fs::write(&path, contents).unwrap();
+            let err = FileKeyStore::open(&path).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
@@ -1,10 +1,23 @@
 //! # Tindercrypt
 //!
 //! Tindercrypt is a library that supports data encryption with symmetric
-//! cryptographic keys or passwords/passphrases. It supports [AES256-GCM] and
-//! [ChaCha20-Poly1305] for encryption/decryption, and [PBKDF2] for key
+//! cryptographic keys or passwords/passphrases. It supports [AES256-GCM],
+//! [AES256-GCM-SIV], [ChaCha20-Poly1305] and [XChaCha20-Poly1305] for
+//! encryption/decryption, and [PBKDF2], [Argon2id] or [scrypt] for key
 //! derivation. These cryptographic primitives are provided by the [Ring]
-//! crypto library.
+//! crypto library, with the exception of AES256-GCM-SIV, XChaCha20-Poly1305,
+//! Argon2id and scrypt, which are provided by the [`aes-gcm-siv`],
+//! [`chacha20poly1305`], [`argon2`] and [`scrypt`] crates respectively.
+//! Finally, unique keys per message can be derived from a long-lived master
+//! key with [HKDF].
+//!
+//! Data can also be encrypted for a recipient, who is identified by an
+//! [X25519] public key. In this case, the key is derived with HKDF from a key
+//! agreement between an ephemeral key pair and the public key of the
+//! recipient, and only the holder of the respective private key can decrypt
+//! the data. See the [`x25519`] module for more info. Finally, the encrypted
+//! data can be signed with an [Ed25519] key, so that the recipients can
+//! verify who has encrypted them. See the [`ed25519`] module for more info.
 //!
 //! Tindercrypt's main goal is to provide a safe and easy API for data
 //! encryption. The user of this library simply chooses an encryption algorithm
@@ -18,10 +31,23 @@
 //! [`metadata`] module.
 //!
 //! [AES256-GCM]: https://en.wikipedia.org/wiki/Galois/Counter_Mode
+//! [AES256-GCM-SIV]: https://tools.ietf.org/html/rfc8452
 //! [ChaCha20-Poly1305]: https://tools.ietf.org/html/rfc7539
+//! [XChaCha20-Poly1305]: https://tools.ietf.org/html/draft-irtf-cfrg-xchacha
 //! [PBKDF2]: https://en.wikipedia.org/wiki/PBKDF2
+//! [Argon2id]: https://tools.ietf.org/html/rfc9106
+//! [scrypt]: https://tools.ietf.org/html/rfc7914
+//! [HKDF]: https://tools.ietf.org/html/rfc5869
 //! [Ring]: https://github.com/briansmith/ring
+//! [`aes-gcm-siv`]: https://github.com/RustCrypto/AEADs
+//! [`chacha20poly1305`]: https://github.com/RustCrypto/AEADs
+//! [`argon2`]: https://github.com/RustCrypto/password-hashes
+//! [`scrypt`]: https://github.com/RustCrypto/password-hashes
 //! [`metadata`]: metadata/index.html
+//! [X25519]: https://tools.ietf.org/html/rfc7748
+//! [`x25519`]: x25519/index.html
+//! [Ed25519]: https://tools.ietf.org/html/rfc8032
+//! [`ed25519`]: ed25519/index.html
 
 #![deny(
     warnings,
@@ -40,11 +66,38 @@
     variant_size_differences
 )]
 
+#[cfg(feature = "ring")]
 pub mod aead;
+pub mod aes_gcm_siv;
+pub mod argon2;
+#[cfg(all(feature = "ring", feature = "tokio"))]
+pub mod async_io;
+#[cfg(feature = "ring")]
+pub mod cache;
 pub mod cryptors;
+#[cfg(feature = "ring")]
+pub mod ed25519;
 pub mod errors;
+#[cfg(any(feature = "ring", feature = "rustcrypto"))]
+mod header;
+#[cfg(feature = "ring")]
+pub mod hkdf;
+#[cfg(feature = "ring")]
+pub mod io;
+#[cfg(feature = "ring")]
+pub mod keys;
 pub mod metadata;
+#[cfg(feature = "ring")]
 pub mod pbkdf2;
+pub mod policy;
 #[path = "../proto/mod.rs"]
 pub mod proto;
 pub mod rand;
+#[cfg(feature = "rustcrypto")]
+pub mod rustcrypto;
+pub mod scrypt;
+#[cfg(feature = "ring")]
+pub mod stream;
+#[cfg(feature = "ring")]
+pub mod x25519;
+pub mod xchacha20;
@@ -35,7 +35,8 @@ extern crate lazy_static;
 #[macro_use]
 extern crate clap;
 
-use tindercrypt::{cryptors, errors, metadata};
+use tindercrypt::io::{DecryptReader, EncryptWriter};
+use tindercrypt::{cryptors, errors, metadata, policy};
 
 #[cfg(target_family = "unix")]
 use std::os::unix::fs::OpenOptionsExt;
@@ -43,6 +44,8 @@ use std::os::unix::fs::OpenOptionsExt;
 const PASSPHRASE_ENVVAR: &'static str = "TINDERCRYPT_PASSPHRASE";
 const AES_ALGO: &'static str = "AES256-GCM";
 const CHACHA_ALGO: &'static str = "CHACHA20-POLY1305";
+const XCHACHA_ALGO: &'static str = "XCHACHA20-POLY1305";
+const AES_SIV_ALGO: &'static str = "AES256-GCM-SIV";
 
 lazy_static! {
     static ref AFTER_HELP: String = {
@@ -120,10 +123,10 @@ fn _validate_iterations(iter_arg: String) -> Result<(), String> {
     }
 }
 
-/// Read file contents into a buffer.
-fn _read_file(name: &str) -> Result<Vec<u8>, CLIError> {
-    match fs::read(name) {
-        Ok(buf) => Ok(buf),
+/// Open a file for reading.
+fn _open_file(name: &str) -> Result<fs::File, CLIError> {
+    match fs::File::open(name) {
+        Ok(file) => Ok(file),
         Err(io_error) => Err(CLIError::from_io_error(
             format!("Could not read file: {}", name),
             io_error,
@@ -131,24 +134,10 @@ fn _read_file(name: &str) -> Result<Vec<u8>, CLIError> {
     }
 }
 
-/// Read buffer from stdin.
-///
-/// Read buffer from stdin, until EOF.
-fn _read_stdin() -> Result<Vec<u8>, CLIError> {
-    let mut buf = Vec::new();
-    match io::stdin().read_to_end(&mut buf) {
-        Ok(_) => Ok(buf),
-        Err(io_error) => Err(CLIError::from_io_error(
-            "Could not read from stdin".to_string(),
-            io_error,
-        )),
-    }
-}
-
-/// Create a file and write a buffer to it.
+/// Create a file for writing.
 ///
 /// The file will be created with read-write rights by the owner only.
-fn _write_file(name: &str, buf: &[u8]) -> Result<(), CLIError> {
+fn _create_file(name: &str) -> Result<fs::File, CLIError> {
     // Construct the options necessary to create a file that is read-writable
     // by the owner only. Note that this concept does not apply to Windows [1],
     // so we protect it via a conditional compilation guard.
@@ -164,52 +153,54 @@ fn _write_file(name: &str, buf: &[u8]) -> Result<(), CLIError> {
     #[cfg(target_family = "unix")]
     let _ = open_opts.mode(0o600);
 
-    let mut file = match open_opts.open(name) {
-        Ok(f) => f,
-        Err(e) => {
-            return Err(CLIError::from_io_error(
-                format!("Could not create file: {}", name),
-                e,
-            ))
-        }
-    };
-
-    match file.write_all(buf) {
-        Ok(_) => Ok(()),
+    match open_opts.open(name) {
+        Ok(f) => Ok(f),
         Err(e) => Err(CLIError::from_io_error(
-            format!("Could not write to file: {}", name),
+            format!("Could not create file: {}", name),
             e,
         )),
     }
 }
 
-/// Write buffer to stdout.
-fn _write_stdout(buf: &[u8]) -> Result<(), CLIError> {
-    match io::stdout().write_all(buf) {
-        Ok(_) => Ok(()),
-        Err(e) => Err(CLIError::from_io_error(
-            "Could not write to stdout".to_string(),
-            e,
-        )),
+/// Open a file or stdin for reading.
+fn open_input(ifile: &Option<&str>) -> Result<Box<dyn Read>, CLIError> {
+    match ifile {
+        Some(name) => Ok(Box::new(_open_file(name)?)),
+        None => Ok(Box::new(io::stdin())),
     }
 }
 
-/// Read a buffer from a file or stdin.
-fn read_file_contents(ifile: &Option<&str>) -> Result<Vec<u8>, CLIError> {
-    match ifile {
-        Some(name) => _read_file(&name),
-        None => _read_stdin(),
+/// Open a file or stdout for writing.
+fn open_output(ofile: &Option<&str>) -> Result<Box<dyn Write>, CLIError> {
+    match ofile {
+        Some(name) => Ok(Box::new(_create_file(name)?)),
+        None => Ok(Box::new(io::stdout())),
     }
 }
 
-/// Write a buffer to a file or stdout.
-fn write_file_contents(
-    ofile: &Option<&str>,
-    buf: &[u8],
-) -> Result<(), CLIError> {
-    match ofile {
-        Some(name) => _write_file(name, buf),
-        None => _write_stdout(buf),
+/// Remove a partially written output file, if any.
+///
+/// If an error occurs while the output is written, we don't want to leave
+/// incomplete files around. Any error during the removal is ignored, since the
+/// original error is more important.
+fn remove_output(ofile: &Option<&str>) {
+    if let Some(name) = ofile {
+        let _ = fs::remove_file(name);
+    }
+}
+
+/// Convert an I/O error to a CLI error.
+///
+/// If the I/O error has been caused by the encryption/decryption process, the
+/// respective Tindercrypt error is extracted from it.
+fn _from_io_error(msg: &str, io_error: io::Error) -> CLIError {
+    let tc_error = io_error
+        .get_ref()
+        .and_then(|e| e.downcast_ref::<errors::Error>())
+        .cloned();
+    match tc_error {
+        Some(tc_error) => CLIError::from_tc_error(msg.to_string(), tc_error),
+        None => CLIError::from_io_error(msg.to_string(), io_error),
     }
 }
 
@@ -236,52 +227,73 @@ fn get_passphrase() -> Result<String, CLIError> {
     }
 }
 
-/// Encrypt plaintext with a passphrase, and return the ciphertext.
+/// Encrypt plaintext with a passphrase, and write the ciphertext.
+///
+/// The plaintext is read from the input and encrypted as a stream, so that
+/// large files do not have to fit in memory.
 fn _seal<'a>(
-    buf: &[u8],
+    input: &mut dyn Read,
+    output: &mut dyn Write,
     passphrase: &[u8],
     iterations: usize,
     algo: &'a str,
-) -> Result<Vec<u8>, CLIError> {
-    let cryptor = cryptors::RingCryptor::new();
-
-    // Generate the metadata for the PBKDF2 key derivation algorithm and
-    // explicitly set the number of iterations.
-    let mut key_meta = metadata::KeyDerivationMetadata::generate();
-    key_meta.iterations = iterations;
-    let key_algo = metadata::KeyDerivationAlgorithm::PBKDF2(key_meta);
+) -> Result<(), CLIError> {
+    let err_msg = "Unexpected error during encryption";
 
-    // Generate the metadata for the encryption algorithm of the user's choice.
-    let enc_meta = metadata::EncryptionMetadata::generate();
+    // Create a cryptor with the encryption algorithm of the user's choice and
+    // the requested number of PBKDF2 iterations.
     let enc_algo = match algo {
-        AES_ALGO => metadata::EncryptionAlgorithm::AES256GCM(enc_meta),
-        CHACHA_ALGO => {
-            metadata::EncryptionAlgorithm::ChaCha20Poly1305(enc_meta)
-        }
+        AES_ALGO => policy::EncryptionKind::AES256GCM,
+        CHACHA_ALGO => policy::EncryptionKind::ChaCha20Poly1305,
+        AES_SIV_ALGO => policy::EncryptionKind::AES256GCMSIV,
+        XCHACHA_ALGO => policy::EncryptionKind::XChaCha20Poly1305,
         _ => unreachable!(),
     };
+    let cryptor = match cryptors::RingCryptor::builder()
+        .enc_algo(enc_algo)
+        .iterations(iterations)
+        .build()
+    {
+        Ok(cryptor) => cryptor,
+        Err(e) => return Err(CLIError::from_tc_error(err_msg.to_string(), e)),
+    };
 
-    let meta = metadata::Metadata::new(key_algo, enc_algo, buf.len());
-
-    // Encrypt the plaintext with the created metadata.
-    match cryptor.seal_with_meta(&meta, passphrase, &buf) {
-        Ok(buf) => Ok(buf),
-        Err(tc_error) => Err(CLIError::from_tc_error(
-            "Unexpected error during encryption".to_string(),
-            tc_error,
-        )),
+    // Generate the metadata for a stream, with the algorithms of the cryptor.
+    let meta = metadata::Metadata::new_stream(
+        cryptor.generate_key_deriv_algo(),
+        cryptor.generate_enc_algo(),
+        metadata::STREAM_DEFAULT_CHUNK_SIZE,
+    );
+
+    // Encrypt the plaintext with the created metadata. Store a key check
+    // value as well, so that a wrong passphrase can be told apart from a
+    // corrupted ciphertext during decryption.
+    let res =
+        EncryptWriter::with_key_check(&cryptor, &meta, passphrase, output)
+            .and_then(|mut writer| {
+                let _ = io::copy(input, &mut writer)?;
+                writer.finish()
+            });
+    match res {
+        Ok(_) => Ok(()),
+        Err(io_error) => Err(_from_io_error(err_msg, io_error)),
     }
 }
 
-/// Decrypt ciphertext with a passphrase, and return the plaintext.
-fn _open(buf: &[u8], passphrase: &[u8]) -> Result<Vec<u8>, CLIError> {
+/// Decrypt ciphertext with a passphrase, and write the plaintext.
+fn _open(
+    input: &mut dyn Read,
+    output: &mut dyn Write,
+    passphrase: &[u8],
+) -> Result<(), CLIError> {
+    let err_msg = "Error during decryption";
     let cryptor = cryptors::RingCryptor::new();
-    match cryptor.open(passphrase, buf) {
-        Ok(buf) => Ok(buf),
-        Err(tc_error) => Err(CLIError::from_tc_error(
-            "Error during decryption".to_string(),
-            tc_error,
-        )),
+    let res = DecryptReader::new(&cryptor, passphrase, input)
+        .and_then(|mut reader| io::copy(&mut reader, output))
+        .and_then(|_| output.flush());
+    match res {
+        Ok(_) => Ok(()),
+        Err(io_error) => Err(_from_io_error(err_msg, io_error)),
     }
 }
 
@@ -293,25 +305,35 @@ fn encrypt<'a>(m: &ArgMatches<'a>) -> Result<(), CLIError> {
 
     let ifile = m.value_of("in_file");
     let ofile = m.value_of("out_file");
-    let contents = read_file_contents(&ifile)?;
+    let mut input = open_input(&ifile)?;
 
     let passphrase = get_passphrase()?;
-    let buf = _seal(&contents, passphrase.as_bytes(), iterations, algo)?;
-
-    let _ = write_file_contents(&ofile, &buf)?;
-    Ok(())
+    let mut output = open_output(&ofile)?;
+    let res = _seal(
+        &mut input,
+        &mut output,
+        passphrase.as_bytes(),
+        iterations,
+        algo,
+    );
+    if res.is_err() {
+        remove_output(&ofile);
+    }
+    res
 }
 
 fn decrypt<'a>(m: &ArgMatches<'a>) -> Result<(), CLIError> {
     let ifile = m.value_of("in_file");
     let ofile = m.value_of("out_file");
-    let contents = read_file_contents(&ifile)?;
+    let mut input = open_input(&ifile)?;
 
     let passphrase = get_passphrase()?;
-    let buf = _open(&contents, passphrase.as_bytes())?;
-
-    let _ = write_file_contents(&ofile, &buf)?;
-    Ok(())
+    let mut output = open_output(&ofile)?;
+    let res = _open(&mut input, &mut output, passphrase.as_bytes());
+    if res.is_err() {
+        remove_output(&ofile);
+    }
+    res
 }
 
 fn create_encrypt_parser<'a, 'b>() -> App<'a, 'b> {
@@ -356,7 +378,12 @@ fn create_encrypt_parser<'a, 'b>() -> App<'a, 'b> {
                 .short("e")
                 .long("encryption-algorithm")
                 .takes_value(true)
-                .possible_values(&[AES_ALGO, CHACHA_ALGO])
+                .possible_values(&[
+                    AES_ALGO,
+                    CHACHA_ALGO,
+                    XCHACHA_ALGO,
+                    AES_SIV_ALGO,
+                ])
                 .default_value(AES_ALGO)
                 .help("The algorithm that will be used for the encryption"),
         )
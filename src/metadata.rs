@@ -11,7 +11,10 @@
 //!   create a key with a specific size. For instance, PBKDF2 requires a salt,
 //!   a hash function and a number of iterations. The available key derivation
 //!   algorithms and their associated metadata are covered in
-//!   [`KeyDerivationAlgorithm`]
+//!   [`KeyDerivationAlgorithm`]. Data can also be encrypted for the X25519
+//!   public key of a recipient, in which case the key is derived from a key
+//!   agreement, and the ephemeral public key of the sender is stored in the
+//!   metadata.
 //! * **Encryption metadata:** The encryption algorithms that Tindercrypt
 //!   currently supports require a unique nonce and optionally supports
 //!   [associated data]. The available algorithms and their metadata are
@@ -50,9 +53,11 @@
 //!
 //! In a nutshell, the serialization process is the following:
 //!
+//! * Write the [`MAGIC`] bytes and the [`FORMAT_VERSION`] to a `Vec<u8>`
+//!   buffer, so that the encrypted data can be easily identified.
 //! * Convert a [`Metadata`] struct to a [`proto::metadata::Metadata`] message.
-//! * Serialize the [`proto::metadata::Metadata`] message to a `Vec<u8>`
-//!   buffer.
+//! * Serialize the [`proto::metadata::Metadata`] message, prefixed by its
+//!   length, to the buffer.
 //! * Extend the buffer to also hold the ciphertext.
 //! * Return the created buffer and the size of the serialized metadata. The
 //!   latter can be used to know where the ciphertext section begins within the
@@ -61,6 +66,11 @@
 //! The deserialization process is a bit more involved, as we must also check
 //! the integrity of the metadata:
 //!
+//! * Check that the buffer starts with the [`MAGIC`] bytes, and that the
+//!   format version that follows is supported. Failure to do so means that
+//!   the buffer has been created by a newer version of this library. Buffers
+//!   created by older versions of this library, which do not start with the
+//!   [`MAGIC`] bytes, are treated as legacy headers.
 //! * Deserialize the buffer header into a [`proto::metadata::Metadata`]
 //!   message. Failure to do so means that the buffer does not contain such a
 //!   header, or that the header is corrupted.
@@ -70,9 +80,23 @@
 //! * Return the created [`Metadata`] struct and the size of the serialized
 //!   metadata.
 //!
+//! ### Header authentication
+//!
+//! By default, the serialized metadata are also mixed into the associated data
+//! of the encryption (see [`Metadata::authenticated_header`]). This way, an
+//! attacker cannot tamper with the metadata header, e.g., change the
+//! ciphertext size or append fields, without making the decryption fail.
+//! Since the cryptors authenticate the metadata by serializing them again,
+//! authenticated headers that contain fields unknown to this library are
+//! rejected as invalid. Headers created by older versions of this library are
+//! not authenticated, but can still be decrypted.
+//!
+//! [`MAGIC`]: constant.MAGIC.html
+//! [`FORMAT_VERSION`]: constant.FORMAT_VERSION.html
 //! [`KeyDerivationAlgorithm`]: enum.KeyDerivationAlgorithm.html
 //! [`EncryptionAlgorithm`]: enum.EncryptionAlgorithm.html
 //! [`Metadata`]: struct.Metadata.html
+//! [`Metadata::authenticated_header`]: struct.Metadata.html#structfield.authenticated_header
 //! [Protocol Buffers]: https://developers.google.com/protocol-buffers/
 //! [`rust-protobuf`]: https://github.com/stepancheg/rust-protobuf
 //! [`proto::metadata`]: ../proto/metadata/index.html
@@ -81,8 +105,10 @@
 //! [associated data]: https://en.wikipedia.org/wiki/Authenticated_encryption
 
 use crate::proto::metadata as pmeta;
-use crate::{errors, rand};
+use crate::{aes_gcm_siv, errors, rand, xchacha20};
 use protobuf::Message;
+use std::cmp;
+use std::convert::TryFrom;
 
 /// The size of the nonces for the encryption algorithms provided by Ring.
 ///
@@ -94,12 +120,41 @@ use protobuf::Message;
 ///          _A length of 12 octets is RECOMMENDED._
 pub const RING_NONCE_SIZE: usize = 12;
 
-/// The size of the salt values for the PBKDF2 key derivation algorithm.
+/// The size of the authentication tags for the encryption algorithms provided
+/// by Ring.
+///
+/// Both AES-256-GCM and ChaCha20-Poly1305 produce 16-byte tags.
+pub const RING_TAG_SIZE: usize = 16;
+
+/// The size of the nonces for the XChaCha20-Poly1305 encryption algorithm.
+///
+/// XChaCha20-Poly1305 uses 24-byte nonces, which are large enough to be
+/// generated randomly for a practically unlimited number of messages, without
+/// the risk of a collision.
+pub const XCHACHA20_NONCE_SIZE: usize = 24;
+
+/// The default size of the salt values for the PBKDF2 key derivation
+/// algorithm.
 ///
-/// We use a constant size of 32 bytes for the salt values, because the
-/// general recommendation is that salts should be globally unique.
+/// We use a size of 32 bytes for the salt values, because the general
+/// recommendation is that salts should be globally unique.
 pub const PBKDF2_SALT_SIZE: usize = 32;
 
+/// The minimum size of the salt values for the PBKDF2, Argon2id and scrypt
+/// key derivation algorithms.
+///
+/// Smaller salts are rejected, since NIST recommends salts of at least 128
+/// bits for password-based key derivation ([SP 800-132]).
+///
+/// [SP 800-132]: https://csrc.nist.gov/publications/detail/sp/800-132/final
+pub const MIN_SALT_SIZE: usize = 16;
+
+/// The maximum size of the salt values for the PBKDF2, Argon2id and scrypt
+/// key derivation algorithms.
+///
+/// Larger salts don't offer better security, and Argon2 does not accept them.
+pub const MAX_SALT_SIZE: usize = 64;
+
 /// The default number of iterations for the PBKDF2 key derivation algorithm.
 ///
 /// We use a constant number of 100,000 iterations. As of 2019, this number
@@ -132,6 +187,140 @@ pub const PBKDF2_DEFAULT_ITERATIONS: usize = 100000;
 /// [^pbkdf2-design-flaw]: https://www.chosenplaintext.ca/2015/10/08/pbkdf2-design-flaw.html
 pub const PBKDF2_DEFAULT_HASH_FN: HashFunction = HashFunction::SHA256;
 
+/// The default size of the salt values for the Argon2id key derivation
+/// algorithm.
+///
+/// We use the same salt size as in PBKDF2, for the same reasons.
+pub const ARGON2_SALT_SIZE: usize = 32;
+
+/// The default memory cost (in KiB) for the Argon2id key derivation
+/// algorithm.
+///
+/// We use 64 MiB of memory, which is the second recommended option of [RFC
+/// 9106], for environments that cannot afford 2 GiB of memory per key
+/// derivation. Being memory-hard, Argon2id with these parameters is
+/// significantly more expensive to attack with GPUs or ASICs than PBKDF2.
+///
+/// [RFC 9106]: https://tools.ietf.org/html/rfc9106#section-4
+pub const ARGON2_DEFAULT_MEMORY_COST: u32 = 65536;
+
+/// The default time cost (number of passes) for the Argon2id key derivation
+/// algorithm.
+///
+/// We use 3 passes over the memory, as recommended by [RFC 9106] for a memory
+/// cost of 64 MiB.
+///
+/// [RFC 9106]: https://tools.ietf.org/html/rfc9106#section-4
+pub const ARGON2_DEFAULT_TIME_COST: u32 = 3;
+
+/// The default degree of parallelism (number of lanes) for the Argon2id key
+/// derivation algorithm.
+///
+/// We use 4 lanes, as recommended by [RFC 9106].
+///
+/// [RFC 9106]: https://tools.ietf.org/html/rfc9106#section-4
+pub const ARGON2_DEFAULT_PARALLELISM: u32 = 4;
+
+/// The default size of the salt values for the scrypt key derivation
+/// algorithm.
+///
+/// We use the same salt size as in PBKDF2, for the same reasons.
+pub const SCRYPT_SALT_SIZE: usize = 32;
+
+/// The default base-2 logarithm of the CPU/memory cost (`N`) for the scrypt
+/// key derivation algorithm.
+///
+/// We use `N = 2^17`, which, combined with the default block size, requires
+/// 128 MiB of memory. This is the minimum configuration that the [OWASP]
+/// recommends for scrypt.
+///
+/// [OWASP]: https://cheatsheetseries.owasp.org/cheatsheets/Password_Storage_Cheat_Sheet.html#scrypt
+pub const SCRYPT_DEFAULT_LOG_N: u8 = 17;
+
+/// The default block size (`r`) for the scrypt key derivation algorithm.
+///
+/// We use a block size of 8, which is the value that the scrypt paper
+/// suggests and most implementations use.
+pub const SCRYPT_DEFAULT_BLOCK_SIZE: u32 = 8;
+
+/// The default degree of parallelism (`p`) for the scrypt key derivation
+/// algorithm.
+pub const SCRYPT_DEFAULT_PARALLELISM: u32 = 1;
+
+/// The size of the salt values for the HKDF key derivation algorithm.
+///
+/// We use the same salt size as in PBKDF2. Since the master key of HKDF is
+/// used for many messages, a random salt of this size makes sure that each
+/// message is encrypted with a unique key.
+pub const HKDF_SALT_SIZE: usize = 32;
+
+/// The default hash function for the HKDF key derivation algorithm.
+///
+/// We use the SHA-256 hash function, for the same reasons as in PBKDF2.
+pub const HKDF_DEFAULT_HASH_FN: HashFunction = HashFunction::SHA256;
+
+/// The size of the X25519 public keys.
+pub const X25519_KEY_SIZE: usize = 32;
+
+/// The size of the Ed25519 public keys.
+pub const ED25519_KEY_SIZE: usize = 32;
+
+/// The size of the Ed25519 signatures.
+pub const ED25519_SIGNATURE_SIZE: usize = 64;
+
+/// The size of the key commitment values.
+///
+/// The key commitment is an HMAC-SHA256 value, so it has the size of a
+/// SHA-256 digest.
+pub const KEY_COMMITMENT_SIZE: usize = 32;
+
+/// The size of the key check values.
+///
+/// The key check value is a truncated HMAC-SHA256 value. Unlike the key
+/// commitment, it's meant to catch honest mistakes, such as a mistyped
+/// passphrase, and not attacks, so 8 bytes are more than enough.
+pub const KEY_CHECK_SIZE: usize = 8;
+
+/// The default size of the plaintext chunks, when encrypting data as a stream.
+///
+/// We use chunks of 64 KiB, which is large enough to make the overhead of the
+/// per-chunk tags negligible (less than 0.03%), and small enough to keep the
+/// memory usage of the streaming cryptors low.
+pub const STREAM_DEFAULT_CHUNK_SIZE: usize = 65536;
+
+/// The maximum size of the plaintext chunks, when encrypting data as a
+/// stream.
+///
+/// Readers of a stream must allocate a buffer as large as a chunk, so we
+/// reject larger chunk sizes, which may come from untrusted metadata headers.
+pub const STREAM_MAX_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+
+/// The magic bytes at the start of every serialized metadata header.
+///
+/// They allow tools to tell apart the data that have been encrypted by this
+/// library, without parsing the rest of the header.
+pub const MAGIC: [u8; 4] = *b"TCRY";
+
+/// The current version of the wire format.
+///
+/// It follows the [`MAGIC`] bytes, and must be bumped whenever the wire
+/// format changes in a backwards-incompatible way.
+///
+/// [`MAGIC`]: constant.MAGIC.html
+pub const FORMAT_VERSION: u8 = 1;
+
+/// The size of the [`MAGIC`] bytes and the [`FORMAT_VERSION`] that precede
+/// the serialized metadata.
+///
+/// [`MAGIC`]: constant.MAGIC.html
+/// [`FORMAT_VERSION`]: constant.FORMAT_VERSION.html
+pub const HEADER_PREFIX_SIZE: usize = 5;
+
+/// The size of the data keys that are wrapped in key slots.
+///
+/// All the encryption algorithms that this library supports use 256-bit keys.
+pub const DATA_KEY_SIZE: usize = 32;
+
 /// The hash functions that this library supports.
 ///
 /// Currently, these hash functions dictate the HMAC function that PBKDF2 will
@@ -201,7 +390,7 @@ impl HashFunction {
 /// assert_eq!(key_meta1.iterations, 100000);
 /// assert_ne!(key_meta1.salt, key_meta2.salt);
 /// ```
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct KeyDerivationMetadata {
     /// The hash function that is used as the basis for the computational work.
     pub hash_fn: HashFunction,
@@ -209,7 +398,7 @@ pub struct KeyDerivationMetadata {
     pub iterations: usize,
     /// A unique value that is used to create different keys from the same
     /// passphrase.
-    pub salt: [u8; PBKDF2_SALT_SIZE],
+    pub salt: Vec<u8>,
 }
 
 impl KeyDerivationMetadata {
@@ -217,15 +406,11 @@ impl KeyDerivationMetadata {
     ///
     /// This method should be used only when the user wants to explicitly set
     /// a specific value. Else, it's better to use `::generate()`.
-    pub fn new(
-        hash_fn: HashFunction,
-        iterations: usize,
-        salt: [u8; PBKDF2_SALT_SIZE],
-    ) -> Self {
+    pub fn new(hash_fn: HashFunction, iterations: usize, salt: &[u8]) -> Self {
         Self {
             hash_fn,
             iterations,
-            salt,
+            salt: salt.to_vec(),
         }
     }
 
@@ -233,7 +418,211 @@ impl KeyDerivationMetadata {
     pub fn generate() -> Self {
         let mut salt = [0u8; PBKDF2_SALT_SIZE];
         rand::fill_buf(&mut salt);
-        Self::new(PBKDF2_DEFAULT_HASH_FN, PBKDF2_DEFAULT_ITERATIONS, salt)
+        Self::new(PBKDF2_DEFAULT_HASH_FN, PBKDF2_DEFAULT_ITERATIONS, &salt)
+    }
+}
+
+/// The metadata that can be used for the Argon2id key derivation process.
+///
+/// # Examples
+///
+/// ```
+/// use tindercrypt::metadata::Argon2idMetadata;
+///
+/// // Generate a struct instance for the Argon2id metadata. The default is to
+/// // use 64 MiB of memory, 3 passes, 4 lanes and a unique salt.
+/// let argon2_meta1 = Argon2idMetadata::generate();
+/// assert_eq!(argon2_meta1.memory_cost, 65536);
+/// assert_eq!(argon2_meta1.time_cost, 3);
+/// assert_eq!(argon2_meta1.parallelism, 4);
+///
+/// // Generate a second struct instance. The salt should be unique.
+/// let argon2_meta2 = Argon2idMetadata::generate();
+/// assert_ne!(argon2_meta1.salt, argon2_meta2.salt);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Argon2idMetadata {
+    /// The amount of memory (in KiB) that will be used.
+    pub memory_cost: u32,
+    /// The number of passes over the memory.
+    pub time_cost: u32,
+    /// The number of lanes that can be computed in parallel.
+    pub parallelism: u32,
+    /// A unique value that is used to create different keys from the same
+    /// passphrase.
+    pub salt: Vec<u8>,
+}
+
+impl Argon2idMetadata {
+    /// Create the Argon2id metadata from user-provided values.
+    ///
+    /// This method should be used only when the user wants to explicitly set
+    /// a specific value. Else, it's better to use `::generate()`.
+    pub fn new(
+        memory_cost: u32,
+        time_cost: u32,
+        parallelism: u32,
+        salt: &[u8],
+    ) -> Self {
+        Self {
+            memory_cost,
+            time_cost,
+            parallelism,
+            salt: salt.to_vec(),
+        }
+    }
+
+    /// Generate Argon2id metadata.
+    pub fn generate() -> Self {
+        let mut salt = [0u8; ARGON2_SALT_SIZE];
+        rand::fill_buf(&mut salt);
+        Self::new(
+            ARGON2_DEFAULT_MEMORY_COST,
+            ARGON2_DEFAULT_TIME_COST,
+            ARGON2_DEFAULT_PARALLELISM,
+            &salt,
+        )
+    }
+}
+
+/// The metadata that can be used for the scrypt key derivation process.
+///
+/// # Examples
+///
+/// ```
+/// use tindercrypt::metadata::ScryptMetadata;
+///
+/// // Generate a struct instance for the scrypt metadata. The default is to
+/// // use `N = 2^17`, `r = 8`, `p = 1` and a unique salt.
+/// let scrypt_meta1 = ScryptMetadata::generate();
+/// assert_eq!(scrypt_meta1.log_n, 17);
+/// assert_eq!(scrypt_meta1.block_size, 8);
+/// assert_eq!(scrypt_meta1.parallelism, 1);
+///
+/// // Generate a second struct instance. The salt should be unique.
+/// let scrypt_meta2 = ScryptMetadata::generate();
+/// assert_ne!(scrypt_meta1.salt, scrypt_meta2.salt);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScryptMetadata {
+    /// The base-2 logarithm of the CPU/memory cost (`N`).
+    pub log_n: u8,
+    /// The block size (`r`).
+    pub block_size: u32,
+    /// The degree of parallelism (`p`).
+    pub parallelism: u32,
+    /// A unique value that is used to create different keys from the same
+    /// passphrase.
+    pub salt: Vec<u8>,
+}
+
+impl ScryptMetadata {
+    /// Create the scrypt metadata from user-provided values.
+    ///
+    /// This method should be used only when the user wants to explicitly set
+    /// a specific value. Else, it's better to use `::generate()`.
+    pub fn new(
+        log_n: u8,
+        block_size: u32,
+        parallelism: u32,
+        salt: &[u8],
+    ) -> Self {
+        Self {
+            log_n,
+            block_size,
+            parallelism,
+            salt: salt.to_vec(),
+        }
+    }
+
+    /// Generate scrypt metadata.
+    pub fn generate() -> Self {
+        let mut salt = [0u8; SCRYPT_SALT_SIZE];
+        rand::fill_buf(&mut salt);
+        Self::new(
+            SCRYPT_DEFAULT_LOG_N,
+            SCRYPT_DEFAULT_BLOCK_SIZE,
+            SCRYPT_DEFAULT_PARALLELISM,
+            &salt,
+        )
+    }
+}
+
+/// The metadata that can be used for the HKDF key derivation process.
+///
+/// # Examples
+///
+/// ```
+/// use tindercrypt::metadata::{HashFunction, HkdfMetadata};
+///
+/// // Generate a struct instance for the HKDF metadata of a context. The
+/// // default is to choose an HMAC function based on SHA-256 and a unique
+/// // salt.
+/// let hkdf_meta1 = HkdfMetadata::generate(b"billing");
+/// assert_eq!(hkdf_meta1.hash_fn, HashFunction::SHA256);
+/// assert_eq!(hkdf_meta1.info, b"billing");
+///
+/// // Generate a second struct instance. The salt should be unique.
+/// let hkdf_meta2 = HkdfMetadata::generate(b"billing");
+/// assert_ne!(hkdf_meta1.salt, hkdf_meta2.salt);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct HkdfMetadata {
+    /// The hash function that the HMAC function of HKDF is based on.
+    pub hash_fn: HashFunction,
+    /// A unique value that is used to create different keys from the same
+    /// master key.
+    pub salt: [u8; HKDF_SALT_SIZE],
+    /// The context string, which binds the derived key to a specific usage.
+    ///
+    /// Different subsystems that share the same master key should use
+    /// different context strings, so that their keys are independent.
+    pub info: Vec<u8>,
+}
+
+impl HkdfMetadata {
+    /// Create the HKDF metadata from user-provided values.
+    ///
+    /// This method should be used only when the user wants to explicitly set
+    /// a specific value. Else, it's better to use `::generate()`.
+    pub fn new(
+        hash_fn: HashFunction,
+        salt: [u8; HKDF_SALT_SIZE],
+        info: &[u8],
+    ) -> Self {
+        Self {
+            hash_fn,
+            salt,
+            info: info.to_vec(),
+        }
+    }
+
+    /// Generate HKDF metadata for a context string.
+    pub fn generate(info: &[u8]) -> Self {
+        let mut salt = [0u8; HKDF_SALT_SIZE];
+        rand::fill_buf(&mut salt);
+        Self::new(HKDF_DEFAULT_HASH_FN, salt, info)
+    }
+}
+
+/// The metadata that can be used for the X25519 key agreement.
+///
+/// Unlike the other key derivation metadata, there is no `::generate()`
+/// constructor, since the ephemeral public key must be created along with
+/// the key agreement. The cryptor's `.seal_for_recipient()` method takes care
+/// of this.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct X25519Metadata {
+    /// The ephemeral public key of the sender.
+    pub ephemeral_public_key: [u8; X25519_KEY_SIZE],
+}
+
+impl X25519Metadata {
+    /// Create the X25519 metadata from user-provided values.
+    pub fn new(ephemeral_public_key: [u8; X25519_KEY_SIZE]) -> Self {
+        Self {
+            ephemeral_public_key,
+        }
     }
 }
 
@@ -242,21 +631,45 @@ impl KeyDerivationMetadata {
 /// ## Examples
 ///
 /// ```
-/// use tindercrypt::metadata::{KeyDerivationMetadata, KeyDerivationAlgorithm};
+/// use tindercrypt::metadata::{
+///     Argon2idMetadata, HkdfMetadata, KeyDerivationAlgorithm,
+///     KeyDerivationMetadata, ScryptMetadata,
+/// };
 ///
 /// // Create a PBKDF2 key derivation algorithm.
 /// let key_meta = KeyDerivationMetadata::generate();
 /// let key_algo_pbkdf2 = KeyDerivationAlgorithm::PBKDF2(key_meta);
 ///
+/// // Create an Argon2id key derivation algorithm.
+/// let argon2_meta = Argon2idMetadata::generate();
+/// let key_algo_argon2id = KeyDerivationAlgorithm::Argon2id(argon2_meta);
+///
+/// // Create an scrypt key derivation algorithm.
+/// let scrypt_meta = ScryptMetadata::generate();
+/// let key_algo_scrypt = KeyDerivationAlgorithm::Scrypt(scrypt_meta);
+///
+/// // Create an HKDF key derivation algorithm, for a master key.
+/// let hkdf_meta = HkdfMetadata::generate(b"billing");
+/// let key_algo_hkdf = KeyDerivationAlgorithm::HKDF(hkdf_meta);
+///
 /// // Create a no-op key derivation algorithm.
 /// let key_algo_none = KeyDerivationAlgorithm::None;
 /// ```
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum KeyDerivationAlgorithm {
     /// No key derivation.
     None,
     /// Derive a key using the PBKDF2 algorithm.
     PBKDF2(KeyDerivationMetadata),
+    /// Derive a key using the Argon2id algorithm.
+    Argon2id(Argon2idMetadata),
+    /// Derive a key using the scrypt algorithm.
+    Scrypt(ScryptMetadata),
+    /// Derive a key from an X25519 key agreement with a recipient, followed
+    /// by HKDF-SHA256.
+    X25519(X25519Metadata),
+    /// Derive a key from a master key using the HKDF algorithm.
+    HKDF(HkdfMetadata),
 }
 
 impl KeyDerivationAlgorithm {
@@ -282,28 +695,156 @@ impl KeyDerivationAlgorithm {
             pmeta::KeyDerivationAlgorithm::KEY_DERIVATION_ALGORITHM_PBKDF2 => {
                 ()
             },
+            pmeta::KeyDerivationAlgorithm::KEY_DERIVATION_ALGORITHM_ARGON2ID => {
+                return Self::_argon2id_from_proto(proto_meta);
+            },
+            pmeta::KeyDerivationAlgorithm::KEY_DERIVATION_ALGORITHM_SCRYPT => {
+                return Self::_scrypt_from_proto(proto_meta);
+            },
+            pmeta::KeyDerivationAlgorithm::KEY_DERIVATION_ALGORITHM_X25519 => {
+                return Self::_x25519_from_proto(proto_meta);
+            },
+            pmeta::KeyDerivationAlgorithm::KEY_DERIVATION_ALGORITHM_HKDF => {
+                return Self::_hkdf_from_proto(proto_meta);
+            },
         }
 
-        // Check if the number of iterations is larger than 0.
-        if proto_meta.iterations == 0 {
+        // Check if the number of iterations is larger than 0, and if it fits
+        // in the 32-bit integer that PBKDF2 expects.
+        let iterations = match u32::try_from(proto_meta.iterations) {
+            Ok(iterations) if iterations > 0 => iterations as usize,
+            _ => return err,
+        };
+
+        // Convert the hash function to the expected enum.
+        let hash_fn = HashFunction::from_proto(proto_meta.hash_fn)?;
+
+        // Check that the salt size is within the accepted range.
+        if !Self::_is_salt_size_valid(proto_meta.salt.len()) {
             return err;
         }
-        let iterations = proto_meta.iterations as usize;
 
+        // Create the metadata object from the parsed values.
+        let meta =
+            KeyDerivationMetadata::new(hash_fn, iterations, &proto_meta.salt);
+        Ok(KeyDerivationAlgorithm::PBKDF2(meta))
+    }
+
+    /// Check if the size of a salt for a passphrase is within the accepted
+    /// range.
+    fn _is_salt_size_valid(salt_size: usize) -> bool {
+        (MIN_SALT_SIZE..=MAX_SALT_SIZE).contains(&salt_size)
+    }
+
+    /// Create an Argon2id key derivation algorithm from the respective
+    /// protobuf-generated metadata.
+    fn _argon2id_from_proto(
+        proto_meta: &pmeta::KeyDerivationMetadata,
+    ) -> Result<Self, errors::Error> {
+        let err = Err(errors::Error::MetadataInvalid);
+
+        // Check that the cost parameters are within the limits of the Argon2
+        // specification, i.e., at least one pass and one lane, and at least 8
+        // KiB of memory per lane.
+        if proto_meta.time_cost == 0
+            || proto_meta.parallelism == 0
+            || proto_meta.memory_cost / 8 < proto_meta.parallelism
+        {
+            return err;
+        }
+
+        // Check that the salt size is within the accepted range.
+        if !Self::_is_salt_size_valid(proto_meta.salt.len()) {
+            return err;
+        }
+
+        // Create the metadata object from the parsed values.
+        let meta = Argon2idMetadata::new(
+            proto_meta.memory_cost,
+            proto_meta.time_cost,
+            proto_meta.parallelism,
+            &proto_meta.salt,
+        );
+        Ok(KeyDerivationAlgorithm::Argon2id(meta))
+    }
+
+    /// Create an scrypt key derivation algorithm from the respective
+    /// protobuf-generated metadata.
+    fn _scrypt_from_proto(
+        proto_meta: &pmeta::KeyDerivationMetadata,
+    ) -> Result<Self, errors::Error> {
+        let err = Err(errors::Error::MetadataInvalid);
+        let log_n = proto_meta.log_n as u64;
+        let block_size = proto_meta.block_size as u64;
+        let parallelism = proto_meta.parallelism as u64;
+
+        // Check that the cost parameters are within the limits of the scrypt
+        // specification (RFC 7914), i.e.:
+        //
+        // * The CPU/memory cost must be larger than 1 and less than
+        //   `2^(128 * r / 8)`.
+        // * The block size and degree of parallelism must be positive, and
+        //   their product must be less than `2^30`.
+        if log_n == 0
+            || log_n >= 64
+            || block_size == 0
+            || parallelism == 0
+            || log_n >= 16 * block_size
+            || block_size * parallelism >= 1 << 30
+        {
+            return err;
+        }
+
+        // Check that the salt size is within the accepted range.
+        if !Self::_is_salt_size_valid(proto_meta.salt.len()) {
+            return err;
+        }
+
+        // Create the metadata object from the parsed values.
+        let meta = ScryptMetadata::new(
+            proto_meta.log_n as u8,
+            proto_meta.block_size,
+            proto_meta.parallelism,
+            &proto_meta.salt,
+        );
+        Ok(KeyDerivationAlgorithm::Scrypt(meta))
+    }
+
+    /// Create an X25519 key derivation algorithm from the respective
+    /// protobuf-generated metadata.
+    fn _x25519_from_proto(
+        proto_meta: &pmeta::KeyDerivationMetadata,
+    ) -> Result<Self, errors::Error> {
+        // Copy the ephemeral public key to a fixed-size array. If the size is
+        // not the expected one, return an error.
+        if proto_meta.ephemeral_public_key.len() != X25519_KEY_SIZE {
+            return Err(errors::Error::MetadataInvalid);
+        }
+        let mut ephemeral_public_key = [0u8; X25519_KEY_SIZE];
+        ephemeral_public_key.copy_from_slice(&proto_meta.ephemeral_public_key);
+
+        let meta = X25519Metadata::new(ephemeral_public_key);
+        Ok(KeyDerivationAlgorithm::X25519(meta))
+    }
+
+    /// Create an HKDF key derivation algorithm from the respective
+    /// protobuf-generated metadata.
+    fn _hkdf_from_proto(
+        proto_meta: &pmeta::KeyDerivationMetadata,
+    ) -> Result<Self, errors::Error> {
         // Convert the hash function to the expected enum.
         let hash_fn = HashFunction::from_proto(proto_meta.hash_fn)?;
 
         // Copy the salt to a fixed-size array. If the size is not the expected
         // one, return an error.
-        if proto_meta.salt.len() != PBKDF2_SALT_SIZE {
-            return err;
+        if proto_meta.salt.len() != HKDF_SALT_SIZE {
+            return Err(errors::Error::MetadataInvalid);
         }
-        let mut salt = [0u8; PBKDF2_SALT_SIZE];
+        let mut salt = [0u8; HKDF_SALT_SIZE];
         salt.copy_from_slice(&proto_meta.salt);
 
-        // Create the metadata object from the parsed values.
-        let meta = KeyDerivationMetadata::new(hash_fn, iterations, salt);
-        Ok(KeyDerivationAlgorithm::PBKDF2(meta))
+        let meta = HkdfMetadata::new(hash_fn, salt, &proto_meta.info);
+        Ok(KeyDerivationAlgorithm::HKDF(meta))
     }
 
     /// Convert a key derivation algorithm to the respective protobuf-generated
@@ -314,6 +855,14 @@ impl KeyDerivationAlgorithm {
             pmeta::KeyDerivationAlgorithm::KEY_DERIVATION_ALGORITHM_NONE;
         let proto_pbkdf2_algo =
             pmeta::KeyDerivationAlgorithm::KEY_DERIVATION_ALGORITHM_PBKDF2;
+        let proto_argon2id_algo =
+            pmeta::KeyDerivationAlgorithm::KEY_DERIVATION_ALGORITHM_ARGON2ID;
+        let proto_scrypt_algo =
+            pmeta::KeyDerivationAlgorithm::KEY_DERIVATION_ALGORITHM_SCRYPT;
+        let proto_x25519_algo =
+            pmeta::KeyDerivationAlgorithm::KEY_DERIVATION_ALGORITHM_X25519;
+        let proto_hkdf_algo =
+            pmeta::KeyDerivationAlgorithm::KEY_DERIVATION_ALGORITHM_HKDF;
 
         let meta = match self {
             KeyDerivationAlgorithm::None => {
@@ -321,6 +870,35 @@ impl KeyDerivationAlgorithm {
                 return proto_meta;
             }
             KeyDerivationAlgorithm::PBKDF2(meta) => meta,
+            KeyDerivationAlgorithm::Argon2id(meta) => {
+                proto_meta.algo = proto_argon2id_algo;
+                proto_meta.memory_cost = meta.memory_cost;
+                proto_meta.time_cost = meta.time_cost;
+                proto_meta.parallelism = meta.parallelism;
+                proto_meta.salt = meta.salt.to_vec();
+                return proto_meta;
+            }
+            KeyDerivationAlgorithm::Scrypt(meta) => {
+                proto_meta.algo = proto_scrypt_algo;
+                proto_meta.log_n = meta.log_n as u32;
+                proto_meta.block_size = meta.block_size;
+                proto_meta.parallelism = meta.parallelism;
+                proto_meta.salt = meta.salt.to_vec();
+                return proto_meta;
+            }
+            KeyDerivationAlgorithm::X25519(meta) => {
+                proto_meta.algo = proto_x25519_algo;
+                proto_meta.ephemeral_public_key =
+                    meta.ephemeral_public_key.to_vec();
+                return proto_meta;
+            }
+            KeyDerivationAlgorithm::HKDF(meta) => {
+                proto_meta.algo = proto_hkdf_algo;
+                proto_meta.hash_fn = meta.hash_fn.to_proto();
+                proto_meta.salt = meta.salt.to_vec();
+                proto_meta.info = meta.info.clone();
+                return proto_meta;
+            }
         };
 
         proto_meta.algo = proto_pbkdf2_algo;
@@ -329,6 +907,38 @@ impl KeyDerivationAlgorithm {
         proto_meta.salt = meta.salt.to_vec();
         proto_meta
     }
+
+    /// Create a key derivation algorithm with the same parameters, but a
+    /// unique salt.
+    ///
+    /// This method is useful when data are encrypted again, since the salt of
+    /// the previous encryption must not be reused. The X25519 algorithm is
+    /// returned as is, since its ephemeral public key can only be created
+    /// along with a new key agreement.
+    pub fn regenerate(&self) -> Self {
+        match self.clone() {
+            KeyDerivationAlgorithm::None => KeyDerivationAlgorithm::None,
+            KeyDerivationAlgorithm::PBKDF2(mut meta) => {
+                rand::fill_buf(&mut meta.salt);
+                KeyDerivationAlgorithm::PBKDF2(meta)
+            }
+            KeyDerivationAlgorithm::Argon2id(mut meta) => {
+                rand::fill_buf(&mut meta.salt);
+                KeyDerivationAlgorithm::Argon2id(meta)
+            }
+            KeyDerivationAlgorithm::Scrypt(mut meta) => {
+                rand::fill_buf(&mut meta.salt);
+                KeyDerivationAlgorithm::Scrypt(meta)
+            }
+            KeyDerivationAlgorithm::X25519(meta) => {
+                KeyDerivationAlgorithm::X25519(meta)
+            }
+            KeyDerivationAlgorithm::HKDF(mut meta) => {
+                rand::fill_buf(&mut meta.salt);
+                KeyDerivationAlgorithm::HKDF(meta)
+            }
+        }
+    }
 }
 
 /// The metadata that can be used for the encryption process.
@@ -370,6 +980,46 @@ impl EncryptionMetadata {
     }
 }
 
+/// The metadata that can be used for the XChaCha20-Poly1305 encryption
+/// process.
+///
+/// ## Examples
+///
+/// ```
+/// use tindercrypt::metadata::XChaCha20Poly1305Metadata;
+///
+/// // Generate a struct instance for the encryption metadata.
+/// let enc_meta1 = XChaCha20Poly1305Metadata::generate();
+///
+/// // Generate a second struct instance. The nonce should be different this time.
+/// let enc_meta2 = XChaCha20Poly1305Metadata::generate();
+/// assert_ne!(enc_meta1.nonce, enc_meta2.nonce);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct XChaCha20Poly1305Metadata {
+    /// The nonce value for the XChaCha20-Poly1305 AEAD.
+    ///
+    /// Nonces are unique, 192-bit values, which are filled with random data.
+    pub nonce: [u8; XCHACHA20_NONCE_SIZE],
+}
+
+impl XChaCha20Poly1305Metadata {
+    /// Create the encryption metadata from user-provided values.
+    ///
+    /// This method should be used only when the user wants to explicitly set
+    /// a specific value. Else, it's better to use `::generate()`.
+    pub fn new(nonce: [u8; XCHACHA20_NONCE_SIZE]) -> Self {
+        Self { nonce }
+    }
+
+    /// Generate encryption metadata.
+    pub fn generate() -> Self {
+        let mut nonce = [0u8; XCHACHA20_NONCE_SIZE];
+        rand::fill_buf(&mut nonce);
+        Self::new(nonce)
+    }
+}
+
 /// The encryption algorithm that will be used.
 ///
 /// ## Examples
@@ -384,6 +1034,16 @@ impl EncryptionMetadata {
 ///
 /// // Create a ChaCha20-Poly1305 encryption algorithm.
 /// let enc_algo_chacha = EncryptionAlgorithm::ChaCha20Poly1305(enc_meta);
+///
+/// // Create a nonce-misuse-resistant AES-256-GCM-SIV encryption algorithm.
+/// let enc_algo_aes_siv = EncryptionAlgorithm::AES256GCMSIV(enc_meta);
+///
+/// // Create an XChaCha20-Poly1305 encryption algorithm, which requires a
+/// // larger nonce.
+/// use tindercrypt::metadata::XChaCha20Poly1305Metadata;
+///
+/// let xchacha_meta = XChaCha20Poly1305Metadata::generate();
+/// let enc_algo_xchacha = EncryptionAlgorithm::XChaCha20Poly1305(xchacha_meta);
 /// ```
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum EncryptionAlgorithm {
@@ -391,6 +1051,10 @@ pub enum EncryptionAlgorithm {
     AES256GCM(EncryptionMetadata),
     /// The ChaCha20-Poly1405 AEAD.
     ChaCha20Poly1305(EncryptionMetadata),
+    /// The XChaCha20-Poly1305 AEAD, which uses 192-bit nonces.
+    XChaCha20Poly1305(XChaCha20Poly1305Metadata),
+    /// The AES-256-GCM-SIV AEAD, which is resistant to nonce misuse.
+    AES256GCMSIV(EncryptionMetadata),
 }
 
 impl EncryptionAlgorithm {
@@ -412,6 +1076,20 @@ impl EncryptionAlgorithm {
             _ => (),
         };
 
+        // XChaCha20-Poly1305 requires a larger nonce, so it has its own
+        // metadata.
+        if proto_meta.algo
+            == pmeta::EncryptionAlgorithm::ENCRYPTION_ALGORITHM_XCHACHA20_POLY1305
+        {
+            if proto_meta.nonce.len() != XCHACHA20_NONCE_SIZE {
+                return err;
+            }
+            let mut nonce = [0u8; XCHACHA20_NONCE_SIZE];
+            nonce.copy_from_slice(&proto_meta.nonce);
+            let meta = XChaCha20Poly1305Metadata::new(nonce);
+            return Ok(EncryptionAlgorithm::XChaCha20Poly1305(meta));
+        }
+
         // Check if the nonce has the appropriate length and copy it.
         if proto_meta.nonce.len() != RING_NONCE_SIZE {
             return err;
@@ -424,13 +1102,16 @@ impl EncryptionAlgorithm {
 
         // Return the appropriate algorithm.
         match proto_meta.algo {
-            pmeta::EncryptionAlgorithm::ENCRYPTION_ALGORITHM_INVALID => err,
             pmeta::EncryptionAlgorithm::ENCRYPTION_ALGORITHM_AES256GCM => {
                 Ok(EncryptionAlgorithm::AES256GCM(meta))
             },
             pmeta::EncryptionAlgorithm::ENCRYPTION_ALGORITHM_CHACHA20_POLY1305 => {
                 Ok(EncryptionAlgorithm::ChaCha20Poly1305(meta))
             }
+            pmeta::EncryptionAlgorithm::ENCRYPTION_ALGORITHM_AES256GCMSIV => {
+                Ok(EncryptionAlgorithm::AES256GCMSIV(meta))
+            }
+            _ => err,
         }
     }
 
@@ -442,6 +1123,10 @@ impl EncryptionAlgorithm {
             pmeta::EncryptionAlgorithm::ENCRYPTION_ALGORITHM_AES256GCM;
         let proto_chacha_algo =
             pmeta::EncryptionAlgorithm::ENCRYPTION_ALGORITHM_CHACHA20_POLY1305;
+        let proto_xchacha_algo =
+            pmeta::EncryptionAlgorithm::ENCRYPTION_ALGORITHM_XCHACHA20_POLY1305;
+        let proto_aes_siv_algo =
+            pmeta::EncryptionAlgorithm::ENCRYPTION_ALGORITHM_AES256GCMSIV;
 
         match self {
             EncryptionAlgorithm::AES256GCM(meta) => {
@@ -452,9 +1137,192 @@ impl EncryptionAlgorithm {
                 proto_meta.algo = proto_chacha_algo;
                 proto_meta.nonce = meta.nonce.to_vec();
             }
+            EncryptionAlgorithm::XChaCha20Poly1305(meta) => {
+                proto_meta.algo = proto_xchacha_algo;
+                proto_meta.nonce = meta.nonce.to_vec();
+            }
+            EncryptionAlgorithm::AES256GCMSIV(meta) => {
+                proto_meta.algo = proto_aes_siv_algo;
+                proto_meta.nonce = meta.nonce.to_vec();
+            }
         };
         proto_meta
     }
+
+    /// Create an encryption algorithm of the same type, but with a unique
+    /// nonce.
+    ///
+    /// This method is useful when data are encrypted again, since the nonce
+    /// of the previous encryption must not be reused.
+    pub fn regenerate(&self) -> Self {
+        match self {
+            EncryptionAlgorithm::AES256GCM(_) => {
+                EncryptionAlgorithm::AES256GCM(EncryptionMetadata::generate())
+            }
+            EncryptionAlgorithm::ChaCha20Poly1305(_) => {
+                let meta = EncryptionMetadata::generate();
+                EncryptionAlgorithm::ChaCha20Poly1305(meta)
+            }
+            EncryptionAlgorithm::XChaCha20Poly1305(_) => {
+                let meta = XChaCha20Poly1305Metadata::generate();
+                EncryptionAlgorithm::XChaCha20Poly1305(meta)
+            }
+            EncryptionAlgorithm::AES256GCMSIV(_) => {
+                let meta = EncryptionMetadata::generate();
+                EncryptionAlgorithm::AES256GCMSIV(meta)
+            }
+        }
+    }
+}
+
+/// A key slot, which holds a wrapped data key.
+///
+/// Data that must be decrypted by more than one secret are encrypted with a
+/// random data key. Then, this key is wrapped (encrypted) once per secret,
+/// with a key that is derived from the secret, and is stored in a key slot.
+/// Anyone that knows the secret of a key slot can unwrap the data key and
+/// decrypt the data. Key slots can be added or removed without encrypting the
+/// data again, see [`RingCryptor::add_key_slot`].
+///
+/// A key slot may also hold a data key that is wrapped with a key-encryption
+/// key (KEK), instead of a key that is derived from a passphrase. In this
+/// case, the key slot has no key derivation algorithm, and it stores the
+/// identifier of the KEK, see [`RingCryptor::seal_with_kek`].
+///
+/// [`RingCryptor::add_key_slot`]: ../cryptors/struct.RingCryptor.html#method.add_key_slot
+/// [`RingCryptor::seal_with_kek`]: ../cryptors/struct.RingCryptor.html#method.seal_with_kek
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeySlot {
+    /// The key derivation algorithm for the secret of the key slot.
+    pub key_deriv_algo: KeyDerivationAlgorithm,
+    /// The encryption algorithm that wraps the data key.
+    pub enc_algo: EncryptionAlgorithm,
+    /// The wrapped data key, along with its tag.
+    pub wrapped_key: Vec<u8>,
+    /// The identifier of the key-encryption key (KEK) that wraps the data
+    /// key, if any.
+    pub kek_id: Option<String>,
+}
+
+impl KeySlot {
+    /// Create a key slot from user-provided values.
+    pub fn new(
+        key_deriv_algo: KeyDerivationAlgorithm,
+        enc_algo: EncryptionAlgorithm,
+        wrapped_key: Vec<u8>,
+    ) -> Self {
+        Self {
+            key_deriv_algo,
+            enc_algo,
+            wrapped_key,
+            kek_id: None,
+        }
+    }
+
+    /// Create a key slot from the respective protobuf-generated key slot.
+    ///
+    /// This method may return an error, if the protobuf-generated key slot
+    /// has any invalid fields.
+    pub fn from_proto(
+        proto_slot: &pmeta::KeySlot,
+    ) -> Result<Self, errors::Error> {
+        let key_deriv_algo = KeyDerivationAlgorithm::from_proto(
+            proto_slot.get_key_deriv_meta(),
+        )?;
+        let enc_algo =
+            EncryptionAlgorithm::from_proto(proto_slot.get_enc_meta())?;
+
+        // Key slots for X25519 recipients are not supported, since the data
+        // key is wrapped with a secret value, and not a public key.
+        if let KeyDerivationAlgorithm::X25519(_) = key_deriv_algo {
+            return Err(errors::Error::MetadataInvalid);
+        }
+
+        // Check that the wrapped key has the expected size.
+        let wrapped_key = proto_slot.get_wrapped_key();
+        let wrapped_key_size =
+            Metadata::calculate_ciphertext_size(DATA_KEY_SIZE, &enc_algo);
+        if wrapped_key.len() != wrapped_key_size {
+            return Err(errors::Error::MetadataInvalid);
+        }
+
+        let mut slot =
+            Self::new(key_deriv_algo, enc_algo, wrapped_key.to_vec());
+        if !proto_slot.get_kek_id().is_empty() {
+            slot.kek_id = Some(proto_slot.get_kek_id().to_string());
+        }
+        Ok(slot)
+    }
+
+    /// Convert the key slot to the respective protobuf-generated key slot.
+    pub fn to_proto(&self) -> pmeta::KeySlot {
+        let mut proto_slot = pmeta::KeySlot::new();
+        proto_slot.set_key_deriv_meta(self.key_deriv_algo.to_proto());
+        proto_slot.set_enc_meta(self.enc_algo.to_proto());
+        proto_slot.set_wrapped_key(self.wrapped_key.clone());
+        if let Some(kek_id) = &self.kek_id {
+            proto_slot.set_kek_id(kek_id.clone());
+        }
+        proto_slot
+    }
+}
+
+/// The Ed25519 signature of the encrypted data.
+///
+/// The signature covers the serialized metadata, without the signature
+/// itself, and the ciphertext that follows them. It's created with
+/// [`RingCryptor::seal_signed`], and is checked with
+/// [`RingCryptor::open_verified`].
+///
+/// [`RingCryptor::seal_signed`]: ../cryptors/struct.RingCryptor.html#method.seal_signed
+/// [`RingCryptor::open_verified`]: ../cryptors/struct.RingCryptor.html#method.open_verified
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Signature {
+    /// The public key of the signer.
+    pub public_key: [u8; ED25519_KEY_SIZE],
+    /// The signature itself.
+    pub signature: [u8; ED25519_SIGNATURE_SIZE],
+}
+
+impl Signature {
+    /// Create a signature from user-provided values.
+    pub fn new(
+        public_key: [u8; ED25519_KEY_SIZE],
+        signature: [u8; ED25519_SIGNATURE_SIZE],
+    ) -> Self {
+        Self {
+            public_key,
+            signature,
+        }
+    }
+
+    /// Create a signature from the respective protobuf-generated signature.
+    ///
+    /// This method may return an error, if the public key or the signature
+    /// do not have the expected size.
+    pub fn from_proto(
+        proto_sig: &pmeta::Signature,
+    ) -> Result<Self, errors::Error> {
+        if proto_sig.public_key.len() != ED25519_KEY_SIZE
+            || proto_sig.signature.len() != ED25519_SIGNATURE_SIZE
+        {
+            return Err(errors::Error::MetadataInvalid);
+        }
+
+        let mut public_key = [0u8; ED25519_KEY_SIZE];
+        public_key.copy_from_slice(&proto_sig.public_key);
+        let mut signature = [0u8; ED25519_SIGNATURE_SIZE];
+        signature.copy_from_slice(&proto_sig.signature);
+        Ok(Self::new(public_key, signature))
+    }
+
+    /// Convert the signature to the respective protobuf-generated signature.
+    pub fn to_proto(&self) -> pmeta::Signature {
+        let mut proto_sig = pmeta::Signature::new();
+        proto_sig.set_public_key(self.public_key.to_vec());
+        proto_sig.set_signature(self.signature.to_vec());
+        proto_sig
+    }
 }
 
 /// The collection of all encryption-related metadata.
@@ -497,7 +1365,7 @@ impl EncryptionAlgorithm {
 /// ```
 ///
 /// [`proto::metadata::Metadata`]: ../proto/metadata/struct.Metadata.html
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Metadata {
     /// The key derivation algorithm to be used.
     pub key_deriv_algo: KeyDerivationAlgorithm,
@@ -509,6 +1377,79 @@ pub struct Metadata {
     /// may also contain its digest. So, this value also takes the digest into
     /// account.
     pub ciphertext_size: usize,
+    /// Whether the serialized metadata should be authenticated along with the
+    /// ciphertext.
+    ///
+    /// If true, the cryptor mixes the serialized metadata into the associated
+    /// data of the encryption, so that any tampering with the metadata header
+    /// will make the decryption fail. This is the default for newly created
+    /// metadata. It's false only for metadata headers that have been created
+    /// by older versions of this library, which did not authenticate them.
+    pub authenticated_header: bool,
+    /// The size of the plaintext chunks, if the data are encrypted as a
+    /// stream.
+    ///
+    /// If set, the plaintext is split into chunks of this size, which are
+    /// encrypted separately. See the [`stream`] module for more info. Since
+    /// the size of a stream is usually not known beforehand, the
+    /// `ciphertext_size` of a stream is not taken into account, and is
+    /// normally 0.
+    ///
+    /// [`stream`]: ../stream/index.html
+    pub chunk_size: Option<usize>,
+    /// Whether the data are encrypted deterministically.
+    ///
+    /// If true, the nonce has been derived from the key and the plaintext,
+    /// so that equal plaintexts are encrypted to equal ciphertexts. This mode
+    /// is meant for equality lookups over encrypted data, and is supported
+    /// only for the AES-256-GCM-SIV encryption algorithm, without any key
+    /// derivation.
+    pub deterministic: bool,
+    /// The key slots that hold the data key.
+    ///
+    /// If not empty, the data are encrypted with a random data key, which is
+    /// wrapped in each key slot, and the key derivation algorithm of the
+    /// metadata must be `None`. Any secret that can unwrap the data key of a
+    /// key slot can decrypt the data. See [`KeySlot`] for more info.
+    ///
+    /// [`KeySlot`]: struct.KeySlot.html
+    pub key_slots: Vec<KeySlot>,
+    /// The identifier of the key that encrypts the data, if any.
+    ///
+    /// It's stored in the metadata header, so that the proper key can be
+    /// picked from a keyring during decryption, instead of trying every key.
+    /// See [`Keyring`] for more info.
+    ///
+    /// [`Keyring`]: ../keys/struct.Keyring.html
+    pub key_id: Option<String>,
+    /// The Ed25519 signature of the encrypted data, if any.
+    ///
+    /// The signature is not part of the associated data, so that the data
+    /// can be signed after their encryption. See [`Signature`] for more info.
+    ///
+    /// [`Signature`]: struct.Signature.html
+    pub signature: Option<Signature>,
+    /// The commitment to the encryption key, if the data are encrypted in
+    /// committing mode.
+    ///
+    /// The AEAD algorithms that this library supports are not
+    /// key-committing, i.e., a crafted ciphertext can be decrypted with more
+    /// than one key. If this value is set, the cryptor checks that the key
+    /// matches it before the decryption, so that only a single key can
+    /// decrypt the ciphertext. It's created with
+    /// [`RingCryptor::seal_committing`].
+    ///
+    /// [`RingCryptor::seal_committing`]: ../cryptors/struct.RingCryptor.html#method.seal_committing
+    pub key_commitment: Option<[u8; KEY_COMMITMENT_SIZE]>,
+    /// The key check value of the encryption key, if any.
+    ///
+    /// If this value is set, the cryptor checks that the key matches it
+    /// before the decryption, so that it can tell a wrong key (`WrongKey`)
+    /// apart from a corrupted ciphertext (`CiphertextCorrupted`). It's
+    /// created with [`RingCryptor::seal_with_key_check`].
+    ///
+    /// [`RingCryptor::seal_with_key_check`]: ../cryptors/struct.RingCryptor.html#method.seal_with_key_check
+    pub key_check: Option<[u8; KEY_CHECK_SIZE]>,
 }
 
 impl<'a> Metadata {
@@ -524,6 +1465,57 @@ impl<'a> Metadata {
             key_deriv_algo,
             enc_algo,
             ciphertext_size,
+            authenticated_header: true,
+            chunk_size: None,
+            deterministic: false,
+            key_slots: Vec::new(),
+            key_id: None,
+            signature: None,
+            key_commitment: None,
+            key_check: None,
+        }
+    }
+
+    /// Create the metadata for a deterministic encryption.
+    ///
+    /// The provided encryption metadata must contain a nonce that has been
+    /// derived from the key and the plaintext, e.g., with
+    /// [`aes_gcm_siv::synthetic_nonce`]. The cryptor's
+    /// `.seal_deterministic()` method takes care of this.
+    ///
+    /// [`aes_gcm_siv::synthetic_nonce`]: ../aes_gcm_siv/fn.synthetic_nonce.html
+    pub fn new_deterministic(
+        enc_meta: EncryptionMetadata,
+        plaintext_size: usize,
+    ) -> Self {
+        let key_deriv_algo = KeyDerivationAlgorithm::None;
+        let enc_algo = EncryptionAlgorithm::AES256GCMSIV(enc_meta);
+        let mut meta = Self::new(key_deriv_algo, enc_algo, plaintext_size);
+        meta.deterministic = true;
+        meta
+    }
+
+    /// Create the metadata for a stream from user-provided values.
+    ///
+    /// The data will be split into chunks of the provided size, which will be
+    /// encrypted separately.
+    pub fn new_stream(
+        key_deriv_algo: KeyDerivationAlgorithm,
+        enc_algo: EncryptionAlgorithm,
+        chunk_size: usize,
+    ) -> Self {
+        Self {
+            key_deriv_algo,
+            enc_algo,
+            ciphertext_size: 0,
+            authenticated_header: true,
+            chunk_size: Some(chunk_size),
+            deterministic: false,
+            key_slots: Vec::new(),
+            key_id: None,
+            signature: None,
+            key_commitment: None,
+            key_check: None,
         }
     }
 
@@ -535,14 +1527,35 @@ impl<'a> Metadata {
     ) -> usize {
         match enc_algo {
             EncryptionAlgorithm::AES256GCM(_) => {
-                plaintext_size + ring::aead::AES_256_GCM.tag_len()
+                plaintext_size + RING_TAG_SIZE
             }
             EncryptionAlgorithm::ChaCha20Poly1305(_) => {
-                plaintext_size + ring::aead::CHACHA20_POLY1305.tag_len()
+                plaintext_size + RING_TAG_SIZE
+            }
+            EncryptionAlgorithm::XChaCha20Poly1305(_) => {
+                plaintext_size + xchacha20::TAG_SIZE
+            }
+            EncryptionAlgorithm::AES256GCMSIV(_) => {
+                plaintext_size + aes_gcm_siv::TAG_SIZE
             }
         }
     }
 
+    /// Calculate the ciphertext size of a stream, from the plaintext size, the
+    /// encryption algorithm and the chunk size.
+    ///
+    /// A stream always consists of at least one chunk, even if the plaintext
+    /// is empty, and each chunk has its own tag.
+    pub fn calculate_stream_ciphertext_size(
+        plaintext_size: usize,
+        enc_algo: &EncryptionAlgorithm,
+        chunk_size: usize,
+    ) -> usize {
+        let tag_size = Self::calculate_ciphertext_size(0, enc_algo);
+        let chunks = cmp::max(plaintext_size.div_ceil(chunk_size), 1);
+        plaintext_size + chunks * tag_size
+    }
+
     /// Generate the necesary metadata for encrypting data with a symmetric
     /// key.
     ///
@@ -556,6 +1569,23 @@ impl<'a> Metadata {
         Self::new(key_deriv_algo, enc_algo, plaintext_size)
     }
 
+    /// Generate the necesary metadata for encrypting data with a master key.
+    ///
+    /// The default suggestion for encrypting data with a long-lived master
+    /// key is to derive a unique key per message with the HKDF key derivation
+    /// algorithm, a random salt and the provided context string, and use the
+    /// AES-256-GCM encryption algorithm.
+    pub fn generate_for_master_key(
+        info: &[u8],
+        plaintext_size: usize,
+    ) -> Self {
+        let hkdf_meta = HkdfMetadata::generate(info);
+        let key_deriv_algo = KeyDerivationAlgorithm::HKDF(hkdf_meta);
+        let enc_meta = EncryptionMetadata::generate();
+        let enc_algo = EncryptionAlgorithm::AES256GCM(enc_meta);
+        Self::new(key_deriv_algo, enc_algo, plaintext_size)
+    }
+
     /// Generate the necesary metadata for encrypting data with a passphrase.
     ///
     /// The default suggestion for encrypting data with a passphrase is to use
@@ -587,23 +1617,133 @@ impl<'a> Metadata {
         let proto_enc_meta = proto_meta.get_enc_meta();
         let enc_algo = EncryptionAlgorithm::from_proto(proto_enc_meta)?;
 
+        // Check that the chunk size, if defined, is not larger than the
+        // maximum one.
+        let chunk_size = match proto_meta.chunk_size {
+            0 => None,
+            size if size > STREAM_MAX_CHUNK_SIZE as u64 => return err,
+            size => Some(size as usize),
+        };
+
         // Check that the ciphertext size is larger or equal to the minimum
-        // ciphertext size for the given encryption algorithm.
+        // ciphertext size for the given encryption algorithm. Streams are
+        // exempt from this check, since their size is not known beforehand.
         let ciphertext_size = proto_meta.ciphertext_size as usize;
         let min_ciphertext_size =
             Self::calculate_ciphertext_size(0, &enc_algo);
-        if min_ciphertext_size > ciphertext_size {
+        if chunk_size.is_none() && min_ciphertext_size > ciphertext_size {
+            return err;
+        }
+
+        // Check that the metadata do not contain fields that we don't
+        // understand, if they are meant to be authenticated. Such fields
+        // would be dropped when the metadata are serialized again, and
+        // therefore they would not be authenticated.
+        let authenticated_header = proto_meta.authenticated_header;
+        if authenticated_header && Self::_has_unknown_fields(proto_meta) {
             return err;
         }
 
+        // Check that deterministic metadata use the only supported
+        // combination of algorithms, and that they are not used for a stream.
+        let deterministic = proto_meta.deterministic;
+        if deterministic {
+            match (&key_deriv_algo, &enc_algo) {
+                (
+                    KeyDerivationAlgorithm::None,
+                    EncryptionAlgorithm::AES256GCMSIV(_),
+                ) if chunk_size.is_none() && authenticated_header => (),
+                _ => return err,
+            }
+        }
+
+        // Parse the key slots, and check that the data key is not derived from
+        // a secret, if there are any.
+        let mut key_slots = Vec::new();
+        for proto_slot in proto_meta.get_key_slots() {
+            key_slots.push(KeySlot::from_proto(proto_slot)?);
+        }
+        if !key_slots.is_empty() {
+            match key_deriv_algo {
+                KeyDerivationAlgorithm::None if !deterministic => (),
+                _ => return err,
+            }
+        }
+
+        let key_id = match proto_meta.get_key_id() {
+            "" => None,
+            key_id => Some(key_id.to_string()),
+        };
+
+        let signature = if proto_meta.has_signature() {
+            Some(Signature::from_proto(proto_meta.get_signature())?)
+        } else {
+            None
+        };
+
+        // Copy the key commitment, if any, to a fixed-size array. If the size
+        // is not the expected one, return an error.
+        let key_commitment = match proto_meta.get_key_commitment() {
+            [] => None,
+            c if c.len() != KEY_COMMITMENT_SIZE => return err,
+            c => {
+                let mut key_commitment = [0u8; KEY_COMMITMENT_SIZE];
+                key_commitment.copy_from_slice(c);
+                Some(key_commitment)
+            }
+        };
+
+        // Copy the key check value, if any, to a fixed-size array. If the
+        // size is not the expected one, return an error.
+        let key_check = match proto_meta.get_key_check() {
+            [] => None,
+            c if c.len() != KEY_CHECK_SIZE => return err,
+            c => {
+                let mut key_check = [0u8; KEY_CHECK_SIZE];
+                key_check.copy_from_slice(c);
+                Some(key_check)
+            }
+        };
+
         // Construct and return the metadata.
         Ok(Self {
             key_deriv_algo,
             enc_algo,
             ciphertext_size,
+            authenticated_header,
+            chunk_size,
+            deterministic,
+            key_slots,
+            key_id,
+            signature,
+            key_commitment,
+            key_check,
         })
     }
 
+    /// Check if the protobuf-generated metadata contain unknown fields.
+    fn _has_unknown_fields(proto_meta: &pmeta::Metadata) -> bool {
+        let unknown_fields = [
+            proto_meta.get_unknown_fields(),
+            proto_meta.get_key_deriv_meta().get_unknown_fields(),
+            proto_meta.get_enc_meta().get_unknown_fields(),
+            proto_meta.get_signature().get_unknown_fields(),
+        ];
+        let unknown_slot_fields =
+            proto_meta.get_key_slots().iter().flat_map(|s| {
+                vec![
+                    s.get_unknown_fields(),
+                    s.get_key_deriv_meta().get_unknown_fields(),
+                    s.get_enc_meta().get_unknown_fields(),
+                ]
+            });
+        unknown_fields
+            .iter()
+            .cloned()
+            .chain(unknown_slot_fields)
+            .any(|f| f.iter().next().is_some())
+    }
+
     /// Convert the metadata to the respective protobuf-generated metadata.
     pub fn to_proto(&self) -> pmeta::Metadata {
         let mut proto_meta = pmeta::Metadata::new();
@@ -613,18 +1753,60 @@ impl<'a> Metadata {
         let enc_meta = self.enc_algo.to_proto();
         proto_meta.set_enc_meta(enc_meta);
         proto_meta.ciphertext_size = self.ciphertext_size as u64;
+        proto_meta.authenticated_header = self.authenticated_header;
+        proto_meta.chunk_size = self.chunk_size.unwrap_or(0) as u64;
+        proto_meta.deterministic = self.deterministic;
+        for slot in &self.key_slots {
+            proto_meta.mut_key_slots().push(slot.to_proto());
+        }
+        if let Some(key_id) = &self.key_id {
+            proto_meta.set_key_id(key_id.clone());
+        }
+        if let Some(signature) = &self.signature {
+            proto_meta.set_signature(signature.to_proto());
+        }
+        if let Some(key_commitment) = &self.key_commitment {
+            proto_meta.set_key_commitment(key_commitment.to_vec());
+        }
+        if let Some(key_check) = &self.key_check {
+            proto_meta.set_key_check(key_check.to_vec());
+        }
 
         proto_meta
     }
 
+    /// Get the size of the prefix of a serialized buffer.
+    ///
+    /// If the buffer starts with the magic bytes, check that the format
+    /// version that follows is supported, and return the size of the prefix.
+    /// Else, the buffer has been created by an older version of this library,
+    /// so there is no prefix.
+    pub(crate) fn _get_prefix_size(
+        buf: &[u8],
+    ) -> Result<usize, errors::Error> {
+        if !buf.starts_with(&MAGIC) {
+            return Ok(0);
+        }
+
+        match buf.get(MAGIC.len()) {
+            Some(&FORMAT_VERSION) => Ok(HEADER_PREFIX_SIZE),
+            Some(_) => Err(errors::Error::UnsupportedVersion),
+            None => Err(errors::Error::MetadataMissing),
+        }
+    }
+
     /// Create a metadata struct from a serialized buffer.
     ///
     /// Deserialize the buffer that was created by the `.to_buf()` method into
     /// a tuple that contains the `Metadata` struct and its serialized size.
     /// If the buffer does not contain a metadata header or the header contains
-    /// invalid fields, this method returns an error.
+    /// invalid fields, this method returns an error. If the buffer has been
+    /// created with an unsupported format version, this method returns an
+    /// `UnsupportedVersion` error.
     pub fn from_buf(buf: &'a [u8]) -> Result<(Self, usize), errors::Error> {
-        let mut is = protobuf::CodedInputStream::from_bytes(&buf);
+        let prefix_size = Self::_get_prefix_size(buf)?;
+        let mut is =
+            protobuf::CodedInputStream::from_bytes(&buf[prefix_size..]);
 
         // Check that the buffer header contains a valid protobuf Metadata
         // message.
@@ -635,23 +1817,46 @@ impl<'a> Metadata {
 
         let proto_meta_size = is.pos() as usize;
         let meta = Metadata::from_proto(&proto_meta)?;
-        Ok((meta, proto_meta_size))
+
+        // Legacy headers have been created before the metadata header could
+        // be authenticated, so they should not claim otherwise.
+        if prefix_size == 0 && meta.authenticated_header {
+            return Err(errors::Error::MetadataInvalid);
+        }
+        Ok((meta, prefix_size + proto_meta_size))
     }
 
-    /// Serialize a metadata struct into a buffer.
+    /// Serialize a metadata struct into a header.
     ///
-    /// Create a buffer that is large enough to hold the serialized metadata
-    /// and the ciphertext. Then, serialize the metadata and store them at the
-    /// start of the buffer. Finally, return the new buffer and the size of the
-    /// serialized metadata.
-    pub fn to_buf(&self) -> (Vec<u8>, usize) {
+    /// Create a buffer that contains just the magic bytes, the format version
+    /// and the serialized metadata, without any space for the ciphertext.
+    /// This header is what the `.to_buf()` method places at the start of its
+    /// buffer, and is what the cryptors authenticate, if
+    /// `authenticated_header` is set.
+    pub fn to_header(&self) -> Vec<u8> {
         let proto_meta = self.to_proto();
+        let mut header = MAGIC.to_vec();
+        header.push(FORMAT_VERSION);
 
         // NOTE: It's probably safe to unwrap the result here, since the errors
         // it can return are by underlying functions that deal with smaller
         // buffers. In our case, we let the protobuf library create the buffer
         // itself, so any errors should be treated as bugs.
-        let mut buf = proto_meta.write_length_delimited_to_bytes().unwrap();
+        let mut os = protobuf::CodedOutputStream::vec(&mut header);
+        proto_meta.write_length_delimited_to(&mut os).unwrap();
+        os.flush().unwrap();
+        drop(os);
+        header
+    }
+
+    /// Serialize a metadata struct into a buffer.
+    ///
+    /// Create a buffer that is large enough to hold the serialized metadata
+    /// and the ciphertext. Then, serialize the metadata and store them at the
+    /// start of the buffer. Finally, return the new buffer and the size of the
+    /// serialized metadata.
+    pub fn to_buf(&self) -> (Vec<u8>, usize) {
+        let mut buf = self.to_header();
         let proto_meta_size = buf.len();
 
         // FIXME: This operation may copy the contents of the buffer again. It
@@ -687,6 +1892,14 @@ mod tests {
             pmeta::KeyDerivationAlgorithm::KEY_DERIVATION_ALGORITHM_NONE;
         let proto_pbkdf2_algo =
             pmeta::KeyDerivationAlgorithm::KEY_DERIVATION_ALGORITHM_PBKDF2;
+        let proto_argon2id_algo =
+            pmeta::KeyDerivationAlgorithm::KEY_DERIVATION_ALGORITHM_ARGON2ID;
+        let proto_scrypt_algo =
+            pmeta::KeyDerivationAlgorithm::KEY_DERIVATION_ALGORITHM_SCRYPT;
+        let proto_x25519_algo =
+            pmeta::KeyDerivationAlgorithm::KEY_DERIVATION_ALGORITHM_X25519;
+        let proto_hkdf_algo =
+            pmeta::KeyDerivationAlgorithm::KEY_DERIVATION_ALGORITHM_HKDF;
 
         // Check that conversion from invalid metadata returns an error.
         let inv_proto_meta = pmeta::KeyDerivationMetadata::new();
@@ -707,7 +1920,10 @@ mod tests {
         let algo = KeyDerivationAlgorithm::PBKDF2(meta);
         let proto_meta = algo.to_proto();
         assert_eq!(proto_meta.algo, proto_pbkdf2_algo);
-        assert_eq!(KeyDerivationAlgorithm::from_proto(&proto_meta), Ok(algo));
+        assert_eq!(
+            KeyDerivationAlgorithm::from_proto(&proto_meta),
+            Ok(algo.clone())
+        );
 
         // Check that invalid values are detected.
         //
@@ -715,7 +1931,160 @@ mod tests {
         let mut proto_meta = algo.to_proto();
         proto_meta.iterations = 0;
         assert_eq!(KeyDerivationAlgorithm::from_proto(&proto_meta), err);
+        proto_meta.iterations = u64::from(u32::MAX) + 1;
+        assert_eq!(KeyDerivationAlgorithm::from_proto(&proto_meta), err);
+        proto_meta.iterations = u64::MAX;
+        assert_eq!(KeyDerivationAlgorithm::from_proto(&proto_meta), err);
+        // * Invalid hash function.
+        let mut proto_meta = algo.to_proto();
+        proto_meta.hash_fn = pmeta::HashFunction::HASH_FUNCTION_INVALID;
+        assert_eq!(KeyDerivationAlgorithm::from_proto(&proto_meta), err);
+        // * Wrong salt size. The limits of the salt size are inclusive.
+        for &(salt_size, allowed) in &[
+            (0, false),
+            (MIN_SALT_SIZE - 1, false),
+            (MIN_SALT_SIZE, true),
+            (MAX_SALT_SIZE, true),
+            (MAX_SALT_SIZE + 1, false),
+        ] {
+            let mut proto_meta = algo.to_proto();
+            proto_meta.salt = vec![1; salt_size];
+            let res = KeyDerivationAlgorithm::from_proto(&proto_meta);
+            assert_eq!(res.is_ok(), allowed);
+        }
+
+        // Check that converting to/from the "Argon2id" key derivation
+        // algorithm works properly.
+        let meta = Argon2idMetadata::generate();
+        let algo = KeyDerivationAlgorithm::Argon2id(meta);
+        let proto_meta = algo.to_proto();
+        assert_eq!(proto_meta.algo, proto_argon2id_algo);
+        assert_eq!(
+            KeyDerivationAlgorithm::from_proto(&proto_meta),
+            Ok(algo.clone())
+        );
+
+        // Check that invalid values are detected.
+        //
+        // * Wrong time cost.
+        let mut proto_meta = algo.to_proto();
+        proto_meta.time_cost = 0;
+        assert_eq!(KeyDerivationAlgorithm::from_proto(&proto_meta), err);
+        // * Wrong degree of parallelism.
+        let mut proto_meta = algo.to_proto();
+        proto_meta.parallelism = 0;
+        assert_eq!(KeyDerivationAlgorithm::from_proto(&proto_meta), err);
+        // * Not enough memory for the lanes.
+        let mut proto_meta = algo.to_proto();
+        proto_meta.memory_cost = 8 * proto_meta.parallelism - 1;
+        assert_eq!(KeyDerivationAlgorithm::from_proto(&proto_meta), err);
+        // * Wrong salt size. The limits of the salt size are inclusive.
+        for &(salt_size, allowed) in &[
+            (0, false),
+            (MIN_SALT_SIZE - 1, false),
+            (MIN_SALT_SIZE, true),
+            (MAX_SALT_SIZE, true),
+            (MAX_SALT_SIZE + 1, false),
+        ] {
+            let mut proto_meta = algo.to_proto();
+            proto_meta.salt = vec![1; salt_size];
+            let res = KeyDerivationAlgorithm::from_proto(&proto_meta);
+            assert_eq!(res.is_ok(), allowed);
+        }
+
+        // Check that converting to/from the "scrypt" key derivation algorithm
+        // works properly.
+        let meta = ScryptMetadata::generate();
+        let algo = KeyDerivationAlgorithm::Scrypt(meta.clone());
+        let proto_meta = algo.to_proto();
+        assert_eq!(proto_meta.algo, proto_scrypt_algo);
+        assert_eq!(
+            KeyDerivationAlgorithm::from_proto(&proto_meta),
+            Ok(algo.clone())
+        );
+
+        // Check that invalid values are detected.
+        //
+        // * Wrong CPU/memory cost.
+        for log_n in &[0, 16 * meta.block_size, 64] {
+            let mut proto_meta = algo.to_proto();
+            proto_meta.log_n = *log_n;
+            assert_eq!(KeyDerivationAlgorithm::from_proto(&proto_meta), err);
+        }
+        // * Wrong block size.
+        let mut proto_meta = algo.to_proto();
+        proto_meta.block_size = 0;
+        assert_eq!(KeyDerivationAlgorithm::from_proto(&proto_meta), err);
+        // * Wrong degree of parallelism.
+        for parallelism in &[0, 1 << 30] {
+            let mut proto_meta = algo.to_proto();
+            proto_meta.parallelism = *parallelism;
+            assert_eq!(KeyDerivationAlgorithm::from_proto(&proto_meta), err);
+        }
+        // * Wrong salt size. The limits of the salt size are inclusive.
+        for &(salt_size, allowed) in &[
+            (0, false),
+            (MIN_SALT_SIZE - 1, false),
+            (MIN_SALT_SIZE, true),
+            (MAX_SALT_SIZE, true),
+            (MAX_SALT_SIZE + 1, false),
+        ] {
+            let mut proto_meta = algo.to_proto();
+            proto_meta.salt = vec![1; salt_size];
+            let res = KeyDerivationAlgorithm::from_proto(&proto_meta);
+            assert_eq!(res.is_ok(), allowed);
+        }
+
+        // Check that regenerated algorithms keep their parameters, but not
+        // their salt.
+        for algo in &[
+            KeyDerivationAlgorithm::PBKDF2(KeyDerivationMetadata::generate()),
+            KeyDerivationAlgorithm::Argon2id(Argon2idMetadata::generate()),
+            KeyDerivationAlgorithm::Scrypt(ScryptMetadata::generate()),
+            KeyDerivationAlgorithm::HKDF(HkdfMetadata::generate(b"info")),
+        ] {
+            let mut proto_meta = algo.to_proto();
+            let mut new_proto_meta = algo.regenerate().to_proto();
+            assert_ne!(proto_meta.salt, new_proto_meta.salt);
+            proto_meta.salt.clear();
+            new_proto_meta.salt.clear();
+            assert_eq!(proto_meta, new_proto_meta);
+        }
+        let algo = KeyDerivationAlgorithm::None;
+        assert_eq!(algo.regenerate(), algo);
+
+        // Check that converting to/from the "X25519" key derivation algorithm
+        // works properly.
+        let meta = X25519Metadata::new([7; X25519_KEY_SIZE]);
+        let algo = KeyDerivationAlgorithm::X25519(meta);
+        let mut proto_meta = algo.to_proto();
+        assert_eq!(proto_meta.algo, proto_x25519_algo);
+        assert_eq!(
+            KeyDerivationAlgorithm::from_proto(&proto_meta),
+            Ok(algo.clone())
+        );
+        assert_eq!(algo.regenerate(), algo);
+
+        // Check that ephemeral public keys with invalid sizes are detected.
+        proto_meta.ephemeral_public_key = vec![7; X25519_KEY_SIZE - 1];
+        assert_eq!(KeyDerivationAlgorithm::from_proto(&proto_meta), err);
+
+        // Check that converting to/from the "HKDF" key derivation algorithm
+        // works properly, even with an empty context string.
+        for info in &[&b"info"[..], b""] {
+            let meta = HkdfMetadata::generate(info);
+            let algo = KeyDerivationAlgorithm::HKDF(meta);
+            let proto_meta = algo.to_proto();
+            assert_eq!(proto_meta.algo, proto_hkdf_algo);
+            assert_eq!(proto_meta.info, *info);
+            let res = KeyDerivationAlgorithm::from_proto(&proto_meta);
+            assert_eq!(res, Ok(algo));
+        }
+
+        // Check that invalid values are detected.
+        //
         // * Invalid hash function.
+        let algo = KeyDerivationAlgorithm::HKDF(HkdfMetadata::generate(b""));
         let mut proto_meta = algo.to_proto();
         proto_meta.hash_fn = pmeta::HashFunction::HASH_FUNCTION_INVALID;
         assert_eq!(KeyDerivationAlgorithm::from_proto(&proto_meta), err);
@@ -732,20 +2101,32 @@ mod tests {
             pmeta::EncryptionAlgorithm::ENCRYPTION_ALGORITHM_AES256GCM;
         let proto_chacha_algo =
             pmeta::EncryptionAlgorithm::ENCRYPTION_ALGORITHM_CHACHA20_POLY1305;
+        let proto_xchacha_algo =
+            pmeta::EncryptionAlgorithm::ENCRYPTION_ALGORITHM_XCHACHA20_POLY1305;
+        let proto_aes_siv_algo =
+            pmeta::EncryptionAlgorithm::ENCRYPTION_ALGORITHM_AES256GCMSIV;
 
         // Check that conversion from invalid metadata returns an error.
         let inv_proto_meta = pmeta::EncryptionMetadata::new();
         assert_eq!(EncryptionAlgorithm::from_proto(&inv_proto_meta), err);
 
-        // Check that converting to/from the "AES-256-GCM"/"ChaCha20-Poly1305"
-        // encrytion algorithms works properly.
+        // Check that converting to/from the "AES-256-GCM"/"ChaCha20-Poly1305"/
+        // "XChaCha20-Poly1305"/"AES-256-GCM-SIV" encrytion algorithms works
+        // properly.
         let meta = EncryptionMetadata::generate();
         let aes_algo = EncryptionAlgorithm::AES256GCM(meta);
         let meta = EncryptionMetadata::generate();
         let chacha_algo = EncryptionAlgorithm::ChaCha20Poly1305(meta);
-        for (algo, proto_algo) in
-            &[(aes_algo, proto_aes_algo), (chacha_algo, proto_chacha_algo)]
-        {
+        let meta = XChaCha20Poly1305Metadata::generate();
+        let xchacha_algo = EncryptionAlgorithm::XChaCha20Poly1305(meta);
+        let meta = EncryptionMetadata::generate();
+        let aes_siv_algo = EncryptionAlgorithm::AES256GCMSIV(meta);
+        for (algo, proto_algo) in &[
+            (aes_algo, proto_aes_algo),
+            (chacha_algo, proto_chacha_algo),
+            (xchacha_algo, proto_xchacha_algo),
+            (aes_siv_algo, proto_aes_siv_algo),
+        ] {
             let mut proto_meta = algo.to_proto();
             assert_eq!(proto_meta.algo, *proto_algo);
             assert_eq!(
@@ -753,10 +2134,25 @@ mod tests {
                 Ok(algo.clone())
             );
 
+            // Check that a regenerated algorithm has the same type, but a
+            // unique nonce.
+            let new_proto_meta = algo.regenerate().to_proto();
+            assert_eq!(new_proto_meta.algo, *proto_algo);
+            assert_ne!(new_proto_meta.nonce, proto_meta.nonce);
+
             // Check that wrong nonce values are detected.
             proto_meta.nonce = vec![];
             assert_eq!(EncryptionAlgorithm::from_proto(&proto_meta), err);
         }
+
+        // Check that the nonce size of one algorithm is not accepted by
+        // another.
+        let mut proto_meta = aes_algo.to_proto();
+        proto_meta.algo = proto_xchacha_algo;
+        assert_eq!(EncryptionAlgorithm::from_proto(&proto_meta), err);
+        let mut proto_meta = xchacha_algo.to_proto();
+        proto_meta.algo = proto_chacha_algo;
+        assert_eq!(EncryptionAlgorithm::from_proto(&proto_meta), err);
     }
 
     #[test]
@@ -774,7 +2170,7 @@ mod tests {
         let enc_algo = EncryptionAlgorithm::ChaCha20Poly1305(enc_meta);
         let meta2 = Metadata::new(KeyDerivationAlgorithm::None, enc_algo, 0);
 
-        for meta in &[meta1, meta2] {
+        for meta in &[meta1.clone(), meta2] {
             // Check that the ciphertext size is not 0, even though the
             // plaintext size is.
             assert!(meta.ciphertext_size > 0);
@@ -798,7 +2194,159 @@ mod tests {
             let mut proto_meta = meta.to_proto();
             proto_meta.ciphertext_size = 0;
             assert_eq!(Metadata::from_proto(&proto_meta), err);
+
+            // Check that unknown fields are rejected only if the metadata
+            // header is authenticated.
+            let mut proto_meta = meta.to_proto();
+            proto_meta.mut_unknown_fields().add_varint(99, 1);
+            assert_eq!(Metadata::from_proto(&proto_meta), err);
+            let mut proto_meta = meta.to_proto();
+            proto_meta
+                .mut_enc_meta()
+                .mut_unknown_fields()
+                .add_varint(99, 1);
+            assert_eq!(Metadata::from_proto(&proto_meta), err);
+            proto_meta.authenticated_header = false;
+            let mut legacy_meta = meta.clone();
+            legacy_meta.authenticated_header = false;
+            assert_eq!(Metadata::from_proto(&proto_meta), Ok(legacy_meta));
         }
+
+        // Check that converting to/from protobuf-generated metadata for
+        // streams works properly.
+        let meta = Metadata::new_stream(
+            KeyDerivationAlgorithm::None,
+            enc_algo,
+            STREAM_DEFAULT_CHUNK_SIZE,
+        );
+        let proto_meta = meta.to_proto();
+        assert_eq!(proto_meta.ciphertext_size, 0);
+        assert_eq!(Metadata::from_proto(&proto_meta), Ok(meta.clone()));
+
+        // Check that invalid chunk sizes are detected.
+        let mut proto_meta = meta.to_proto();
+        proto_meta.chunk_size = STREAM_MAX_CHUNK_SIZE as u64 + 1;
+        assert_eq!(Metadata::from_proto(&proto_meta), err);
+
+        // Check that the key identifier is converted properly.
+        let mut meta = Metadata::generate_for_key(9);
+        meta.key_id = Some("key-1".to_string());
+        let proto_meta = meta.to_proto();
+        assert_eq!(proto_meta.get_key_id(), "key-1");
+        assert_eq!(Metadata::from_proto(&proto_meta), Ok(meta));
+
+        // Check that the signature is converted properly, and that signatures
+        // with invalid sizes are detected.
+        let mut meta = Metadata::generate_for_key(9);
+        meta.signature = Some(Signature::new([1; 32], [2; 64]));
+        let proto_meta = meta.to_proto();
+        assert_eq!(proto_meta.get_signature().get_public_key(), &[1; 32]);
+        assert_eq!(Metadata::from_proto(&proto_meta), Ok(meta));
+        let mut bad_proto_meta = proto_meta.clone();
+        bad_proto_meta.mut_signature().set_signature(vec![2; 63]);
+        assert_eq!(Metadata::from_proto(&bad_proto_meta), err);
+        let mut bad_proto_meta = proto_meta;
+        bad_proto_meta.mut_signature().clear_public_key();
+        assert_eq!(Metadata::from_proto(&bad_proto_meta), err);
+
+        // Check that the key commitment is converted properly, and that key
+        // commitments with invalid sizes are detected.
+        let mut meta = Metadata::generate_for_key(9);
+        meta.key_commitment = Some([3; KEY_COMMITMENT_SIZE]);
+        let mut proto_meta = meta.to_proto();
+        assert_eq!(Metadata::from_proto(&proto_meta), Ok(meta));
+        proto_meta.set_key_commitment(vec![3; KEY_COMMITMENT_SIZE - 1]);
+        assert_eq!(Metadata::from_proto(&proto_meta), err);
+
+        // Check that the key check value is converted properly, and that key
+        // check values with invalid sizes are detected.
+        let mut meta = Metadata::generate_for_key(9);
+        meta.key_check = Some([4; KEY_CHECK_SIZE]);
+        let mut proto_meta = meta.to_proto();
+        assert_eq!(Metadata::from_proto(&proto_meta), Ok(meta));
+        proto_meta.set_key_check(vec![4; KEY_CHECK_SIZE + 1]);
+        assert_eq!(Metadata::from_proto(&proto_meta), err);
+
+        // Check that converting to/from protobuf-generated metadata for
+        // deterministic encryption works properly.
+        let enc_meta = EncryptionMetadata::generate();
+        let meta = Metadata::new_deterministic(enc_meta, 0);
+        let proto_meta = meta.to_proto();
+        assert!(proto_meta.deterministic);
+        assert_eq!(Metadata::from_proto(&proto_meta), Ok(meta.clone()));
+
+        // Check that deterministic metadata with unsupported algorithms, or
+        // for streams, are detected.
+        let mut proto_meta = meta.to_proto();
+        proto_meta.set_key_deriv_meta(meta1.key_deriv_algo.to_proto());
+        assert_eq!(Metadata::from_proto(&proto_meta), err);
+        let mut proto_meta = meta.to_proto();
+        proto_meta.set_enc_meta(enc_algo.to_proto());
+        assert_eq!(Metadata::from_proto(&proto_meta), err);
+        let mut proto_meta = meta.to_proto();
+        proto_meta.chunk_size = STREAM_DEFAULT_CHUNK_SIZE as u64;
+        assert_eq!(Metadata::from_proto(&proto_meta), err);
+        let mut proto_meta = meta.to_proto();
+        proto_meta.authenticated_header = false;
+        assert_eq!(Metadata::from_proto(&proto_meta), err);
+    }
+
+    #[test]
+    fn test_key_slot_proto() {
+        let err = errors::Error::MetadataInvalid;
+        let key_deriv_meta = KeyDerivationMetadata::generate();
+        let key_deriv_algo = KeyDerivationAlgorithm::PBKDF2(key_deriv_meta);
+        let enc_meta = EncryptionMetadata::generate();
+        let enc_algo = EncryptionAlgorithm::AES256GCM(enc_meta);
+        let wrapped_key_size =
+            Metadata::calculate_ciphertext_size(DATA_KEY_SIZE, &enc_algo);
+        let slot =
+            KeySlot::new(key_deriv_algo, enc_algo, vec![1; wrapped_key_size]);
+
+        // Check that converting a key slot to/from protobuf-generated
+        // metadata works properly.
+        let proto_slot = slot.to_proto();
+        assert_eq!(KeySlot::from_proto(&proto_slot), Ok(slot.clone()));
+        let mut kek_slot = slot.clone();
+        kek_slot.key_deriv_algo = KeyDerivationAlgorithm::None;
+        kek_slot.kek_id = Some("kek-1".to_string());
+        let proto_slot = kek_slot.to_proto();
+        assert_eq!(proto_slot.get_kek_id(), "kek-1");
+        assert_eq!(KeySlot::from_proto(&proto_slot), Ok(kek_slot));
+
+        // Check that wrapped keys with invalid sizes are detected.
+        let mut proto_slot = slot.to_proto();
+        proto_slot.set_wrapped_key(vec![1; wrapped_key_size - 1]);
+        assert_eq!(KeySlot::from_proto(&proto_slot), Err(err));
+
+        // Check that key slots for X25519 recipients are rejected.
+        let mut x25519_slot = slot.clone();
+        let x25519_meta = X25519Metadata::new([7; X25519_KEY_SIZE]);
+        x25519_slot.key_deriv_algo =
+            KeyDerivationAlgorithm::X25519(x25519_meta);
+        let proto_slot = x25519_slot.to_proto();
+        assert_eq!(KeySlot::from_proto(&proto_slot), Err(err));
+
+        // Check that the key slots of the metadata are converted too, and
+        // that they are allowed only if there's no key derivation.
+        let mut meta = Metadata::generate_for_key(9);
+        meta.key_slots.push(slot.clone());
+        let proto_meta = meta.to_proto();
+        assert_eq!(Metadata::from_proto(&proto_meta), Ok(meta));
+
+        let mut meta = Metadata::generate_for_passphrase(9);
+        meta.key_slots.push(slot.clone());
+        let proto_meta = meta.to_proto();
+        assert_eq!(Metadata::from_proto(&proto_meta), Err(err));
+
+        // Check that unknown fields in the key slots are detected.
+        let mut meta = Metadata::generate_for_key(9);
+        meta.key_slots.push(slot);
+        let mut proto_meta = meta.to_proto();
+        proto_meta.mut_key_slots()[0]
+            .mut_unknown_fields()
+            .add_varint(99, 1);
+        assert_eq!(Metadata::from_proto(&proto_meta), Err(err));
     }
 
     #[test]
@@ -809,11 +2357,40 @@ mod tests {
         // Check that metadata serialization/deserialization works properly.
         let meta = Metadata::generate_for_passphrase(9);
         let (buf, meta_size) = meta.to_buf();
-        assert_eq!(Metadata::from_buf(&buf), Ok((meta, meta_size)));
+        assert_eq!(Metadata::from_buf(&buf), Ok((meta.clone(), meta_size)));
+        assert_eq!(meta.to_header(), buf[..meta_size].to_vec());
+
+        // Check that the header starts with the magic bytes and the format
+        // version.
+        assert_eq!(buf[..MAGIC.len()], MAGIC);
+        assert_eq!(buf[MAGIC.len()], FORMAT_VERSION);
 
         // Check that buffers with missing/invalid metadata headers are
         // detected.
         assert_eq!(Metadata::from_buf(&[]), missing_err);
+        assert_eq!(Metadata::from_buf(&MAGIC), missing_err);
+        assert_eq!(
+            Metadata::from_buf(&buf[..HEADER_PREFIX_SIZE]),
+            missing_err
+        );
+
+        // Check that unsupported format versions are detected.
+        let mut bad_buf = buf.clone();
+        bad_buf[MAGIC.len()] = FORMAT_VERSION + 1;
+        let res = Metadata::from_buf(&bad_buf);
+        assert_eq!(res, Err(errors::Error::UnsupportedVersion));
+
+        // Check that legacy headers, which do not start with the magic bytes,
+        // can be deserialized only if they are not authenticated.
+        let mut legacy_meta = meta.clone();
+        legacy_meta.authenticated_header = false;
+        let proto_meta = legacy_meta.to_proto();
+        let mut buf = proto_meta.write_length_delimited_to_bytes().unwrap();
+        let size = buf.len();
+        assert_eq!(Metadata::from_buf(&buf), Ok((legacy_meta, size)));
+        let proto_meta = meta.to_proto();
+        buf = proto_meta.write_length_delimited_to_bytes().unwrap();
+        assert_eq!(Metadata::from_buf(&buf), invalid_err);
 
         let mut proto_meta = Metadata::generate_for_key(9).to_proto();
         proto_meta.clear_key_deriv_meta();
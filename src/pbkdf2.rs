@@ -3,6 +3,7 @@
 //! This module contains helpers for the PBKDF2 algorithm.
 
 use crate::errors;
+use core::convert::TryFrom;
 use core::num;
 use ring::pbkdf2;
 
@@ -12,8 +13,9 @@ use ring::pbkdf2;
 /// parameters; an HMAC function, a salt and a number of iterations.
 ///
 /// This method returns an error if the parameters are too weak, e.g., a SHA-1
-/// digest function, zero number of iterations or no salt. Also, it returns an
-/// error if the user has not provided a buffer for the key or a secret value.
+/// digest function, zero number of iterations or no salt, or if the number of
+/// iterations does not fit in 32 bits. Also, it returns an error if the user
+/// has not provided a buffer for the key or a secret value.
 ///
 /// ## Examples
 ///
@@ -51,7 +53,10 @@ pub fn derive_key(
         return Err(errors::Error::PassphraseTooSmall);
     }
 
-    let iterations = num::NonZeroU32::new(iterations as u32).unwrap();
+    let iterations = match u32::try_from(iterations) {
+        Ok(iterations) => num::NonZeroU32::new(iterations).unwrap(),
+        Err(_) => return Err(errors::Error::CryptoParamsUnsupported),
+    };
     pbkdf2::derive(digest_algo, iterations, salt, secret, key);
     Ok(())
 }
@@ -87,6 +92,19 @@ mod tests {
         res =
             derive_key(pbkdf2::PBKDF2_HMAC_SHA256, 1, &[], &secret, &mut key1);
         assert_eq!(res, params_err);
+
+        // Check that a number of iterations that does not fit in 32 bits is
+        // rejected, instead of being truncated.
+        if usize::MAX > u32::MAX as usize {
+            res = derive_key(
+                pbkdf2::PBKDF2_HMAC_SHA256,
+                usize::MAX,
+                &salt,
+                &secret,
+                &mut key1,
+            );
+            assert_eq!(res, Err(errors::Error::CryptoParamsUnsupported));
+        }
         res = derive_key(pbkdf2::PBKDF2_HMAC_SHA256, 1, &salt, &[], &mut key1);
         assert_eq!(res, size_err);
         res =
@@ -0,0 +1,472 @@
+//! # Decryption policies
+//!
+//! The metadata header of the encrypted data dictates how the key will be
+//! derived and how the data will be decrypted. If the encrypted data come from
+//! an untrusted source, an attacker can craft a header with huge cost
+//! parameters, e.g., billions of PBKDF2 iterations or gigabytes of Argon2
+//! memory, and stall the decryption for an arbitrary amount of time.
+//!
+//! This module provides the `DecryptionPolicy` struct, which sets limits on
+//! the cost parameters of the key derivation, and on the algorithms and hash
+//! functions that the metadata may use. A cryptor with a policy checks the
+//! metadata against it, and rejects them with an `Error::PolicyViolation`
+//! error, before it performs any work.
+//!
+//! ## Examples
+//!
+//! ```
+//! # #[cfg(feature = "ring")] {
+//! use tindercrypt::cryptors::RingCryptor;
+//! use tindercrypt::errors;
+//! use tindercrypt::policy::{DecryptionPolicy, KeyDerivationKind};
+//!
+//! let plaintext = "The cake is a lie".as_bytes();
+//! let pass = "My secret passphrase".as_bytes();
+//! let ciphertext = RingCryptor::new().seal_with_passphrase(pass, plaintext)?;
+//!
+//! // Accept up to 1,000,000 PBKDF2 iterations.
+//! let mut policy = DecryptionPolicy::new();
+//! policy.max_iterations = 1_000_000;
+//! let cryptor = RingCryptor::new().with_policy(&policy);
+//! assert_eq!(cryptor.open(pass, &ciphertext)?, plaintext);
+//!
+//! // Accept only data that have been encrypted with a symmetric key.
+//! policy.allowed_key_deriv_algos = vec![KeyDerivationKind::None];
+//! let cryptor = RingCryptor::new().with_policy(&policy);
+//! let res = cryptor.open(pass, &ciphertext);
+//! assert_eq!(res, Err(errors::Error::PolicyViolation));
+//! # }
+//!
+//! # Ok::<(), tindercrypt::errors::Error>(())
+//! ```
+
+use crate::errors;
+use crate::metadata;
+
+/// The default minimum number of PBKDF2 iterations.
+pub const DEFAULT_MIN_ITERATIONS: usize = 1;
+
+/// The default maximum number of PBKDF2 iterations.
+///
+/// This is 10 times the number of iterations that this library uses by
+/// default.
+pub const DEFAULT_MAX_ITERATIONS: usize =
+    10 * metadata::PBKDF2_DEFAULT_ITERATIONS;
+
+/// The default maximum amount of memory (in KiB) for the key derivation.
+///
+/// This is 1 GiB, i.e., 16 times the memory that Argon2id uses by default.
+pub const DEFAULT_MAX_MEMORY_COST: u64 = 1024 * 1024;
+
+/// The default maximum number of Argon2id passes over the memory.
+pub const DEFAULT_MAX_TIME_COST: u32 = 10;
+
+/// The default maximum degree of parallelism for the key derivation.
+pub const DEFAULT_MAX_PARALLELISM: u32 = 16;
+
+/// The default maximum number of key slots in the metadata.
+///
+/// Each key slot may require a key derivation, so the number of key slots
+/// multiplies the cost of the decryption.
+pub const DEFAULT_MAX_KEY_SLOTS: usize = 8;
+
+/// The key derivation algorithms, without their parameters.
+#[allow(missing_docs)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum KeyDerivationKind {
+    None,
+    PBKDF2,
+    Argon2id,
+    Scrypt,
+    X25519,
+    HKDF,
+}
+
+impl KeyDerivationKind {
+    /// Get the kind of a key derivation algorithm.
+    pub fn of(key_deriv_algo: &metadata::KeyDerivationAlgorithm) -> Self {
+        match key_deriv_algo {
+            metadata::KeyDerivationAlgorithm::None => KeyDerivationKind::None,
+            metadata::KeyDerivationAlgorithm::PBKDF2(_) => {
+                KeyDerivationKind::PBKDF2
+            }
+            metadata::KeyDerivationAlgorithm::Argon2id(_) => {
+                KeyDerivationKind::Argon2id
+            }
+            metadata::KeyDerivationAlgorithm::Scrypt(_) => {
+                KeyDerivationKind::Scrypt
+            }
+            metadata::KeyDerivationAlgorithm::X25519(_) => {
+                KeyDerivationKind::X25519
+            }
+            metadata::KeyDerivationAlgorithm::HKDF(_) => {
+                KeyDerivationKind::HKDF
+            }
+        }
+    }
+}
+
+/// The encryption algorithms, without their parameters.
+#[allow(missing_docs)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum EncryptionKind {
+    AES256GCM,
+    ChaCha20Poly1305,
+    XChaCha20Poly1305,
+    AES256GCMSIV,
+}
+
+impl EncryptionKind {
+    /// Get the kind of an encryption algorithm.
+    pub fn of(enc_algo: &metadata::EncryptionAlgorithm) -> Self {
+        match enc_algo {
+            metadata::EncryptionAlgorithm::AES256GCM(_) => {
+                EncryptionKind::AES256GCM
+            }
+            metadata::EncryptionAlgorithm::ChaCha20Poly1305(_) => {
+                EncryptionKind::ChaCha20Poly1305
+            }
+            metadata::EncryptionAlgorithm::XChaCha20Poly1305(_) => {
+                EncryptionKind::XChaCha20Poly1305
+            }
+            metadata::EncryptionAlgorithm::AES256GCMSIV(_) => {
+                EncryptionKind::AES256GCMSIV
+            }
+        }
+    }
+}
+
+/// The limits that the metadata of the encrypted data must respect.
+///
+/// The cost limits apply to the key derivation algorithms as follows:
+///
+/// * PBKDF2: The number of iterations must be between `min_iterations` and
+///   `max_iterations`.
+/// * Argon2id: The memory cost, time cost and degree of parallelism must not
+///   exceed `max_memory_cost`, `max_time_cost` and `max_parallelism`
+///   respectively.
+/// * scrypt: The memory that the algorithm requires (`128 * r * N` bytes) and
+///   the degree of parallelism must not exceed `max_memory_cost` and
+///   `max_parallelism` respectively.
+///
+/// The hash function restrictions apply to PBKDF2 and HKDF. All the limits
+/// apply to the key slots of the metadata as well. Since a key derivation may
+/// take place for every key slot, the number of key slots is limited by
+/// `max_key_slots`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecryptionPolicy {
+    /// The minimum number of PBKDF2 iterations.
+    pub min_iterations: usize,
+    /// The maximum number of PBKDF2 iterations.
+    pub max_iterations: usize,
+    /// The maximum amount of memory (in KiB) for the key derivation.
+    pub max_memory_cost: u64,
+    /// The maximum number of Argon2id passes over the memory.
+    pub max_time_cost: u32,
+    /// The maximum degree of parallelism for the key derivation.
+    pub max_parallelism: u32,
+    /// The maximum number of key slots in the metadata.
+    pub max_key_slots: usize,
+    /// The hash functions that the key derivation may use.
+    pub allowed_hash_fns: Vec<metadata::HashFunction>,
+    /// The key derivation algorithms that the metadata may use.
+    pub allowed_key_deriv_algos: Vec<KeyDerivationKind>,
+    /// The encryption algorithms that the metadata may use.
+    pub allowed_enc_algos: Vec<EncryptionKind>,
+    /// Whether the metadata must have a key commitment.
+    ///
+    /// The key commitment is checked only if it's present in the metadata,
+    /// and an attacker can simply omit it. Require it if the data must be
+    /// decrypted only with the key that they have been encrypted with, e.g.,
+    /// to thwart partitioning oracle attacks.
+    pub require_key_commitment: bool,
+}
+
+impl DecryptionPolicy {
+    /// Create a decryption policy with the default limits.
+    ///
+    /// The default policy allows all the algorithms and hash functions that
+    /// this library supports, and sets generous limits on the cost parameters
+    /// of the key derivation, so that the metadata that this library creates
+    /// by default are accepted.
+    pub fn new() -> Self {
+        Self {
+            min_iterations: DEFAULT_MIN_ITERATIONS,
+            max_iterations: DEFAULT_MAX_ITERATIONS,
+            max_memory_cost: DEFAULT_MAX_MEMORY_COST,
+            max_time_cost: DEFAULT_MAX_TIME_COST,
+            max_parallelism: DEFAULT_MAX_PARALLELISM,
+            max_key_slots: DEFAULT_MAX_KEY_SLOTS,
+            allowed_hash_fns: vec![
+                metadata::HashFunction::SHA256,
+                metadata::HashFunction::SHA384,
+                metadata::HashFunction::SHA512,
+            ],
+            allowed_key_deriv_algos: vec![
+                KeyDerivationKind::None,
+                KeyDerivationKind::PBKDF2,
+                KeyDerivationKind::Argon2id,
+                KeyDerivationKind::Scrypt,
+                KeyDerivationKind::X25519,
+                KeyDerivationKind::HKDF,
+            ],
+            allowed_enc_algos: vec![
+                EncryptionKind::AES256GCM,
+                EncryptionKind::ChaCha20Poly1305,
+                EncryptionKind::XChaCha20Poly1305,
+                EncryptionKind::AES256GCMSIV,
+            ],
+            require_key_commitment: false,
+        }
+    }
+
+    /// Check that the metadata respect this policy.
+    ///
+    /// The key derivation and encryption algorithms of the metadata, as well
+    /// as those of their key slots, are checked against the policy, along
+    /// with the number of key slots and the presence of a key commitment. If
+    /// any of them violates it, an `Error::PolicyViolation` error is
+    /// returned.
+    pub fn check(
+        &self,
+        meta: &metadata::Metadata,
+    ) -> Result<(), errors::Error> {
+        if meta.key_slots.len() > self.max_key_slots {
+            return Err(errors::Error::PolicyViolation);
+        }
+        if self.require_key_commitment && meta.key_commitment.is_none() {
+            return Err(errors::Error::PolicyViolation);
+        }
+        self._check_enc_algo(&meta.enc_algo)?;
+        self._check_key_deriv_algo(&meta.key_deriv_algo)?;
+        for slot in &meta.key_slots {
+            self._check_enc_algo(&slot.enc_algo)?;
+            self._check_key_deriv_algo(&slot.key_deriv_algo)?;
+        }
+        Ok(())
+    }
+
+    /// Check that an encryption algorithm is allowed.
+    fn _check_enc_algo(
+        &self,
+        enc_algo: &metadata::EncryptionAlgorithm,
+    ) -> Result<(), errors::Error> {
+        if !self
+            .allowed_enc_algos
+            .contains(&EncryptionKind::of(enc_algo))
+        {
+            return Err(errors::Error::PolicyViolation);
+        }
+        Ok(())
+    }
+
+    /// Check that a hash function is allowed.
+    fn _check_hash_fn(
+        &self,
+        hash_fn: metadata::HashFunction,
+    ) -> Result<(), errors::Error> {
+        if !self.allowed_hash_fns.contains(&hash_fn) {
+            return Err(errors::Error::PolicyViolation);
+        }
+        Ok(())
+    }
+
+    /// Check that a key derivation algorithm is allowed, and that its cost
+    /// parameters are within the limits of the policy.
+    fn _check_key_deriv_algo(
+        &self,
+        key_deriv_algo: &metadata::KeyDerivationAlgorithm,
+    ) -> Result<(), errors::Error> {
+        let err = Err(errors::Error::PolicyViolation);
+        let kind = KeyDerivationKind::of(key_deriv_algo);
+        if !self.allowed_key_deriv_algos.contains(&kind) {
+            return err;
+        }
+
+        match key_deriv_algo {
+            metadata::KeyDerivationAlgorithm::PBKDF2(meta) => {
+                self._check_hash_fn(meta.hash_fn)?;
+                if meta.iterations < self.min_iterations
+                    || meta.iterations > self.max_iterations
+                {
+                    return err;
+                }
+            }
+            metadata::KeyDerivationAlgorithm::Argon2id(meta) => {
+                if u64::from(meta.memory_cost) > self.max_memory_cost
+                    || meta.time_cost > self.max_time_cost
+                    || meta.parallelism > self.max_parallelism
+                {
+                    return err;
+                }
+            }
+            metadata::KeyDerivationAlgorithm::Scrypt(meta) => {
+                // The memory of scrypt is `128 * r * N` bytes, or
+                // `r * 2^(log_n - 3)` KiB. Compare the base-2 logarithms, so
+                // that a large `log_n` cannot overflow the computation.
+                let max_memory_bytes = u128::from(self.max_memory_cost) * 1024;
+                let memory_bytes = 128 * u128::from(meta.block_size);
+                if meta.log_n >= 64
+                    || memory_bytes << meta.log_n > max_memory_bytes
+                    || meta.parallelism > self.max_parallelism
+                {
+                    return err;
+                }
+            }
+            metadata::KeyDerivationAlgorithm::HKDF(meta) => {
+                self._check_hash_fn(meta.hash_fn)?;
+            }
+            metadata::KeyDerivationAlgorithm::None
+            | metadata::KeyDerivationAlgorithm::X25519(_) => (),
+        }
+        Ok(())
+    }
+}
+
+impl Default for DecryptionPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate_meta(
+        key_deriv_algo: metadata::KeyDerivationAlgorithm,
+    ) -> metadata::Metadata {
+        let enc_meta = metadata::EncryptionMetadata::generate();
+        let enc_algo = metadata::EncryptionAlgorithm::AES256GCM(enc_meta);
+        metadata::Metadata::new(key_deriv_algo, enc_algo, 0)
+    }
+
+    #[test]
+    fn test_policy_check() {
+        let err = Err(errors::Error::PolicyViolation);
+        let policy = DecryptionPolicy::new();
+
+        // Check that the default policy accepts the default metadata for every
+        // key derivation algorithm.
+        let pbkdf2_meta = metadata::KeyDerivationMetadata::generate();
+        let argon2_meta = metadata::Argon2idMetadata::generate();
+        let scrypt_meta = metadata::ScryptMetadata::generate();
+        let hkdf_meta = metadata::HkdfMetadata::generate(b"info");
+        let algos = vec![
+            metadata::KeyDerivationAlgorithm::None,
+            metadata::KeyDerivationAlgorithm::PBKDF2(pbkdf2_meta.clone()),
+            metadata::KeyDerivationAlgorithm::Argon2id(argon2_meta.clone()),
+            metadata::KeyDerivationAlgorithm::Scrypt(scrypt_meta.clone()),
+            metadata::KeyDerivationAlgorithm::HKDF(hkdf_meta.clone()),
+        ];
+        for algo in algos {
+            assert_eq!(policy.check(&generate_meta(algo)), Ok(()));
+        }
+
+        // Check that the PBKDF2 iterations must be within the limits, and
+        // that the limits are inclusive.
+        let mut pbkdf2_meta = pbkdf2_meta;
+        for &(iterations, allowed) in &[
+            (0, false),
+            (DEFAULT_MIN_ITERATIONS, true),
+            (DEFAULT_MAX_ITERATIONS, true),
+            (DEFAULT_MAX_ITERATIONS + 1, false),
+            (usize::MAX, false),
+        ] {
+            pbkdf2_meta.iterations = iterations;
+            let algo =
+                metadata::KeyDerivationAlgorithm::PBKDF2(pbkdf2_meta.clone());
+            let res = policy.check(&generate_meta(algo));
+            assert_eq!(res.is_ok(), allowed);
+        }
+
+        // Check that the Argon2id cost parameters must be within the limits.
+        for &(memory_cost, time_cost, parallelism) in &[
+            (DEFAULT_MAX_MEMORY_COST as u32 + 1, 1, 1),
+            (65536, DEFAULT_MAX_TIME_COST + 1, 1),
+            (65536, 1, DEFAULT_MAX_PARALLELISM + 1),
+            (u32::MAX, u32::MAX, u32::MAX),
+        ] {
+            let mut argon2_meta = argon2_meta.clone();
+            argon2_meta.memory_cost = memory_cost;
+            argon2_meta.time_cost = time_cost;
+            argon2_meta.parallelism = parallelism;
+            let algo = metadata::KeyDerivationAlgorithm::Argon2id(argon2_meta);
+            assert_eq!(policy.check(&generate_meta(algo)), err);
+        }
+
+        // Check that the scrypt memory (1 GiB at most, by default) and
+        // parallelism must be within the limits.
+        for &(log_n, block_size, parallelism, allowed) in &[
+            (20, 8, 1, true),
+            (21, 8, 1, false),
+            (20, 9, 1, false),
+            (63, u32::MAX, 1, false),
+            (17, 8, DEFAULT_MAX_PARALLELISM + 1, false),
+        ] {
+            let mut scrypt_meta = scrypt_meta.clone();
+            scrypt_meta.log_n = log_n;
+            scrypt_meta.block_size = block_size;
+            scrypt_meta.parallelism = parallelism;
+            let algo = metadata::KeyDerivationAlgorithm::Scrypt(scrypt_meta);
+            let res = policy.check(&generate_meta(algo));
+            assert_eq!(res.is_ok(), allowed);
+        }
+
+        // Check that the hash functions, key derivation algorithms and
+        // encryption algorithms must be allowed.
+        let mut strict_policy = policy.clone();
+        strict_policy.allowed_hash_fns = vec![metadata::HashFunction::SHA512];
+        let algo = metadata::KeyDerivationAlgorithm::HKDF(hkdf_meta);
+        assert_eq!(strict_policy.check(&generate_meta(algo)), err);
+
+        let mut strict_policy = policy.clone();
+        strict_policy.allowed_key_deriv_algos = vec![KeyDerivationKind::HKDF];
+        let algo = metadata::KeyDerivationAlgorithm::None;
+        assert_eq!(strict_policy.check(&generate_meta(algo)), err);
+
+        let mut strict_policy = policy.clone();
+        strict_policy.allowed_enc_algos = vec![EncryptionKind::AES256GCMSIV];
+        let algo = metadata::KeyDerivationAlgorithm::None;
+        assert_eq!(strict_policy.check(&generate_meta(algo)), err);
+
+        // Check that the key slots are checked as well.
+        let mut meta = generate_meta(metadata::KeyDerivationAlgorithm::None);
+        let mut pbkdf2_meta = metadata::KeyDerivationMetadata::generate();
+        pbkdf2_meta.iterations = DEFAULT_MAX_ITERATIONS + 1;
+        meta.key_slots.push(metadata::KeySlot::new(
+            metadata::KeyDerivationAlgorithm::PBKDF2(pbkdf2_meta),
+            metadata::EncryptionAlgorithm::AES256GCM(
+                metadata::EncryptionMetadata::generate(),
+            ),
+            vec![0; 48],
+        ));
+        assert_eq!(policy.check(&meta), err);
+
+        // Check that the number of key slots must be within the limits.
+        let mut meta = generate_meta(metadata::KeyDerivationAlgorithm::None);
+        let slot = metadata::KeySlot::new(
+            metadata::KeyDerivationAlgorithm::None,
+            metadata::EncryptionAlgorithm::AES256GCM(
+                metadata::EncryptionMetadata::generate(),
+            ),
+            vec![0; 48],
+        );
+        meta.key_slots = vec![slot; DEFAULT_MAX_KEY_SLOTS];
+        assert_eq!(policy.check(&meta), Ok(()));
+        meta.key_slots.push(meta.key_slots[0].clone());
+        assert_eq!(policy.check(&meta), err);
+        let mut lax_policy = policy.clone();
+        lax_policy.max_key_slots = DEFAULT_MAX_KEY_SLOTS + 1;
+        assert_eq!(lax_policy.check(&meta), Ok(()));
+
+        // Check that a key commitment can be required.
+        let mut meta = generate_meta(metadata::KeyDerivationAlgorithm::None);
+        let mut strict_policy = policy.clone();
+        strict_policy.require_key_commitment = true;
+        assert_eq!(policy.check(&meta), Ok(()));
+        assert_eq!(strict_policy.check(&meta), err);
+        meta.key_commitment = Some([0; metadata::KEY_COMMITMENT_SIZE]);
+        assert_eq!(strict_policy.check(&meta), Ok(()));
+    }
+}
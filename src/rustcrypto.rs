@@ -0,0 +1,600 @@
+//! # RustCrypto cryptor
+//!
+//! This module provides [`RustCryptoCryptor`], a cryptor that uses the
+//! [RustCrypto] crates instead of `ring` for the key derivation, the key
+//! verification and the encryption of the data. It produces the same wire
+//! format as the [`RingCryptor`], so data that have been encrypted by one
+//! cryptor can be decrypted by the other. This is useful for platforms where
+//! `ring` is not available or not desired, and for cross-checking the two
+//! implementations.
+//!
+//! This module is available only if the `rustcrypto` feature is enabled.
+//!
+//! The RustCrypto cryptor supports the core format of the encrypted data,
+//! i.e., data that have been encrypted with a symmetric key, a passphrase or
+//! a master key, in committing mode or with a key check value. It does not
+//! support streams, key slots or data that have been encrypted for a
+//! recipient, and returns a `CryptoParamsUnsupported` error for them.
+//!
+//! ## Examples
+//!
+//! ```
+//! use tindercrypt::cryptors::Cryptor;
+//! # #[cfg(feature = "ring")]
+//! use tindercrypt::cryptors::RingCryptor;
+//! use tindercrypt::rustcrypto::RustCryptoCryptor;
+//!
+//! let plaintext = "The cake is a lie".as_bytes();
+//! let pass = "My secret passphrase".as_bytes();
+//!
+//! // Encrypt the data with the RustCrypto cryptor, and decrypt them with the
+//! // `ring` one.
+//! let cryptor = RustCryptoCryptor::new();
+//! let ciphertext = cryptor.seal_with_passphrase(pass, plaintext)?;
+//! # #[cfg(feature = "ring")]
+//! assert_eq!(RingCryptor::new().open(pass, &ciphertext)?, plaintext);
+//!
+//! # use tindercrypt::errors;
+//! # Ok::<(), errors::Error>(())
+//! ```
+//!
+//! [`RustCryptoCryptor`]: struct.RustCryptoCryptor.html
+//! [RustCrypto]: https://github.com/RustCrypto
+//! [`RingCryptor`]: ../cryptors/struct.RingCryptor.html
+
+use crate::aes_gcm_siv;
+use crate::argon2;
+use crate::cryptors::Cryptor;
+use crate::errors;
+use crate::header;
+use crate::metadata;
+use crate::policy;
+use crate::scrypt;
+use crate::xchacha20;
+use aes_gcm::aead::{AeadInPlace, KeyInit};
+use aes_gcm::Aes256Gcm;
+use chacha20poly1305::ChaCha20Poly1305;
+use core::convert::TryFrom;
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use sha2::{Sha256, Sha384, Sha512};
+use zeroize::Zeroizing;
+
+/// The size of the keys for AES-256-GCM and ChaCha20-Poly1305.
+const KEY_SIZE: usize = 32;
+
+/// The size of the nonces for AES-256-GCM and ChaCha20-Poly1305.
+const NONCE_SIZE: usize = metadata::RING_NONCE_SIZE;
+
+/// The size of the tags for AES-256-GCM and ChaCha20-Poly1305.
+const TAG_SIZE: usize = metadata::RING_TAG_SIZE;
+
+/// A cryptor that uses cryptographic primitives from the RustCrypto crates.
+///
+/// This cryptor follows the same encryption/decryption logic as the
+/// [`RingCryptor`], and can be used through the [`Cryptor`] trait. See the
+/// [module-level docs] for the data that it supports.
+///
+/// [`RingCryptor`]: ../cryptors/struct.RingCryptor.html
+/// [`Cryptor`]: ../cryptors/trait.Cryptor.html
+/// [module-level docs]: index.html
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RustCryptoCryptor<'a> {
+    aad: &'a [u8],
+    policy: Option<&'a policy::DecryptionPolicy>,
+}
+
+impl<'a> Default for RustCryptoCryptor<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> RustCryptoCryptor<'a> {
+    /// Create a new cryptor instance.
+    pub fn new() -> Self {
+        Self {
+            aad: &[],
+            policy: None,
+        }
+    }
+
+    /// Add associated data to the cryptor instance.
+    ///
+    /// See `RingCryptor::with_aad()` for more info.
+    pub fn with_aad(self, aad: &'a [u8]) -> Self {
+        Self { aad, ..self }
+    }
+
+    /// Add a decryption policy to the cryptor instance.
+    ///
+    /// See `RingCryptor::with_policy()` for more info.
+    pub fn with_policy(self, policy: &'a policy::DecryptionPolicy) -> Self {
+        Self {
+            policy: Some(policy),
+            ..self
+        }
+    }
+
+    /// Compute the HMAC-SHA256 of a fixed label, with a symmetric key as the
+    /// HMAC key.
+    fn _new_label_mac(key: &[u8], label: &[u8]) -> Hmac<Sha256> {
+        // NOTE: It's safe to unwrap the result, since HMAC accepts keys of
+        // any size.
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).unwrap();
+        mac.update(label);
+        mac
+    }
+
+    /// Create a symmetric key from a secret value.
+    ///
+    /// This method mirrors `RingCryptor::_derive_key()`, and performs the
+    /// same parameter checks, so that both cryptors fail in the same way.
+    fn _derive_key(
+        key_deriv_algo: &metadata::KeyDerivationAlgorithm,
+        secret: &[u8],
+        key: &mut [u8],
+    ) -> Result<(), errors::Error> {
+        match key_deriv_algo {
+            metadata::KeyDerivationAlgorithm::None => {
+                if key.len() != secret.len() {
+                    return Err(errors::Error::KeySizeMismatch);
+                }
+                key.copy_from_slice(secret);
+                Ok(())
+            }
+            metadata::KeyDerivationAlgorithm::PBKDF2(meta) => {
+                if meta.iterations < 1 || meta.salt.is_empty() {
+                    return Err(errors::Error::CryptoParamsWeak);
+                }
+                if secret.is_empty() || key.is_empty() {
+                    return Err(errors::Error::PassphraseTooSmall);
+                }
+                let iterations = match u32::try_from(meta.iterations) {
+                    Ok(iterations) => iterations,
+                    Err(_) => {
+                        return Err(errors::Error::CryptoParamsUnsupported)
+                    }
+                };
+
+                let salt = &meta.salt;
+                match meta.hash_fn {
+                    metadata::HashFunction::SHA256 => {
+                        pbkdf2::pbkdf2_hmac::<Sha256>(
+                            secret, salt, iterations, key,
+                        )
+                    }
+                    metadata::HashFunction::SHA384 => {
+                        pbkdf2::pbkdf2_hmac::<Sha384>(
+                            secret, salt, iterations, key,
+                        )
+                    }
+                    metadata::HashFunction::SHA512 => {
+                        pbkdf2::pbkdf2_hmac::<Sha512>(
+                            secret, salt, iterations, key,
+                        )
+                    }
+                }
+                Ok(())
+            }
+            // NOTE: The Argon2id and scrypt wrappers of this crate already
+            // use the RustCrypto implementations.
+            metadata::KeyDerivationAlgorithm::Argon2id(meta) => {
+                argon2::derive_key(
+                    meta.memory_cost,
+                    meta.time_cost,
+                    meta.parallelism,
+                    &meta.salt,
+                    secret,
+                    key,
+                )
+            }
+            metadata::KeyDerivationAlgorithm::Scrypt(meta) => {
+                scrypt::derive_key(
+                    meta.log_n,
+                    meta.block_size,
+                    meta.parallelism,
+                    &meta.salt,
+                    secret,
+                    key,
+                )
+            }
+            metadata::KeyDerivationAlgorithm::X25519(_) => {
+                Err(errors::Error::CryptoParamsUnsupported)
+            }
+            metadata::KeyDerivationAlgorithm::HKDF(meta) => {
+                if meta.salt.is_empty() {
+                    return Err(errors::Error::CryptoParamsWeak);
+                }
+                if key.is_empty() || secret.len() < key.len() {
+                    return Err(errors::Error::KeySizeMismatch);
+                }
+
+                let salt = Some(&meta.salt[..]);
+                let res = match meta.hash_fn {
+                    metadata::HashFunction::SHA256 => {
+                        Hkdf::<Sha256>::new(salt, secret)
+                            .expand(&meta.info, key)
+                    }
+                    metadata::HashFunction::SHA384 => {
+                        Hkdf::<Sha384>::new(salt, secret)
+                            .expand(&meta.info, key)
+                    }
+                    metadata::HashFunction::SHA512 => {
+                        Hkdf::<Sha512>::new(salt, secret)
+                            .expand(&meta.info, key)
+                    }
+                };
+                match res {
+                    Ok(_) => Ok(()),
+                    Err(_) => Err(errors::Error::KeySizeMismatch),
+                }
+            }
+        }
+    }
+
+    /// Create the symmetric key of the data from a secret value.
+    ///
+    /// The metadata are first checked against the decryption policy, if any.
+    /// If the metadata have a key commitment or a key check value, the key is
+    /// checked against them, before it's used for any encryption/decryption.
+    fn _get_key(
+        &self,
+        meta: &metadata::Metadata,
+        secret: &[u8],
+        key: &mut [u8],
+    ) -> Result<(), errors::Error> {
+        if let Some(policy) = self.policy {
+            policy.check(meta)?;
+        }
+        if !meta.key_slots.is_empty() {
+            return Err(errors::Error::CryptoParamsUnsupported);
+        }
+        Self::_derive_key(&meta.key_deriv_algo, secret, key)?;
+
+        if let Some(key_commitment) = &meta.key_commitment {
+            let mac = Self::_new_label_mac(key, header::KEY_COMMITMENT_LABEL);
+            if mac.verify_slice(key_commitment).is_err() {
+                return Err(errors::Error::KeyCommitmentMismatch);
+            }
+        }
+        if let Some(key_check) = &meta.key_check {
+            let mac = Self::_new_label_mac(key, header::KEY_CHECK_LABEL);
+            if mac.verify_truncated_left(key_check).is_err() {
+                return Err(errors::Error::WrongKey);
+            }
+        }
+        Ok(())
+    }
+
+    /// Encrypt (seal) or decrypt (open) the data buffer in place, with
+    /// AES-256-GCM or ChaCha20-Poly1305.
+    ///
+    /// The data buffer must contain the plaintext/ciphertext and its tag.
+    /// This function returns the size of the plaintext.
+    fn _crypt_in_place<C: AeadInPlace + KeyInit>(
+        seal: bool,
+        nonce: [u8; NONCE_SIZE],
+        aad: &[u8],
+        key: &[u8],
+        buf: &mut [u8],
+    ) -> Result<usize, errors::Error> {
+        if key.len() != KEY_SIZE {
+            return Err(errors::Error::KeySizeMismatch);
+        }
+        if buf.len() < TAG_SIZE {
+            return Err(errors::Error::BufferTooSmall);
+        }
+        // NOTE: It's safe to unwrap the result, since we have checked the key
+        // size above.
+        let cipher = C::new_from_slice(key).unwrap();
+
+        let data_size = buf.len() - TAG_SIZE;
+        let (data, tag) = buf.split_at_mut(data_size);
+        let nonce = aes_gcm::Nonce::from_slice(&nonce);
+        if seal {
+            match cipher.encrypt_in_place_detached(nonce, aad, data) {
+                Ok(t) => tag.copy_from_slice(&t),
+                Err(error) => panic!("Error during sealing: {:?}", error),
+            }
+        } else {
+            let tag = aes_gcm::Tag::from_slice(tag);
+            if cipher
+                .decrypt_in_place_detached(nonce, aad, data, tag)
+                .is_err()
+            {
+                return Err(errors::Error::DecryptionError);
+            }
+        }
+        Ok(data_size)
+    }
+
+    /// Encrypt (seal) or decrypt (open) the data buffer in place, according
+    /// to the encryption algorithm of the metadata.
+    fn _crypt_with_algo(
+        seal: bool,
+        enc_algo: &metadata::EncryptionAlgorithm,
+        aad: &[u8],
+        key: &[u8],
+        buf: &mut [u8],
+    ) -> Result<usize, errors::Error> {
+        match enc_algo {
+            metadata::EncryptionAlgorithm::AES256GCM(meta) => {
+                Self::_crypt_in_place::<Aes256Gcm>(
+                    seal, meta.nonce, aad, key, buf,
+                )
+            }
+            metadata::EncryptionAlgorithm::ChaCha20Poly1305(meta) => {
+                Self::_crypt_in_place::<ChaCha20Poly1305>(
+                    seal, meta.nonce, aad, key, buf,
+                )
+            }
+            metadata::EncryptionAlgorithm::XChaCha20Poly1305(meta) => {
+                if seal {
+                    xchacha20::seal_in_place(meta.nonce, aad, key, buf)
+                } else {
+                    xchacha20::open_in_place(meta.nonce, aad, key, buf)
+                }
+            }
+            metadata::EncryptionAlgorithm::AES256GCMSIV(meta) => {
+                if seal {
+                    aes_gcm_siv::seal_in_place(meta.nonce, aad, key, buf)
+                } else {
+                    aes_gcm_siv::open_in_place(meta.nonce, aad, key, buf)
+                }
+            }
+        }
+    }
+
+    /// Encrypt (seal) or decrypt (open) the data buffer in place.
+    fn _crypt(
+        &self,
+        seal: bool,
+        meta: &metadata::Metadata,
+        secret: &[u8],
+        buf: &mut [u8],
+    ) -> Result<usize, errors::Error> {
+        if meta.chunk_size.is_some() {
+            return Err(errors::Error::CryptoParamsUnsupported);
+        }
+
+        let mut key = Zeroizing::new([0u8; metadata::DATA_KEY_SIZE]);
+        self._get_key(meta, secret, &mut key[..])?;
+        let aad = header::get_aad(meta, self.aad);
+        Self::_crypt_with_algo(seal, &meta.enc_algo, &aad, &key[..], buf)
+    }
+}
+
+impl<'a> Cryptor for RustCryptoCryptor<'a> {
+    fn seal_in_place(
+        &self,
+        meta: &metadata::Metadata,
+        secret: &[u8],
+        buf: &mut [u8],
+    ) -> Result<usize, errors::Error> {
+        self._crypt(true, meta, secret, buf)
+    }
+
+    fn open_in_place(
+        &self,
+        meta: &metadata::Metadata,
+        secret: &[u8],
+        buf: &mut [u8],
+    ) -> Result<usize, errors::Error> {
+        match self._crypt(false, meta, secret, buf) {
+            Err(errors::Error::DecryptionError)
+                if header::is_key_verified(meta) =>
+            {
+                Err(errors::Error::CiphertextCorrupted)
+            }
+            res => res,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "ring")]
+    use crate::cryptors::RingCryptor;
+
+    /// Generate the key derivation algorithms for the tests, along with a
+    /// proper secret for each one.
+    #[cfg(feature = "ring")]
+    fn generate_key_deriv_algos(
+    ) -> Vec<(metadata::KeyDerivationAlgorithm, Vec<u8>)> {
+        let pass = b"password".to_vec();
+        let mut pbkdf2_meta = metadata::KeyDerivationMetadata::generate();
+        pbkdf2_meta.iterations = 1;
+        let mut pbkdf2_sha512_meta = pbkdf2_meta.clone();
+        pbkdf2_sha512_meta.hash_fn = metadata::HashFunction::SHA512;
+        let mut hkdf_meta = metadata::HkdfMetadata::generate(b"context");
+        hkdf_meta.hash_fn = metadata::HashFunction::SHA384;
+        let argon2_meta = metadata::Argon2idMetadata::new(8, 1, 1, &[1; 32]);
+        let scrypt_meta = metadata::ScryptMetadata::new(4, 8, 1, &[2; 32]);
+
+        vec![
+            (metadata::KeyDerivationAlgorithm::None, vec![3; 32]),
+            (
+                metadata::KeyDerivationAlgorithm::PBKDF2(pbkdf2_meta),
+                pass.clone(),
+            ),
+            (
+                metadata::KeyDerivationAlgorithm::PBKDF2(pbkdf2_sha512_meta),
+                pass.clone(),
+            ),
+            (
+                metadata::KeyDerivationAlgorithm::HKDF(hkdf_meta),
+                vec![4; 32],
+            ),
+            (
+                metadata::KeyDerivationAlgorithm::Argon2id(argon2_meta),
+                pass.clone(),
+            ),
+            (metadata::KeyDerivationAlgorithm::Scrypt(scrypt_meta), pass),
+        ]
+    }
+
+    /// Generate the encryption algorithms for the tests.
+    #[cfg(feature = "ring")]
+    fn generate_enc_algos() -> Vec<metadata::EncryptionAlgorithm> {
+        let enc_meta = metadata::EncryptionMetadata::generate();
+        let xenc_meta = metadata::XChaCha20Poly1305Metadata::generate();
+        vec![
+            metadata::EncryptionAlgorithm::AES256GCM(enc_meta),
+            metadata::EncryptionAlgorithm::ChaCha20Poly1305(enc_meta),
+            metadata::EncryptionAlgorithm::XChaCha20Poly1305(xenc_meta),
+            metadata::EncryptionAlgorithm::AES256GCMSIV(enc_meta),
+        ]
+    }
+
+    #[test]
+    #[cfg(feature = "ring")]
+    fn test_cross_check() {
+        let plaintext = "The cake is a lie".as_bytes();
+        let aad = "My encryption context".as_bytes();
+        let ring = RingCryptor::new().with_aad(aad);
+        let rc = RustCryptoCryptor::new().with_aad(aad);
+
+        // Check that both cryptors produce the same ciphertext for every
+        // supported algorithm, and that each one can decrypt the ciphertext
+        // of the other.
+        for (key_deriv_algo, secret) in generate_key_deriv_algos() {
+            for enc_algo in generate_enc_algos() {
+                let mut meta = metadata::Metadata::new(
+                    key_deriv_algo.clone(),
+                    enc_algo,
+                    plaintext.len(),
+                );
+                for &authenticated_header in &[true, false] {
+                    meta.authenticated_header = authenticated_header;
+                    let buf1 = ring.seal_with_meta(&meta, &secret, plaintext);
+                    let buf2 = rc.seal_with_meta(&meta, &secret, plaintext);
+                    let buf1 = buf1.unwrap();
+                    assert_eq!(Ok(buf1.clone()), buf2);
+
+                    let buf2 = buf2.unwrap();
+                    assert_eq!(
+                        rc.open(&secret, &buf1),
+                        Ok(plaintext.to_vec())
+                    );
+                    assert_eq!(
+                        ring.open(&secret, &buf2),
+                        Ok(plaintext.to_vec())
+                    );
+                }
+            }
+        }
+
+        // Check that a different AAD or a corrupted ciphertext is detected.
+        let buf = rc.seal_with_passphrase(b"password", plaintext).unwrap();
+        let res = RustCryptoCryptor::new().open(b"password", &buf);
+        assert_eq!(res, Err(errors::Error::DecryptionError));
+        let res = RingCryptor::new().open(b"password", &buf);
+        assert_eq!(res, Err(errors::Error::DecryptionError));
+        let mut bad_buf = buf.clone();
+        let last = bad_buf.len() - 1;
+        bad_buf[last] ^= 1;
+        let res = rc.open(b"password", &bad_buf);
+        assert_eq!(res, Err(errors::Error::DecryptionError));
+        assert_eq!(rc.open(b"password", &buf), Ok(plaintext.to_vec()));
+
+        // Check that the default methods of the trait work as expected.
+        let key = [5u8; 32];
+        let buf = rc.seal_with_key(&key, plaintext).unwrap();
+        assert_eq!(ring.open(&key, &buf), Ok(plaintext.to_vec()));
+    }
+
+    #[test]
+    #[cfg(feature = "ring")]
+    fn test_key_values() {
+        let plaintext = "The cake is a lie".as_bytes();
+        let pass = "password".as_bytes();
+        let ring = RingCryptor::new();
+        let rc = RustCryptoCryptor::new();
+        let mut key_deriv_meta = metadata::KeyDerivationMetadata::generate();
+        key_deriv_meta.iterations = 1;
+        let meta = metadata::Metadata::new(
+            metadata::KeyDerivationAlgorithm::PBKDF2(key_deriv_meta),
+            metadata::EncryptionAlgorithm::AES256GCM(
+                metadata::EncryptionMetadata::generate(),
+            ),
+            plaintext.len(),
+        );
+
+        // Check that the RustCrypto cryptor verifies the key commitment of
+        // the data.
+        let buf = ring.seal_committing(&meta, pass, plaintext).unwrap();
+        assert_eq!(rc.open(pass, &buf), Ok(plaintext.to_vec()));
+        let res = rc.open(b"pass", &buf);
+        assert_eq!(res, Err(errors::Error::KeyCommitmentMismatch));
+        let mut bad_buf = buf.clone();
+        let last = bad_buf.len() - 1;
+        bad_buf[last] ^= 1;
+        let res = rc.open(pass, &bad_buf);
+        assert_eq!(res, Err(errors::Error::CiphertextCorrupted));
+
+        // Check that the RustCrypto cryptor verifies the key check value of
+        // the data.
+        let buf = ring.seal_with_key_check(&meta, pass, plaintext).unwrap();
+        assert_eq!(rc.open(pass, &buf), Ok(plaintext.to_vec()));
+        assert_eq!(rc.open(b"pass", &buf), Err(errors::Error::WrongKey));
+        let mut bad_buf = buf.clone();
+        let last = bad_buf.len() - 1;
+        bad_buf[last] ^= 1;
+        let res = rc.open(pass, &bad_buf);
+        assert_eq!(res, Err(errors::Error::CiphertextCorrupted));
+    }
+
+    #[test]
+    #[cfg(feature = "ring")]
+    fn test_unsupported() {
+        let plaintext = "The cake is a lie".as_bytes();
+        let key = [1u8; 32];
+        let ring = RingCryptor::new();
+        let rc = RustCryptoCryptor::new();
+        let unsupported_err = Err(errors::Error::CryptoParamsUnsupported);
+
+        // Check that streams are not supported.
+        let meta = metadata::Metadata::new_stream(
+            metadata::KeyDerivationAlgorithm::None,
+            metadata::EncryptionAlgorithm::AES256GCM(
+                metadata::EncryptionMetadata::generate(),
+            ),
+            16,
+        );
+        assert_eq!(rc.seal_with_meta(&meta, &key, plaintext), unsupported_err);
+        let buf = ring.seal_with_meta(&meta, &key, plaintext).unwrap();
+        assert_eq!(rc.open(&key, &buf), unsupported_err);
+
+        // Check that key slots are not supported.
+        let buf = ring
+            .seal_with_passphrases(&[b"pass1", b"pass2"], plaintext)
+            .unwrap();
+        assert_eq!(rc.open(b"pass1", &buf), unsupported_err);
+
+        // Check that data for a recipient are not supported.
+        let private_key = crate::x25519::generate_private_key();
+        let public_key = crate::x25519::public_key(&private_key).unwrap();
+        let buf = ring.seal_for_recipient(&public_key, plaintext).unwrap();
+        assert_eq!(rc.open(&private_key, &buf), unsupported_err);
+    }
+
+    #[test]
+    fn test_policy() {
+        let plaintext = "The cake is a lie".as_bytes();
+        let pass = "password".as_bytes();
+        let buf = RustCryptoCryptor::new()
+            .seal_with_passphrase(pass, plaintext)
+            .unwrap();
+
+        let mut policy = policy::DecryptionPolicy::new();
+        let rc = RustCryptoCryptor::new().with_policy(&policy);
+        assert_eq!(rc.open(pass, &buf), Ok(plaintext.to_vec()));
+
+        policy.max_iterations = 1;
+        let rc = RustCryptoCryptor::new().with_policy(&policy);
+        let res = rc.open(pass, &buf);
+        assert_eq!(res, Err(errors::Error::PolicyViolation));
+    }
+}
@@ -0,0 +1,118 @@
+//! # scrypt helpers
+//!
+//! This module contains helpers for the scrypt algorithm.
+
+use crate::errors;
+use scrypt::Params;
+
+/// Cryptographically create a symmetric key from a secret value.
+///
+/// Create a symmetric key from a secret value, based on various scrypt
+/// parameters; the base-2 logarithm of the CPU/memory cost (`N`), the block
+/// size (`r`), the degree of parallelism (`p`) and a salt.
+///
+/// This method returns an error if the parameters are too weak or not
+/// accepted by the scrypt specification, e.g., a CPU/memory cost of 1, a zero
+/// block size or degree of parallelism, or no salt. Also, it returns an error
+/// if the user has not provided a buffer for the key or a secret value.
+///
+/// ## Examples
+///
+/// A safe method to derive a key with scrypt is the following:
+///
+/// ```
+/// use tindercrypt::scrypt::derive_key;
+/// use tindercrypt::rand::fill_buf;
+///
+/// let log_n = 17; // N = 131072
+/// let block_size = 8;
+/// let parallelism = 1;
+/// let mut salt = [0u8; 32];
+/// let secret = "My secret password".as_bytes();
+/// let mut key = [0u8; 32];
+///
+/// fill_buf(&mut salt);
+/// # // Lower the CPU/memory cost for the tests.
+/// # let log_n = 1;
+/// derive_key(log_n, block_size, parallelism, &salt, &secret, &mut key);
+/// ```
+pub fn derive_key(
+    log_n: u8,
+    block_size: u32,
+    parallelism: u32,
+    salt: &[u8],
+    secret: &[u8],
+    key: &mut [u8],
+) -> Result<(), errors::Error> {
+    if log_n < 1 || salt.is_empty() {
+        return Err(errors::Error::CryptoParamsWeak);
+    }
+
+    if secret.is_empty() || key.is_empty() {
+        return Err(errors::Error::PassphraseTooSmall);
+    }
+
+    let params = match Params::new(log_n, block_size, parallelism, key.len()) {
+        Ok(params) => params,
+        Err(_) => return Err(errors::Error::CryptoParamsWeak),
+    };
+
+    match scrypt::scrypt(secret, salt, &params, key) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(errors::Error::CryptoParamsWeak),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scrypt_derive_key() {
+        let mut salt = [9; 10];
+        let mut secret = [99; 10];
+        let mut key1 = [0u8; 32];
+        let mut key2 = [0u8; 32];
+        let mut key3 = [0u8; 32];
+        let mut key4 = [0u8; 32];
+        let mut res: Result<(), errors::Error>;
+        let params_err = Err(errors::Error::CryptoParamsWeak);
+        let size_err = Err(errors::Error::PassphraseTooSmall);
+
+        // Check that weak parameters and empty buffers are reported as errors.
+        res = derive_key(0, 8, 1, &salt, &secret, &mut key1);
+        assert_eq!(res, params_err);
+        res = derive_key(1, 0, 1, &salt, &secret, &mut key1);
+        assert_eq!(res, params_err);
+        res = derive_key(1, 8, 0, &salt, &secret, &mut key1);
+        assert_eq!(res, params_err);
+        res = derive_key(16, 1, 1, &salt, &secret, &mut key1);
+        assert_eq!(res, params_err);
+        res = derive_key(1, 8, 1, &[], &secret, &mut key1);
+        assert_eq!(res, params_err);
+        res = derive_key(1, 8, 1, &salt, &[], &mut key1);
+        assert_eq!(res, size_err);
+        res = derive_key(1, 8, 1, &salt, &secret, &mut []);
+        assert_eq!(res, size_err);
+
+        // Check that key derivation works, and that changes in the salt and
+        // secret produce different keys.
+        res = derive_key(1, 8, 1, &salt, &secret, &mut key1);
+        assert!(res.is_ok());
+        salt[0] = 0;
+        res = derive_key(1, 8, 1, &salt, &secret, &mut key2);
+        assert!(res.is_ok());
+        salt[0] = 9;
+        secret[0] = 0;
+        res = derive_key(1, 8, 1, &salt, &secret, &mut key3);
+        assert!(res.is_ok());
+        secret[0] = 99;
+        res = derive_key(1, 8, 1, &salt, &secret, &mut key4);
+        assert!(res.is_ok());
+
+        assert_ne!(key1, key2);
+        assert_ne!(key1, key3);
+        assert_ne!(key2, key3);
+        assert_eq!(key1, key4);
+    }
+}
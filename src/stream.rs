@@ -0,0 +1,644 @@
+//! # Streaming encryption/decryption
+//!
+//! The cryptors of this library normally require the whole plaintext or
+//! ciphertext to be in memory. For large data, this is not always possible.
+//! This module provides the [`StreamSealer`] and [`StreamOpener`] structs,
+//! which can encrypt/decrypt data in chunks, so that the memory usage stays
+//! bounded.
+//!
+//! The streaming format is based on the [STREAM] construction. The plaintext
+//! is split into chunks of a fixed size, which is stored in the metadata
+//! header (see [`Metadata::chunk_size`]). The last chunk may be smaller, or
+//! even empty. Each chunk is encrypted separately with the same key, and has
+//! its own tag. The nonce of each chunk is derived from the nonce of the
+//! metadata header, by XOR-ing its last 5 bytes with:
+//!
+//! * A 32-bit, big-endian counter, which is the index of the chunk in the
+//!   stream.
+//! * A single byte, which is 1 for the last chunk of the stream, and 0 for the
+//!   rest.
+//!
+//! This way, an attacker cannot reorder, drop or duplicate chunks, since the
+//! chunks would then be decrypted with a different nonce. Also, an attacker
+//! cannot truncate the stream at a chunk boundary, since the chunk that would
+//! become the last one has not been encrypted with the last-chunk flag.
+//!
+//! The layout of an encrypted stream is the following:
+//!
+//! ```text
+//! +--------+--------------+--------------+-----+-------------------+
+//! | header | chunk 0, tag | chunk 1, tag | ... | last chunk, tag   |
+//! +--------+--------------+--------------+-----+-------------------+
+//! ```
+//!
+//! ## Examples
+//!
+//! ```
+//! use tindercrypt::cryptors::RingCryptor;
+//! use tindercrypt::metadata;
+//! use tindercrypt::stream::{StreamOpener, StreamSealer};
+//!
+//! let key = [9u8; 32];
+//! let cryptor = RingCryptor::new();
+//!
+//! // Create the metadata for a stream, with a chunk size of 4 bytes.
+//! let enc_meta = metadata::EncryptionMetadata::generate();
+//! let enc_algo = metadata::EncryptionAlgorithm::AES256GCM(enc_meta);
+//! let key_algo = metadata::KeyDerivationAlgorithm::None;
+//! let meta = metadata::Metadata::new_stream(key_algo, enc_algo, 4);
+//!
+//! // Encrypt the chunks of the stream.
+//! let mut sealer = StreamSealer::new(&cryptor, &meta, &key)?;
+//! let chunk0 = sealer.seal_chunk("The ".as_bytes())?;
+//! let chunk1 = sealer.seal_chunk("cake".as_bytes())?;
+//! let chunk2 = sealer.seal_last_chunk(" is ".as_bytes())?;
+//!
+//! // Decrypt the chunks of the stream.
+//! let mut opener = StreamOpener::new(&cryptor, &meta, &key)?;
+//! assert_eq!(opener.open_chunk(&chunk0)?, "The ".as_bytes());
+//! assert_eq!(opener.open_chunk(&chunk1)?, "cake".as_bytes());
+//! assert_eq!(opener.open_last_chunk(&chunk2)?, " is ".as_bytes());
+//!
+//! // Since the metadata header is serialized along with the ciphertext, the
+//! // cryptor can also decrypt a whole stream.
+//! let mut buf = meta.to_header();
+//! buf.extend(&chunk0);
+//! buf.extend(&chunk1);
+//! buf.extend(&chunk2);
+//! assert_eq!(cryptor.open(&key, &buf)?, "The cake is ".as_bytes());
+//!
+//! # use tindercrypt::errors;
+//! # Ok::<(), errors::Error>(())
+//! ```
+//!
+//! [STREAM]: https://eprint.iacr.org/2015/189.pdf
+//! [`StreamSealer`]: struct.StreamSealer.html
+//! [`StreamOpener`]: struct.StreamOpener.html
+//! [`Metadata::chunk_size`]: ../metadata/struct.Metadata.html#structfield.chunk_size
+
+use crate::cryptors::{RingCryptor, MAX_KEY_SIZE};
+use crate::errors;
+use crate::header;
+use crate::metadata;
+use std::cmp;
+use std::fmt;
+use zeroize::Zeroizing;
+
+/// The number of trailing nonce bytes that are XOR-ed with the chunk counter
+/// and the last-chunk flag.
+const NONCE_SUFFIX_SIZE: usize = 5;
+
+/// Derive the encryption algorithm for a specific chunk of the stream.
+///
+/// The derived encryption algorithm is the same as the provided one, with the
+/// exception of the nonce, which is derived from the chunk counter and the
+/// last-chunk flag.
+fn _chunk_enc_algo(
+    enc_algo: &metadata::EncryptionAlgorithm,
+    counter: u32,
+    last: bool,
+) -> metadata::EncryptionAlgorithm {
+    let mut suffix = [0u8; NONCE_SUFFIX_SIZE];
+    suffix[..4].copy_from_slice(&counter.to_be_bytes());
+    suffix[4] = last as u8;
+
+    let mut enc_algo = *enc_algo;
+    let nonce = match &mut enc_algo {
+        metadata::EncryptionAlgorithm::AES256GCM(meta) => &mut meta.nonce[..],
+        metadata::EncryptionAlgorithm::ChaCha20Poly1305(meta) => {
+            &mut meta.nonce[..]
+        }
+        metadata::EncryptionAlgorithm::XChaCha20Poly1305(meta) => {
+            &mut meta.nonce[..]
+        }
+        metadata::EncryptionAlgorithm::AES256GCMSIV(meta) => {
+            &mut meta.nonce[..]
+        }
+    };
+
+    let offset = nonce.len() - NONCE_SUFFIX_SIZE;
+    for (n, s) in nonce[offset..].iter_mut().zip(suffix.iter()) {
+        *n ^= s;
+    }
+    enc_algo
+}
+
+/// The state that is shared between stream sealers and openers.
+struct StreamState<'a> {
+    cryptor: RingCryptor<'a>,
+    enc_algo: metadata::EncryptionAlgorithm,
+    aad: Vec<u8>,
+    key: Zeroizing<[u8; MAX_KEY_SIZE]>,
+    key_size: usize,
+    chunk_size: usize,
+    tag_size: usize,
+    counter: u64,
+    key_verified: bool,
+}
+
+impl<'a> StreamState<'a> {
+    /// Get the chunk size from the metadata.
+    ///
+    /// Return an error if the metadata do not describe a stream.
+    fn _get_chunk_size(
+        meta: &metadata::Metadata,
+    ) -> Result<usize, errors::Error> {
+        match meta.chunk_size {
+            Some(size)
+                if size > 0 && size <= metadata::STREAM_MAX_CHUNK_SIZE =>
+            {
+                Ok(size)
+            }
+            _ => Err(errors::Error::MetadataInvalid),
+        }
+    }
+
+    /// Create the stream state from the metadata and a secret value.
+    ///
+    /// Return an error if the metadata do not describe a stream, or if the
+    /// key derivation fails.
+    fn new(
+        cryptor: &RingCryptor<'a>,
+        meta: &metadata::Metadata,
+        secret: &[u8],
+    ) -> Result<Self, errors::Error> {
+        let _ = Self::_get_chunk_size(meta)?;
+
+        let mut key = Zeroizing::new([0u8; MAX_KEY_SIZE]);
+        let key_size = cryptor._get_key_size(meta);
+        cryptor._get_key(meta, secret, &mut key[..key_size])?;
+        Self::from_key(cryptor, meta, &key[..key_size])
+    }
+
+    /// Create the stream state from the metadata and an already derived
+    /// key.
+    ///
+    /// Return an error if the metadata do not describe a stream, or if the
+    /// key does not have the expected size.
+    fn from_key(
+        cryptor: &RingCryptor<'a>,
+        meta: &metadata::Metadata,
+        derived_key: &[u8],
+    ) -> Result<Self, errors::Error> {
+        let chunk_size = Self::_get_chunk_size(meta)?;
+        let key_size = cryptor._get_key_size(meta);
+        if derived_key.len() != key_size {
+            return Err(errors::Error::KeySizeMismatch);
+        }
+
+        let mut key = Zeroizing::new([0u8; MAX_KEY_SIZE]);
+        key[..key_size].copy_from_slice(derived_key);
+
+        Ok(Self {
+            cryptor: *cryptor,
+            enc_algo: meta.enc_algo,
+            aad: cryptor._get_aad(meta),
+            key,
+            key_size,
+            chunk_size,
+            tag_size: metadata::Metadata::calculate_ciphertext_size(
+                0,
+                &meta.enc_algo,
+            ),
+            counter: 0,
+            key_verified: header::is_key_verified(meta),
+        })
+    }
+
+    /// Get the encryption algorithm for the next chunk, and advance the
+    /// counter.
+    fn next_enc_algo(
+        &mut self,
+        last: bool,
+    ) -> Result<metadata::EncryptionAlgorithm, errors::Error> {
+        if self.counter > u64::from(u32::MAX) {
+            return Err(errors::Error::StreamTooLong);
+        }
+        let enc_algo =
+            _chunk_enc_algo(&self.enc_algo, self.counter as u32, last);
+        self.counter += 1;
+        Ok(enc_algo)
+    }
+
+    /// Get the number of chunks in a ciphertext buffer that holds a whole
+    /// stream.
+    ///
+    /// Every chunk except the last one has the full chunk size. The last
+    /// chunk may be shorter, or consist of just the tag for an empty stream,
+    /// so a buffer smaller than the tag is rejected.
+    fn chunks_of(&self, buf_size: usize) -> Result<usize, errors::Error> {
+        if buf_size < self.tag_size {
+            return Err(errors::Error::BufferTooSmall);
+        }
+        let ct_chunk_size = self.chunk_size + self.tag_size;
+        Ok(buf_size.div_ceil(ct_chunk_size))
+    }
+}
+
+impl<'a> fmt::Debug for StreamState<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // NOTE: We intentionally omit the key from the debug output.
+        f.debug_struct("StreamState")
+            .field("enc_algo", &self.enc_algo)
+            .field("chunk_size", &self.chunk_size)
+            .field("counter", &self.counter)
+            .finish()
+    }
+}
+
+/// A struct that encrypts (seals) a stream, chunk by chunk.
+///
+/// The user must pass every chunk of the stream to the `.seal_chunk()`
+/// method, in order, except for the last one, which must be passed to the
+/// `.seal_last_chunk()` method. All chunks must have the exact size that is
+/// specified in the metadata, except for the last one, which may be smaller,
+/// or even empty.
+///
+/// The user is responsible for writing the serialized metadata header (see
+/// `Metadata::to_header()`) before the encrypted chunks.
+#[derive(Debug)]
+pub struct StreamSealer<'a> {
+    state: StreamState<'a>,
+}
+
+impl<'a> StreamSealer<'a> {
+    /// Create a new stream sealer.
+    ///
+    /// This method accepts a cryptor, whose associated data will be used for
+    /// every chunk, the metadata of the stream and a secret value (either a
+    /// key or a passphrase). It returns an error if the metadata do not
+    /// describe a stream, or if the key derivation fails.
+    pub fn new(
+        cryptor: &RingCryptor<'a>,
+        meta: &metadata::Metadata,
+        secret: &[u8],
+    ) -> Result<Self, errors::Error> {
+        let state = StreamState::new(cryptor, meta, secret)?;
+        Ok(Self { state })
+    }
+
+    /// Create a new stream sealer from an already derived key.
+    ///
+    /// This is useful when the key derivation has been performed elsewhere,
+    /// e.g., in a separate thread.
+    pub(crate) fn _from_key(
+        cryptor: &RingCryptor<'a>,
+        meta: &metadata::Metadata,
+        key: &[u8],
+    ) -> Result<Self, errors::Error> {
+        let state = StreamState::from_key(cryptor, meta, key)?;
+        Ok(Self { state })
+    }
+
+    /// Get the size of the plaintext chunks.
+    pub fn chunk_size(&self) -> usize {
+        self.state.chunk_size
+    }
+
+    /// Encrypt (seal) a chunk in place.
+    ///
+    /// The buffer must contain the plaintext, followed by enough space for
+    /// the tag.
+    fn _seal_chunk_in_place(
+        &mut self,
+        buf: &mut [u8],
+        last: bool,
+    ) -> Result<usize, errors::Error> {
+        let enc_algo = self.state.next_enc_algo(last)?;
+        let state = &self.state;
+        let key = &state.key[..state.key_size];
+        state
+            .cryptor
+            ._seal_in_place(&enc_algo, &state.aad, key, buf)
+    }
+
+    /// Encrypt (seal) a chunk and return it along with its tag.
+    fn _seal_chunk(
+        &mut self,
+        chunk: &[u8],
+        last: bool,
+    ) -> Result<Vec<u8>, errors::Error> {
+        let mut buf = vec![0u8; chunk.len() + self.state.tag_size];
+        buf[..chunk.len()].copy_from_slice(chunk);
+        let _ = self._seal_chunk_in_place(&mut buf, last)?;
+        Ok(buf)
+    }
+
+    /// Encrypt (seal) a chunk of the stream.
+    ///
+    /// The chunk must have the exact size that is specified in the metadata,
+    /// else an error is returned. The returned buffer contains the encrypted
+    /// chunk and its tag.
+    pub fn seal_chunk(
+        &mut self,
+        chunk: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        if chunk.len() != self.state.chunk_size {
+            return Err(errors::Error::ChunkSizeMismatch);
+        }
+        self._seal_chunk(chunk, false)
+    }
+
+    /// Encrypt (seal) the last chunk of the stream.
+    ///
+    /// The chunk must not be larger than the size that is specified in the
+    /// metadata, else an error is returned. The returned buffer contains the
+    /// encrypted chunk and its tag. This method consumes the sealer, since no
+    /// other chunk can follow.
+    pub fn seal_last_chunk(
+        mut self,
+        chunk: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        if chunk.len() > self.state.chunk_size {
+            return Err(errors::Error::ChunkSizeMismatch);
+        }
+        self._seal_chunk(chunk, true)
+    }
+
+    /// Encrypt (seal) a whole stream in place.
+    ///
+    /// The buffer must contain the plaintext, followed by enough space for
+    /// the tags of every chunk (see
+    /// `Metadata::calculate_stream_ciphertext_size()`). The plaintext chunks
+    /// are spread within the buffer, so that each one is followed by its tag,
+    /// and then they are sealed in place.
+    pub(crate) fn _seal_in_place(
+        mut self,
+        buf: &mut [u8],
+    ) -> Result<usize, errors::Error> {
+        let chunks = self.state.chunks_of(buf.len())?;
+        let chunk_size = self.state.chunk_size;
+        let ct_chunk_size = chunk_size + self.state.tag_size;
+        let plaintext_size = buf.len() - chunks * self.state.tag_size;
+        if plaintext_size > chunks * chunk_size {
+            return Err(errors::Error::BufferTooSmall);
+        }
+
+        // Spread the plaintext chunks, starting from the last one, so that we
+        // don't overwrite the ones that have not been moved yet.
+        for i in (1..chunks).rev() {
+            let start = i * chunk_size;
+            let end = cmp::min(start + chunk_size, plaintext_size);
+            buf.copy_within(start..end, i * ct_chunk_size);
+        }
+
+        for i in 0..chunks {
+            let start = i * ct_chunk_size;
+            let end = cmp::min(start + ct_chunk_size, buf.len());
+            let last = i == chunks - 1;
+            let _ = self._seal_chunk_in_place(&mut buf[start..end], last)?;
+        }
+        Ok(plaintext_size)
+    }
+}
+
+/// A struct that decrypts (opens) a stream, chunk by chunk.
+///
+/// The user must pass every encrypted chunk of the stream to the
+/// `.open_chunk()` method, in order, except for the last one, which must be
+/// passed to the `.open_last_chunk()` method. If a chunk has been tampered
+/// with, reordered, or is not the last one when it should be (e.g., due to
+/// truncation), a decryption error is returned.
+#[derive(Debug)]
+pub struct StreamOpener<'a> {
+    state: StreamState<'a>,
+}
+
+impl<'a> StreamOpener<'a> {
+    /// Create a new stream opener.
+    ///
+    /// This method accepts a cryptor, whose associated data will be used for
+    /// every chunk, the metadata of the stream and a secret value (either a
+    /// key or a passphrase). It returns an error if the metadata do not
+    /// describe a stream, or if the key derivation fails.
+    pub fn new(
+        cryptor: &RingCryptor<'a>,
+        meta: &metadata::Metadata,
+        secret: &[u8],
+    ) -> Result<Self, errors::Error> {
+        let state = StreamState::new(cryptor, meta, secret)?;
+        Ok(Self { state })
+    }
+
+    /// Create a new stream opener from an already derived key.
+    ///
+    /// This is useful when the key derivation has been performed elsewhere,
+    /// e.g., in a separate thread.
+    pub(crate) fn _from_key(
+        cryptor: &RingCryptor<'a>,
+        meta: &metadata::Metadata,
+        key: &[u8],
+    ) -> Result<Self, errors::Error> {
+        let state = StreamState::from_key(cryptor, meta, key)?;
+        Ok(Self { state })
+    }
+
+    /// Get the size of the plaintext chunks.
+    pub fn chunk_size(&self) -> usize {
+        self.state.chunk_size
+    }
+
+    /// Get the size of the encrypted chunks, including their tag.
+    pub fn encrypted_chunk_size(&self) -> usize {
+        self.state.chunk_size + self.state.tag_size
+    }
+
+    /// Decrypt (open) a chunk in place.
+    ///
+    /// The buffer must contain the encrypted chunk, followed by its tag.
+    fn _open_chunk_in_place(
+        &mut self,
+        buf: &mut [u8],
+        last: bool,
+    ) -> Result<usize, errors::Error> {
+        let enc_algo = self.state.next_enc_algo(last)?;
+        let state = &self.state;
+        let key = &state.key[..state.key_size];
+        match state
+            .cryptor
+            ._open_in_place(&enc_algo, &state.aad, key, buf)
+        {
+            // If the key has been verified, the decryption can fail only due
+            // to a corrupted chunk.
+            Err(errors::Error::DecryptionError) if state.key_verified => {
+                Err(errors::Error::CiphertextCorrupted)
+            }
+            res => res,
+        }
+    }
+
+    /// Decrypt (open) a chunk and return its plaintext.
+    fn _open_chunk(
+        &mut self,
+        chunk: &[u8],
+        last: bool,
+    ) -> Result<Vec<u8>, errors::Error> {
+        let mut buf = chunk.to_vec();
+        let size = self._open_chunk_in_place(&mut buf, last)?;
+        buf.truncate(size);
+        Ok(buf)
+    }
+
+    /// Decrypt (open) a chunk of the stream.
+    ///
+    /// The encrypted chunk must have the exact size that is specified in the
+    /// metadata, plus the size of the tag, else an error is returned.
+    pub fn open_chunk(
+        &mut self,
+        chunk: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        if chunk.len() != self.encrypted_chunk_size() {
+            return Err(errors::Error::ChunkSizeMismatch);
+        }
+        self._open_chunk(chunk, false)
+    }
+
+    /// Decrypt (open) the last chunk of the stream.
+    ///
+    /// The encrypted chunk must not be larger than the size that is specified
+    /// in the metadata, plus the size of the tag, else an error is returned.
+    /// This method consumes the opener, since no other chunk can follow.
+    pub fn open_last_chunk(
+        mut self,
+        chunk: &[u8],
+    ) -> Result<Vec<u8>, errors::Error> {
+        if chunk.len() > self.encrypted_chunk_size() {
+            return Err(errors::Error::ChunkSizeMismatch);
+        }
+        self._open_chunk(chunk, true)
+    }
+
+    /// Decrypt (open) a whole stream in place.
+    ///
+    /// The buffer must contain every encrypted chunk of the stream, along
+    /// with its tag. The decrypted chunks are moved to the start of the
+    /// buffer, so that the plaintext is contiguous.
+    pub(crate) fn _open_in_place(
+        mut self,
+        buf: &mut [u8],
+    ) -> Result<usize, errors::Error> {
+        let chunks = self.state.chunks_of(buf.len())?;
+        let ct_chunk_size = self.encrypted_chunk_size();
+        let mut plaintext_size = 0;
+
+        for i in 0..chunks {
+            let start = i * ct_chunk_size;
+            let end = cmp::min(start + ct_chunk_size, buf.len());
+            let last = i == chunks - 1;
+            let size =
+                self._open_chunk_in_place(&mut buf[start..end], last)?;
+            buf.copy_within(start..start + size, plaintext_size);
+            plaintext_size += size;
+        }
+        Ok(plaintext_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Generate metadata for a stream with the provided chunk size.
+    fn generate_meta(chunk_size: usize) -> metadata::Metadata {
+        let enc_meta = metadata::EncryptionMetadata::generate();
+        let enc_algo =
+            metadata::EncryptionAlgorithm::ChaCha20Poly1305(enc_meta);
+        let key_algo = metadata::KeyDerivationAlgorithm::None;
+        metadata::Metadata::new_stream(key_algo, enc_algo, chunk_size)
+    }
+
+    #[test]
+    fn test_seal_open_chunks() {
+        let key = [9u8; 32];
+        let cryptor = RingCryptor::new();
+        let meta = generate_meta(4);
+        let size_err = Err(errors::Error::ChunkSizeMismatch);
+        let dec_err = Err(errors::Error::DecryptionError);
+        let seal = || {
+            let mut sealer = StreamSealer::new(&cryptor, &meta, &key).unwrap();
+            let chunk0 = sealer.seal_chunk(&[0; 4]).unwrap();
+            let chunk1 = sealer.seal_chunk(&[1; 4]).unwrap();
+            let chunk2 = sealer.seal_last_chunk(&[2; 2]).unwrap();
+            (chunk0, chunk1, chunk2)
+        };
+
+        // Check that non-stream metadata are rejected.
+        let non_stream_meta = metadata::Metadata::generate_for_key(0);
+        let res = StreamSealer::new(&cryptor, &non_stream_meta, &key);
+        assert_eq!(res.unwrap_err(), errors::Error::MetadataInvalid);
+
+        // Check that chunks with an unexpected size are rejected.
+        let mut sealer = StreamSealer::new(&cryptor, &meta, &key).unwrap();
+        assert_eq!(sealer.seal_chunk(&[0; 3]), size_err);
+        assert_eq!(sealer.seal_chunk(&[0; 5]), size_err);
+        assert_eq!(sealer.seal_last_chunk(&[0; 5]), size_err);
+
+        let (chunk0, chunk1, chunk2) = seal();
+        let mut opener = StreamOpener::new(&cryptor, &meta, &key).unwrap();
+        assert_eq!(opener.open_chunk(&chunk2), size_err);
+
+        // Check that reordered chunks are detected.
+        let mut opener = StreamOpener::new(&cryptor, &meta, &key).unwrap();
+        assert_eq!(opener.open_chunk(&chunk1), dec_err);
+
+        // Check that a truncated stream is detected.
+        let mut opener = StreamOpener::new(&cryptor, &meta, &key).unwrap();
+        assert_eq!(opener.open_chunk(&chunk0), Ok(vec![0; 4]));
+        assert_eq!(opener.open_last_chunk(&chunk1), dec_err);
+
+        // Check that data appended after the last chunk are detected.
+        let mut opener = StreamOpener::new(&cryptor, &meta, &key).unwrap();
+        assert_eq!(opener.open_chunk(&chunk0), Ok(vec![0; 4]));
+        assert_eq!(opener.open_chunk(&chunk1), Ok(vec![1; 4]));
+        assert_eq!(opener.open_chunk(&chunk2), size_err);
+        assert_eq!(opener.open_last_chunk(&chunk0), dec_err);
+
+        // Check that the chunks can be decrypted in order.
+        let mut opener = StreamOpener::new(&cryptor, &meta, &key).unwrap();
+        assert_eq!(opener.open_chunk(&chunk0), Ok(vec![0; 4]));
+        assert_eq!(opener.open_chunk(&chunk1), Ok(vec![1; 4]));
+        assert_eq!(opener.open_last_chunk(&chunk2), Ok(vec![2; 2]));
+    }
+
+    #[test]
+    fn test_seal_open_in_place() {
+        let key = [9u8; 32];
+        let cryptor = RingCryptor::new();
+        let meta = generate_meta(4);
+
+        // Check that streams of various sizes, including empty ones and ones
+        // whose last chunk is full, can be encrypted and decrypted as a whole.
+        for size in 0..13 {
+            let plaintext: Vec<u8> = (0..size as u8).collect();
+            let ciphertext = cryptor.seal_with_meta(&meta, &key, &plaintext);
+            assert!(ciphertext.is_ok());
+            let ciphertext = ciphertext.unwrap();
+            assert_eq!(cryptor.open(&key, &ciphertext), Ok(plaintext.clone()));
+
+            // Check that the whole stream can be decrypted chunk by chunk.
+            let (_, meta_size) =
+                metadata::Metadata::from_buf(&ciphertext).unwrap();
+            let mut opener = StreamOpener::new(&cryptor, &meta, &key).unwrap();
+            let ct_chunks: Vec<&[u8]> = ciphertext[meta_size..]
+                .chunks(opener.encrypted_chunk_size())
+                .collect();
+            let mut res = Vec::new();
+            for chunk in &ct_chunks[..ct_chunks.len() - 1] {
+                res.extend(opener.open_chunk(chunk).unwrap());
+            }
+            res.extend(
+                opener.open_last_chunk(ct_chunks.last().unwrap()).unwrap(),
+            );
+            assert_eq!(res, plaintext);
+
+            // Check that truncating the stream at a chunk boundary is
+            // detected.
+            if ct_chunks.len() > 1 {
+                let truncated_size =
+                    ciphertext.len() - ct_chunks.last().unwrap().len();
+                let res = cryptor.open(&key, &ciphertext[..truncated_size]);
+                assert_eq!(res, Err(errors::Error::DecryptionError));
+            }
+        }
+    }
+}
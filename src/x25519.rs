@@ -0,0 +1,224 @@
+//! # X25519 helpers
+//!
+//! This module contains helpers for encrypting data for a recipient, who is
+//! identified by an X25519 public key. The sender generates an ephemeral key
+//! pair, performs a key agreement with the public key of the recipient, and
+//! derives the encryption key from the shared secret with HKDF-SHA256. The
+//! ephemeral public key is stored in the metadata header, so that the
+//! recipient can perform the same key agreement with their private key.
+//!
+//! The sender side uses the `ring::agreement` module. Since `ring` supports
+//! only ephemeral private keys, the recipient side uses the [`x25519-dalek`]
+//! crate instead. You are advised to not use these low-level functions
+//! directly, and instead use the functions provided by the [`cryptors`]
+//! module.
+//!
+//! ## Examples
+//!
+//! ```
+//! use tindercrypt::x25519;
+//!
+//! // The recipient creates a key pair, and shares the public key.
+//! let private_key = x25519::generate_private_key();
+//! let public_key = x25519::public_key(&private_key)?;
+//!
+//! // The sender derives a key from the public key of the recipient.
+//! let mut key1 = [0u8; 32];
+//! let ephemeral_public_key =
+//!     x25519::derive_ephemeral_key(&public_key, &mut key1)?;
+//!
+//! // The recipient derives the same key with their private key.
+//! let mut key2 = [0u8; 32];
+//! x25519::derive_key(&private_key, &ephemeral_public_key, &mut key2)?;
+//! assert_eq!(key1, key2);
+//!
+//! # use tindercrypt::errors;
+//! # Ok::<(), errors::Error>(())
+//! ```
+//!
+//! [`x25519-dalek`]: https://github.com/dalek-cryptography/curve25519-dalek
+//! [`cryptors`]: ../cryptors/index.html
+
+use crate::errors;
+use crate::hkdf;
+use crate::metadata;
+use crate::rand;
+use ring::agreement;
+
+/// The size of the X25519 public and private keys.
+pub const KEY_SIZE: usize = metadata::X25519_KEY_SIZE;
+
+/// The HKDF info string, which binds the derived key to this scheme.
+const HKDF_INFO: &[u8] = b"tindercrypt x25519";
+
+/// Copy a key to a fixed-size array, after checking its size.
+fn _to_array(key: &[u8]) -> Result<[u8; KEY_SIZE], errors::Error> {
+    if key.len() != KEY_SIZE {
+        return Err(errors::Error::KeySizeMismatch);
+    }
+    let mut array = [0u8; KEY_SIZE];
+    array.copy_from_slice(key);
+    Ok(array)
+}
+
+/// Derive a symmetric key from the shared secret of the key agreement.
+///
+/// Both public keys are used as the HKDF salt, so that the derived key is
+/// bound to the specific sender and recipient.
+fn _hkdf(
+    shared_secret: &[u8],
+    ephemeral_public_key: &[u8],
+    recipient_public_key: &[u8],
+    key: &mut [u8],
+) -> Result<(), errors::Error> {
+    let mut salt = [0u8; 2 * KEY_SIZE];
+    salt[..KEY_SIZE].copy_from_slice(ephemeral_public_key);
+    salt[KEY_SIZE..].copy_from_slice(recipient_public_key);
+    hkdf::derive_key(
+        ring::hkdf::HKDF_SHA256,
+        &salt,
+        HKDF_INFO,
+        shared_secret,
+        key,
+    )
+}
+
+/// Generate a random X25519 private key.
+pub fn generate_private_key() -> [u8; KEY_SIZE] {
+    let mut private_key = [0u8; KEY_SIZE];
+    rand::fill_buf(&mut private_key);
+    private_key
+}
+
+/// Compute the X25519 public key of a private key.
+///
+/// This function returns an error if the private key does not have the
+/// expected size.
+pub fn public_key(
+    private_key: &[u8],
+) -> Result<[u8; KEY_SIZE], errors::Error> {
+    let secret = x25519_dalek::StaticSecret::from(_to_array(private_key)?);
+    Ok(x25519_dalek::PublicKey::from(&secret).to_bytes())
+}
+
+/// Derive a symmetric key for a recipient, using an ephemeral key pair.
+///
+/// This function generates an ephemeral key pair, performs a key agreement
+/// with the public key of the recipient, and derives the symmetric key from
+/// the shared secret. It returns the ephemeral public key, which the
+/// recipient needs in order to derive the same key.
+///
+/// This function returns an error if the public key does not have the
+/// expected size, or if it's a low-order point, which would lead to a
+/// predictable shared secret.
+pub fn derive_ephemeral_key(
+    recipient_public_key: &[u8],
+    key: &mut [u8],
+) -> Result<[u8; KEY_SIZE], errors::Error> {
+    let recipient_public_key = _to_array(recipient_public_key)?;
+    let rng = ring::rand::SystemRandom::new();
+
+    // NOTE: It's safe to unwrap the results, since the key generation can
+    // fail only if the system RNG fails.
+    let private_key =
+        agreement::EphemeralPrivateKey::generate(&agreement::X25519, &rng)
+            .unwrap();
+    let ephemeral_public_key =
+        _to_array(private_key.compute_public_key().unwrap().as_ref())?;
+
+    let peer_public_key = agreement::UnparsedPublicKey::new(
+        &agreement::X25519,
+        recipient_public_key,
+    );
+    agreement::agree_ephemeral(
+        private_key,
+        &peer_public_key,
+        errors::Error::CryptoParamsWeak,
+        |shared_secret| {
+            _hkdf(
+                shared_secret,
+                &ephemeral_public_key,
+                &recipient_public_key,
+                key,
+            )
+        },
+    )?;
+    Ok(ephemeral_public_key)
+}
+
+/// Derive the symmetric key of a recipient, using their private key.
+///
+/// This function performs a key agreement between the private key of the
+/// recipient and the ephemeral public key of the sender, and derives the
+/// symmetric key from the shared secret.
+///
+/// This function returns an error if the keys do not have the expected size,
+/// or if the ephemeral public key is a low-order point.
+pub fn derive_key(
+    private_key: &[u8],
+    ephemeral_public_key: &[u8],
+    key: &mut [u8],
+) -> Result<(), errors::Error> {
+    let secret = x25519_dalek::StaticSecret::from(_to_array(private_key)?);
+    let recipient_public_key = x25519_dalek::PublicKey::from(&secret);
+    let ephemeral_public_key = _to_array(ephemeral_public_key)?;
+
+    let shared_secret = secret
+        .diffie_hellman(&x25519_dalek::PublicKey::from(ephemeral_public_key));
+    if !shared_secret.was_contributory() {
+        return Err(errors::Error::CryptoParamsWeak);
+    }
+    _hkdf(
+        shared_secret.as_bytes(),
+        &ephemeral_public_key,
+        recipient_public_key.as_bytes(),
+        key,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_x25519_derive_key() {
+        let size_err = Err(errors::Error::KeySizeMismatch);
+        let weak_err = Err(errors::Error::CryptoParamsWeak);
+        let private_key = generate_private_key();
+        let pub_key = public_key(&private_key).unwrap();
+        let mut key1 = [0u8; 32];
+        let mut key2 = [0u8; 32];
+        let mut key3 = [0u8; 32];
+
+        // Check that the sender and the recipient derive the same key, and
+        // that a different ephemeral key pair is used each time.
+        let ephemeral_public_key1 =
+            derive_ephemeral_key(&pub_key, &mut key1).unwrap();
+        let ephemeral_public_key2 =
+            derive_ephemeral_key(&pub_key, &mut key2).unwrap();
+        assert_ne!(ephemeral_public_key1, ephemeral_public_key2);
+        assert_ne!(key1, key2);
+        let res = derive_key(&private_key, &ephemeral_public_key1, &mut key3);
+        assert_eq!(res, Ok(()));
+        assert_eq!(key1, key3);
+
+        // Check that a different private key derives a different key.
+        let res = derive_key(&[1; 32], &ephemeral_public_key1, &mut key3);
+        assert_eq!(res, Ok(()));
+        assert_ne!(key1, key3);
+
+        // Check that keys with wrong sizes are rejected.
+        let res = public_key(&private_key[1..]);
+        assert_eq!(res, Err(errors::Error::KeySizeMismatch));
+        let res = derive_ephemeral_key(&pub_key[1..], &mut key1);
+        assert_eq!(res, Err(errors::Error::KeySizeMismatch));
+        let res = derive_key(&private_key, &[], &mut key1);
+        assert_eq!(res, size_err);
+
+        // Check that low-order public keys are rejected.
+        let res = derive_ephemeral_key(&[0; 32], &mut key1);
+        assert_eq!(res, Err(errors::Error::CryptoParamsWeak));
+        let res = derive_key(&private_key, &[0; 32], &mut key1);
+        assert_eq!(res, weak_err);
+    }
+}
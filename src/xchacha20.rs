@@ -0,0 +1,196 @@
+//! # XChaCha20-Poly1305 helpers
+//!
+//! This module contains some wrappers over the XChaCha20-Poly1305 AEAD of the
+//! [`chacha20poly1305`] crate, since the `ring` library does not support it.
+//! They have the same signature and semantics as the wrappers in the
+//! [`aead`] module, with the exception of the nonce size, which is 24 bytes.
+//! You are advised to not use these low-level functions directly, and instead
+//! use the functions provided by the [`cryptors`] module.
+//!
+//! ## Examples
+//!
+//! ```
+//! use tindercrypt::rand::fill_buf;
+//! use tindercrypt::xchacha20::{seal_in_place, open_in_place};
+//! use tindercrypt::xchacha20::{KEY_SIZE, NONCE_SIZE, TAG_SIZE};
+//!
+//! let mut nonce = [0u8; NONCE_SIZE];
+//! let aad = "My encryption context".as_bytes();
+//! let mut key = [0u8; KEY_SIZE];
+//! let data = "The cake is a lie".as_bytes();
+//!
+//! // Create a unique nonce and key. Since the nonce is 192 bits long, it's
+//! // safe to create it randomly, even for a large number of messages.
+//! fill_buf(&mut nonce);
+//! fill_buf(&mut key);
+//!
+//! // Create a buffer large enough to hold the ciphertext and its tag.
+//! let mut buf = vec![0; data.len() + TAG_SIZE];
+//! buf[..data.len()].copy_from_slice(&data);
+//!
+//! // Encrypt (seal) and decrypt (open) the data buffer in place.
+//! seal_in_place(nonce, &aad, &key, &mut buf)?;
+//! open_in_place(nonce, &aad, &key, &mut buf)?;
+//! assert_eq!(data, &buf[..data.len()]);
+//!
+//! # use tindercrypt::errors;
+//! # Ok::<(), errors::Error>(())
+//! ```
+//!
+//! [`chacha20poly1305`]: https://github.com/RustCrypto/AEADs
+//! [`aead`]: ../aead/index.html
+//! [`cryptors`]: ../cryptors/index.html
+
+use crate::errors;
+use chacha20poly1305::aead::{AeadInPlace, KeyInit};
+use chacha20poly1305::{Tag, XChaCha20Poly1305, XNonce};
+
+/// The size of the nonces for XChaCha20-Poly1305.
+pub const NONCE_SIZE: usize = 24;
+
+/// The size of the keys for XChaCha20-Poly1305.
+pub const KEY_SIZE: usize = 32;
+
+/// The size of the tags for XChaCha20-Poly1305.
+pub const TAG_SIZE: usize = 16;
+
+/// Create a cipher instance, after checking the key size.
+fn _new_cipher(key: &[u8]) -> Result<XChaCha20Poly1305, errors::Error> {
+    if key.len() != KEY_SIZE {
+        return Err(errors::Error::KeySizeMismatch);
+    }
+    // NOTE: It's safe to unwrap the result, since we have checked the key
+    // size above.
+    Ok(XChaCha20Poly1305::new_from_slice(key).unwrap())
+}
+
+/// Seal the contents of a data buffer in place.
+///
+/// The data buffer must start with the plaintext, and have enough space at the
+/// end for the tag. This function returns an error if the key/buffer sizes are
+/// not the expected ones. If the encryption succeeds, it returns the length of
+/// the plaintext.
+pub fn seal_in_place(
+    nonce: [u8; NONCE_SIZE],
+    aad: &[u8],
+    key: &[u8],
+    in_out: &mut [u8],
+) -> Result<usize, errors::Error> {
+    let cipher = _new_cipher(key)?;
+    if in_out.len() < TAG_SIZE {
+        return Err(errors::Error::BufferTooSmall);
+    }
+
+    let plaintext_size = in_out.len() - TAG_SIZE;
+    let (plaintext, tag) = in_out.split_at_mut(plaintext_size);
+    let nonce = XNonce::from_slice(&nonce);
+    let res = cipher.encrypt_in_place_detached(nonce, aad, plaintext);
+
+    match res {
+        Ok(t) => {
+            tag.copy_from_slice(&t);
+            Ok(plaintext_size)
+        }
+        Err(error) => panic!("Error during sealing: {:?}", error),
+    }
+}
+
+/// Open the contents of a sealed data buffer in place.
+///
+/// The data buffer must contain the ciphertext and its tag. This function
+/// returns an error if the key/buffer sizes are not the expected ones, or if
+/// the decryption process fails, e.g., due to a wrong key, nonce, etc. If the
+/// decryption succeeds, it returns the length of the plaintext.
+pub fn open_in_place(
+    nonce: [u8; NONCE_SIZE],
+    aad: &[u8],
+    key: &[u8],
+    in_out: &mut [u8],
+) -> Result<usize, errors::Error> {
+    let cipher = _new_cipher(key)?;
+    if in_out.len() < TAG_SIZE {
+        return Err(errors::Error::BufferTooSmall);
+    }
+
+    let ciphertext_size = in_out.len() - TAG_SIZE;
+    let (ciphertext, tag) = in_out.split_at_mut(ciphertext_size);
+    let nonce = XNonce::from_slice(&nonce);
+    let tag = Tag::from_slice(tag);
+    let res = cipher.decrypt_in_place_detached(nonce, aad, ciphertext, tag);
+
+    match res {
+        Ok(_) => Ok(ciphertext_size),
+        Err(_) => Err(errors::Error::DecryptionError),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUF_SIZE: usize = 36; // 36 bytes can contain the tag and data.
+
+    #[test]
+    fn test_seal_open() {
+        let nonce = [1; NONCE_SIZE];
+        let aad = [2; 9];
+        let key = [3; KEY_SIZE];
+        let mut in_out: [u8; BUF_SIZE];
+        let mut res: Result<usize, errors::Error>;
+        let exp_res = Ok(BUF_SIZE - TAG_SIZE);
+        let dec_err = Err(errors::Error::DecryptionError);
+        let buf_err = Err(errors::Error::BufferTooSmall);
+        let key_err = Err(errors::Error::KeySizeMismatch);
+
+        let seal = || {
+            let mut _in_out = [4; BUF_SIZE];
+            let r = seal_in_place(nonce, &aad, &key, &mut _in_out);
+            assert_eq!(r, exp_res);
+            _in_out
+        };
+
+        // Check that any type of data corruption makes decryption fail.
+        //
+        // Corrupted nonce.
+        in_out = seal();
+        let mut bad_nonce = nonce;
+        bad_nonce[NONCE_SIZE - 1] = 9;
+        res = open_in_place(bad_nonce, &aad, &key, &mut in_out);
+        assert_eq!(res, dec_err);
+
+        // Corrupted additional authenticated data.
+        in_out = seal();
+        res = open_in_place(nonce, &[9; 9], &key, &mut in_out);
+        assert_eq!(res, dec_err);
+
+        // Corrupted key.
+        in_out = seal();
+        res = open_in_place(nonce, &aad, &[9; KEY_SIZE], &mut in_out);
+        assert_eq!(res, dec_err);
+
+        // Corrupted tag.
+        in_out = seal();
+        in_out[BUF_SIZE - 1] ^= 1;
+        res = open_in_place(nonce, &aad, &key, &mut in_out);
+        assert_eq!(res, dec_err);
+
+        // Incomplete data buffer.
+        res = seal_in_place(nonce, &aad, &key, &mut [0; TAG_SIZE - 1]);
+        assert_eq!(res, buf_err);
+        res = open_in_place(nonce, &aad, &key, &mut []);
+        assert_eq!(res, buf_err);
+
+        // Incomplete key.
+        in_out = seal();
+        res = seal_in_place(nonce, &aad, &[], &mut in_out);
+        assert_eq!(res, key_err);
+        res = open_in_place(nonce, &aad, &key[1..], &mut in_out);
+        assert_eq!(res, key_err);
+
+        // Correct decryption.
+        in_out = seal();
+        res = open_in_place(nonce, &aad, &key, &mut in_out);
+        assert_eq!(res, exp_res);
+        assert_eq!(in_out[..res.unwrap()], [4u8; BUF_SIZE - TAG_SIZE][..]);
+    }
+}
@@ -56,6 +56,36 @@ fn test_encrypt_decrypt() {
         .success()
         .stdout("secret");
 
+    let xchacha_output = encrypt()
+        .args(&["-e", "XCHACHA20-POLY1305"])
+        .write_stdin("secret")
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    decrypt()
+        .write_stdin(xchacha_output)
+        .assert()
+        .success()
+        .stdout("secret");
+
+    let aes_siv_output = encrypt()
+        .args(&["-e", "AES256-GCM-SIV"])
+        .write_stdin("secret")
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    decrypt()
+        .write_stdin(aes_siv_output)
+        .assert()
+        .success()
+        .stdout("secret");
+
     // Test that invalid ciphertexts return the appropriate error.
     decrypt()
         .write_stdin("secret")
@@ -72,8 +102,52 @@ fn test_encrypt_decrypt() {
         .failure()
         .stderr(predicate::str::starts_with("Error during decryption"))
         .stderr(predicate::str::ends_with(
-            "Could not decrypt the ciphertext\n",
+            "The provided key or passphrase is wrong\n",
         ));
+
+    // Test that a corrupted ciphertext results to a different error.
+    let mut corrupted_output = output.clone();
+    let last = corrupted_output.len() - 1;
+    corrupted_output[last] ^= 1;
+    decrypt()
+        .write_stdin(corrupted_output)
+        .assert()
+        .failure()
+        .stderr(predicate::str::starts_with("Error during decryption"))
+        .stderr(predicate::str::ends_with(
+            "The ciphertext is corrupted or has been tampered with\n",
+        ));
+
+    // Test that a failed decryption does not leave a partially written file.
+    temp_dir
+        .child("bad_ciphertext")
+        .write_str("secret")
+        .unwrap();
+    decrypt()
+        .args(&["-i", "bad_ciphertext", "-o", "plaintext3"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .failure();
+    temp_dir
+        .child("plaintext3")
+        .assert(predicate::path::missing());
+
+    // Test that large files, which span multiple chunks, can be encrypted and
+    // decrypted.
+    let contents = "secret".repeat(50000);
+    temp_dir.child("large").write_str(&contents).unwrap();
+    encrypt()
+        .args(&["-i", "large", "-o", "large_ciphertext"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+
+    decrypt()
+        .args(&["-i", "large_ciphertext"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(contents);
 }
 
 #[test]